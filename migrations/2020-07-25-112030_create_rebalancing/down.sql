@@ -0,0 +1 @@
+DROP TABLE rebalancing
\ No newline at end of file
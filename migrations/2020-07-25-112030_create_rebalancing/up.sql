@@ -0,0 +1,5 @@
+CREATE TABLE rebalancing (
+  portfolio TEXT NOT NULL,
+  date DATE NOT NULL,
+  PRIMARY KEY (portfolio)
+) WITHOUT ROWID
\ No newline at end of file
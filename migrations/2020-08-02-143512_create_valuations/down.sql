@@ -0,0 +1 @@
+DROP TABLE valuations
\ No newline at end of file
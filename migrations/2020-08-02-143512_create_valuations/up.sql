@@ -0,0 +1,7 @@
+CREATE TABLE valuations (
+  portfolio TEXT NOT NULL,
+  date DATE NOT NULL,
+  currency TEXT NOT NULL,
+  value TEXT NOT NULL,
+  PRIMARY KEY (portfolio, date)
+) WITHOUT ROWID
\ No newline at end of file
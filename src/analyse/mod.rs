@@ -1,6 +1,6 @@
 use std::rc::Rc;
 
-use crate::broker_statement::BrokerStatement;
+use crate::broker_statement::{BrokerStatement, TaxLotMethod};
 use crate::commissions::CommissionCalc;
 use crate::config::{Config, PortfolioConfig};
 use crate::core::{GenericResult, EmptyResult};
@@ -13,7 +13,10 @@ use self::performance::PortfolioPerformanceAnalyser;
 
 pub mod deposit_emulator;
 mod performance;
+pub mod profit;
 mod sell_simulation;
+pub mod twr;
+pub mod xirr;
 
 pub fn analyse(config: &Config, portfolio_name: &str, show_closed_positions: bool) -> EmptyResult {
     let mut portfolios = Vec::new();
@@ -48,14 +51,23 @@ pub fn analyse(config: &Config, portfolio_name: &str, show_closed_positions: boo
         for (symbol, quantity) in statement.open_positions.clone() {
             statement.emulate_sell(&symbol, quantity, quotes.get(&symbol)?, &mut commission_calc)?;
         }
-        statement.process_trades()?;
+        statement.process_trades(TaxLotMethod::Fifo)?;
         statement.emulate_commissions(commission_calc);
 
         statement.merge_symbols(&portfolio.merge_performance).map_err(|e| format!(
             "Invalid performance merging configuration: {}", e))?;
     }
 
-    for &currency in &["USD", "RUB"] {
+    let mut currencies = vec!["USD", "RUB"];
+    for &(portfolio, _) in &portfolios {
+        if let Some(ref currency) = portfolio.currency {
+            if !currencies.contains(&currency.as_str()) {
+                currencies.push(currency);
+            }
+        }
+    }
+
+    for currency in currencies {
         let mut analyser = PortfolioPerformanceAnalyser::new(
             country, currency, &converter, show_closed_positions);
 
@@ -69,15 +81,17 @@ pub fn analyse(config: &Config, portfolio_name: &str, show_closed_positions: boo
     Ok(())
 }
 
-pub fn simulate_sell(config: &Config, portfolio_name: &str, positions: &[(String, Option<u32>)]) -> EmptyResult {
+pub fn simulate_sell(
+    config: &Config, portfolio_name: &str, positions: &[(String, Option<u32>)], method: TaxLotMethod,
+) -> EmptyResult {
     let portfolio = config.get_portfolio(portfolio_name)?;
     let statement = load_portfolio(config, portfolio, true)?;
     let (converter, quotes) = load_tools(config)?;
-    sell_simulation::simulate_sell(portfolio, statement, &converter, &quotes, positions)
+    sell_simulation::simulate_sell(portfolio, statement, &converter, &quotes, positions, method)
 }
 
 fn load_portfolio(config: &Config, portfolio: &PortfolioConfig, strict_mode: bool) -> GenericResult<BrokerStatement> {
-    let broker = portfolio.broker.get_info(config, portfolio.plan.as_ref())?;
+    let broker = portfolio.get_broker_info(config)?;
     BrokerStatement::read(
         broker, &portfolio.statements, &portfolio.symbol_remapping, &portfolio.instrument_names,
         portfolio.get_tax_remapping()?, strict_mode)
@@ -86,6 +100,6 @@ fn load_portfolio(config: &Config, portfolio: &PortfolioConfig, strict_mode: boo
 fn load_tools(config: &Config) -> GenericResult<(CurrencyConverter, Rc<Quotes>)> {
     let database = db::connect(&config.db_path)?;
     let quotes = Rc::new(Quotes::new(&config, database.clone())?);
-    let converter = CurrencyConverter::new(database, Some(quotes.clone()), false);
+    let converter = CurrencyConverter::new(config, database, Some(quotes.clone()), false);
     Ok((converter, quotes))
 }
\ No newline at end of file
@@ -6,18 +6,20 @@ use log::{self, debug, log_enabled, trace, warn};
 use num_traits::Zero;
 use static_table_derive::StaticTable;
 
-use crate::broker_statement::BrokerStatement;
+use crate::broker_statement::{BrokerStatement, SaleKind};
 use crate::config::PortfolioConfig;
 use crate::core::{EmptyResult, GenericResult};
 use crate::currency::Cash;
 use crate::currency::converter::CurrencyConverter;
 use crate::formatting::{self, table::{Cell, Style}};
 use crate::localities::Country;
-use crate::taxes::NetTaxCalculator;
+use crate::taxes::{self, IiaType, NetTaxCalculator};
 use crate::types::{Date, Decimal};
 use crate::util;
 
 use super::deposit_emulator::{DepositEmulator, Transaction, InterestPeriod};
+use super::twr;
+use super::xirr;
 
 #[derive(StaticTable)]
 struct Row {
@@ -33,10 +35,18 @@ struct Row {
     duration: String,
     #[column(name="Interest", align="right")]
     interest: String,
+    #[column(name="XIRR", align="right")]
+    xirr: String,
+    #[column(name="TWR", align="right")]
+    twr: String,
 }
 
 /// Calculates average rate of return from cash investments by comparing portfolio performance to
 /// performance of a bank deposit with exactly the same investments and monthly capitalization.
+///
+/// If portfolio valuations are specified, time-weighted return is calculated as well. Unlike XIRR it
+/// doesn't depend on timing and size of deposits and withdrawals, so it's comparable with benchmark
+/// indices.
 pub struct PortfolioPerformanceAnalyser<'a> {
     country: Country,
     currency: &'a str,
@@ -47,6 +57,10 @@ pub struct PortfolioPerformanceAnalyser<'a> {
     instruments: Option<HashMap<String, StockDepositView>>,
     current_assets: Decimal,
     table: Table,
+
+    // Valuations of each portfolio and external cash flows for time-weighted return calculation
+    valuations: Vec<BTreeMap<Date, Decimal>>,
+    external_cash_flows: Vec<(Date, Decimal)>,
 }
 
 impl <'a> PortfolioPerformanceAnalyser<'a> {
@@ -64,6 +78,9 @@ impl <'a> PortfolioPerformanceAnalyser<'a> {
             instruments: Some(HashMap::new()),
             current_assets: dec!(0),
             table: Table::new(),
+
+            valuations: Vec::new(),
+            external_cash_flows: Vec::new(),
         }
     }
 
@@ -78,8 +95,10 @@ impl <'a> PortfolioPerformanceAnalyser<'a> {
         self.process_deposits_and_withdrawals(statement)?;
         self.process_positions(statement, portfolio)?;
         self.process_dividends(statement, portfolio)?;
+        self.process_coupons(statement, portfolio)?;
         self.process_interest(statement, portfolio)?;
-        self.process_tax_deductions(portfolio)?;
+        self.process_tax_deductions(portfolio, statement)?;
+        self.process_valuations(portfolio)?;
 
         self.current_assets += statement.cash_assets.total_assets_real_time(
             self.currency, self.converter)?;
@@ -136,8 +155,11 @@ impl <'a> PortfolioPerformanceAnalyser<'a> {
             }
         }
 
+        let xirr = xirr::calculate(&get_cash_flows(&deposit_view.transactions));
+
         self.add_results(
-            &deposit_view.name.unwrap(), investments, result, interest, days, deposit_view.closed);
+            &deposit_view.name.unwrap(), investments, result, interest, xirr, None, days,
+            deposit_view.closed);
 
         Ok(())
     }
@@ -161,15 +183,21 @@ impl <'a> PortfolioPerformanceAnalyser<'a> {
 
         check_emulation_precision("portfolio", self.currency, self.current_assets, difference)?;
 
+        let mut cash_flows = get_cash_flows(&self.transactions);
+        cash_flows.push((util::today(), self.current_assets));
+        let xirr = xirr::calculate(&cash_flows);
+
+        let twr = self.calculate_twr();
+
         let days = get_total_activity_duration(&activity_periods);
-        self.add_results("", investments, self.current_assets, interest, days, false);
+        self.add_results("", investments, self.current_assets, interest, xirr, twr, days, false);
 
         Ok(())
     }
 
     fn add_results(
         &mut self, name: &str, investments: Decimal, result: Decimal, interest: Decimal,
-        days: i64, inactive: bool
+        xirr: Option<Decimal>, twr: Option<Decimal>, days: i64, inactive: bool
     ) {
         let investments = util::round(investments, 0);
         let result = util::round(result, 0);
@@ -193,6 +221,8 @@ impl <'a> PortfolioPerformanceAnalyser<'a> {
             result: Cell::new_round_decimal(result),
             duration: duration,
             interest: format!("{}%", interest),
+            xirr: xirr.map(|xirr| format!("{}%", xirr)).unwrap_or_default(),
+            twr: twr.map(|twr| format!("{}%", twr)).unwrap_or_default(),
         });
 
         if inactive {
@@ -277,11 +307,7 @@ impl <'a> PortfolioPerformanceAnalyser<'a> {
     }
 
     fn process_deposits_and_withdrawals(&mut self, statement: &BrokerStatement) -> EmptyResult {
-        for mut cash_flow in statement.cash_flows.iter().cloned() {
-            if cash_flow.cash.is_positive() {
-                cash_flow.cash.amount += statement.broker.get_deposit_commission(cash_flow)?;
-            }
-
+        for cash_flow in &statement.cash_flows {
             let amount = self.converter.convert_to(cash_flow.date, cash_flow.cash, self.currency)?;
 
             trace!("* {} {}: {}", if amount.is_sign_positive() {
@@ -291,6 +317,15 @@ impl <'a> PortfolioPerformanceAnalyser<'a> {
             }, formatting::format_date(cash_flow.date), amount.normalize());
 
             self.transaction(cash_flow.date, amount);
+            self.external_cash_flows.push((cash_flow.date, amount));
+        }
+
+        // Deposit and withdrawal commissions are paid by the investor in addition to the
+        // transferred amount
+        for commission in &statement.deposit_commissions {
+            let amount = self.converter.convert_to(commission.date, -commission.amount, self.currency)?;
+            trace!("* Deposit commission {}: {}", formatting::format_date(commission.date), amount.normalize());
+            self.transaction(commission.date, amount);
         }
 
         Ok(())
@@ -327,11 +362,16 @@ impl <'a> PortfolioPerformanceAnalyser<'a> {
                 deposit_view.transaction(stock_sell.conclusion_date, commission);
 
                 deposit_view.last_sell_volume.replace(assets);
-                if stock_sell.emulation {
+                if stock_sell.kind == SaleKind::Emulation {
                     deposit_view.closed = false;
                 }
             }
 
+            // Type B IIA income is exempt from taxes
+            if portfolio.iia_type == Some(IiaType::B) {
+                continue;
+            }
+
             let local_profit = stock_sell.calculate(&self.country, self.converter)?.local_profit.amount;
 
             stock_taxes.entry(&stock_sell.symbol)
@@ -341,6 +381,27 @@ impl <'a> PortfolioPerformanceAnalyser<'a> {
             taxes.add_profit(stock_sell.execution_date, local_profit);
         }
 
+        // Cost basis of the fractional shares is carried over to the remaining lots, so the whole
+        // cash-in-lieu is a profit
+        for cash_in_lieu in &statement.cash_in_lieu {
+            let assets = self.converter.convert_to(cash_in_lieu.date, cash_in_lieu.amount, self.currency)?;
+            self.get_deposit_view(&cash_in_lieu.symbol).transaction(cash_in_lieu.date, -assets);
+
+            // Type B IIA income is exempt from taxes
+            if portfolio.iia_type == Some(IiaType::B) {
+                continue;
+            }
+
+            let local_profit = self.converter.convert_to(
+                cash_in_lieu.date, cash_in_lieu.amount, self.country.currency)?;
+
+            stock_taxes.entry(&cash_in_lieu.symbol)
+                .or_insert_with(|| NetTaxCalculator::new(self.country, portfolio.tax_payment_day))
+                .add_profit(cash_in_lieu.date, local_profit);
+
+            taxes.add_profit(cash_in_lieu.date, local_profit);
+        }
+
         for (&symbol, symbol_taxes) in stock_taxes.iter() {
             for (&tax_payment_date, &tax_to_pay) in symbol_taxes.get_taxes().iter() {
                 if let Some(deposit_amount) = self.map_tax_to_deposit_amount(tax_payment_date, tax_to_pay)? {
@@ -388,6 +449,31 @@ impl <'a> PortfolioPerformanceAnalyser<'a> {
         Ok(())
     }
 
+    fn process_coupons(&mut self, statement: &BrokerStatement, portfolio: &PortfolioConfig) -> EmptyResult {
+        for coupon in &statement.coupons {
+            let profit = coupon.amount.sub(coupon.paid_tax).map_err(|e| format!(
+                "{} coupon: The tax is paid in currency different from the coupon currency: {}",
+                coupon.symbol, e))?;
+
+            let profit = self.converter.convert_to(coupon.date, profit, self.currency)?;
+            self.get_deposit_view(&coupon.symbol).transaction(coupon.date, -profit);
+
+            let tax_to_pay = coupon.tax_to_pay(&self.country, self.converter)?;
+            let tax_payment_date = portfolio.tax_payment_day.get(coupon.date);
+
+            if let Some(deposit_amount) = self.map_tax_to_deposit_amount(tax_payment_date, tax_to_pay)? {
+                trace!("* {} {} coupon {} tax: {}",
+                       coupon.symbol, formatting::format_date(coupon.date),
+                       formatting::format_date(tax_payment_date), deposit_amount);
+
+                self.get_deposit_view(&coupon.symbol).transaction(tax_payment_date, deposit_amount);
+                self.transaction(tax_payment_date, deposit_amount);
+            }
+        }
+
+        Ok(())
+    }
+
     fn process_interest(&mut self, statement: &BrokerStatement, portfolio: &PortfolioConfig) -> EmptyResult {
         for interest in &statement.idle_cash_interest {
             let tax_to_pay = interest.tax_to_pay(&self.country, self.converter)?;
@@ -405,9 +491,28 @@ impl <'a> PortfolioPerformanceAnalyser<'a> {
         Ok(())
     }
 
-    fn process_tax_deductions(&mut self, portfolio: &PortfolioConfig) -> EmptyResult {
-        for &(date, amount) in &portfolio.tax_deductions {
-            let amount = self.converter.convert(self.country.currency, self.currency, date, amount)?;
+    fn process_tax_deductions(&mut self, portfolio: &PortfolioConfig, statement: &BrokerStatement) -> EmptyResult {
+        // Manually specified tax deductions override the calculated ones
+        let mut tax_deductions = portfolio.tax_deductions.clone();
+
+        if tax_deductions.is_empty() && portfolio.iia_type == Some(IiaType::A) {
+            let mut contributions = Vec::new();
+
+            for cash_flow in &statement.cash_flows {
+                if cash_flow.cash.is_positive() {
+                    let amount = self.converter.convert_to(
+                        cash_flow.date, cash_flow.cash, self.country.currency)?;
+                    contributions.push((cash_flow.date, amount));
+                }
+            }
+
+            tax_deductions = taxes::calculate_iia_tax_deductions(&self.country, &contributions);
+        }
+
+        for (date, amount) in tax_deductions {
+            // Deductions that will be received in the future are converted at the current rate
+            let conversion_date = std::cmp::min(date, util::today());
+            let amount = self.converter.convert(self.country.currency, self.currency, conversion_date, amount)?;
             trace!("* Tax deduction {}: {}", formatting::format_date(date), -amount);
             self.transaction(date, -amount);
         }
@@ -415,6 +520,41 @@ impl <'a> PortfolioPerformanceAnalyser<'a> {
         Ok(())
     }
 
+    fn process_valuations(&mut self, portfolio: &PortfolioConfig) -> EmptyResult {
+        let mut valuations = BTreeMap::new();
+
+        if let Some(ref currency) = portfolio.currency {
+            for &(date, value) in &portfolio.valuations {
+                let value = self.converter.convert(currency, self.currency, date, value)?;
+                valuations.insert(date, value);
+            }
+        }
+
+        self.valuations.push(valuations);
+        Ok(())
+    }
+
+    fn calculate_twr(&self) -> Option<Decimal> {
+        // Valuations of several portfolios can be summed up only if they are made on the same dates
+        let first = self.valuations.first()?;
+        if first.is_empty() || self.valuations.iter().any(|other| !other.keys().eq(first.keys())) {
+            return None;
+        }
+
+        let today = util::today();
+        let mut valuations: BTreeMap<Date, Decimal> = BTreeMap::new();
+
+        for portfolio_valuations in &self.valuations {
+            for (&date, &value) in portfolio_valuations.range(..today) {
+                *valuations.entry(date).or_insert(dec!(0)) += value;
+            }
+        }
+        valuations.insert(today, self.current_assets);
+
+        let valuations: Vec<(Date, Decimal)> = valuations.into_iter().collect();
+        twr::calculate(&valuations, &self.external_cash_flows)
+    }
+
     fn get_deposit_view(&mut self, symbol: &str) -> &mut StockDepositView {
         self.instruments.as_mut().unwrap()
             .entry(symbol.to_owned())
@@ -479,6 +619,11 @@ impl StockDepositView {
     }
 }
 
+// Converts deposit transactions to investor's cash flows
+fn get_cash_flows(transactions: &[Transaction]) -> Vec<(Date, Decimal)> {
+    transactions.iter().map(|transaction| (transaction.date, -transaction.amount)).collect()
+}
+
 fn compare_to_bank_deposit(
     transactions: &[Transaction], interest_periods: &[InterestPeriod], current_assets: Decimal
 ) -> GenericResult<(Decimal, Decimal)> {
@@ -568,8 +713,33 @@ fn get_total_activity_duration(periods: &[InterestPeriod]) -> i64 {
 
 #[cfg(test)]
 mod tests {
+    use crate::localities;
     use super::*;
 
+    #[test]
+    fn twr() {
+        let converter = CurrencyConverter::mock(dec!(1), &[]);
+        let mut analyser = PortfolioPerformanceAnalyser::new(
+            localities::russia(), "RUB", &converter, false);
+
+        // Two portfolios which have grown by 10% in January and by 10% since February with a
+        // deposit at the end of January
+        analyser.valuations = vec![
+            btreemap!{date!(1, 1, 2020) => dec!(400), date!(1, 2, 2020) => dec!(900)},
+            btreemap!{date!(1, 1, 2020) => dec!(600), date!(1, 2, 2020) => dec!(1200)},
+        ];
+        analyser.external_cash_flows = vec![
+            (date!(1, 1, 2020), dec!(1000)),
+            (date!(1, 2, 2020), dec!(1000)),
+        ];
+        analyser.current_assets = dec!(2310);
+        assert_eq!(analyser.calculate_twr(), Some(dec!(21)));
+
+        // Valuations of different dates can't be summed up
+        analyser.valuations.push(btreemap!{date!(2, 1, 2020) => dec!(100)});
+        assert_eq!(analyser.calculate_twr(), None);
+    }
+
     #[test]
     fn real_joint_deposits() {
         let compare = |transactions: &[Transaction], interest_periods: &[InterestPeriod], current_assets: Decimal| {
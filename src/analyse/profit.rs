@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+use crate::broker_statement::{Dividend, ForexTrade};
+use crate::core::GenericResult;
+use crate::currency::{Cash, CashAssets, MultiCurrencyCashAccount};
+use crate::currency::converter::CurrencyConverter;
+use crate::types::Decimal;
+
+/// Absolute portfolio profit (current value minus net contributions) in the specified currency.
+///
+/// The profit is attributed to the following sources:
+/// * Dividends - net dividend income converted at payment date.
+/// * Price appreciation - the remaining profit calculated in the original currency of the assets
+///   and converted at the current rate.
+/// * FX - the rest of the profit which comes from revaluation of the contributions and dividends
+///   due to currency rate changes (including gains/losses from currency conversions).
+///
+/// Currency conversions move the investments from one currency to another, so they are taken into
+/// account to not attribute revaluation of the converted assets to price appreciation.
+#[derive(Debug)]
+pub struct ProfitBreakdown {
+    pub value: Decimal,
+    pub contributions: Decimal,
+    pub profit: Decimal,
+
+    pub price_appreciation: Decimal,
+    pub dividends: Decimal,
+    pub fx: Decimal,
+}
+
+impl ProfitBreakdown {
+    pub fn calculate(
+        value: &MultiCurrencyCashAccount, cash_flows: &[CashAssets], dividends: &[Dividend],
+        forex_trades: &[ForexTrade], currency: &str, converter: &CurrencyConverter,
+    ) -> GenericResult<ProfitBreakdown> {
+        // Profit of the assets in their original currency
+        let mut native_profit: HashMap<&str, Decimal> = HashMap::new();
+
+        let mut total_value = dec!(0);
+        for assets in value.iter() {
+            *native_profit.entry(assets.currency).or_default() += assets.amount;
+            total_value += converter.real_time_convert_to(assets, currency)?;
+        }
+
+        let mut contributions = dec!(0);
+        for cash_flow in cash_flows {
+            let cash = cash_flow.cash;
+            *native_profit.entry(cash.currency).or_default() -= cash.amount;
+            contributions += converter.convert_to(cash_flow.date, cash, currency)?;
+        }
+
+        for trade in forex_trades {
+            *native_profit.entry(trade.from.currency).or_default() += trade.from.amount;
+            *native_profit.entry(trade.to.currency).or_default() -= trade.to.amount;
+            *native_profit.entry(trade.commission.currency).or_default() += trade.commission.amount;
+        }
+
+        let mut dividend_income = dec!(0);
+        for dividend in dividends {
+            let income = dividend.amount.sub(dividend.paid_tax).map_err(|e| format!(
+                "{}: {}", dividend.description(), e))?;
+
+            *native_profit.entry(income.currency).or_default() -= income.amount;
+            dividend_income += converter.convert_to(dividend.date, income, currency)?;
+        }
+
+        let mut price_appreciation = dec!(0);
+        for (&assets_currency, &profit) in &native_profit {
+            price_appreciation += converter.real_time_convert_to(
+                Cash::new(assets_currency, profit), currency)?;
+        }
+
+        let profit = total_value - contributions;
+
+        Ok(ProfitBreakdown {
+            value: total_value,
+            contributions,
+            profit,
+
+            price_appreciation,
+            dividends: dividend_income,
+            fx: profit - price_appreciation - dividend_income,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profit_attribution() {
+        let converter = CurrencyConverter::mock(dec!(60), &[(date!(1, 1, 2020), dec!(70))]);
+        let currency = "RUB";
+
+        // Deposited $1000 when USD/RUB was 60, bought stocks that have grown by $150 and received
+        // $50 of dividends (with $5 of withheld tax) when USD/RUB was 70.
+        let cash_flows = vec![CashAssets::new(date!(1, 6, 2019), "USD", dec!(1000))];
+        let dividends = vec![Dividend {
+            date: date!(1, 3, 2020),
+            issuer: s!("AAA"),
+            amount: Cash::new("USD", dec!(50)),
+            paid_tax: Cash::new("USD", dec!(5)),
+        }];
+
+        let mut value = MultiCurrencyCashAccount::new();
+        value.deposit(Cash::new("USD", dec!(1195)));
+
+        let profit = ProfitBreakdown::calculate(
+            &value, &cash_flows, &dividends, &[], currency, &converter).unwrap();
+
+        assert_eq!(profit.value, dec!(83650));
+        assert_eq!(profit.contributions, dec!(60000));
+        assert_eq!(profit.profit, dec!(23650));
+
+        assert_eq!(profit.price_appreciation, dec!(10500));
+        assert_eq!(profit.dividends, dec!(3150));
+        assert_eq!(profit.fx, dec!(10000));
+    }
+
+    #[test]
+    fn converted_contributions() {
+        let converter = CurrencyConverter::mock(dec!(60), &[(date!(1, 1, 2020), dec!(70))]);
+        let currency = "RUB";
+
+        // Deposited 60000 RUB and converted them to $1000 when USD/RUB was 60, then bought stocks
+        // that have grown by $150 when USD/RUB became 70.
+        let date = date!(1, 6, 2019);
+        let cash_flows = vec![CashAssets::new(date, currency, dec!(60000))];
+        let forex_trades = vec![ForexTrade {
+            from: Cash::new(currency, dec!(60000)),
+            to: Cash::new("USD", dec!(1000)),
+            commission: Cash::new(currency, dec!(0)),
+            conclusion_date: date,
+        }];
+
+        let mut value = MultiCurrencyCashAccount::new();
+        value.deposit(Cash::new("USD", dec!(1150)));
+
+        let profit = ProfitBreakdown::calculate(
+            &value, &cash_flows, &[], &forex_trades, currency, &converter).unwrap();
+
+        assert_eq!(profit.value, dec!(80500));
+        assert_eq!(profit.contributions, dec!(60000));
+        assert_eq!(profit.profit, dec!(20500));
+
+        assert_eq!(profit.price_appreciation, dec!(10500));
+        assert_eq!(profit.dividends, dec!(0));
+        assert_eq!(profit.fx, dec!(10000));
+    }
+}
\ No newline at end of file
@@ -1,6 +1,6 @@
 use static_table_derive::StaticTable;
 
-use crate::broker_statement::{BrokerStatement, StockSell};
+use crate::broker_statement::{BrokerStatement, SaleKind, StockSell, TaxLotMethod};
 use crate::commissions::CommissionCalc;
 use crate::config::PortfolioConfig;
 use crate::core::EmptyResult;
@@ -13,7 +13,7 @@ use crate::util;
 
 pub fn simulate_sell(
     portfolio: &PortfolioConfig, mut statement: BrokerStatement, converter: &CurrencyConverter,
-    quotes: &Quotes, positions: &[(String, Option<u32>)],
+    quotes: &Quotes, positions: &[(String, Option<u32>)], method: TaxLotMethod,
 ) -> EmptyResult {
     let mut commission_calc = CommissionCalc::new(statement.broker.commission_spec.clone());
 
@@ -37,11 +37,11 @@ pub fn simulate_sell(
         statement.emulate_sell(&symbol, quantity, quotes.get(&symbol)?, &mut commission_calc)?;
     }
 
-    statement.process_trades()?;
+    statement.process_trades(method)?;
     let additional_commissions = statement.emulate_commissions(commission_calc);
 
     let stock_sells = statement.stock_sells.iter()
-        .filter(|stock_sell| stock_sell.emulation)
+        .filter(|stock_sell| stock_sell.kind == SaleKind::Emulation)
         .cloned().collect::<Vec<_>>();
     assert_eq!(stock_sells.len(), positions.len());
 
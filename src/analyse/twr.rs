@@ -0,0 +1,67 @@
+use num_traits::Zero;
+
+use crate::types::{Date, Decimal};
+use crate::util;
+
+/// Calculates time-weighted rate of return in percents for the whole period covered by the
+/// specified portfolio valuations.
+///
+/// Unlike XIRR, which measures the return on the invested money and so depends on timing and size
+/// of deposits and withdrawals, time-weighted return eliminates the effect of external cash flows:
+/// the period is split into sub-periods at valuation dates and their returns are chained. It makes
+/// the result comparable with benchmark indices.
+///
+/// Valuations must include cash flows of the same day, and to get an exact result there must be a
+/// valuation at each cash flow date. Cash flows are positive for deposits and negative for
+/// withdrawals. Returns None if the return can't be calculated.
+pub fn calculate(valuations: &[(Date, Decimal)], cash_flows: &[(Date, Decimal)]) -> Option<Decimal> {
+    let mut valuations = valuations.to_vec();
+    valuations.sort_by_key(|&(date, _)| date);
+
+    if valuations.len() < 2 {
+        return None;
+    }
+
+    let mut growth = dec!(1);
+
+    for period in valuations.windows(2) {
+        let (start_date, start_value) = period[0];
+        let (end_date, end_value) = period[1];
+
+        if !start_value.is_sign_positive() || start_value.is_zero() {
+            return None;
+        }
+
+        let period_cash_flows: Decimal = cash_flows.iter()
+            .filter(|&&(date, _)| start_date < date && date <= end_date)
+            .map(|&(_, amount)| amount)
+            .sum();
+
+        growth *= (end_value - period_cash_flows) / start_value;
+    }
+
+    Some(util::round((growth - dec!(1)) * dec!(100), 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn twr() {
+        // Portfolio has grown by 10% in January and by 10% in February with a deposit at the end
+        // of January.
+        let valuations = [
+            (date!(1, 1, 2020), dec!(1000)),
+            (date!(1, 2, 2020), dec!(2100)),
+            (date!(1, 3, 2020), dec!(2310)),
+        ];
+        let cash_flows = [
+            (date!(1, 1, 2020), dec!(1000)),
+            (date!(1, 2, 2020), dec!(1000)),
+        ];
+
+        assert_eq!(calculate(&valuations, &cash_flows), Some(dec!(21)));
+        assert_eq!(calculate(&valuations[..1], &cash_flows), None);
+    }
+}
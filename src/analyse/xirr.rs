@@ -0,0 +1,83 @@
+use num_traits::{FromPrimitive, ToPrimitive};
+
+use crate::types::{Date, Decimal};
+use crate::util;
+
+const MAX_ITERATIONS: usize = 100;
+const PRECISION: f64 = 1e-9;
+
+/// Calculates money-weighted rate of return (XIRR) in percents for the specified cash flows where
+/// investments are negative and returns are positive. Returns None if the rate can't be calculated.
+pub fn calculate(cash_flows: &[(Date, Decimal)]) -> Option<Decimal> {
+    if cash_flows.len() < 2 {
+        return None;
+    }
+
+    let start_date = cash_flows.iter().map(|&(date, _)| date).min().unwrap();
+    let mut flows = Vec::with_capacity(cash_flows.len());
+
+    for &(date, amount) in cash_flows {
+        let years = (date - start_date).num_days() as f64 / 365.0;
+        flows.push((years, amount.to_f64()?));
+    }
+
+    if !flows.iter().any(|&(_, amount)| amount > 0.0) || !flows.iter().any(|&(_, amount)| amount < 0.0) {
+        return None;
+    }
+
+    // Newton's method
+    let mut rate = 0.1;
+
+    for _ in 0..MAX_ITERATIONS {
+        let mut value = 0.0;
+        let mut derivative = 0.0;
+
+        for &(years, amount) in &flows {
+            let discount = (1.0 + rate).powf(years);
+            value += amount / discount;
+            derivative -= years * amount / (discount * (1.0 + rate));
+        }
+
+        if derivative == 0.0 || !derivative.is_finite() {
+            return None;
+        }
+
+        let next_rate = rate - value / derivative;
+        if !next_rate.is_finite() || next_rate <= -1.0 {
+            return None;
+        }
+
+        if (next_rate - rate).abs() < PRECISION {
+            return Decimal::from_f64(next_rate * 100.0).map(|rate| util::round(rate, 2));
+        }
+
+        rate = next_rate;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xirr() {
+        assert_eq!(calculate(&[
+            (date!(1, 1, 2019), dec!(-1000)),
+            (date!(1, 1, 2020), dec!(1100)),
+        ]), Some(dec!(10)));
+
+        assert_eq!(calculate(&[
+            (date!(1, 1, 2019), dec!(-1000)),
+            (date!(1, 1, 2020), dec!(-1000)),
+            (date!(1, 1, 2021), dec!(2000)),
+        ]), Some(dec!(0)));
+
+        assert_eq!(calculate(&[(date!(1, 1, 2019), dec!(-1000))]), None);
+        assert_eq!(calculate(&[
+            (date!(1, 1, 2019), dec!(-1000)),
+            (date!(1, 1, 2020), dec!(-100)),
+        ]), None);
+    }
+}
@@ -6,7 +6,9 @@ use std::str::FromStr;
 use clap::{App, Arg, AppSettings, SubCommand, ArgMatches};
 use log::{self, debug, error};
 
+use investments::broker_statement::TaxLotMethod;
 use investments::config::{Config, load_config};
+use investments::portfolio::PendingOrder;
 use investments::core::GenericResult;
 use investments::types::{Date, Decimal};
 use investments::util;
@@ -19,6 +21,7 @@ pub enum Action {
     SimulateSell {
         name: String,
         positions: Vec<(String, Option<u32>)>,
+        method: TaxLotMethod,
     },
 
     Sync(String),
@@ -32,8 +35,16 @@ pub enum Action {
     },
     Rebalance {
         name: String,
+        pending_orders: Vec<PendingOrder>,
         flat: bool,
+        force: bool,
     },
+    Invest {
+        name: String,
+        cash: Decimal,
+        flat: bool,
+    },
+    CheckDrift(String),
 
     TaxStatement {
         name: String,
@@ -68,6 +79,9 @@ pub fn initialize() -> (Action, Config) {
             .value_name("DURATION")
             .help("Quote cache expire time (in $number{m|h|d} format)")
             .takes_value(true))
+        .arg(Arg::with_name("offline")
+            .long("offline")
+            .help("Use only cached currency rates and quotes"))
         .arg(Arg::with_name("verbose")
             .short("v")
             .long("verbose")
@@ -116,9 +130,36 @@ pub fn initialize() -> (Action, Config) {
                 .short("f")
                 .long("flat")
                 .help("Flat view"))
+            .arg(Arg::with_name("force")
+                .long("force")
+                .help("Rebalance the portfolio even if rebalancing is not due yet"))
+            .arg(Arg::with_name("pending")
+                .short("p")
+                .long("pending")
+                .value_name("ORDER")
+                .multiple(true)
+                .number_of_values(1)
+                .help("Pending order which should be treated as already executed (in {buy|sell}:$shares:$symbol format)"))
+            .arg(portfolio::arg()))
+        .subcommand(SubCommand::with_name("invest")
+            .about("Plan investment of new cash into the portfolio without selling any assets")
+            .arg(Arg::with_name("flat")
+                .short("f")
+                .long("flat")
+                .help("Flat view"))
+            .arg(portfolio::arg())
+            .arg(cash_to_invest::arg()))
+        .subcommand(SubCommand::with_name("check-drift")
+            .about("Show assets which weight has drifted beyond the rebalance threshold")
+            .long_about(concat!(
+                "\nPrints nothing if all assets are within the configured rebalance threshold, so ",
+                "can be used in cron mode."))
             .arg(portfolio::arg()))
         .subcommand(SubCommand::with_name("simulate-sell")
             .about("Simulates stock selling (calculates revenue, profit and taxes)")
+            .arg(Arg::with_name("lifo")
+                .long("lifo")
+                .help("Sell the most recently bought lots first (for what-if analysis: taxes use FIFO)"))
             .arg(portfolio::arg())
             .arg(Arg::with_name("POSITIONS")
                 .min_values(2)
@@ -206,6 +247,10 @@ fn parse_arguments(config: &mut Config, matches: &ArgMatches) -> GenericResult<A
             "Invalid cache expire time: {:?}", expire_time))?;
     };
 
+    if matches.is_present("offline") {
+        config.offline = true;
+    }
+
     let (command, matches) = matches.subcommand();
     let matches = matches.unwrap();
 
@@ -252,10 +297,28 @@ fn parse_arguments(config: &mut Config, matches: &ArgMatches) -> GenericResult<A
             name: portfolio_name,
             flat: matches.is_present("flat"),
         },
-        "rebalance" => Action::Rebalance {
+        "rebalance" => {
+            let mut pending_orders = Vec::new();
+
+            if let Some(orders) = matches.values_of("pending") {
+                for order in orders {
+                    pending_orders.push(order.parse()?);
+                }
+            }
+
+            Action::Rebalance {
+                name: portfolio_name,
+                pending_orders: pending_orders,
+                flat: matches.is_present("flat"),
+                force: matches.is_present("force"),
+            }
+        },
+        "invest" => Action::Invest {
             name: portfolio_name,
+            cash: Decimal::from_str(&cash_to_invest::get(matches)).map_err(|_| "Invalid cash value")?,
             flat: matches.is_present("flat"),
         },
+        "check-drift" => Action::CheckDrift(portfolio_name),
         "simulate-sell" => {
             let mut positions = Vec::new();
             let mut positions_spec_iter = matches.values_of("POSITIONS").unwrap();
@@ -286,6 +349,11 @@ fn parse_arguments(config: &mut Config, matches: &ArgMatches) -> GenericResult<A
             Action::SimulateSell {
                 name: portfolio_name,
                 positions: positions,
+                method: if matches.is_present("lifo") {
+                    TaxLotMethod::Lifo
+                } else {
+                    TaxLotMethod::Fifo
+                },
             }
         }
 
@@ -343,4 +411,5 @@ arg!(portfolio, "PORTFOLIO", "Portfolio name");
 arg!(portfolio_all, "PORTFOLIO", r"Portfolio name (use 'all' to show an aggregated result for all portfolios)");
 arg!(shares, "SHARES", "Shares");
 arg!(symbol, "SYMBOL", "Symbol");
-arg!(cash_assets, "CASH_ASSETS", "Current cash assets");
\ No newline at end of file
+arg!(cash_assets, "CASH_ASSETS", "Current cash assets");
+arg!(cash_to_invest, "CASH", "Cash to invest");
\ No newline at end of file
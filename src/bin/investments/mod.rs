@@ -38,8 +38,8 @@ fn run(action: Action, config: Config) -> EmptyResult {
     match action {
         Action::Analyse {name, show_closed_positions} => analyse::analyse(
             &config, &name, show_closed_positions)?,
-        Action::SimulateSell {name, positions} => analyse::simulate_sell(
-            &config, &name, &positions)?,
+        Action::SimulateSell {name, positions, method} => analyse::simulate_sell(
+            &config, &name, &positions, method)?,
 
         Action::Sync(name) => portfolio::sync(&config, &name)?,
         Action::Buy(name, shares, symbol, cash_assets) =>
@@ -50,7 +50,10 @@ fn run(action: Action, config: Config) -> EmptyResult {
             portfolio::set_cash_assets(&config, &name, cash_assets)?,
 
         Action::Show {name, flat} => portfolio::show(&config, &name, flat)?,
-        Action::Rebalance {name, flat} => portfolio::rebalance(&config, &name, flat)?,
+        Action::Rebalance {name, pending_orders, flat, force} => portfolio::rebalance(
+            &config, &name, &pending_orders, flat, force)?,
+        Action::Invest {name, cash, flat} => portfolio::invest(&config, &name, cash, flat)?,
+        Action::CheckDrift(name) => portfolio::check_drift(&config, &name)?,
 
         Action::TaxStatement {name, year, tax_statement_path} =>
             tax_statement::generate_tax_statement(
@@ -1,3 +1,4 @@
+use crate::broker_statement::bonds::{BondAmortization, Coupon};
 use crate::broker_statement::fees::Fee;
 use crate::broker_statement::xls::{XlsStatementParser, SectionParser};
 use crate::core::{EmptyResult, GenericResult};
@@ -44,6 +45,32 @@ impl CashFlowParser {
                 deposit_restrictions = DecimalRestrictions::StrictlyPositive;
                 parser.statement.cash_flows.push(CashAssets::new(date, currency, cash_flow.deposit));
             },
+            "Погашение купона" => {
+                deposit_restrictions = DecimalRestrictions::StrictlyPositive;
+                parser.statement.coupons.push(Coupon::new(
+                    date, &get_bond_symbol(operation, cash_flow)?, Cash::new(currency, cash_flow.deposit)));
+            },
+            "НДФЛ" => {
+                withdrawal_restrictions = DecimalRestrictions::StrictlyPositive;
+
+                // The broker withholds tax from coupons as a tax agent
+                let symbol = get_bond_symbol(operation, cash_flow)?;
+                let coupon = parser.statement.coupons.iter_mut()
+                    .find(|coupon| coupon.date == date && coupon.symbol == symbol)
+                    .ok_or_else(|| format!(
+                        "Unable to find origin coupon for {} tax from {}",
+                        symbol, formatting::format_date(date)))?;
+
+                coupon.paid_tax.add_assign(Cash::new(currency, cash_flow.withdrawal))?;
+            },
+            "Частичное погашение номинала" => {
+                deposit_restrictions = DecimalRestrictions::StrictlyPositive;
+                parser.statement.bond_amortizations.push(BondAmortization {
+                    date,
+                    symbol: get_bond_symbol(operation, cash_flow)?,
+                    amount: Cash::new(currency, cash_flow.deposit),
+                });
+            },
             "Покупка/Продажа" => {
                 deposit_restrictions = DecimalRestrictions::PositiveOrZero;
                 withdrawal_restrictions = DecimalRestrictions::PositiveOrZero;
@@ -58,9 +85,13 @@ impl CashFlowParser {
                     date,
                     amount: Cash::new(currency, -cash_flow.withdrawal),
                     description: Some(description),
+                    tax_deductible: false,
                 });
             },
-            _ => return Err!("Unsupported cash flow operation: {:?}", cash_flow.operation),
+            _ => {
+                parser.statement.add_unsupported_operation("cash flow operation", operation);
+                return Ok(());
+            },
         };
 
         for &(name, value, restrictions) in &[
@@ -78,6 +109,13 @@ impl CashFlowParser {
     }
 }
 
+fn get_bond_symbol(operation: &str, cash_flow: &CashFlowRow) -> GenericResult<String> {
+    match cash_flow.note.as_ref().map(|note| note.trim()) {
+        Some(symbol) if !symbol.is_empty() => Ok(symbol.to_owned()),
+        _ => Err!("Unable to determine the bond for {:?} operation", operation),
+    }
+}
+
 #[derive(XlsTableRow)]
 struct CashFlowRow {
     #[column(name="Дата")]
@@ -99,7 +137,7 @@ struct CashFlowRow {
     #[column(name="Площадка")]
     _8: SkipCell,
     #[column(name="Примечание")]
-    _9: SkipCell,
+    note: Option<String>,
     #[column(name="Промежуточный клиринг (FORTS)")]
     _10: SkipCell,
 }
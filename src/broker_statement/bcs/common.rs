@@ -13,6 +13,21 @@ pub fn parse_short_date(date: &str) -> GenericResult<Date> {
     util::parse_date(date, "%d.%m.%y")
 }
 
+pub fn parse_period(value: &str) -> GenericResult<(Date, Date)> {
+    lazy_static! {
+        static ref PERIOD_REGEX: Regex = Regex::new(
+            r"^с (?P<start>\d{2}\.\d{2}\.\d{4}) по (?P<end>\d{2}\.\d{2}\.\d{4})$").unwrap();
+    }
+
+    let captures = PERIOD_REGEX.captures(value).ok_or_else(|| format!(
+        "Invalid period: {:?}", value))?;
+
+    util::parse_period(
+        parse_date(captures.name("start").unwrap().as_str())?,
+        parse_date(captures.name("end").unwrap().as_str())?,
+    )
+}
+
 pub fn map_currency(name: &str) -> Option<&'static str> {
     Some(match name {
         "Рубль" => "RUB",
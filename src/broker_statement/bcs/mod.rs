@@ -1,6 +1,6 @@
 mod assets;
 mod cash_flow;
-mod common;
+pub mod common;
 mod period;
 mod trades;
 
@@ -1,13 +1,8 @@
-use lazy_static::lazy_static;
-use regex::Regex;
-
 use crate::broker_statement::xls::{XlsStatementParser, SectionParser};
-use crate::core::{EmptyResult, GenericResult};
-use crate::types::Date;
-use crate::util;
+use crate::core::EmptyResult;
 use crate::xls;
 
-use super::common::parse_date;
+use super::common::parse_period;
 
 pub struct PeriodParser {
 }
@@ -21,19 +16,4 @@ impl SectionParser for PeriodParser {
         parser.statement.set_period(period)?;
         Ok(())
     }
-}
-
-fn parse_period(value: &str) -> GenericResult<(Date, Date)> {
-    lazy_static! {
-        static ref PERIOD_REGEX: Regex = Regex::new(
-            r"^с (?P<start>\d{2}\.\d{2}\.\d{4}) по (?P<end>\d{2}\.\d{2}\.\d{4})$").unwrap();
-    }
-
-    let captures = PERIOD_REGEX.captures(value).ok_or_else(|| format!(
-        "Invalid period: {:?}", value))?;
-
-    util::parse_period(
-        parse_date(captures.name("start").unwrap().as_str())?,
-        parse_date(captures.name("end").unwrap().as_str())?,
-    )
 }
\ No newline at end of file
@@ -1,7 +1,7 @@
 use num_traits::cast::ToPrimitive;
 
 use crate::broker_statement::partial::PartialBrokerStatement;
-use crate::broker_statement::trades::{StockBuy, StockSell};
+use crate::broker_statement::trades::{SaleKind, StockBuy, StockSell};
 use crate::broker_statement::xls::{XlsStatementParser, SectionParser};
 use crate::core::{EmptyResult, GenericResult};
 use crate::currency::Cash;
@@ -119,7 +119,7 @@ impl TradesParser {
                 symbol, quantity, price, volume, commission, conclusion_date, execution_date));
         } else {
             statement.stock_sells.push(StockSell::new(
-                symbol, quantity, price, volume, commission, conclusion_date, execution_date, false));
+                symbol, quantity, price, volume, commission, conclusion_date, execution_date, SaleKind::Close));
         }
 
         Ok(())
@@ -0,0 +1,44 @@
+use crate::core::GenericResult;
+use crate::currency::Cash;
+use crate::currency::converter::CurrencyConverter;
+use crate::localities::Country;
+use crate::types::{Date, Decimal};
+
+#[derive(Debug)]
+pub struct Coupon {
+    pub date: Date,
+    pub symbol: String,
+    pub amount: Cash, // Gross coupon amount
+    pub paid_tax: Cash, // Tax withheld by the broker as a tax agent
+
+    // Accrued interest paid on bond purchase which is deducted from the coupon's taxable income
+    pub paid_accrued_interest: Cash,
+}
+
+impl Coupon {
+    pub fn new(date: Date, symbol: &str, amount: Cash) -> Coupon {
+        Coupon {
+            date, symbol: symbol.to_owned(), amount,
+            paid_tax: Cash::new(amount.currency, dec!(0)),
+            paid_accrued_interest: Cash::new(amount.currency, dec!(0)),
+        }
+    }
+
+    pub fn taxable_amount(&self) -> Cash {
+        self.amount.sub(self.paid_accrued_interest).unwrap()
+    }
+
+    pub fn tax_to_pay(&self, country: &Country, converter: &CurrencyConverter) -> GenericResult<Decimal> {
+        let amount = converter.convert_to_rounding(self.date, self.taxable_amount(), country.currency)?;
+        let paid_tax = converter.convert_to_rounding(self.date, self.paid_tax, country.currency)?;
+        Ok(country.tax_to_pay(amount, Some(paid_tax)))
+    }
+}
+
+/// Partial repayment of bond principal. It's not an income and reduces the bond's cost basis.
+#[derive(Debug)]
+pub struct BondAmortization {
+    pub date: Date,
+    pub symbol: String,
+    pub amount: Cash,
+}
@@ -0,0 +1,91 @@
+use num_traits::{ToPrimitive, Zero};
+
+use crate::core::GenericResult;
+use crate::currency::Cash;
+use crate::formatting;
+use crate::types::{Date, Decimal};
+
+#[derive(Debug, Clone)]
+pub struct StockSplit {
+    pub date: Date,
+    pub symbol: String,
+
+    // The split turns `from` shares into `to` shares
+    pub from: u32,
+    pub to: u32,
+
+    pub price: Option<Cash>, // Post-split share price (used to calculate cash-in-lieu)
+    pub cash_in_lieu: Option<Cash>, // Cash-in-lieu paid by the broker if it's reported in the statement
+}
+
+/// Cash paid by the broker for the fractional shares
+#[derive(Debug)]
+pub struct CashInLieu {
+    pub date: Date,
+    pub symbol: String,
+    pub amount: Cash,
+}
+
+impl StockSplit {
+    /// Calculates lot quantities after the split. Brokers don't allow fractional shares, so the
+    /// fractional residual of the whole position is paid out as cash-in-lieu.
+    pub fn split_lots(&self, quantities: &[u32]) -> GenericResult<(Vec<u32>, Option<Cash>)> {
+        let ratio = Decimal::from(self.to) / Decimal::from(self.from);
+
+        let mut old_total = 0;
+        let mut new_total = 0;
+        let mut new_quantities = Vec::with_capacity(quantities.len());
+
+        // Distribute the shares so that the rounding is applied to the whole position instead of
+        // individual lots
+        for &quantity in quantities {
+            old_total += quantity;
+            let total = (Decimal::from(old_total) * ratio).floor().to_u32().unwrap();
+            new_quantities.push(total - new_total);
+            new_total = total;
+        }
+
+        let fraction = Decimal::from(old_total) * ratio - Decimal::from(new_total);
+        if fraction.is_zero() {
+            return Ok((new_quantities, None));
+        }
+
+        let cash_in_lieu = match (self.cash_in_lieu, self.price) {
+            (Some(cash_in_lieu), _) => cash_in_lieu,
+            (None, Some(price)) => price * fraction,
+            (None, None) => return Err!(
+                "Unable to calculate cash-in-lieu for {} stock split from {}: its price is unknown",
+                self.symbol, formatting::format_date(self.date)),
+        };
+
+        Ok((new_quantities, Some(cash_in_lieu)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cash_in_lieu() {
+        let mut split = StockSplit {
+            date: date!(1, 7, 2020),
+            symbol: s!("AAA"),
+            from: 2,
+            to: 3,
+            price: Some(Cash::new("USD", dec!(10))),
+            cash_in_lieu: None,
+        };
+
+        assert_eq!(split.split_lots(&[4, 6]).unwrap(), (vec![6, 9], None));
+        assert_eq!(split.split_lots(&[3, 4]).unwrap(),
+                   (vec![4, 6], Some(Cash::new("USD", dec!(5)))));
+
+        split.cash_in_lieu = Some(Cash::new("USD", dec!(4.9)));
+        assert_eq!(split.split_lots(&[3]).unwrap(), (vec![4], Some(Cash::new("USD", dec!(4.9)))));
+
+        split.cash_in_lieu = None;
+        split.price = None;
+        assert!(split.split_lots(&[3]).is_err());
+    }
+}
@@ -1,10 +1,10 @@
 use std::collections::HashMap;
 
 use crate::core::GenericResult;
-use crate::currency::Cash;
+use crate::currency::{self, Cash};
 use crate::currency::converter::CurrencyConverter;
 use crate::formatting;
-use crate::localities::Country;
+use crate::localities::{self, Country};
 use crate::types::{Date, Decimal};
 
 use super::payments::Payments;
@@ -30,6 +30,18 @@ impl Dividend {
         Ok(country.tax_to_pay(amount, Some(paid_tax)))
     }
 
+    // Tax which is withheld above the treaty rate can't be credited against the local tax and has
+    // to be reclaimed from the foreign tax authority
+    pub fn exceeds_treaty_tax_rate(&self) -> bool {
+        match localities::get_dividend_treaty_tax_rate(self.amount.currency) {
+            Some(tax_rate) => {
+                self.paid_tax.currency == self.amount.currency &&
+                    self.paid_tax.amount > currency::round(self.amount.amount * tax_rate)
+            },
+            None => false,
+        }
+    }
+
     pub fn description(&self) -> String {
         format!("{} dividend from {}", self.issuer, formatting::format_date(self.date))
     }
@@ -0,0 +1,227 @@
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::core::EmptyResult;
+use crate::currency::Cash;
+use crate::types::{Date, Decimal};
+
+use super::BrokerStatement;
+
+/// Exports transactions of broker statements in a normalized broker-independent CSV format.
+///
+/// All amounts including commissions are signed as changes of the account's cash balance: buys,
+/// taxes and commissions are negative, sells and income are positive.
+pub struct TransactionsExporter<W: Write> {
+    writer: csv::Writer<W>,
+}
+
+impl<W: Write> TransactionsExporter<W> {
+    pub fn new(writer: W) -> TransactionsExporter<W> {
+        TransactionsExporter {
+            writer: csv::Writer::from_writer(writer),
+        }
+    }
+
+    pub fn add(&mut self, statement: &BrokerStatement) -> EmptyResult {
+        let mut transactions = get_transactions(statement);
+        transactions.sort_by(|a, b| (a.date, a.type_, &a.symbol).cmp(&(b.date, b.type_, &b.symbol)));
+
+        for transaction in transactions {
+            self.writer.serialize(TransactionRow::new(transaction))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> EmptyResult {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+struct Transaction {
+    date: Date,
+    type_: &'static str,
+    symbol: Option<String>,
+    quantity: Option<u32>,
+    price: Option<Cash>,
+    amount: Cash,
+    commission: Option<Cash>,
+}
+
+impl Transaction {
+    fn new(date: Date, type_: &'static str, symbol: Option<&str>, amount: Cash) -> Transaction {
+        Transaction {
+            date, type_,
+            symbol: symbol.map(ToOwned::to_owned),
+            quantity: None,
+            price: None,
+            amount,
+            commission: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TransactionRow {
+    date: String,
+    #[serde(rename = "type")]
+    type_: &'static str,
+    symbol: Option<String>,
+    quantity: Option<u32>,
+    price: Option<Decimal>,
+    amount: Decimal,
+    currency: &'static str,
+    commission: Option<Decimal>,
+    commission_currency: Option<&'static str>,
+}
+
+impl TransactionRow {
+    fn new(transaction: Transaction) -> TransactionRow {
+        TransactionRow {
+            date: transaction.date.format("%Y-%m-%d").to_string(),
+            type_: transaction.type_,
+            symbol: transaction.symbol,
+            quantity: transaction.quantity,
+            price: transaction.price.map(|price| price.amount),
+            amount: transaction.amount.amount,
+            currency: transaction.amount.currency,
+            commission: transaction.commission.map(|commission| commission.amount),
+            commission_currency: transaction.commission.map(|commission| commission.currency),
+        }
+    }
+}
+
+fn get_transactions(statement: &BrokerStatement) -> Vec<Transaction> {
+    let mut transactions = Vec::new();
+
+    for cash_flow in &statement.cash_flows {
+        transactions.push(Transaction::new(cash_flow.date, "cash_flow", None, cash_flow.cash));
+    }
+
+    // Currency conversion is exported as a pair of transactions: sold and bought currency
+    for trade in &statement.forex_trades {
+        let mut transaction = Transaction::new(trade.conclusion_date, "forex", None, -trade.from);
+        transaction.commission = Some(-trade.commission);
+        transactions.push(transaction);
+        transactions.push(Transaction::new(trade.conclusion_date, "forex", None, trade.to));
+    }
+
+    for trade in &statement.stock_buys {
+        let mut transaction = Transaction::new(
+            trade.conclusion_date, "buy", Some(&trade.symbol), -trade.volume);
+        transaction.quantity = Some(trade.quantity);
+        transaction.price = Some(trade.price);
+        transaction.commission = Some(-trade.commission);
+        transactions.push(transaction);
+
+        if let Some(accrued_interest) = trade.accrued_interest {
+            transactions.push(Transaction::new(
+                trade.conclusion_date, "accrued_interest", Some(&trade.symbol), -accrued_interest));
+        }
+    }
+
+    for trade in &statement.stock_sells {
+        let mut transaction = Transaction::new(
+            trade.conclusion_date, "sell", Some(&trade.symbol), trade.volume);
+        transaction.quantity = Some(trade.quantity);
+        transaction.price = Some(trade.price);
+        transaction.commission = Some(-trade.commission);
+        transactions.push(transaction);
+
+        if let Some(accrued_interest) = trade.accrued_interest {
+            transactions.push(Transaction::new(
+                trade.conclusion_date, "accrued_interest", Some(&trade.symbol), accrued_interest));
+        }
+    }
+
+    for dividend in &statement.dividends {
+        transactions.push(Transaction::new(
+            dividend.date, "dividend", Some(&dividend.issuer), dividend.amount));
+
+        if !dividend.paid_tax.is_zero() {
+            transactions.push(Transaction::new(
+                dividend.date, "tax", Some(&dividend.issuer), -dividend.paid_tax));
+        }
+    }
+
+    for interest in &statement.idle_cash_interest {
+        transactions.push(Transaction::new(interest.date, "interest", None, interest.amount));
+    }
+
+    for coupon in &statement.coupons {
+        transactions.push(Transaction::new(coupon.date, "coupon", Some(&coupon.symbol), coupon.amount));
+
+        if !coupon.paid_tax.is_zero() {
+            transactions.push(Transaction::new(coupon.date, "tax", Some(&coupon.symbol), -coupon.paid_tax));
+        }
+    }
+
+    for amortization in &statement.bond_amortizations {
+        transactions.push(Transaction::new(
+            amortization.date, "amortization", Some(&amortization.symbol), amortization.amount));
+    }
+
+    for fee in &statement.fees {
+        transactions.push(Transaction::new(fee.date, "fee", None, fee.amount));
+    }
+
+    transactions
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::brokers::Broker;
+    use crate::broker_statement::partial::PartialBrokerStatement;
+    use crate::broker_statement::trades::{StockBuy, StockSell, SaleKind};
+    use crate::config::Config;
+    use crate::currency::CashAssets;
+
+    use super::*;
+
+    #[test]
+    fn export() {
+        let broker = Broker::Tinkoff.get_info(&Config::mock(), None).unwrap();
+        let price = Cash::new("USD", dec!(12.5));
+
+        let statement = |period: (Date, Date), starting_assets: bool| {
+            let mut statement = PartialBrokerStatement::new();
+            statement.set_period(period).unwrap();
+            statement.set_starting_assets(starting_assets).unwrap();
+            statement.cash_assets.deposit(Cash::new("USD", dec!(0)));
+
+            statement.cash_flows.push(CashAssets::new(date!(1, 2, 2020), "USD", dec!(1000)));
+            statement.stock_buys.push(StockBuy::new(
+                "AAA", 10, price, price * 10, Cash::new("RUB", dec!(30)),
+                date!(3, 2, 2020), date!(5, 2, 2020)));
+
+            let mut stock_sell = StockSell::new(
+                "BBB", 2, price, price * 2, Cash::new("USD", dec!(1)),
+                date!(3, 2, 2020), date!(5, 2, 2020), SaleKind::Close);
+            stock_sell.accrued_interest = Some(Cash::new("USD", dec!(0.5)));
+            statement.stock_sells.push(stock_sell);
+
+            statement
+        };
+
+        // The second statement overlaps the first one, so its operations must be deduplicated
+        let statement = BrokerStatement::merge_statements(broker, vec![
+            statement((date!(1, 2, 2020), date!(1, 3, 2020)), false),
+            statement((date!(1, 2, 2020), date!(1, 4, 2020)), true),
+        ]).unwrap();
+
+        let mut buffer = Vec::new();
+        let mut exporter = TransactionsExporter::new(&mut buffer);
+        exporter.add(&statement).unwrap();
+        exporter.finish().unwrap();
+
+        assert_eq!(String::from_utf8(buffer).unwrap(), concat!(
+            "date,type,symbol,quantity,price,amount,currency,commission,commission_currency\n",
+            "2020-02-01,cash_flow,,,,1000,USD,,\n",
+            "2020-02-03,accrued_interest,BBB,,,0.5,USD,,\n",
+            "2020-02-03,buy,AAA,10,12.5,-125,USD,-30,RUB\n",
+            "2020-02-03,sell,BBB,2,12.5,25,USD,-1,USD\n",
+        ));
+    }
+}
\ No newline at end of file
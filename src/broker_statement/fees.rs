@@ -6,4 +6,5 @@ pub struct Fee {
     pub date: Date,
     pub amount: Cash, // The amount is negative for commission and positive for refund
     pub description: Option<String>,
+    pub tax_deductible: bool, // Investment expense which reduces taxable income from trading
 }
\ No newline at end of file
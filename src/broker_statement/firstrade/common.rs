@@ -33,6 +33,10 @@ pub fn deserialize_decimal<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
     Ok(decimal.value)
 }
 
+pub fn deserialize_optional_decimal<'de, D>(deserializer: D) -> Result<Option<Decimal>, D::Error> where D: Deserializer<'de> {
+    deserialize_decimal(deserializer).map(Some)
+}
+
 pub fn validate_sub_account(name: &str) -> EmptyResult {
     match name {
         "CASH" => Ok(()),
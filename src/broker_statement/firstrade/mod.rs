@@ -13,6 +13,7 @@ use std::io::{Read, BufReader, BufRead, Seek, SeekFrom};
 #[cfg(test)] use crate::config::Config;
 use crate::core::GenericResult;
 #[cfg(test)] use crate::taxes::TaxRemapping;
+use crate::types::Decimal;
 
 #[cfg(test)] use super::{BrokerStatement};
 use super::{BrokerStatementReader, PartialBrokerStatement};
@@ -20,11 +21,15 @@ use super::{BrokerStatementReader, PartialBrokerStatement};
 use self::parser::OFX;
 
 pub struct StatementReader {
+    sweep_fund: Option<String>,
+    trade_volume_tolerance: Decimal,
 }
 
 impl StatementReader {
-    pub fn new() -> GenericResult<Box<dyn BrokerStatementReader>> {
-        Ok(Box::new(StatementReader{}))
+    pub fn new(
+        sweep_fund: Option<String>, trade_volume_tolerance: Decimal,
+    ) -> GenericResult<Box<dyn BrokerStatementReader>> {
+        Ok(Box::new(StatementReader {sweep_fund, trade_volume_tolerance}))
     }
 }
 
@@ -34,7 +39,7 @@ impl BrokerStatementReader for StatementReader {
     }
 
     fn read(&mut self, path: &str) -> GenericResult<PartialBrokerStatement> {
-        read_statement(path)?.parse()
+        read_statement(path)?.parse(self.sweep_fund.as_deref(), self.trade_volume_tolerance)
     }
 }
 
@@ -3,9 +3,11 @@ use serde::Deserialize;
 
 use crate::broker_statement::partial::PartialBrokerStatement;
 use crate::core::EmptyResult;
+use crate::currency::Cash;
+use crate::types::Decimal;
 use crate::util::{self, DecimalRestrictions};
 
-use super::common::{Ignore, validate_sub_account};
+use super::common::{Ignore, deserialize_decimal, validate_sub_account};
 use super::security_info::{SecurityInfo, SecurityId, SecurityType};
 
 #[derive(Deserialize)]
@@ -13,12 +15,20 @@ use super::security_info::{SecurityInfo, SecurityId, SecurityType};
 pub struct OpenPositions {
     #[serde(rename = "POSSTOCK")]
     stocks: Vec<OpenStockPosition>,
+    #[serde(rename = "POSOPT", default)]
+    options: Vec<OpenOptionPosition>,
 }
 
 impl OpenPositions {
-    pub fn parse(self, statement: &mut PartialBrokerStatement, securities: &SecurityInfo) -> EmptyResult {
+    pub fn parse(
+        self, statement: &mut PartialBrokerStatement, currency: &str, securities: &SecurityInfo,
+        sweep_fund: Option<&str>,
+    ) -> EmptyResult {
         for stock in self.stocks {
-            stock.open_position.parse(statement, securities)?;
+            stock.open_position.parse(statement, currency, securities, sweep_fund)?;
+        }
+        for option in self.options {
+            option.open_position.parse(statement, currency, securities, sweep_fund)?;
         }
         Ok(())
     }
@@ -31,6 +41,13 @@ pub struct OpenStockPosition {
     open_position: OpenPosition,
 }
 
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OpenOptionPosition {
+    #[serde(rename = "INVPOS")]
+    open_position: OpenPosition,
+}
+
 #[derive(Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct OpenPosition {
@@ -44,8 +61,8 @@ pub struct OpenPosition {
     units: String,
     #[serde(rename = "UNITPRICE")]
     _price: Ignore,
-    #[serde(rename = "MKTVAL")]
-    _volume: Ignore,
+    #[serde(rename = "MKTVAL", deserialize_with = "deserialize_decimal")]
+    value: Decimal,
     #[serde(rename = "DTPRICEASOF")]
     _price_date: Ignore,
     #[serde(rename = "MEMO")]
@@ -53,17 +70,28 @@ pub struct OpenPosition {
 }
 
 impl OpenPosition {
-    fn parse(self, statement: &mut PartialBrokerStatement, securities: &SecurityInfo) -> EmptyResult {
+    fn parse(
+        self, statement: &mut PartialBrokerStatement, currency: &str, securities: &SecurityInfo,
+        sweep_fund: Option<&str>,
+    ) -> EmptyResult {
         if self._type != "LONG" {
             return Err!("Unsupported {} open position type: {:?}", self.security_id, self._type);
         }
         validate_sub_account(&self.sub_account)?;
 
         let symbol = match securities.get(&self.security_id)? {
-            SecurityType::Stock(symbol) => symbol,
+            SecurityType::Stock(symbol) => symbol.clone(),
+            SecurityType::Option {contract, ..} => contract.symbol(),
             _ => return Err!("Got {} open position with an unexpected security type", self.security_id),
         };
 
+        if sweep_fund == Some(symbol.as_str()) {
+            let value = util::validate_named_decimal(
+                "sweep fund value", self.value, DecimalRestrictions::PositiveOrZero)?;
+            statement.cash_assets.deposit(Cash::new(currency, value));
+            return Ok(());
+        }
+
         let quantity = util::parse_decimal(&self.units, DecimalRestrictions::StrictlyPositive)
             .ok().and_then(|quantity| {
                 if quantity.trunc() == quantity {
@@ -73,7 +101,7 @@ impl OpenPosition {
                 }
             }).ok_or_else(|| format!("Invalid {} open positions quantity: {:?}", symbol, self.units))?;
 
-        if statement.open_positions.insert(symbol.to_owned(), quantity).is_some() {
+        if statement.open_positions.insert(symbol.clone(), quantity).is_some() {
             return Err!("Got a duplicated open position for {}", symbol);
         }
 
@@ -2,7 +2,7 @@ use serde::Deserialize;
 
 use crate::core::GenericResult;
 use crate::broker_statement::partial::PartialBrokerStatement;
-use crate::types::Date;
+use crate::types::{Date, Decimal};
 use crate::util;
 
 use super::balance::Balance;
@@ -60,7 +60,9 @@ struct Report {
 }
 
 impl OFX {
-    pub fn parse(self) -> GenericResult<PartialBrokerStatement> {
+    pub fn parse(
+        self, sweep_fund: Option<&str>, trade_volume_tolerance: Decimal,
+    ) -> GenericResult<PartialBrokerStatement> {
         let report = self.statement.response.report;
         let currency = report.currency;
         let transactions = report.transactions;
@@ -83,8 +85,8 @@ impl OFX {
         report.balance.parse(&mut statement, &currency)?;
 
         let securities = self.security_info.parse()?;
-        transactions.parse(&mut statement, &currency, &securities)?;
-        report.open_positions.parse(&mut statement, &securities)?;
+        transactions.parse(&mut statement, &currency, &securities, sweep_fund, trade_volume_tolerance)?;
+        report.open_positions.parse(&mut statement, &currency, &securities, sweep_fund)?;
 
         statement.validate()
     }
@@ -4,7 +4,12 @@ use std::collections::hash_map::Entry;
 
 use serde::Deserialize;
 
+use crate::broker_statement::options::{OptionContract, OptionType};
 use crate::core::{EmptyResult, GenericResult};
+use crate::types::{Date, Decimal};
+use crate::util::{self, DecimalRestrictions};
+
+use super::common::{deserialize_date, deserialize_decimal};
 
 pub struct SecurityInfo {
     info: HashMap<SecurityId, SecurityType>
@@ -13,6 +18,7 @@ pub struct SecurityInfo {
 pub enum SecurityType {
     Interest,
     Stock(String),
+    Option {contract: OptionContract, multiplier: Decimal},
 }
 
 impl SecurityInfo {
@@ -47,7 +53,9 @@ pub struct SecurityInfoSection {
 struct SecurityList {
     #[serde(rename = "STOCKINFO")]
     stock_info: Vec<StockInfo>,
-    #[serde(rename = "OTHERINFO")]
+    #[serde(rename = "OPTINFO", default)]
+    option_info: Vec<OptionInfo>,
+    #[serde(rename = "OTHERINFO", default)]
     other_info: Vec<OtherInfo>,
 }
 
@@ -61,6 +69,11 @@ impl SecurityInfoSection {
             securities.add(info.id, SecurityType::Stock(info.symbol))?;
         }
 
+        for option_info in all_info.option_info {
+            let (id, info) = option_info.parse(&securities)?;
+            securities.add(id, info)?;
+        }
+
         for other_info in all_info.other_info {
             let info = other_info.security_info;
 
@@ -82,6 +95,53 @@ struct StockInfo {
     security_info: SecurityInfoModel,
 }
 
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OptionInfo {
+    #[serde(rename = "SECINFO")]
+    security_info: SecurityInfoModel,
+    #[serde(rename = "OPTTYPE")]
+    _type: String,
+    #[serde(rename = "STRIKEPRICE", deserialize_with = "deserialize_decimal")]
+    strike: Decimal,
+    #[serde(rename = "DTEXPIRE", deserialize_with = "deserialize_date")]
+    expiration: Date,
+    #[serde(rename = "SHPERCTRCT", deserialize_with = "deserialize_decimal")]
+    multiplier: Decimal,
+    #[serde(rename = "SECID")]
+    underlying_id: SecurityId,
+}
+
+impl OptionInfo {
+    fn parse(self, securities: &SecurityInfo) -> GenericResult<(SecurityId, SecurityType)> {
+        let id = self.security_info.id;
+
+        let underlying = match securities.get(&self.underlying_id)? {
+            SecurityType::Stock(symbol) => symbol.clone(),
+            _ => return Err!("Got {} option with an unexpected underlying security type", id),
+        };
+
+        let option_type = match self._type.as_str() {
+            "CALL" => OptionType::Call,
+            "PUT" => OptionType::Put,
+            _ => return Err!("Got {} option of an unsupported type: {:?}", id, self._type),
+        };
+
+        let strike = util::validate_named_decimal(
+            "option strike price", self.strike, DecimalRestrictions::StrictlyPositive)?;
+
+        let multiplier = util::validate_named_decimal(
+            "option multiplier", self.multiplier, DecimalRestrictions::StrictlyPositive)?;
+
+        let contract = OptionContract {
+            underlying, strike, option_type,
+            expiration: self.expiration,
+        };
+
+        Ok((id, SecurityType::Option {contract, multiplier}))
+    }
+}
+
 #[derive(Deserialize)]
 #[serde(deny_unknown_fields)]
 struct OtherInfo {
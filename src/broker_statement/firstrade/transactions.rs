@@ -1,15 +1,20 @@
+use num_traits::Zero;
 use num_traits::cast::ToPrimitive;
 use serde::Deserialize;
 
-use crate::broker_statement::{StockBuy, StockSell, IdleCashInterest};
+use crate::broker_statement::{SaleKind, StockBuy, StockSell, IdleCashInterest};
+use crate::broker_statement::dividends::{DividendId, DividendAccruals};
+use crate::broker_statement::fees::Fee;
 use crate::broker_statement::partial::PartialBrokerStatement;
+use crate::broker_statement::taxes::{TaxId, TaxAccruals};
 use crate::core::EmptyResult;
 use crate::currency::{Cash, CashAssets};
 use crate::formatting;
 use crate::types::{Date, Decimal};
 use crate::util::{self, DecimalRestrictions};
 
-use super::common::{Ignore, deserialize_date, deserialize_decimal, validate_sub_account};
+use super::common::{
+    Ignore, deserialize_date, deserialize_decimal, deserialize_optional_decimal, validate_sub_account};
 use super::security_info::{SecurityInfo, SecurityId, SecurityType};
 
 #[derive(Deserialize)]
@@ -25,6 +30,10 @@ pub struct Transactions {
     stock_buys: Vec<StockBuyInfo>,
     #[serde(rename = "SELLSTOCK")]
     stock_sells: Vec<StockSellInfo>,
+    #[serde(rename = "BUYOPT", default)]
+    option_buys: Vec<OptionBuyInfo>,
+    #[serde(rename = "SELLOPT", default)]
+    option_sells: Vec<OptionSellInfo>,
     #[serde(rename = "INCOME")]
     income: Vec<IncomeInfo>,
 }
@@ -32,33 +41,63 @@ pub struct Transactions {
 impl Transactions {
     pub fn parse(
         self, statement: &mut PartialBrokerStatement, currency: &str, securities: &SecurityInfo,
+        sweep_fund: Option<&str>, trade_volume_tolerance: Decimal,
     ) -> EmptyResult {
+        let trade_options = TradeOptions {sweep_fund, trade_volume_tolerance};
+
         for cash_flow in self.cash_flows {
             cash_flow.parse(statement, currency)?;
         }
 
         for stock_buy in self.stock_buys {
-            if stock_buy._type != "BUY" {
+            // Covering of a short position is an ordinary purchase which is matched against the
+            // short sale by trades processing
+            if stock_buy._type != "BUY" && stock_buy._type != "BUYTOCOVER" {
                 return Err!("Got an unsupported type of stock purchase: {:?}", stock_buy._type);
             }
-            stock_buy.transaction.parse(statement, currency, securities, true)?;
+            stock_buy.transaction.parse(statement, currency, securities, &trade_options, None)?;
         }
 
         for stock_sell in self.stock_sells {
-            if stock_sell._type != "SELL" {
-                return Err!("Got an unsupported type of stock sell: {:?}", stock_sell._type);
-            }
-            stock_sell.transaction.parse(statement, currency, securities, false)?;
+            let kind = match stock_sell._type.as_str() {
+                "SELL" => SaleKind::Close,
+                "SELLSHORT" => SaleKind::Short,
+                _ => return Err!("Got an unsupported type of stock sell: {:?}", stock_sell._type),
+            };
+            stock_sell.transaction.parse(statement, currency, securities, &trade_options, Some(kind))?;
+        }
+
+        for option_buy in self.option_buys {
+            check_option_trade_type(&option_buy._type, true)?;
+            option_buy.transaction.parse(statement, currency, securities, &trade_options, None)?;
+        }
+
+        for option_sell in self.option_sells {
+            check_option_trade_type(&option_sell._type, false)?;
+            option_sell.transaction.parse(
+                statement, currency, securities, &trade_options, Some(SaleKind::Close))?;
         }
 
         for income in self.income {
-            income.parse(statement, currency, securities)?;
+            income.parse(statement, currency, securities, sweep_fund)?;
         }
 
         Ok(())
     }
 }
 
+// Only long option positions are supported: short ones (written options) can't be matched against
+// open positions by trades processing
+fn check_option_trade_type(trade_type: &str, buy: bool) -> EmptyResult {
+    match (trade_type, buy) {
+        ("BUYTOOPEN", true) | ("SELLTOCLOSE", false) => Ok(()),
+        ("BUYTOCLOSE", true) | ("SELLTOOPEN", false) => Err!(
+            "Got an unsupported {} option trade: short option positions are not supported", trade_type),
+        (_, true) => Err!("Got an unsupported type of option purchase: {:?}", trade_type),
+        (_, false) => Err!("Got an unsupported type of option sell: {:?}", trade_type),
+    }
+}
+
 #[derive(Deserialize)]
 #[serde(deny_unknown_fields)]
 struct CashFlowInfo {
@@ -80,23 +119,50 @@ struct CashFlowTransaction {
     #[serde(rename = "FITID")]
     id: String,
     #[serde(rename = "NAME")]
-    _name: Ignore,
+    name: String,
+    #[serde(rename = "MEMO", default)]
+    memo: Option<String>,
 }
 
 impl CashFlowInfo {
     fn parse(self, statement: &mut PartialBrokerStatement, currency: &str) -> EmptyResult {
         let transaction = self.transaction;
-
-        if transaction._type != "CREDIT" {
-            return Err!(
-                "Got {:?} cash flow transaction of an unsupported type: {}",
-                transaction.id, transaction._type);
-        }
         validate_sub_account(&self.sub_account)?;
 
-        let amount = util::validate_named_decimal(
-            "transaction amount", transaction.amount, DecimalRestrictions::StrictlyPositive)?;
-        statement.cash_flows.push(CashAssets::new(transaction.date, currency, amount));
+        match transaction._type.as_str() {
+            "CREDIT" => {
+                let amount = util::validate_named_decimal(
+                    "transaction amount", transaction.amount, DecimalRestrictions::StrictlyPositive)?;
+                statement.cash_flows.push(CashAssets::new(transaction.date, currency, amount));
+            },
+            "DEBIT" => {
+                let amount = util::validate_named_decimal(
+                    "transaction amount", transaction.amount, DecimalRestrictions::StrictlyNegative
+                ).map(|amount| Cash::new(currency, amount))?;
+
+                let description = transaction.memo.as_ref().unwrap_or(&transaction.name);
+
+                // Account maintenance, wire and other fees are charged as debit transactions. All
+                // other debit transactions are withdrawals.
+                let tax_deductible = match description.to_uppercase().as_str() {
+                    "ACCOUNT MAINTENANCE FEE" | "ACCOUNT TRANSFER FEE" => Some(true),
+                    "WIRE FEE" => Some(false),
+                    _ => None,
+                };
+
+                if let Some(tax_deductible) = tax_deductible {
+                    statement.fees.push(Fee {
+                        date: transaction.date,
+                        amount,
+                        description: Some(description.clone()),
+                        tax_deductible,
+                    });
+                } else {
+                    statement.cash_flows.push(CashAssets::new_from_cash(transaction.date, amount));
+                }
+            },
+            _ => statement.add_unsupported_operation("cash flow transaction", &transaction._type),
+        };
 
         Ok(())
     }
@@ -120,6 +186,33 @@ struct StockSellInfo {
     transaction: StockTradeTransaction,
 }
 
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OptionBuyInfo {
+    #[serde(rename = "INVBUY")]
+    transaction: StockTradeTransaction,
+    #[serde(rename = "OPTBUYTYPE")]
+    _type: String,
+    #[serde(rename = "SHPERCTRCT")]
+    _multiplier: Ignore,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OptionSellInfo {
+    #[serde(rename = "INVSELL")]
+    transaction: StockTradeTransaction,
+    #[serde(rename = "OPTSELLTYPE")]
+    _type: String,
+    #[serde(rename = "SHPERCTRCT")]
+    _multiplier: Ignore,
+}
+
+struct TradeOptions<'a> {
+    sweep_fund: Option<&'a str>,
+    trade_volume_tolerance: Decimal,
+}
+
 #[derive(Deserialize)]
 #[serde(deny_unknown_fields)]
 struct StockTradeTransaction {
@@ -137,6 +230,8 @@ struct StockTradeTransaction {
     fees: Decimal,
     #[serde(rename = "TOTAL", deserialize_with = "deserialize_decimal")]
     total: Decimal,
+    #[serde(rename = "WITHHOLDING", default, deserialize_with = "deserialize_optional_decimal")]
+    withholding: Option<Decimal>,
     #[serde(rename = "SUBACCTSEC")]
     sub_account_to: String,
     #[serde(rename = "SUBACCTFUND")]
@@ -144,18 +239,42 @@ struct StockTradeTransaction {
 }
 
 impl StockTradeTransaction {
+    // Sale kind is None for purchases
     fn parse(
         self, statement: &mut PartialBrokerStatement, currency: &str, securities: &SecurityInfo,
-        buy: bool,
+        options: &TradeOptions, sale_kind: Option<SaleKind>,
     ) -> EmptyResult {
+        let buy = sale_kind.is_none();
+
         validate_sub_account(&self.sub_account_from)?;
         validate_sub_account(&self.sub_account_to)?;
 
-        let symbol = match securities.get(&self.security_id)? {
-            SecurityType::Stock(symbol) => symbol,
-            _ => return Err!("Got {} stock trade with an unexpected security type", self.security_id),
+        let (symbol, multiplier) = match securities.get(&self.security_id)? {
+            SecurityType::Stock(symbol) => (symbol.clone(), dec!(1)),
+            SecurityType::Option {contract, multiplier} => {
+                let symbol = contract.symbol();
+                statement.option_multipliers.insert(symbol.clone(), *multiplier);
+                (symbol, *multiplier)
+            },
+            _ => return Err!("Got {} trade with an unexpected security type", self.security_id),
         };
 
+        // Sweep fund trades are automatic investments of idle cash which is still treated as cash
+        if options.sweep_fund == Some(symbol.as_str()) {
+            return Ok(());
+        }
+
+        // Tax withheld from trade proceeds (backup withholding) has no income to be attributed to
+        if let Some(withholding) = self.withholding {
+            let withholding = util::validate_named_decimal(
+                "tax withholding", withholding, DecimalRestrictions::PositiveOrZero)?;
+
+            if !withholding.is_zero() {
+                return Err!("Got {} trade with unsupported tax withholding: {}",
+                    symbol, Cash::new(currency, withholding));
+            }
+        }
+
         let quantity = util::parse_decimal(
             &self.units, if buy {
                 DecimalRestrictions::StrictlyPositive
@@ -171,9 +290,10 @@ impl StockTradeTransaction {
             })
             .ok_or_else(|| format!("Invalid trade quantity: {:?}", self.units))?;
 
+        // Convert option price to per-contract price to keep volume = price * quantity invariant
         let price = util::validate_named_decimal(
             "price", self.price, DecimalRestrictions::StrictlyPositive)
-            .map(|price| Cash::new(currency, price.normalize()))?;
+            .map(|price| Cash::new(currency, (price * multiplier).normalize()))?;
 
         let commission = util::validate_named_decimal(
             "commission", self.commission, DecimalRestrictions::PositiveOrZero
@@ -200,16 +320,27 @@ impl StockTradeTransaction {
 
                 Cash::new(currency, volume)
             })?;
-        debug_assert_eq!(volume, (price * quantity).round());
 
-        if buy {
-            statement.stock_buys.push(StockBuy::new(
+        // Broker may round the volume differently, so allow a small difference
+        let expected_volume = (price * quantity).round();
+        if (volume.amount - expected_volume.amount).abs() > options.trade_volume_tolerance {
+            return Err!(
+                "Got {} trade with unexpected volume: {} ({} * {} = {} is expected)",
+                symbol, volume, price, quantity, expected_volume);
+        }
+
+        if let Some(kind) = sale_kind {
+            let mut trade = StockSell::new(
                 &symbol, quantity, price, volume, commission,
-                self.info.conclusion_date, self.info.execution_date));
+                self.info.conclusion_date, self.info.execution_date, kind);
+            trade.id = Some(self.info.id);
+            statement.stock_sells.push(trade);
         } else {
-            statement.stock_sells.push(StockSell::new(
+            let mut trade = StockBuy::new(
                 &symbol, quantity, price, volume, commission,
-                self.info.conclusion_date, self.info.execution_date, false));
+                self.info.conclusion_date, self.info.execution_date);
+            trade.id = Some(self.info.id);
+            statement.stock_buys.push(trade);
         }
 
         Ok(())
@@ -227,6 +358,8 @@ struct IncomeInfo {
     _type: String,
     #[serde(rename = "TOTAL", deserialize_with = "deserialize_decimal")]
     total: Decimal,
+    #[serde(rename = "WITHHOLDING", default, deserialize_with = "deserialize_optional_decimal")]
+    withholding: Option<Decimal>,
     #[serde(rename = "SUBACCTSEC")]
     sub_account_to: String,
     #[serde(rename = "SUBACCTFUND")]
@@ -236,6 +369,7 @@ struct IncomeInfo {
 impl IncomeInfo {
     fn parse(
         self, statement: &mut PartialBrokerStatement, currency: &str, securities: &SecurityInfo,
+        sweep_fund: Option<&str>,
     ) -> EmptyResult {
         validate_sub_account(&self.sub_account_from)?;
         validate_sub_account(&self.sub_account_to)?;
@@ -254,8 +388,30 @@ impl IncomeInfo {
         match (self._type.as_str(), securities.get(&self.security_id)?) {
             ("MISC", SecurityType::Interest) => {
                 statement.idle_cash_interest.push(IdleCashInterest::new(date, amount));
-            }
-            _ => return Err!("Got an unsupported income: {:?}", self.info.memo),
+            },
+            ("DIV", SecurityType::Stock(symbol)) if sweep_fund == Some(symbol.as_str()) => {
+                statement.idle_cash_interest.push(IdleCashInterest::new(date, amount));
+            },
+            ("DIV", SecurityType::Stock(symbol)) |
+            ("CGLONG", SecurityType::Stock(symbol)) |
+            ("CGSHORT", SecurityType::Stock(symbol)) => {
+                statement.dividend_accruals.entry(DividendId {
+                    date: date,
+                    issuer: symbol.clone(),
+                }).or_insert_with(DividendAccruals::new).add(amount);
+
+                if let Some(withholding) = self.withholding {
+                    let tax = util::validate_named_decimal(
+                        "tax withholding", withholding, DecimalRestrictions::PositiveOrZero)?;
+
+                    if !tax.is_zero() {
+                        statement.tax_accruals.entry(TaxId::new(date, symbol))
+                            .or_insert_with(TaxAccruals::new)
+                            .add(Cash::new(currency, tax));
+                    }
+                }
+            },
+            _ => statement.add_unsupported_operation("income", &self.info.memo),
         };
 
         Ok(())
@@ -266,11 +422,253 @@ impl IncomeInfo {
 #[serde(deny_unknown_fields)]
 struct TransactionInfo {
     #[serde(rename = "FITID")]
-    _id: Ignore,
+    id: String,
     #[serde(rename = "DTTRADE", deserialize_with = "deserialize_date")]
     conclusion_date: Date,
     #[serde(rename = "DTSETTLE", deserialize_with = "deserialize_date")]
     execution_date: Date,
     #[serde(rename = "MEMO")]
     memo: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::broker_statement::dividends::process_dividend_accruals;
+
+    use super::super::security_info::SecurityInfoSection;
+    use super::*;
+
+    #[test]
+    fn sweep_fund_income() {
+        let securities: SecurityInfoSection = quick_xml::de::from_str(r#"
+            <SECLISTMSGSRSV1><SECLIST>
+                <STOCKINFO><SECINFO>
+                    <SECID><UNIQUEID>316175108</UNIQUEID><UNIQUEIDTYPE>CUSIP</UNIQUEIDTYPE></SECID>
+                    <SECNAME>FIDELITY MONEY MARKET</SECNAME><TICKER>FMPXX</TICKER>
+                </SECINFO></STOCKINFO>
+            </SECLIST></SECLISTMSGSRSV1>
+        "#).unwrap();
+        let securities = securities.parse().unwrap();
+
+        let parse = |sweep_fund| {
+            let income: IncomeInfo = quick_xml::de::from_str(r#"
+                <INCOME>
+                    <INVTRAN>
+                        <FITID>1</FITID><DTTRADE>20201130</DTTRADE><DTSETTLE>20201130</DTSETTLE>
+                        <MEMO>FIDELITY MONEY MARKET DIVIDEND</MEMO>
+                    </INVTRAN>
+                    <SECID><UNIQUEID>316175108</UNIQUEID><UNIQUEIDTYPE>CUSIP</UNIQUEIDTYPE></SECID>
+                    <INCOMETYPE>DIV</INCOMETYPE><TOTAL>0.15</TOTAL>
+                    <SUBACCTSEC>CASH</SUBACCTSEC><SUBACCTFUND>CASH</SUBACCTFUND>
+                </INCOME>
+            "#).unwrap();
+
+            let mut statement = PartialBrokerStatement::new();
+            income.parse(&mut statement, "USD", &securities, sweep_fund).unwrap();
+            statement
+        };
+
+        let statement = parse(None);
+        assert!(statement.idle_cash_interest.is_empty());
+        assert_eq!(statement.dividend_accruals.len(), 1);
+
+        let statement = parse(Some("FMPXX"));
+        assert_eq!(statement.idle_cash_interest.len(), 1);
+        assert!(statement.dividend_accruals.is_empty());
+    }
+
+    #[test]
+    fn dividend_income() {
+        let securities: SecurityInfoSection = quick_xml::de::from_str(r#"
+            <SECLISTMSGSRSV1><SECLIST>
+                <STOCKINFO><SECINFO>
+                    <SECID><UNIQUEID>922908769</UNIQUEID><UNIQUEIDTYPE>CUSIP</UNIQUEIDTYPE></SECID>
+                    <SECNAME>VANGUARD TOTAL STOCK MARKET ETF</SECNAME><TICKER>VTI</TICKER>
+                </SECINFO></STOCKINFO>
+            </SECLIST></SECLISTMSGSRSV1>
+        "#).unwrap();
+        let securities = securities.parse().unwrap();
+
+        let mut statement = PartialBrokerStatement::new();
+
+        for &(income_type, date, total, withholding) in &[
+            ("DIV", "20201230", "10.00", "<WITHHOLDING>1.00</WITHHOLDING>"),
+            ("CGLONG", "20201230", "5.00", ""),
+            ("CGSHORT", "20210115", "2.00", ""),
+        ] {
+            let income: IncomeInfo = quick_xml::de::from_str(&format!(r#"
+                <INCOME>
+                    <INVTRAN>
+                        <FITID>1</FITID><DTTRADE>{date}</DTTRADE><DTSETTLE>{date}</DTSETTLE>
+                        <MEMO>VANGUARD TOTAL STOCK MARKET ETF</MEMO>
+                    </INVTRAN>
+                    <SECID><UNIQUEID>922908769</UNIQUEID><UNIQUEIDTYPE>CUSIP</UNIQUEIDTYPE></SECID>
+                    <INCOMETYPE>{income_type}</INCOMETYPE><TOTAL>{total}</TOTAL>{withholding}
+                    <SUBACCTSEC>CASH</SUBACCTSEC><SUBACCTFUND>CASH</SUBACCTFUND>
+                </INCOME>
+            "#, date=date, income_type=income_type, total=total, withholding=withholding)).unwrap();
+
+            income.parse(&mut statement, "USD", &securities, None).unwrap();
+        }
+
+        let mut dividends = Vec::new();
+        for (dividend_id, accruals) in statement.dividend_accruals.drain() {
+            dividends.extend(process_dividend_accruals(
+                dividend_id, accruals, &mut statement.tax_accruals).unwrap());
+        }
+        assert!(statement.tax_accruals.is_empty());
+
+        dividends.sort_by_key(|dividend| dividend.date);
+        let dividends: Vec<(Date, &str, Cash, Cash)> = dividends.iter().map(|dividend| (
+            dividend.date, dividend.issuer.as_str(), dividend.amount, dividend.paid_tax,
+        )).collect();
+
+        // Capital gain distributions are taxed as dividends
+        assert_eq!(dividends, vec![
+            (date!(30, 12, 2020), "VTI", Cash::new("USD", dec!(15)), Cash::new("USD", dec!(1))),
+            (date!(15, 1, 2021), "VTI", Cash::new("USD", dec!(2)), Cash::new("USD", dec!(0))),
+        ]);
+    }
+
+    #[test]
+    fn option_trade() {
+        let securities: SecurityInfoSection = quick_xml::de::from_str(r#"
+            <SECLISTMSGSRSV1><SECLIST>
+                <STOCKINFO><SECINFO>
+                    <SECID><UNIQUEID>037833100</UNIQUEID><UNIQUEIDTYPE>CUSIP</UNIQUEIDTYPE></SECID>
+                    <SECNAME>APPLE INC</SECNAME><TICKER>AAPL</TICKER>
+                </SECINFO></STOCKINFO>
+                <OPTINFO>
+                    <SECINFO>
+                        <SECID><UNIQUEID>AAPL201218C00120000</UNIQUEID><UNIQUEIDTYPE>CUSIP</UNIQUEIDTYPE></SECID>
+                        <SECNAME>AAPL DEC 18 2020 120.0 CALL</SECNAME><TICKER>AAPL201218C00120000</TICKER>
+                    </SECINFO>
+                    <OPTTYPE>CALL</OPTTYPE><STRIKEPRICE>120</STRIKEPRICE><DTEXPIRE>20201218</DTEXPIRE>
+                    <SHPERCTRCT>100</SHPERCTRCT>
+                    <SECID><UNIQUEID>037833100</UNIQUEID><UNIQUEIDTYPE>CUSIP</UNIQUEIDTYPE></SECID>
+                </OPTINFO>
+            </SECLIST></SECLISTMSGSRSV1>
+        "#).unwrap();
+        let securities = securities.parse().unwrap();
+
+        let option_buy: OptionBuyInfo = quick_xml::de::from_str(r#"
+            <BUYOPT>
+                <INVBUY>
+                    <INVTRAN>
+                        <FITID>1</FITID><DTTRADE>20201102</DTTRADE><DTSETTLE>20201103</DTSETTLE>
+                        <MEMO>AAPL DEC 18 2020 120.0 CALL</MEMO>
+                    </INVTRAN>
+                    <SECID><UNIQUEID>AAPL201218C00120000</UNIQUEID><UNIQUEIDTYPE>CUSIP</UNIQUEIDTYPE></SECID>
+                    <UNITS>2</UNITS><UNITPRICE>5.00</UNITPRICE><COMMISSION>0</COMMISSION><FEES>0.05</FEES>
+                    <TOTAL>-1000.05</TOTAL><SUBACCTSEC>CASH</SUBACCTSEC><SUBACCTFUND>CASH</SUBACCTFUND>
+                </INVBUY>
+                <OPTBUYTYPE>BUYTOOPEN</OPTBUYTYPE><SHPERCTRCT>100</SHPERCTRCT>
+            </BUYOPT>
+        "#).unwrap();
+
+        let options = TradeOptions {sweep_fund: None, trade_volume_tolerance: dec!(0.01)};
+
+        let mut statement = PartialBrokerStatement::new();
+        option_buy.transaction.parse(&mut statement, "USD", &securities, &options, None).unwrap();
+
+        let symbol = "AAPL 18DEC20 120.0 C";
+        assert_eq!(statement.stock_buys.len(), 1);
+
+        let trade = &statement.stock_buys[0];
+        assert_eq!(trade.symbol, symbol);
+        assert_eq!(trade.quantity, 2);
+        assert_eq!(trade.price, Cash::new("USD", dec!(500)));
+        assert_eq!(trade.volume, Cash::new("USD", dec!(1000)));
+        assert_eq!(trade.commission, Cash::new("USD", dec!(0.05)));
+        assert_eq!(statement.option_multipliers, hashmap!{symbol.to_owned() => dec!(100)});
+    }
+
+    #[rstest(trade_type, buy, supported,
+        case("BUYTOOPEN", true, true),
+        case("SELLTOCLOSE", false, true),
+        case("SELLTOOPEN", false, false),
+        case("BUYTOCLOSE", true, false),
+    )]
+    fn option_trade_type(trade_type: &str, buy: bool, supported: bool) {
+        assert_eq!(check_option_trade_type(trade_type, buy).is_ok(), supported);
+    }
+
+    #[test]
+    fn trade_volume_validation() {
+        let securities: SecurityInfoSection = quick_xml::de::from_str(r#"
+            <SECLISTMSGSRSV1><SECLIST>
+                <STOCKINFO><SECINFO>
+                    <SECID><UNIQUEID>037833100</UNIQUEID><UNIQUEIDTYPE>CUSIP</UNIQUEIDTYPE></SECID>
+                    <SECNAME>APPLE INC</SECNAME><TICKER>AAPL</TICKER>
+                </SECINFO></STOCKINFO>
+            </SECLIST></SECLISTMSGSRSV1>
+        "#).unwrap();
+        let securities = securities.parse().unwrap();
+
+        let parse = |trade_volume_tolerance| {
+            let stock_buy: StockBuyInfo = quick_xml::de::from_str(r#"
+                <BUYSTOCK>
+                    <INVBUY>
+                        <INVTRAN>
+                            <FITID>1</FITID><DTTRADE>20201102</DTTRADE><DTSETTLE>20201104</DTSETTLE>
+                            <MEMO>APPLE INC</MEMO>
+                        </INVTRAN>
+                        <SECID><UNIQUEID>037833100</UNIQUEID><UNIQUEIDTYPE>CUSIP</UNIQUEIDTYPE></SECID>
+                        <UNITS>10</UNITS><UNITPRICE>100.00</UNITPRICE><COMMISSION>0</COMMISSION><FEES>0</FEES>
+                        <TOTAL>-1000.05</TOTAL><SUBACCTSEC>CASH</SUBACCTSEC><SUBACCTFUND>CASH</SUBACCTFUND>
+                    </INVBUY>
+                    <BUYTYPE>BUY</BUYTYPE>
+                </BUYSTOCK>
+            "#).unwrap();
+
+            let options = TradeOptions {sweep_fund: None, trade_volume_tolerance};
+            let mut statement = PartialBrokerStatement::new();
+            stock_buy.transaction.parse(&mut statement, "USD", &securities, &options, None)
+        };
+
+        assert_eq!(
+            parse(dec!(0.01)).unwrap_err().to_string(),
+            "Got AAPL trade with unexpected volume: $1,000.05 ($100 * 10 = $1,000 is expected)");
+        parse(dec!(0.05)).unwrap();
+    }
+
+    #[rstest(name, memo, fee,
+        case("ACCOUNT MAINTENANCE FEE", None, Some(true)),
+        case("WIRE", Some("Wire Fee"), Some(false)),
+        case("WIRE OUT", None, None),
+        case("WIRE OUT", Some("FEE-FREE TRANSFER"), None),
+    )]
+    fn debit_cash_flow(name: &str, memo: Option<&str>, fee: Option<bool>) {
+        let memo = memo.map(|memo| format!("<MEMO>{}</MEMO>", memo)).unwrap_or_default();
+        let cash_flow: CashFlowInfo = quick_xml::de::from_str(&format!(r#"
+            <INVBANKTRAN>
+                <STMTTRN>
+                    <TRNTYPE>DEBIT</TRNTYPE><DTPOSTED>20201130</DTPOSTED><TRNAMT>-25.00</TRNAMT>
+                    <FITID>1</FITID><NAME>{}</NAME>{}
+                </STMTTRN>
+                <SUBACCTFUND>CASH</SUBACCTFUND>
+            </INVBANKTRAN>
+        "#, name, memo)).unwrap();
+
+        let mut statement = PartialBrokerStatement::new();
+        cash_flow.parse(&mut statement, "USD").unwrap();
+
+        let amount = Cash::new("USD", dec!(-25));
+
+        if let Some(tax_deductible) = fee {
+            assert!(statement.cash_flows.is_empty());
+            assert_eq!(statement.fees.len(), 1);
+
+            let fee = &statement.fees[0];
+            assert_eq!(fee.date, date!(30, 11, 2020));
+            assert_eq!(fee.amount, amount);
+            assert_eq!(fee.tax_deductible, tax_deductible);
+        } else {
+            assert!(statement.fees.is_empty());
+            assert_eq!(statement.cash_flows.len(), 1);
+            assert_eq!(statement.cash_flows[0].cash, amount);
+        }
+    }
 }
\ No newline at end of file
@@ -41,6 +41,11 @@ impl<'a> Record<'a> {
         Err!("{:?} record doesn't have {:?} field", self.spec.name, field)
     }
 
+    /// Returns name of the first field which starts with the specified prefix
+    pub fn find_field(&self, prefix: &str) -> Option<&'a str> {
+        self.spec.fields.iter().find(|field| field.starts_with(prefix)).copied()
+    }
+
     pub fn check_value(&self, field: &str, value: &str) -> EmptyResult {
         self.check_values(&[(field, value)])
     }
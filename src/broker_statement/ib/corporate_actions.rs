@@ -0,0 +1,120 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::broker_statement::corporate_actions::StockSplit;
+use crate::core::EmptyResult;
+use crate::formatting;
+use crate::util::DecimalRestrictions;
+
+use super::StatementParser;
+use super::common::{Record, RecordParser, parse_date_time};
+
+pub struct CorporateActionsParser {}
+
+impl RecordParser for CorporateActionsParser {
+    fn skip_totals(&self) -> bool {
+        true
+    }
+
+    fn parse(&self, parser: &mut StatementParser, record: &Record) -> EmptyResult {
+        record.check_value("Asset Category", "Stocks")?;
+
+        let currency = record.get_value("Currency")?;
+        let date = parse_date_time(record.get_value("Date/Time")?)?.date();
+        let description = record.get_value("Description")?;
+
+        let action = match parse_corporate_action(description) {
+            Some(action) => action,
+            None => {
+                parser.statement.add_unsupported_operation("corporate action", description);
+                return Ok(());
+            },
+        };
+
+        match action {
+            CorporateAction::StockSplit {symbol, from, to} => {
+                parser.statement.stock_splits.push(StockSplit {
+                    date, symbol, from, to,
+                    price: None,
+                    cash_in_lieu: None,
+                });
+            },
+
+            CorporateAction::CashInLieu {symbol} => {
+                let amount = record.parse_cash("Proceeds", currency, DecimalRestrictions::StrictlyPositive)?;
+
+                // Cash-in-lieu follows the split which it relates to
+                let split = parser.statement.stock_splits.iter_mut().rev()
+                    .find(|split| split.symbol == symbol && split.date == date)
+                    .ok_or_else(|| format!(
+                        "Got cash-in-lieu for {} from {} without a corresponding stock split",
+                        symbol, formatting::format_date(date)))?;
+
+                if split.cash_in_lieu.replace(amount).is_some() {
+                    return Err!("Got duplicated cash-in-lieu for {} stock split from {}",
+                                symbol, formatting::format_date(date));
+                }
+            },
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum CorporateAction {
+    StockSplit {symbol: String, from: u32, to: u32},
+    CashInLieu {symbol: String},
+}
+
+fn parse_corporate_action(description: &str) -> Option<CorporateAction> {
+    lazy_static! {
+        static ref SPLIT_REGEX: Regex = Regex::new(
+            r"^(?P<symbol>[A-Z0-9.]+) ?\([A-Z0-9]+\) Split (?P<to>[1-9]\d*) for (?P<from>[1-9]\d*) ").unwrap();
+
+        static ref CASH_IN_LIEU_REGEX: Regex = Regex::new(
+            r"^(?P<symbol>[A-Z0-9.]+) ?\([A-Z0-9]+\) Cash in Lieu of Fractional Shares").unwrap();
+    }
+
+    if let Some(captures) = SPLIT_REGEX.captures(description) {
+        let symbol = captures.name("symbol").unwrap().as_str().to_owned();
+        let from = captures.name("from").unwrap().as_str().parse().ok();
+        let to = captures.name("to").unwrap().as_str().parse().ok();
+
+        if let (Some(from), Some(to)) = (from, to) {
+            if from != to {
+                return Some(CorporateAction::StockSplit {symbol, from, to});
+            }
+        }
+    } else if let Some(captures) = CASH_IN_LIEU_REGEX.captures(description) {
+        let symbol = captures.name("symbol").unwrap().as_str().to_owned();
+        return Some(CorporateAction::CashInLieu {symbol});
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corporate_action_parsing() {
+        assert_eq!(
+            parse_corporate_action("AAPL(US0378331005) Split 2 for 1 (AAPL, APPLE INC, US0378331005)").unwrap(),
+            CorporateAction::StockSplit {symbol: s!("AAPL"), from: 1, to: 2},
+        );
+
+        assert_eq!(
+            parse_corporate_action("XYZ(US98389B1008) Split 1 for 3 (XYZ, XYZ CORP, US98389B1008)").unwrap(),
+            CorporateAction::StockSplit {symbol: s!("XYZ"), from: 3, to: 1},
+        );
+
+        assert_eq!(
+            parse_corporate_action("XYZ(US98389B1008) Cash in Lieu of Fractional Shares (XYZ, XYZ CORP, US98389B1008)").unwrap(),
+            CorporateAction::CashInLieu {symbol: s!("XYZ")},
+        );
+
+        assert_eq!(parse_corporate_action("XYZ(US98389B1008) Merged(Acquisition) WITH US0000000001"), None);
+    }
+}
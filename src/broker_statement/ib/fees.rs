@@ -21,6 +21,7 @@ impl RecordParser for FeesParser {
             date: date,
             amount: amount,
             description: None,
+            tax_deductible: false,
         });
 
         Ok(())
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+use crate::core::{EmptyResult, GenericResult};
+use crate::types::Decimal;
+use crate::util::DecimalRestrictions;
+
+use super::StatementParser;
+use super::common::{Record, RecordParser};
+
+pub struct InstrumentInfo {
+    instruments: HashMap<String, Instrument>,
+}
+
+pub struct Instrument {
+    pub symbol: String,
+    pub multiplier: Decimal,
+}
+
+impl InstrumentInfo {
+    pub fn new() -> InstrumentInfo {
+        InstrumentInfo {
+            instruments: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, instrument: Instrument) -> EmptyResult {
+        match self.instruments.entry(instrument.symbol.clone()) {
+            Entry::Vacant(entry) => entry.insert(instrument),
+            Entry::Occupied(entry) => return Err!("Duplicated symbol: {}", entry.key()),
+        };
+        Ok(())
+    }
+
+    pub fn get(&self, symbol: &str) -> GenericResult<&Instrument> {
+        Ok(self.instruments.get(symbol).ok_or_else(|| format!(
+            "Unable to find {:?} in financial instrument information", symbol))?)
+    }
+}
+
+pub struct FinancialInstrumentInformationParser {
+}
+
+impl RecordParser for FinancialInstrumentInformationParser {
+    fn parse(&self, parser: &mut StatementParser, record: &Record) -> EmptyResult {
+        let symbol = record.get_value("Symbol")?;
+
+        // The field is missing for stocks in old statements
+        let multiplier = match record.get_value("Multiplier") {
+            Ok(_) => record.parse_amount("Multiplier", DecimalRestrictions::StrictlyPositive)?,
+            Err(_) => dec!(1),
+        };
+
+        parser.instruments.add(Instrument {
+            symbol: symbol.to_owned(),
+            multiplier,
+        })?;
+
+        parser.statement.instrument_names.insert(
+            symbol.to_owned(), record.get_value("Description")?.to_owned());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::currency::Cash;
+    use crate::taxes::TaxRemapping;
+
+    use super::super::StatementReader;
+
+    #[test]
+    fn option_multiplier() {
+        let path = Path::new(file!()).parent().unwrap().join("testdata/option-multiplier.csv");
+        let statement = StatementReader::new(TaxRemapping::new(), false).unwrap()
+            .read(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(statement.stock_buys.len(), 1);
+        let trade = statement.stock_buys.first().unwrap();
+
+        assert_eq!(trade.symbol, "AAPL 18DEC20 120.0 C");
+        assert_eq!(trade.quantity, 2);
+        assert_eq!(trade.price, Cash::new("USD", dec!(150)));
+        assert_eq!(trade.volume, Cash::new("USD", dec!(300)));
+        assert_eq!(trade.commission, Cash::new("USD", dec!(2.1)));
+
+        assert_eq!(statement.instrument_names.get("AAPL 18DEC20 120.0 C").unwrap(),
+                   "AAPL 18DEC20 120 C");
+    }
+}
@@ -1,7 +1,9 @@
 mod common;
 mod confirmation;
+mod corporate_actions;
 mod dividends;
 mod fees;
+mod instruments;
 mod interest;
 mod parsers;
 mod taxes;
@@ -27,6 +29,8 @@ use super::{BrokerStatementReader, PartialBrokerStatement};
 
 use self::common::{RecordSpec, Record, RecordParser, format_record};
 use self::confirmation::{TradeExecutionDates, OrderId};
+use self::instruments::InstrumentInfo;
+use self::trades::PendingTrade;
 
 pub struct StatementReader {
     tax_remapping: RefCell<TaxRemapping>,
@@ -72,6 +76,9 @@ impl BrokerStatementReader for StatementReader {
             base_currency: None,
             base_currency_summary: None,
 
+            instruments: InstrumentInfo::new(),
+            pending_trades: Vec::new(),
+
             tax_remapping: &mut self.tax_remapping.borrow_mut(),
             trade_execution_dates: &self.trade_execution_dates.borrow(),
 
@@ -97,6 +104,9 @@ pub struct StatementParser<'a> {
     base_currency: Option<String>,
     base_currency_summary: Option<Cash>,
 
+    instruments: InstrumentInfo,
+    pending_trades: Vec<PendingTrade>,
+
     tax_remapping: &'a mut TaxRemapping,
     trade_execution_dates: &'a TradeExecutionDates,
 
@@ -149,8 +159,9 @@ impl<'a> StatementParser<'a> {
                         "Fees" => Box::new(fees::FeesParser {}),
                         "Dividends" => Box::new(dividends::DividendsParser {}),
                         "Withholding Tax" => Box::new(taxes::WithholdingTaxParser {}),
+                        "Corporate Actions" => Box::new(corporate_actions::CorporateActionsParser {}),
                         "Interest" => Box::new(interest::InterestParser {}),
-                        "Financial Instrument Information" => Box::new(parsers::FinancialInstrumentInformationParser {}),
+                        "Financial Instrument Information" => Box::new(instruments::FinancialInstrumentInformationParser {}),
                         _ => Box::new(parsers::UnknownRecordParser {}),
                     };
 
@@ -210,6 +221,8 @@ impl<'a> StatementParser<'a> {
             }
         }
 
+        trades::process_pending_trades(&mut self)?;
+
         // When statement has no non-base currency activity it contains only base currency summary
         // and we have to use it as the only source of current cash assets info.
         if self.statement.cash_assets.is_empty() {
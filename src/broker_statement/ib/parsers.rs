@@ -78,7 +78,8 @@ impl RecordParser for CashReportParser {
         }
 
         let currency = record.get_value("Currency")?;
-        let amount = record.parse_amount("Total", DecimalRestrictions::PositiveOrZero)?;
+        // Margin accounts may have negative cash balance (debt)
+        let amount = record.parse_amount("Total", DecimalRestrictions::No)?;
 
         record.check_value("Futures", "0")?;
         record.check_value("Total", record.get_value("Securities")?)?;
@@ -115,22 +116,6 @@ impl RecordParser for DepositsAndWithdrawalsParser {
     }
 }
 
-pub struct FinancialInstrumentInformationParser {
-}
-
-impl RecordParser for FinancialInstrumentInformationParser {
-    fn parse(&self, parser: &mut StatementParser, record: &Record) -> EmptyResult {
-        let symbol = record.get_value("Symbol")?;
-
-        if parser.statement.instrument_names.insert(
-            symbol.to_owned(), record.get_value("Description")?.to_owned()).is_some() {
-            return Err!("Duplicated symbol: {}", symbol);
-        }
-
-        Ok(())
-    }
-}
-
 pub struct UnknownRecordParser {}
 
 impl RecordParser for UnknownRecordParser {
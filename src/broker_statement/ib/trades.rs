@@ -1,9 +1,9 @@
 use std::ops::Deref;
 
-use crate::broker_statement::trades::{ForexTrade, StockBuy, StockSell};
-use crate::core::EmptyResult;
-use crate::currency;
-use crate::types::Date;
+use crate::broker_statement::trades::{ForexTrade, SaleKind, StockBuy, StockSell};
+use crate::core::{EmptyResult, GenericResult};
+use crate::currency::{self, Cash};
+use crate::types::{Date, Decimal};
 use crate::util::DecimalRestrictions;
 
 use super::StatementParser;
@@ -51,6 +51,7 @@ impl RecordParser for TradesParser {
         match asset_category {
             "Forex" => parse_forex_record(parser, record, symbol, conclusion_date),
             "Stocks" => parse_stock_record(parser, record, symbol, conclusion_date),
+            "Equity and Index Options" => parse_option_record(parser, record, symbol, conclusion_date),
             _ => return Err!("Unsupported asset category: {}", asset_category)
         }
     }
@@ -91,31 +92,104 @@ fn parse_forex_record(
     Ok(())
 }
 
+// Commission is usually reported in trade currency, but some statements report it in a separate
+// currency column (for example "Comm in EUR" for EUR fees on USD trade) which is kept as is
+fn parse_commission(record: &Record, currency: &str) -> GenericResult<Cash> {
+    let (field, currency) = match record.find_field("Comm in ") {
+        Some(field) if record.find_field("Comm/Fee").is_none() => (field, &field["Comm in ".len()..]),
+        _ => ("Comm/Fee", currency),
+    };
+    Ok(-record.parse_cash(field, currency, DecimalRestrictions::NegativeOrZero)?)
+}
+
 fn parse_stock_record(
     parser: &mut StatementParser, record: &Record, symbol: &str, conclusion_date: Date,
 ) -> EmptyResult {
-    let currency = record.get_value("Currency")?;
-    let quantity: i32 = record.parse_value("Quantity")?;
-    let price = record.parse_cash("T. Price", currency, DecimalRestrictions::StrictlyPositive)?;
-    let commission = -record.parse_cash("Comm/Fee", currency, DecimalRestrictions::NegativeOrZero)?;
-    let execution_date = parser.get_execution_date(symbol, conclusion_date);
-
-    let volume = record.parse_cash("Proceeds", currency, if quantity < 0 {
-        DecimalRestrictions::StrictlyPositive
-    } else {
-        DecimalRestrictions::StrictlyNegative
-    })?;
-    debug_assert_eq!(volume.amount, currency::round_to((price * -quantity).amount, 4));
-
-    if quantity > 0 {
-        parser.statement.stock_buys.push(StockBuy::new(
-            symbol, quantity as u32, price, -volume, commission, conclusion_date, execution_date));
-    } else if quantity < 0 {
-        parser.statement.stock_sells.push(StockSell::new(
-            symbol, -quantity as u32, price, volume, commission, conclusion_date, execution_date, false));
-    } else {
-        return Err!("Invalid quantity: {}", quantity)
-    }
+    let trade = PendingTrade::parse(parser, record, symbol, conclusion_date)?;
+    trade.process(parser, dec!(1))
+}
 
+// Financial Instrument Information section which contains option multipliers goes after trades, so
+// option trades are processed after the whole statement is read.
+fn parse_option_record(
+    parser: &mut StatementParser, record: &Record, symbol: &str, conclusion_date: Date,
+) -> EmptyResult {
+    let trade = PendingTrade::parse(parser, record, symbol, conclusion_date)?;
+    parser.pending_trades.push(trade);
     Ok(())
-}
\ No newline at end of file
+}
+
+pub fn process_pending_trades(parser: &mut StatementParser) -> EmptyResult {
+    for trade in std::mem::replace(&mut parser.pending_trades, Vec::new()) {
+        let multiplier = parser.instruments.get(&trade.symbol)?.multiplier;
+        parser.statement.option_multipliers.insert(trade.symbol.clone(), multiplier);
+
+        trade.process(parser, multiplier).map_err(|e| format!(
+            "Failed to process {} trade: {}", trade.symbol, e))?;
+    }
+    Ok(())
+}
+
+pub struct PendingTrade {
+    symbol: String,
+    quantity: i32,
+    price: Cash,
+    volume: Cash,
+    commission: Cash,
+    conclusion_date: Date,
+    execution_date: Date,
+    short: bool,
+}
+
+impl PendingTrade {
+    fn parse(
+        parser: &mut StatementParser, record: &Record, symbol: &str, conclusion_date: Date,
+    ) -> GenericResult<PendingTrade> {
+        let currency = record.get_value("Currency")?;
+        let quantity: i32 = record.parse_value("Quantity")?;
+        let price = record.parse_cash("T. Price", currency, DecimalRestrictions::StrictlyPositive)?;
+        let commission = parse_commission(record, currency)?;
+        let execution_date = parser.get_execution_date(symbol, conclusion_date);
+
+        let volume = record.parse_cash("Proceeds", currency, if quantity < 0 {
+            DecimalRestrictions::StrictlyPositive
+        } else {
+            DecimalRestrictions::StrictlyNegative
+        })?;
+
+        // Sells which open a position ("O" code) are short sales
+        let short = quantity < 0 && record.get_value("Code").map(|codes| {
+            codes.split(';').any(|code| code == "O")
+        }).unwrap_or(false);
+
+        Ok(PendingTrade {
+            symbol: symbol.to_owned(), quantity, price, volume, commission,
+            conclusion_date, execution_date, short,
+        })
+    }
+
+    fn process(&self, parser: &mut StatementParser, multiplier: Decimal) -> EmptyResult {
+        let symbol = &self.symbol;
+        let quantity = self.quantity;
+        let volume = self.volume;
+
+        // Convert price to per-contract price to keep volume = price * quantity invariant
+        let price = self.price * multiplier;
+        debug_assert_eq!(volume.amount, currency::round_to((price * -quantity).amount, 4));
+
+        if quantity > 0 {
+            parser.statement.stock_buys.push(StockBuy::new(
+                symbol, quantity as u32, price, -volume, self.commission,
+                self.conclusion_date, self.execution_date));
+        } else if quantity < 0 {
+            parser.statement.stock_sells.push(StockSell::new(
+                symbol, -quantity as u32, price, volume, self.commission,
+                self.conclusion_date, self.execution_date,
+                if self.short { SaleKind::Short } else { SaleKind::Close }));
+        } else {
+            return Err!("Invalid quantity: {}", quantity)
+        }
+
+        Ok(())
+    }
+}
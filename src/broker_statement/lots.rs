@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+use crate::commissions::{CommissionCalc, CommissionSpec};
+use crate::core::GenericResult;
+use crate::currency::Cash;
+use crate::currency::converter::CurrencyConverter;
+use crate::localities::Country;
+use crate::types::{Date, Decimal, TradeType};
+use crate::util;
+
+use super::trades::StockBuy;
+
+/// Open FIFO lot (unsold part of a buy trade)
+#[derive(Debug)]
+pub struct TaxLot {
+    pub symbol: String,
+    pub quantity: u32,
+    pub acquisition_date: Date,
+
+    pub price: Cash,
+    pub basis: Decimal, // Per share basis in the specified currency (including commission)
+    pub value: Decimal,
+    pub unrealized_profit: Decimal,
+}
+
+pub fn calculate_tax_lots(
+    stock_buys: &[StockBuy], prices: &HashMap<String, Cash>, currency: &str,
+    converter: &CurrencyConverter,
+) -> GenericResult<Vec<TaxLot>> {
+    let mut lots = Vec::new();
+
+    for stock_buy in stock_buys {
+        if stock_buy.is_sold() {
+            continue;
+        }
+
+        let quantity = stock_buy.get_unsold();
+        let price = *prices.get(&stock_buy.symbol).ok_or_else(|| format!(
+            "There is no current price for {}", stock_buy.symbol))?;
+
+        let cost = converter.convert_to(
+            stock_buy.execution_date, stock_buy.volume / stock_buy.quantity, currency)?;
+        let commission = converter.convert_to(
+            stock_buy.conclusion_date, stock_buy.commission / stock_buy.quantity, currency)?;
+
+        let basis = cost + commission;
+        let value = converter.real_time_convert_to(price * quantity, currency)?;
+        let unrealized_profit = value - basis * Decimal::from(quantity);
+
+        lots.push(TaxLot {
+            symbol: stock_buy.symbol.clone(),
+            quantity,
+            acquisition_date: stock_buy.conclusion_date,
+
+            price: stock_buy.price,
+            basis,
+            value,
+            unrealized_profit,
+        });
+    }
+
+    lots.sort_by(|a, b| (&a.symbol, a.acquisition_date).cmp(&(&b.symbol, b.acquisition_date)));
+
+    Ok(lots)
+}
+
+/// Calculates the price at which the open position can be sold without loss taking into account
+/// sell commission and (optionally) the tax that will have to be paid for the sell.
+pub fn calculate_break_even_price(
+    stock_buys: &[StockBuy], symbol: &str, commission_spec: &CommissionSpec, date: Date,
+    tax_country: Option<&Country>, converter: &CurrencyConverter,
+) -> GenericResult<Option<Cash>> {
+    let mut quantity = 0;
+    let mut cost: Option<Cash> = None;
+    let mut local_cost = dec!(0);
+
+    for stock_buy in stock_buys {
+        if stock_buy.symbol != symbol || stock_buy.is_sold() {
+            continue;
+        }
+
+        let unsold = stock_buy.get_unsold();
+        let lot_volume = stock_buy.volume / stock_buy.quantity * unsold;
+        let lot_commission = stock_buy.commission / stock_buy.quantity * unsold;
+
+        let mut lot_cost = lot_volume;
+        lot_cost.add_assign(Cash::new(lot_volume.currency, converter.convert_to(
+            stock_buy.conclusion_date, lot_commission, lot_volume.currency)?)).unwrap();
+
+        match cost {
+            Some(ref mut cost) => cost.add_assign(lot_cost).map_err(|e| format!(
+                "{} trades have different currency: {}", symbol, e))?,
+            None => cost = Some(lot_cost),
+        };
+
+        if let Some(country) = tax_country {
+            local_cost += converter.convert_to(stock_buy.execution_date, lot_volume, country.currency)?;
+            local_cost += converter.convert_to(stock_buy.conclusion_date, lot_commission, country.currency)?;
+        }
+
+        quantity += unsold;
+    }
+
+    let cost = match cost {
+        Some(cost) => cost,
+        None => return Ok(None),
+    };
+    let currency = cost.currency;
+
+    let calculate_result = |price: Decimal| -> GenericResult<Decimal> {
+        let price = Cash::new(currency, price);
+
+        let mut commission_calc = CommissionCalc::new(commission_spec.clone());
+        let commission = commission_calc.add_trade(date, TradeType::Sell, quantity, price)?;
+        let commission = converter.convert_to(date, commission, currency)?;
+
+        let mut result = (price * quantity).amount - commission;
+
+        if let Some(country) = tax_country {
+            let local_revenue = converter.convert(currency, country.currency, date, result)?;
+            let tax_to_pay = country.tax_to_pay(local_revenue - local_cost, None);
+            result -= converter.convert(country.currency, currency, date, tax_to_pay)?;
+        }
+
+        Ok(result)
+    };
+
+    // The result is a monotonic function of price with derivative close to quantity (a little less
+    // because of taxes and percent commissions), so the iteration converges pretty fast.
+    let shares = Decimal::from(quantity);
+    let mut price = cost.amount / shares;
+
+    for _ in 0..100 {
+        let shortage = cost.amount - calculate_result(price)?;
+        if shortage.abs() < dec!(0.0001) {
+            break;
+        }
+        price += shortage / shares;
+    }
+
+    Ok(Some(Cash::new(currency, util::round(price, 2))))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::commissions::{
+        CommissionSpecBuilder, TradeCommissionSpecBuilder, TransactionCommissionSpecBuilder};
+    use crate::config::Config;
+    use crate::db;
+    use crate::localities;
+
+    use super::*;
+
+    #[test]
+    fn open_lots() {
+        let (_database, connection) = db::new_temporary();
+        let converter = CurrencyConverter::new(&Config::mock(), connection, None, false);
+
+        let currency = "USD";
+        let mut stock_buys = vec![
+            mock_buy(10, dec!(100), dec!(1), date!(1, 2, 2020)),
+            mock_buy(5, dec!(120), dec!(0.5), date!(1, 3, 2020)),
+        ];
+
+        // Partial sell of the first lot
+        stock_buys[0].sell(7);
+
+        let prices = hashmap!{s!("AAA") => Cash::new(currency, dec!(110))};
+        let lots = calculate_tax_lots(&stock_buys, &prices, currency, &converter).unwrap();
+        assert_eq!(lots.len(), 2);
+
+        let lot = &lots[0];
+        assert_eq!(lot.quantity, 3);
+        assert_eq!(lot.acquisition_date, date!(1, 2, 2020));
+        assert_eq!(lot.basis, dec!(100.1));
+        assert_eq!(lot.value, dec!(330));
+        assert_eq!(lot.unrealized_profit, dec!(29.7));
+
+        let lot = &lots[1];
+        assert_eq!(lot.quantity, 5);
+        assert_eq!(lot.acquisition_date, date!(1, 3, 2020));
+        assert_eq!(lot.basis, dec!(120.1));
+        assert_eq!(lot.value, dec!(550));
+        assert_eq!(lot.unrealized_profit, dec!(-50.5));
+    }
+
+    #[test]
+    fn break_even_price() {
+        let converter = CurrencyConverter::mock(dec!(60), &[(date!(1, 1, 2021), dec!(70))]);
+        let country = localities::russia();
+        let date = date!(1, 2, 2021);
+
+        let free = CommissionSpecBuilder::new("USD").build();
+        let per_share = CommissionSpecBuilder::new("USD")
+            .trade(TradeCommissionSpecBuilder::new()
+                .commission(TransactionCommissionSpecBuilder::new()
+                    .per_share(dec!(1))
+                    .build().unwrap())
+                .build())
+            .build();
+
+        let mut stock_buys = vec![
+            mock_buy(10, dec!(100), dec!(0), date!(1, 2, 2020)),
+            mock_buy(5, dec!(90), dec!(0), date!(1, 3, 2020)),
+        ];
+        stock_buys[0].sell(10);
+
+        let calculate = |stock_buys: &[StockBuy], commission_spec, tax_country| {
+            calculate_break_even_price(
+                stock_buys, "AAA", commission_spec, date, tax_country, &converter).unwrap()
+        };
+
+        let price = calculate(&stock_buys, &free, None).unwrap().amount;
+        assert_eq!(price, dec!(90));
+
+        let commission_adjusted_price = calculate(&stock_buys, &per_share, None).unwrap().amount;
+        assert_eq!(commission_adjusted_price, dec!(91));
+
+        // USD/RUB has grown, so there is taxable income in RUB even with zero profit in USD
+        let tax_adjusted_price = calculate(&stock_buys, &per_share, Some(&country)).unwrap().amount;
+        assert!(tax_adjusted_price > commission_adjusted_price);
+        assert_eq!(util::round(tax_adjusted_price, 1), dec!(92.9));
+
+        stock_buys[1].sell(5);
+        assert_eq!(calculate(&stock_buys, &per_share, Some(&country)), None);
+    }
+
+    fn mock_buy(quantity: u32, price: Decimal, commission: Decimal, date: Date) -> StockBuy {
+        let currency = "USD";
+        let price = Cash::new(currency, price);
+
+        StockBuy::new(
+            "AAA", quantity, price, price * quantity, Cash::new(currency, commission),
+            date, date)
+    }
+}
@@ -13,20 +13,21 @@ pub enum StatementsMergingStrategy {
 
 impl StatementsMergingStrategy {
     pub fn validate(self, first: (Date, Date), second: (Date, Date)) -> EmptyResult {
-        let error = |message| {
+        let error = |message, period| {
+            let period = formatting::format_period(period);
             let first = formatting::format_period(first);
             let second = formatting::format_period(second);
-            Err!("{}: {}, {}", message, first, second)
+            Err!("{} {} between statement periods: {}, {}", message, period, first, second)
         };
 
         if second.0 < first.1 {
-            return error("Overlapping periods");
+            return error("Got an overlap of", (second.0, std::cmp::min(first.1, second.1)));
         }
 
         match self {
             StatementsMergingStrategy::ContinuousOnly => {
                 if second.0 != first.1 {
-                    return error("Non-continuous periods");
+                    return error("Got a gap of", (first.1, second.0));
                 }
             },
             StatementsMergingStrategy::SparseOnHolidays(max_days) => {
@@ -36,7 +37,7 @@ impl StatementsMergingStrategy {
                 while date < second.0 {
                     if !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
                         if missing_days >= max_days {
-                            return error("Non-continuous periods");
+                            return error("Got a gap of", (first.1, second.0));
                         }
                         missing_days += 1;
                     }
@@ -48,4 +49,65 @@ impl StatementsMergingStrategy {
 
         Ok(())
     }
+}
+
+/// Removes operations from the overlapping part of a new statement which are already present in the
+/// previous statements. Operations are matched one-to-one, so equal operations of a single statement
+/// are preserved.
+pub fn deduplicate<T, K, D, F>(existing: &[T], new: &mut Vec<T>, overlap: (Date, Date), get_date: D, get_key: F)
+    where K: PartialEq, D: Fn(&T) -> Date, F: Fn(&T) -> K
+{
+    let is_overlapping = |item: &T| {
+        let date = get_date(item);
+        overlap.0 <= date && date < overlap.1
+    };
+
+    let mut existing_keys: Vec<K> = existing.iter()
+        .filter(|&item| is_overlapping(item))
+        .map(&get_key)
+        .collect();
+
+    new.retain(|item| {
+        if !is_overlapping(item) {
+            return true;
+        }
+
+        let key = get_key(item);
+
+        match existing_keys.iter().position(|existing_key| *existing_key == key) {
+            Some(index) => {
+                existing_keys.remove(index);
+                false
+            },
+            None => true,
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation() {
+        let strategy = StatementsMergingStrategy::ContinuousOnly;
+        let first = (date!(1, 1, 2020), date!(1, 2, 2020));
+
+        assert!(strategy.validate(first, (date!(1, 2, 2020), date!(1, 3, 2020))).is_ok());
+
+        assert_eq!(
+            strategy.validate(first, (date!(15, 1, 2020), date!(1, 3, 2020))).unwrap_err().to_string(),
+            "Got an overlap of 15.01.2020 - 31.01.2020 between statement periods: \
+             01.01.2020 - 31.01.2020, 15.01.2020 - 29.02.2020");
+
+        assert_eq!(
+            strategy.validate(first, (date!(1, 3, 2020), date!(1, 4, 2020))).unwrap_err().to_string(),
+            "Got a gap of 01.02.2020 - 29.02.2020 between statement periods: \
+             01.01.2020 - 31.01.2020, 01.03.2020 - 31.03.2020");
+
+        // 01.02.2020 is Saturday
+        let strategy = StatementsMergingStrategy::SparseOnHolidays(1);
+        assert!(strategy.validate(first, (date!(4, 2, 2020), date!(1, 3, 2020))).is_ok());
+        assert!(strategy.validate(first, (date!(5, 2, 2020), date!(1, 3, 2020))).is_err());
+    }
 }
\ No newline at end of file
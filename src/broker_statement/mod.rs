@@ -1,7 +1,12 @@
+mod bonds;
+mod corporate_actions;
 mod dividends;
+mod export;
 mod fees;
 mod interest;
+mod lots;
 mod merging;
+mod options;
 mod partial;
 mod payments;
 mod taxes;
@@ -12,36 +17,50 @@ mod bcs;
 mod firstrade;
 mod ib;
 mod open;
+mod sber;
+mod schwab;
 mod tinkoff;
 
 use std::{self, fs};
-use std::collections::{HashMap, HashSet, BTreeMap};
+use std::collections::{HashMap, HashSet, BTreeMap, BTreeSet};
 use std::collections::hash_map::Entry;
+use std::io::Write;
 use std::path::Path;
 
 use chrono::Duration;
 use log::{debug, warn};
+use rayon::prelude::*;
+use regex::Regex;
 
 use crate::brokers::{Broker, BrokerInfo};
 use crate::commissions::CommissionCalc;
 use crate::core::{EmptyResult, GenericResult};
 use crate::currency::{Cash, CashAssets, MultiCurrencyCashAccount};
+use crate::currency::converter::CurrencyConverter;
 use crate::formatting;
-use crate::localities;
+use crate::localities::{self, Country};
 use crate::quotes::Quotes;
 use crate::taxes::TaxRemapping;
 use crate::types::{Date, Decimal, TradeType};
 use crate::util;
 
-use self::dividends::{DividendAccruals, process_dividend_accruals};
+use self::dividends::{DividendId, DividendAccruals, process_dividend_accruals};
 use self::partial::PartialBrokerStatement;
 use self::taxes::{TaxId, TaxAccruals};
 
+pub use self::bonds::{BondAmortization, Coupon};
+pub use self::corporate_actions::{CashInLieu, StockSplit};
 pub use self::dividends::Dividend;
+pub use self::export::TransactionsExporter;
 pub use self::fees::Fee;
 pub use self::interest::IdleCashInterest;
+pub use self::lots::TaxLot;
 pub use self::merging::StatementsMergingStrategy;
-pub use self::trades::{ForexTrade, StockBuy, StockSell, StockSellSource, SellDetails, FifoDetails};
+pub use self::options::{OptionStrategy, OptionStrategyKind};
+pub use self::partial::UnsupportedOperation;
+pub use self::trades::{
+    ForexTrade, ForexConversionCost, StockBuy, StockSell, StockSellSource, SellDetails, FifoDetails,
+    SaleKind, TaxLotMethod};
 
 #[derive(Debug)]
 pub struct BrokerStatement {
@@ -53,58 +72,41 @@ pub struct BrokerStatement {
 
     pub fees: Vec<Fee>,
     pub cash_flows: Vec<CashAssets>,
+    pub deposit_commissions: Vec<Fee>,
     pub idle_cash_interest: Vec<IdleCashInterest>,
+    pub coupons: Vec<Coupon>,
+    pub bond_amortizations: Vec<BondAmortization>,
 
     pub forex_trades: Vec<ForexTrade>,
     pub stock_buys: Vec<StockBuy>,
     pub stock_sells: Vec<StockSell>,
     pub dividends: Vec<Dividend>,
+    pub cash_in_lieu: Vec<CashInLieu>,
+    stock_splits: Vec<StockSplit>,
 
     pub open_positions: HashMap<String, u32>,
     instrument_names: HashMap<String, String>,
+    option_multipliers: HashMap<String, Decimal>,
 }
 
 impl BrokerStatement {
     pub fn read(
-        broker: BrokerInfo, statement_dir_path: &str,
+        broker: BrokerInfo, statements_path: &str,
         symbol_remapping: &HashMap<String, String>, instrument_names: &HashMap<String, String>,
         tax_remapping: TaxRemapping, strict_mode: bool,
     ) -> GenericResult<BrokerStatement> {
-        let mut tax_remapping = Some(tax_remapping);
-        let mut statement_reader = match broker.type_ {
-            Broker::Bcs => bcs::StatementReader::new(),
-            Broker::Firstrade => firstrade::StatementReader::new(),
-            Broker::InteractiveBrokers => ib::StatementReader::new(
-                tax_remapping.take().unwrap(), strict_mode),
-            Broker::Open => open::StatementReader::new(),
-            Broker::Tinkoff => tinkoff::StatementReader::new(),
-        }?;
-
-        let mut file_names = get_statement_files(statement_dir_path, statement_reader.as_ref())
-            .map_err(|e| format!("Error while reading {:?}: {}", statement_dir_path, e))?;
-
-        if file_names.is_empty() {
-            return Err!("{:?} doesn't contain any broker statement", statement_dir_path);
-        }
-
-        file_names.sort();
-
-        let mut statements = Vec::new();
-
-        for file_name in &file_names {
-            let path = Path::new(statement_dir_path).join(file_name);
-            let path = path.to_str().unwrap();
-
-            let statement = statement_reader.read(path).map_err(|e| format!(
-                "Error while reading {:?} broker statement: {}", path, e))?;
-
-            statements.push(statement);
-        }
+        BrokerStatement::read_impl(
+            broker, statements_path, symbol_remapping, instrument_names, tax_remapping,
+            strict_mode, true)
+    }
 
-        if let Some(tax_remapping) = tax_remapping {
-            tax_remapping.ensure_all_mapped()?;
-        }
-        statement_reader.close()?;
+    fn read_impl(
+        broker: BrokerInfo, statements_path: &str,
+        symbol_remapping: &HashMap<String, String>, instrument_names: &HashMap<String, String>,
+        tax_remapping: TaxRemapping, strict_mode: bool, concurrent: bool,
+    ) -> GenericResult<BrokerStatement> {
+        let statements = read_statements(
+            &broker, statements_path, tax_remapping, strict_mode, concurrent)?;
 
         let joint_statement = BrokerStatement::new_from(
             broker, statements, symbol_remapping, instrument_names)?;
@@ -113,8 +115,28 @@ impl BrokerStatement {
     }
 
     fn new_from(
-        broker: BrokerInfo, mut statements: Vec<PartialBrokerStatement>,
+        broker: BrokerInfo, statements: Vec<PartialBrokerStatement>,
         symbol_remapping: &HashMap<String, String>, instrument_names: &HashMap<String, String>,
+    ) -> GenericResult<BrokerStatement> {
+        let mut statement = BrokerStatement::merge_statements(broker, statements)?;
+
+        statement.remap_symbols(symbol_remapping)?;
+        statement.instrument_names.extend(
+            instrument_names.iter().map(|(symbol, name)| (symbol.clone(), name.clone())));
+
+        statement.validate()?;
+        statement.deposit_commissions = statement.broker.get_deposit_commissions(&statement.cash_flows)?;
+        statement.process_accrued_interest()?;
+        statement.process_bond_amortizations()?;
+        statement.process_trades(TaxLotMethod::Fifo)?;
+        statement.validate_cash_assets();
+
+        Ok(statement)
+    }
+
+    /// Merges deduplicated partial statements into one statement without any further processing
+    fn merge_statements(
+        broker: BrokerInfo, mut statements: Vec<PartialBrokerStatement>,
     ) -> GenericResult<BrokerStatement> {
         statements.sort_by(|a, b| a.period.unwrap().0.cmp(&b.period.unwrap().0));
 
@@ -123,6 +145,10 @@ impl BrokerStatement {
         let mut tax_accruals = HashMap::new();
 
         for mut partial in statements.drain(..) {
+            if !statement.deduplicate(&mut partial, &dividend_accruals, &tax_accruals)? {
+                continue;
+            }
+
             for (dividend_id, accruals) in partial.dividend_accruals.drain() {
                 dividend_accruals.entry(dividend_id)
                     .and_modify(|existing: &mut DividendAccruals| existing.merge(&accruals))
@@ -156,13 +182,6 @@ impl BrokerStatement {
             return Err!("Unable to find origin operations for the following taxes:\n{}", taxes);
         }
 
-        statement.remap_symbols(symbol_remapping)?;
-        statement.instrument_names.extend(
-            instrument_names.iter().map(|(symbol, name)| (symbol.clone(), name.clone())));
-
-        statement.validate()?;
-        statement.process_trades()?;
-
         Ok(statement)
     }
 
@@ -183,15 +202,21 @@ impl BrokerStatement {
 
             fees: Vec::new(),
             cash_flows: Vec::new(),
+            deposit_commissions: Vec::new(),
             idle_cash_interest: Vec::new(),
+            coupons: Vec::new(),
+            bond_amortizations: Vec::new(),
 
             forex_trades: Vec::new(),
             stock_buys: Vec::new(),
             stock_sells: Vec::new(),
             dividends: Vec::new(),
+            cash_in_lieu: Vec::new(),
+            stock_splits: Vec::new(),
 
             open_positions: HashMap::new(),
             instrument_names: HashMap::new(),
+            option_multipliers: HashMap::new(),
         })
     }
 
@@ -244,6 +269,30 @@ impl BrokerStatement {
         }
     }
 
+    pub fn get_tax_lots(
+        &self, quotes: &Quotes, currency: &str, converter: &CurrencyConverter,
+    ) -> GenericResult<Vec<TaxLot>> {
+        let mut prices = HashMap::new();
+
+        for symbol in self.open_positions.keys() {
+            prices.insert(symbol.clone(), quotes.get(symbol)?);
+        }
+
+        lots::calculate_tax_lots(&self.stock_buys, &prices, currency, converter)
+    }
+
+    pub fn get_break_even_price(
+        &self, symbol: &str, tax_country: Option<&Country>, converter: &CurrencyConverter,
+    ) -> GenericResult<Option<Cash>> {
+        lots::calculate_break_even_price(
+            &self.stock_buys, symbol, &self.broker.commission_spec,
+            util::today_trade_execution_date(), tax_country, converter)
+    }
+
+    pub fn get_option_strategies(&self) -> GenericResult<Vec<OptionStrategy>> {
+        options::group_option_trades(&self.stock_buys, &self.stock_sells, &self.option_multipliers)
+    }
+
     pub fn emulate_sell(
         &mut self, symbol: &str, quantity: u32, price: Cash, commission_calc: &mut CommissionCalc
     ) -> EmptyResult {
@@ -261,7 +310,7 @@ impl BrokerStatement {
 
         let stock_cell = StockSell::new(
             symbol, quantity, price, price * quantity, commission,
-            conclusion_date, execution_date, true);
+            conclusion_date, execution_date, SaleKind::Emulation);
 
         if let Entry::Occupied(mut open_position) = self.open_positions.entry(symbol.to_owned()) {
             let available = *open_position.get();
@@ -295,7 +344,7 @@ impl BrokerStatement {
         total
     }
 
-    pub fn process_trades(&mut self) -> EmptyResult {
+    pub fn process_trades(&mut self, method: TaxLotMethod) -> EmptyResult {
         let mut unsold_buys: HashMap<String, Vec<usize>> = HashMap::new();
 
         for (index, stock_buy) in self.stock_buys.iter().enumerate().rev() {
@@ -311,7 +360,19 @@ impl BrokerStatement {
             symbol_buys.push(index);
         }
 
-        for stock_sell in &mut self.stock_sells {
+        // Splits are consumed by the processing, so they are never applied twice
+        let mut stock_splits = std::mem::replace(&mut self.stock_splits, Vec::new());
+        stock_splits.sort_by_key(|split| split.date);
+        let mut stock_splits = stock_splits.into_iter().peekable();
+
+        for sell_index in 0..self.stock_sells.len() {
+            let sell_date = self.stock_sells[sell_index].conclusion_date;
+
+            while stock_splits.peek().map(|split| split.date <= sell_date).unwrap_or(false) {
+                self.apply_stock_split(&stock_splits.next().unwrap(), &mut unsold_buys)?;
+            }
+
+            let stock_sell = &mut self.stock_sells[sell_index];
             if stock_sell.is_processed() {
                 continue;
             }
@@ -319,16 +380,42 @@ impl BrokerStatement {
             let mut remaining_quantity = stock_sell.quantity;
             let mut sources = Vec::new();
 
-            let symbol_buys = unsold_buys.get_mut(&stock_sell.symbol).ok_or_else(|| format!(
-                "Error while processing {} position closing: There are no open positions for it",
-                stock_sell.symbol
-            ))?;
+            let short = stock_sell.kind == SaleKind::Short;
+            let no_positions_error = |symbol: &str| if short {
+                format!(concat!(
+                    "Error while processing {} short sale: It isn't covered by the following purchases ",
+                    "(open short positions aren't supported)"), symbol)
+            } else {
+                format!(
+                    "Error while processing {} position closing: There are no open positions for it",
+                    symbol)
+            };
+
+            let symbol_buys = unsold_buys.get_mut(&stock_sell.symbol).ok_or_else(|| {
+                no_positions_error(&stock_sell.symbol)
+            })?;
 
             while remaining_quantity > 0 {
-                let index = symbol_buys.last().copied().ok_or_else(|| format!(
-                    "Error while processing {} position closing: There are no open positions for it",
-                    stock_sell.symbol
-                ))?;
+                // The stack is in reverse order: the oldest lot is the last one
+                let position = match method {
+                    // Short sale is covered by the oldest purchases made after it regardless of tax
+                    // lot method
+                    _ if short => {
+                        let stock_buys = &self.stock_buys;
+                        symbol_buys.iter().rposition(|&index| {
+                            stock_buys[index].conclusion_date >= sell_date
+                        })
+                    },
+                    TaxLotMethod::Fifo => symbol_buys.len().checked_sub(1),
+                    TaxLotMethod::Lifo => {
+                        let stock_buys = &self.stock_buys;
+                        symbol_buys.iter().position(|&index| {
+                            stock_buys[index].conclusion_date <= sell_date
+                        })
+                    },
+                }.ok_or_else(|| no_positions_error(&stock_sell.symbol))?;
+
+                let index = symbol_buys[position];
                 let stock_buy = &mut self.stock_buys[index];
 
                 let sell_quantity = std::cmp::min(remaining_quantity, stock_buy.get_unsold());
@@ -347,16 +434,132 @@ impl BrokerStatement {
                 stock_buy.sell(sell_quantity);
 
                 if stock_buy.is_sold() {
-                    symbol_buys.pop();
+                    symbol_buys.remove(position);
                 }
             }
 
             stock_sell.process(sources);
         }
 
+        for stock_split in stock_splits {
+            self.apply_stock_split(&stock_split, &mut unsold_buys)?;
+        }
+
+        // Drop the lots which have been fully merged into other ones by stock splits
+        self.stock_buys.retain(|trade| trade.quantity != 0);
+
         self.validate_open_positions()
     }
 
+    fn apply_stock_split(
+        &mut self, split: &StockSplit, unsold_buys: &mut HashMap<String, Vec<usize>>,
+    ) -> EmptyResult {
+        let symbol_buys = unsold_buys.get_mut(&split.symbol).ok_or_else(|| format!(
+            "Error while processing {} stock split: There are no open positions for it",
+            split.symbol))?;
+
+        // The stack is in reverse order: the oldest lot is the last one
+        let lots: Vec<usize> = symbol_buys.iter().rev().copied()
+            .filter(|&index| self.stock_buys[index].conclusion_date < split.date)
+            .collect();
+
+        let quantities: Vec<u32> = lots.iter()
+            .map(|&index| self.stock_buys[index].get_unsold())
+            .collect();
+
+        let (quantities, cash_in_lieu) = split.split_lots(&quantities)?;
+        if !lots.is_empty() && quantities.iter().all(|&quantity| quantity == 0) {
+            return Err!(
+                "Error while processing {} stock split: The whole position is paid out as cash-in-lieu",
+                split.symbol);
+        }
+
+        // Small lots may get no shares after rounding. Their cost basis is carried over to the
+        // next lot which receives shares (or to the previous one for the newest lots).
+        let mut carried_cost: Option<(Cash, Cash)> = None;
+        let mut last_lot = None;
+
+        for (&index, quantity) in lots.iter().zip(quantities) {
+            let position = symbol_buys.iter().position(|&other| other == index).unwrap();
+
+            if quantity == 0 {
+                let (volume, commission) = self.stock_buys[index].detach_unsold();
+                carried_cost = Some(match carried_cost {
+                    Some((carried_volume, carried_commission)) => (
+                        carried_volume.add(volume)?, carried_commission.add(commission)?),
+                    None => (volume, commission),
+                });
+                symbol_buys.remove(position);
+                continue;
+            }
+
+            let lot_index = match self.stock_buys[index].split(quantity) {
+                Some(lot) => {
+                    self.stock_buys.push(lot);
+                    symbol_buys[position] = self.stock_buys.len() - 1;
+                    self.stock_buys.len() - 1
+                },
+                None => index,
+            };
+
+            if let Some((volume, commission)) = carried_cost.take() {
+                self.stock_buys[lot_index].add_cost(volume, commission)?;
+            }
+
+            last_lot.replace(lot_index);
+        }
+
+        if let Some((volume, commission)) = carried_cost {
+            self.stock_buys[last_lot.unwrap()].add_cost(volume, commission)?;
+        }
+
+        if let Some(amount) = cash_in_lieu {
+            self.cash_in_lieu.push(CashInLieu {
+                date: split.date,
+                symbol: split.symbol.clone(),
+                amount,
+            });
+        }
+
+        Ok(())
+    }
+
+    // A wrong symbol remapping silently corrupts trading history, so check that the symbols look
+    // like the same instrument
+    fn check_symbols_compatibility(&self, symbol: &str, other: &str) -> EmptyResult {
+        let get_trades = |symbol: &str| -> Vec<(Date, u32, Cash)> {
+            self.stock_buys.iter()
+                .filter(|trade| trade.symbol == symbol)
+                .map(|trade| (trade.conclusion_date, trade.quantity, trade.price))
+                .chain(self.stock_sells.iter()
+                    .filter(|trade| trade.symbol == symbol)
+                    .map(|trade| (trade.conclusion_date, trade.quantity, trade.price)))
+                .collect()
+        };
+
+        let get_currencies = |trades: &[(Date, u32, Cash)]| -> BTreeSet<&'static str> {
+            trades.iter().map(|trade| trade.2.currency).collect()
+        };
+
+        let (trades, other_trades) = (get_trades(symbol), get_trades(other));
+        let (currencies, other_currencies) = (get_currencies(&trades), get_currencies(&other_trades));
+
+        if !currencies.is_empty() && !other_currencies.is_empty() && currencies != other_currencies {
+            return Err!("{} and {} are traded in different currencies ({} vs {})",
+                symbol, other, currencies.into_iter().collect::<Vec<_>>().join(", "),
+                other_currencies.into_iter().collect::<Vec<_>>().join(", "));
+        }
+
+        if let Some(trade) = trades.iter().find(|&trade| other_trades.contains(trade)) {
+            warn!(concat!(
+                "{} and {} have identical trades from {}. ",
+                "Please check symbol remapping configuration: the trades may be duplicated."
+            ), symbol, other, formatting::format_date(trade.0));
+        }
+
+        Ok(())
+    }
+
     pub fn merge_symbols(&mut self, symbols_to_merge: &HashMap<String, HashSet<String>>) -> EmptyResult {
         assert!(self.open_positions.is_empty());
         assert!(!self.stock_buys.iter().any(|stock_buy| !stock_buy.is_sold()));
@@ -397,6 +600,72 @@ impl BrokerStatement {
         Ok(())
     }
 
+    /// Brokers allow to download statements for overlapping periods, so removes operations of the
+    /// overlapping part that are already present in the previous statements. Returns false if the
+    /// statement is fully covered by the previous ones and must be skipped.
+    fn deduplicate(
+        &self, statement: &mut PartialBrokerStatement,
+        dividend_accruals: &HashMap<DividendId, DividendAccruals>,
+        tax_accruals: &HashMap<TaxId, TaxAccruals>,
+    ) -> GenericResult<bool> {
+        let period = statement.get_period()?;
+        if period.0 >= self.period.1 {
+            return Ok(true);
+        }
+
+        let overlap = (period.0, std::cmp::min(period.1, self.period.1));
+        let is_overlapping = |date: Date| overlap.0 <= date && date < overlap.1;
+
+        merging::deduplicate(
+            &self.cash_flows, &mut statement.cash_flows, overlap,
+            |assets| assets.date, |assets| (assets.date, assets.cash));
+        merging::deduplicate(
+            &self.fees, &mut statement.fees, overlap,
+            |fee| fee.date, |fee| (fee.date, fee.amount, fee.description.clone()));
+        merging::deduplicate(
+            &self.idle_cash_interest, &mut statement.idle_cash_interest, overlap,
+            |interest| interest.date, |interest| (interest.date, interest.amount));
+        merging::deduplicate(
+            &self.coupons, &mut statement.coupons, overlap,
+            |coupon| coupon.date, |coupon| (coupon.date, coupon.symbol.clone(), coupon.amount));
+        merging::deduplicate(
+            &self.bond_amortizations, &mut statement.bond_amortizations, overlap,
+            |amortization| amortization.date,
+            |amortization| (amortization.date, amortization.symbol.clone(), amortization.amount));
+
+        merging::deduplicate(
+            &self.forex_trades, &mut statement.forex_trades, overlap,
+            |trade| trade.conclusion_date, |trade| (trade.conclusion_date, trade.from, trade.to));
+        merging::deduplicate(
+            &self.stock_buys, &mut statement.stock_buys, overlap,
+            |trade| trade.conclusion_date,
+            |trade| (trade.id.clone(), trade.conclusion_date, trade.symbol.clone(), trade.quantity, trade.price));
+        merging::deduplicate(
+            &self.stock_sells, &mut statement.stock_sells, overlap,
+            |trade| trade.conclusion_date,
+            |trade| (trade.id.clone(), trade.conclusion_date, trade.symbol.clone(), trade.quantity, trade.price));
+        merging::deduplicate(
+            &self.dividends, &mut statement.dividends, overlap,
+            |dividend| dividend.date, |dividend| (dividend.date, dividend.issuer.clone()));
+        merging::deduplicate(
+            &self.stock_splits, &mut statement.stock_splits, overlap,
+            |split| split.date, |split| (split.date, split.symbol.clone()));
+
+        statement.dividend_accruals.retain(|dividend_id, _| {
+            !is_overlapping(dividend_id.date) || !dividend_accruals.contains_key(dividend_id)
+        });
+        statement.tax_accruals.retain(|tax_id, _| {
+            !is_overlapping(tax_id.date) || !tax_accruals.contains_key(tax_id)
+        });
+
+        if period.1 <= self.period.1 {
+            return Ok(false);
+        }
+
+        statement.period.replace((self.period.1, period.1));
+        Ok(true)
+    }
+
     fn merge(&mut self, mut statement: PartialBrokerStatement) -> EmptyResult {
         let period = statement.get_period()?;
         self.broker.statements_merging_strategy.validate(self.period, period)?;
@@ -408,32 +677,42 @@ impl BrokerStatement {
         self.fees.extend(statement.fees.drain(..));
         self.cash_flows.extend(statement.cash_flows.drain(..));
         self.idle_cash_interest.extend(statement.idle_cash_interest.drain(..));
+        self.coupons.extend(statement.coupons.drain(..));
+        self.bond_amortizations.extend(statement.bond_amortizations.drain(..));
 
         self.forex_trades.extend(statement.forex_trades.drain(..));
         self.stock_buys.extend(statement.stock_buys.drain(..));
         self.stock_sells.extend(statement.stock_sells.drain(..));
         self.dividends.extend(statement.dividends.drain(..));
+        self.stock_splits.extend(statement.stock_splits.drain(..));
 
         self.open_positions = statement.open_positions;
         self.instrument_names.extend(statement.instrument_names.drain());
+        self.option_multipliers.extend(statement.option_multipliers.drain());
 
         Ok(())
     }
 
     fn remap_symbols(&mut self, remapping: &HashMap<String, String>) -> EmptyResult {
         for (symbol, mapping) in remapping {
-            if self.open_positions.contains_key(mapping) || self.instrument_names.contains_key(mapping) {
+            // The target symbol may already exist in the statements when the instrument has been
+            // renamed in the middle of the statements period, but both of them can't be held at
+            // the same time.
+            if self.open_positions.contains_key(symbol) && self.open_positions.contains_key(mapping) {
                 return Err!(
                     "Invalid symbol remapping configuration: The portfolio already has {} symbol",
                     mapping);
             }
 
+            self.check_symbols_compatibility(symbol, mapping).map_err(|e| format!(
+                "Invalid symbol remapping configuration: {}", e))?;
+
             if let Some(quantity) = self.open_positions.remove(symbol) {
                 self.open_positions.insert(mapping.to_owned(), quantity);
             }
 
             if let Some(name) = self.instrument_names.remove(symbol) {
-                self.instrument_names.insert(mapping.to_owned(), name);
+                self.instrument_names.entry(mapping.to_owned()).or_insert(name);
             }
         }
 
@@ -449,12 +728,30 @@ impl BrokerStatement {
             }
         }
 
+        for stock_split in &mut self.stock_splits {
+            if let Some(mapping) = remapping.get(&stock_split.symbol) {
+                stock_split.symbol = mapping.to_owned();
+            }
+        }
+
         for dividend in &mut self.dividends {
             if let Some(mapping) = remapping.get(&dividend.issuer) {
                 dividend.issuer = mapping.to_owned();
             }
         }
 
+        for coupon in &mut self.coupons {
+            if let Some(mapping) = remapping.get(&coupon.symbol) {
+                coupon.symbol = mapping.to_owned();
+            }
+        }
+
+        for amortization in &mut self.bond_amortizations {
+            if let Some(mapping) = remapping.get(&amortization.symbol) {
+                amortization.symbol = mapping.to_owned();
+            }
+        }
+
         Ok(())
     }
 
@@ -496,6 +793,20 @@ impl BrokerStatement {
             validate_date("idle cash interest", first_date, last_date)?;
         }
 
+        if !self.coupons.is_empty() {
+            self.coupons.sort_by(|a, b| (a.date, &a.symbol).cmp(&(b.date, &b.symbol)));
+            let first_date = self.coupons.first().unwrap().date;
+            let last_date = self.coupons.last().unwrap().date;
+            validate_date("coupon", first_date, last_date)?;
+        }
+
+        if !self.bond_amortizations.is_empty() {
+            self.bond_amortizations.sort_by(|a, b| (a.date, &a.symbol).cmp(&(b.date, &b.symbol)));
+            let first_date = self.bond_amortizations.first().unwrap().date;
+            let last_date = self.bond_amortizations.last().unwrap().date;
+            validate_date("bond amortization", first_date, last_date)?;
+        }
+
         if !self.forex_trades.is_empty() {
             self.forex_trades.sort_by_key(|trade| trade.conclusion_date);
             let first_date = self.forex_trades.first().unwrap().conclusion_date;
@@ -527,6 +838,104 @@ impl BrokerStatement {
         Ok(())
     }
 
+    // Accrued interest received on bond sale is a coupon income for the seller, and accrued
+    // interest paid on bond purchase reduces taxable income of the following coupons.
+    fn process_accrued_interest(&mut self) -> EmptyResult {
+        for trade in &self.stock_sells {
+            if let Some(accrued_interest) = trade.accrued_interest {
+                self.coupons.push(Coupon::new(trade.conclusion_date, &trade.symbol, accrued_interest));
+            }
+        }
+        self.coupons.sort_by(|a, b| (a.date, &a.symbol).cmp(&(b.date, &b.symbol)));
+
+        for trade in &self.stock_buys {
+            let mut remaining = match trade.accrued_interest {
+                Some(accrued_interest) => accrued_interest,
+                None => continue,
+            };
+
+            for coupon in &mut self.coupons {
+                if coupon.symbol != trade.symbol || coupon.date < trade.conclusion_date {
+                    continue;
+                } else if remaining.is_zero() {
+                    break;
+                }
+
+                let taxable_amount = coupon.taxable_amount();
+                if taxable_amount.currency != remaining.currency {
+                    return Err!(
+                        "{} coupon is paid in a currency different from the trade currency",
+                        trade.symbol);
+                }
+
+                let deduction = Cash::new(remaining.currency, std::cmp::min(
+                    remaining.amount, taxable_amount.amount));
+
+                coupon.paid_accrued_interest.add_assign(deduction).unwrap();
+                remaining.sub_assign(deduction).unwrap();
+            }
+        }
+
+        Ok(())
+    }
+
+    // Amortization returns a part of bond principal, so reduce cost basis of the bonds held at
+    // amortization date
+    fn process_bond_amortizations(&mut self) -> EmptyResult {
+        for amortization in &self.bond_amortizations {
+            let symbol = &amortization.symbol;
+
+            // Bonds sold before the amortization date are taken from the oldest lots (FIFO)
+            let mut sold: u32 = self.stock_sells.iter()
+                .filter(|trade| &trade.symbol == symbol && trade.conclusion_date < amortization.date)
+                .map(|trade| trade.quantity)
+                .sum();
+
+            let mut lots = Vec::new();
+
+            for (index, trade) in self.stock_buys.iter().enumerate() {
+                if &trade.symbol != symbol || trade.conclusion_date >= amortization.date {
+                    continue;
+                }
+
+                let sold_quantity = std::cmp::min(sold, trade.quantity);
+                sold -= sold_quantity;
+
+                if sold_quantity < trade.quantity {
+                    lots.push((index, trade.quantity - sold_quantity));
+                }
+            }
+
+            let quantity: u32 = lots.iter().map(|&(_, quantity)| quantity).sum();
+            if quantity == 0 {
+                return Err!(
+                    "Got {} bond amortization from {} for bonds which aren't held",
+                    symbol, formatting::format_date(amortization.date));
+            }
+
+            let bond_amortization = amortization.amount / quantity;
+
+            // Iterate in reverse order to not shift indices of the lots which aren't processed yet
+            for &(mut index, quantity) in lots.iter().rev() {
+                // Partially sold lot: only the part held at amortization date is affected
+                if quantity < self.stock_buys[index].quantity {
+                    let lot = self.stock_buys[index].detach(quantity);
+                    index += 1;
+                    self.stock_buys.insert(index, lot);
+                }
+
+                let trade = &mut self.stock_buys[index];
+
+                trade.price.sub_assign(bond_amortization).map_err(|e| format!(
+                    "{} bond amortization is paid in a currency different from the trade currency: {}",
+                    symbol, e))?;
+                trade.volume.sub_assign(bond_amortization * trade.quantity).unwrap();
+            }
+        }
+
+        Ok(())
+    }
+
     fn sort_and_alter_fees(&mut self, max_date: Date) {
         if self.broker.allow_future_fees {
             for fee in &mut self.fees {
@@ -592,22 +1001,249 @@ impl BrokerStatement {
             }
         }
 
-        if open_positions != self.open_positions {
-            return Err!("The calculated open positions don't match declared ones in the statement");
+        let symbols: BTreeSet<&String> = open_positions.keys().chain(self.open_positions.keys()).collect();
+
+        for symbol in symbols {
+            let calculated = open_positions.get(symbol).cloned().unwrap_or(0);
+            let declared = self.open_positions.get(symbol).cloned().unwrap_or(0);
+
+            if calculated != declared {
+                return Err!(concat!(
+                    "The calculated open positions don't match declared ones in the statement: ",
+                    "{} - {} declared, {} calculated"), symbol, declared, calculated);
+            }
         }
 
         Ok(())
     }
+
+    // Not all cash operations are supported by statement parsers yet, so the discrepancy is reported
+    // as a warning instead of an error
+    fn validate_cash_assets(&self) {
+        let calculated = self.calculate_cash_assets();
+
+        let currencies: BTreeSet<&str> = calculated.iter().chain(self.cash_assets.iter())
+            .map(|assets| assets.currency).collect();
+
+        for currency in currencies {
+            let zero = Cash::new(currency, dec!(0));
+            let calculated = calculated.get(currency).unwrap_or(zero).round();
+            let declared = self.cash_assets.get(currency).unwrap_or(zero).round();
+
+            if calculated != declared {
+                warn!(concat!(
+                    "The calculated {} cash assets don't match the ones declared in the {} broker ",
+                    "statement: {} declared, {} calculated."
+                ), currency, self.broker.name, declared, calculated);
+            }
+        }
+    }
+
+    // Calculates cash assets from all cash operations of the statement. Bond amortizations are
+    // already accounted by reducing volume of the bond purchases.
+    fn calculate_cash_assets(&self) -> MultiCurrencyCashAccount {
+        let mut assets = MultiCurrencyCashAccount::new();
+
+        for cash_flow in &self.cash_flows {
+            assets.deposit(cash_flow.cash);
+        }
+
+        for fee in &self.fees {
+            assets.deposit(fee.amount);
+        }
+
+        for interest in &self.idle_cash_interest {
+            assets.deposit(interest.amount);
+        }
+
+        for coupon in &self.coupons {
+            assets.deposit(coupon.amount);
+            assets.withdraw(coupon.paid_tax);
+        }
+
+        for trade in &self.forex_trades {
+            assets.withdraw(trade.from);
+            assets.deposit(trade.to);
+            assets.withdraw(trade.commission);
+        }
+
+        for trade in &self.stock_buys {
+            assets.withdraw(trade.volume);
+            assets.withdraw(trade.commission);
+
+            if let Some(accrued_interest) = trade.accrued_interest {
+                assets.withdraw(accrued_interest);
+            }
+        }
+
+        for trade in &self.stock_sells {
+            assets.deposit(trade.volume);
+            assets.withdraw(trade.commission);
+
+            if let Some(accrued_interest) = trade.accrued_interest {
+                assets.deposit(accrued_interest);
+            }
+        }
+
+        for dividend in &self.dividends {
+            assets.deposit(dividend.amount);
+            assets.withdraw(dividend.paid_tax);
+        }
+
+        for cash_in_lieu in &self.cash_in_lieu {
+            assets.deposit(cash_in_lieu.amount);
+        }
+
+        assets
+    }
 }
 
+/// Exports transactions of all broker statements in a normalized broker-independent CSV format
+pub fn export_transactions<W: Write>(
+    broker: BrokerInfo, statements_path: &str, tax_remapping: TaxRemapping, writer: W,
+) -> EmptyResult {
+    let statements = read_statements(&broker, statements_path, tax_remapping, false, true)?;
+    let statement = BrokerStatement::merge_statements(broker, statements)?;
+
+    let mut exporter = TransactionsExporter::new(writer);
+    exporter.add(&statement)?;
+    exporter.finish()
+}
+
+fn read_statements(
+    broker: &BrokerInfo, statements_path: &str, tax_remapping: TaxRemapping, strict_mode: bool,
+    concurrent: bool,
+) -> GenericResult<Vec<PartialBrokerStatement>> {
+    let mut tax_remapping = Some(tax_remapping);
+    let mut statement_reader = match broker.type_ {
+        Broker::Bcs => bcs::StatementReader::new(),
+        Broker::Firstrade => firstrade::StatementReader::new(
+            broker.sweep_fund.clone(), broker.trade_volume_tolerance()),
+        Broker::InteractiveBrokers => ib::StatementReader::new(
+            tax_remapping.take().unwrap(), strict_mode),
+        Broker::Open => open::StatementReader::new(),
+        Broker::Sber => sber::StatementReader::new(broker.trade_volume_tolerance()),
+        Broker::Schwab => schwab::StatementReader::new(),
+        Broker::Tinkoff => tinkoff::StatementReader::new(),
+    }?;
+
+    let (statement_dir_path, mut file_names) = get_statement_files(
+        statements_path, statement_reader.as_ref()
+    ).map_err(|e| format!("Error while reading {:?}: {}", statements_path, e))?;
+    let statement_dir_path = statement_dir_path.as_str();
+
+    if file_names.is_empty() {
+        return Err!("{:?} doesn't contain any broker statement", statements_path);
+    }
+
+    file_names.sort();
+
+    let statements = match get_concurrent_reader_factory(broker) {
+        // The statements are independent, so parse them in rayon's thread pool (which is bounded
+        // by the number of CPUs) with a separate reader per statement. The order is preserved
+        // by collect().
+        Some(new_reader) if concurrent => {
+            file_names.par_iter().map(|file_name| -> GenericResult<PartialBrokerStatement> {
+                let mut statement_reader = new_reader()?;
+                let statement = read_statement(
+                    broker, statement_reader.as_mut(), statement_dir_path, file_name)?;
+                statement_reader.close()?;
+                Ok(statement)
+            }).collect::<GenericResult<Vec<_>>>()?
+        },
+        _ => {
+            let mut statements = Vec::new();
+
+            for file_name in &file_names {
+                statements.push(read_statement(
+                    broker, statement_reader.as_mut(), statement_dir_path, file_name)?);
+            }
+
+            statements
+        },
+    };
+
+    if let Some(tax_remapping) = tax_remapping {
+        tax_remapping.ensure_all_mapped()?;
+    }
+    statement_reader.close()?;
+
+    Ok(statements)
+}
+
+fn read_statement(
+    broker: &BrokerInfo, statement_reader: &mut dyn BrokerStatementReader,
+    statement_dir_path: &str, file_name: &str,
+) -> GenericResult<PartialBrokerStatement> {
+    let path = Path::new(statement_dir_path).join(file_name);
+    let path = path.to_str().unwrap();
+
+    Ok(statement_reader.read(path).and_then(|statement| {
+        broker.check_unsupported_operations(&statement.unsupported_operations)?;
+        Ok(statement)
+    }).map_err(|e| format!("Error while reading {:?} broker statement: {}", path, e))?)
+}
+
+type StatementReaderFactory = Box<dyn Fn() -> GenericResult<Box<dyn BrokerStatementReader>> + Sync>;
+
+// Returns statement reader constructor for brokers which statement readers don't share any state
+// between statements, so the statements may be read concurrently
+fn get_concurrent_reader_factory(broker: &BrokerInfo) -> Option<StatementReaderFactory> {
+    let factory: StatementReaderFactory = match broker.type_ {
+        Broker::Bcs => Box::new(bcs::StatementReader::new),
+        Broker::Firstrade => {
+            let sweep_fund = broker.sweep_fund.clone();
+            let trade_volume_tolerance = broker.trade_volume_tolerance();
+            Box::new(move || firstrade::StatementReader::new(sweep_fund.clone(), trade_volume_tolerance))
+        },
+        Broker::InteractiveBrokers => return None,
+        Broker::Open => Box::new(open::StatementReader::new),
+        Broker::Sber => {
+            let trade_volume_tolerance = broker.trade_volume_tolerance();
+            Box::new(move || sber::StatementReader::new(trade_volume_tolerance))
+        },
+        Broker::Schwab => return None,
+        Broker::Tinkoff => Box::new(tinkoff::StatementReader::new),
+    };
+    Some(factory)
+}
+
+// The statements path may be either a path to a directory with broker statements or a file name
+// pattern like ~/statements/ib/*.csv. Returns the statements directory and names of the statement
+// files in it.
 fn get_statement_files(
-    statement_dir_path: &str, statement_reader: &dyn BrokerStatementReader
-) -> GenericResult<Vec<String>> {
+    statements_path: &str, statement_reader: &dyn BrokerStatementReader
+) -> GenericResult<(String, Vec<String>)> {
+    let path = Path::new(statements_path);
+
+    let (statement_dir_path, file_name_regex) = if path.is_dir() {
+        (statements_path.to_owned(), None)
+    } else {
+        let pattern = path.file_name().and_then(|file_name| file_name.to_str()).ok_or_else(|| format!(
+            "Got an invalid statements path: {:?}", statements_path))?;
+
+        let dir_path = match path.parent() {
+            Some(dir_path) if !dir_path.as_os_str().is_empty() => dir_path.to_str().unwrap(),
+            _ => ".",
+        };
+
+        (dir_path.to_owned(), Some(get_file_name_regex(pattern)))
+    };
+
     let mut file_names = Vec::new();
 
-    for entry in fs::read_dir(statement_dir_path)? {
+    for entry in fs::read_dir(&statement_dir_path)? {
         let entry = entry?;
 
+        let file_name = entry.file_name().into_string().map_err(|file_name| format!(
+            "Got an invalid file name: {:?}", file_name.to_string_lossy()))?;
+
+        if let Some(ref regex) = file_name_regex {
+            if !regex.is_match(&file_name) {
+                continue;
+            }
+        }
+
         let path = entry.path();
         let path = path.to_str().ok_or_else(|| format!(
             "Got an invalid path: {:?}", path.to_string_lossy()))?;
@@ -616,17 +1252,564 @@ fn get_statement_files(
             continue;
         }
 
-        let file_name = entry.file_name().into_string().map_err(|file_name| format!(
-            "Got an invalid file name: {:?}", file_name.to_string_lossy()))?;
         file_names.push(file_name);
     }
 
-    Ok(file_names)
+    Ok((statement_dir_path, file_names))
 }
 
+// Converts a file name pattern with `*` and `?` wildcards to a regular expression
+fn get_file_name_regex(pattern: &str) -> Regex {
+    let mut expression = String::from("^");
+
+    for symbol in pattern.chars() {
+        match symbol {
+            '*' => expression.push_str(".*"),
+            '?' => expression.push('.'),
+            _ => expression.push_str(&regex::escape(&symbol.to_string())),
+        }
+    }
+
+    expression.push('$');
+    Regex::new(&expression).unwrap()
+}
+
+/// A contract for broker statement parsers. A new broker is added by implementing the trait and
+/// dispatching on its `Broker` variant in `read_statements()` (and in
+/// `get_concurrent_reader_factory()` if the reader doesn't share any state between statements).
 pub trait BrokerStatementReader {
+    /// Filters files in the statements directory
     fn is_statement(&self, path: &str) -> GenericResult<bool>;
+
+    /// Parses a single statement. Statements are read in order of their file names.
     fn read(&mut self, path: &str) -> GenericResult<PartialBrokerStatement>;
+
+    /// Called after all statements are read to validate the reader's accumulated state
     #[allow(clippy::boxed_local)]
     fn close(self: Box<Self>) -> EmptyResult { Ok(()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use crate::config::Config;
+    use super::*;
+
+    #[rstest(broker, path,
+        case(Broker::Bcs, "testdata/bcs"),
+        case(Broker::Firstrade, "testdata/firstrade"),
+        case(Broker::Open, "testdata/open-broker"),
+        case(Broker::Tinkoff, "testdata/tinkoff"),
+    )]
+    fn concurrent_reading(broker: Broker, path: &str) {
+        let read = |concurrent| {
+            let broker = broker.get_info(&Config::mock(), None).unwrap();
+            BrokerStatement::read_impl(
+                broker, path, &hashmap!{}, &hashmap!{}, TaxRemapping::new(), true, concurrent,
+            ).unwrap()
+        };
+
+        let sequential = read(false);
+        let concurrent = read(true);
+
+        assert_eq!(concurrent.period, sequential.period);
+        assert_eq!(concurrent.cash_assets, sequential.cash_assets);
+        assert_eq!(concurrent.historical_cash_assets, sequential.historical_cash_assets);
+        assert_eq!(concurrent.open_positions, sequential.open_positions);
+        assert_eq!(concurrent.instrument_names, sequential.instrument_names);
+
+        let cash_flows = |statement: &BrokerStatement| statement.cash_flows.iter().map(|cash_flow| (
+            cash_flow.date, cash_flow.cash,
+        )).collect::<Vec<_>>();
+        assert_eq!(cash_flows(&concurrent), cash_flows(&sequential));
+
+        let fees = |statement: &BrokerStatement| statement.fees.iter().map(|fee| (
+            fee.date, fee.amount, fee.description.clone(),
+        )).collect::<Vec<_>>();
+        assert_eq!(fees(&concurrent), fees(&sequential));
+
+        let idle_cash_interest = |statement: &BrokerStatement| statement.idle_cash_interest.iter().map(|interest| (
+            interest.date, interest.amount,
+        )).collect::<Vec<_>>();
+        assert_eq!(idle_cash_interest(&concurrent), idle_cash_interest(&sequential));
+
+        let forex_trades = |statement: &BrokerStatement| statement.forex_trades.iter().map(|trade| (
+            trade.conclusion_date, trade.from, trade.to, trade.commission,
+        )).collect::<Vec<_>>();
+        assert_eq!(forex_trades(&concurrent), forex_trades(&sequential));
+
+        let stock_buys = |statement: &BrokerStatement| statement.stock_buys.iter().map(|trade| (
+            trade.symbol.clone(), trade.quantity, trade.price, trade.volume, trade.commission,
+            trade.conclusion_date, trade.execution_date, trade.get_unsold(),
+        )).collect::<Vec<_>>();
+        assert_eq!(stock_buys(&concurrent), stock_buys(&sequential));
+
+        let stock_sells = |statement: &BrokerStatement| statement.stock_sells.iter().map(|trade| (
+            trade.symbol.clone(), trade.quantity, trade.price, trade.volume, trade.commission,
+            trade.conclusion_date, trade.execution_date,
+        )).collect::<Vec<_>>();
+        assert_eq!(stock_sells(&concurrent), stock_sells(&sequential));
+
+        let dividends = |statement: &BrokerStatement| statement.dividends.iter().map(|dividend| (
+            dividend.date, dividend.issuer.clone(), dividend.amount, dividend.paid_tax,
+        )).collect::<Vec<_>>();
+        assert_eq!(dividends(&concurrent), dividends(&sequential));
+    }
+
+    #[rstest(pattern, file_name, matches,
+        case("*.csv", "2020-01.csv", true),
+        case("*.csv", "2020-01.csv.bak", false),
+        case("statement-????.csv", "statement-2020.csv", true),
+        case("statement-????.csv", "statement-20.csv", false),
+        case("statement.csv", "statement.csv", true),
+        case("statement.csv", "statement-csv", false),
+    )]
+    fn statement_file_name_pattern(pattern: &str, file_name: &str, matches: bool) {
+        assert_eq!(get_file_name_regex(pattern).is_match(file_name), matches);
+    }
+
+    #[rstest(method, expected_unsold,
+        case(TaxLotMethod::Fifo, vec![0, 5, 5]),
+        case(TaxLotMethod::Lifo, vec![5, 0, 5]),
+    )]
+    fn tax_lot_method(method: TaxLotMethod, expected_unsold: Vec<u32>) {
+        let broker = Broker::Firstrade.get_info(&Config::mock(), None).unwrap();
+
+        let mut partial = PartialBrokerStatement::new();
+        partial.set_period((date!(1, 1, 2020), date!(1, 2, 2020))).unwrap();
+        partial.set_starting_assets(false).unwrap();
+
+        let mut statement = BrokerStatement::new_empty_from(broker, &partial).unwrap();
+        let currency = "USD";
+        let commission = Cash::new(currency, dec!(0));
+
+        for &(day, quantity, price) in &[(1, 10, dec!(10)), (2, 10, dec!(20)), (4, 5, dec!(30))] {
+            let date = date!(day, 1, 2020);
+            let price = Cash::new(currency, price);
+            statement.stock_buys.push(StockBuy::new(
+                "AAA", quantity, price, price * quantity, commission, date, date));
+        }
+
+        let date = date!(3, 1, 2020);
+        let price = Cash::new(currency, dec!(25));
+        statement.stock_sells.push(StockSell::new(
+            "AAA", 15, price, price * 15, commission, date, date, SaleKind::Close));
+
+        statement.open_positions.insert(s!("AAA"), 10);
+        statement.process_trades(method).unwrap();
+
+        let unsold: Vec<u32> = statement.stock_buys.iter().map(|trade| trade.get_unsold()).collect();
+        assert_eq!(unsold, expected_unsold);
+    }
+
+    #[test]
+    fn short_sale() {
+        let broker = Broker::InteractiveBrokers.get_info(&Config::mock(), None).unwrap();
+        let converter = CurrencyConverter::mock(dec!(70), &[]);
+        let country = localities::russia();
+
+        let mut partial = PartialBrokerStatement::new();
+        partial.set_period((date!(1, 1, 2020), date!(1, 2, 2020))).unwrap();
+        partial.set_starting_assets(false).unwrap();
+
+        let mut statement = BrokerStatement::new_empty_from(broker, &partial).unwrap();
+        let currency = "USD";
+        let commission = Cash::new(currency, dec!(0));
+
+        for &(day, quantity, price) in &[(1, 10, dec!(10)), (5, 5, dec!(20)), (6, 5, dec!(15))] {
+            let date = date!(day, 1, 2020);
+            let price = Cash::new(currency, price);
+            statement.stock_buys.push(StockBuy::new(
+                "AAA", quantity, price, price * quantity, commission, date, date));
+        }
+
+        let date = date!(3, 1, 2020);
+        let price = Cash::new(currency, dec!(25));
+        statement.stock_sells.push(StockSell::new(
+            "AAA", 8, price, price * 8, commission, date, date, SaleKind::Short));
+
+        {
+            let broker = Broker::InteractiveBrokers.get_info(&Config::mock(), None).unwrap();
+            let mut statement = BrokerStatement::new_empty_from(broker, &partial).unwrap();
+            statement.stock_buys.push(StockBuy::new(
+                "AAA", 1, price, price, commission, date!(1, 1, 2020), date!(1, 1, 2020)));
+            statement.stock_sells.push(StockSell::new(
+                "AAA", 1, price, price, commission, date, date, SaleKind::Short));
+
+            assert_eq!(statement.process_trades(TaxLotMethod::Fifo).unwrap_err().to_string(), concat!(
+                "Error while processing AAA short sale: It isn't covered by the following purchases ",
+                "(open short positions aren't supported)"));
+        }
+
+        statement.open_positions.insert(s!("AAA"), 12);
+        statement.process_trades(TaxLotMethod::Fifo).unwrap();
+
+        // The short sale is covered by the purchases made after it, the lot bought before it stays
+        // untouched
+        let unsold: Vec<u32> = statement.stock_buys.iter().map(|trade| trade.get_unsold()).collect();
+        assert_eq!(unsold, vec![10, 0, 2]);
+
+        let details = statement.stock_sells[0].calculate(&country, &converter).unwrap();
+        assert_eq!(details.purchase_cost, Cash::new(currency, dec!(145)));
+        assert_eq!(details.profit, Cash::new(currency, dec!(55)));
+
+        let cover_dates: Vec<Date> = details.fifo.iter().map(|trade| trade.conclusion_date).collect();
+        assert_eq!(cover_dates, vec![date!(5, 1, 2020), date!(6, 1, 2020)]);
+    }
+
+    #[test]
+    fn reverse_stock_split() {
+        let broker = Broker::Firstrade.get_info(&Config::mock(), None).unwrap();
+
+        let mut partial = PartialBrokerStatement::new();
+        partial.set_period((date!(1, 1, 2020), date!(1, 2, 2020))).unwrap();
+        partial.set_starting_assets(false).unwrap();
+
+        let mut statement = BrokerStatement::new_empty_from(broker, &partial).unwrap();
+        let currency = "USD";
+
+        for &(day, price) in &[(1, dec!(10)), (2, dec!(20)), (3, dec!(30))] {
+            let date = date!(day, 1, 2020);
+            let price = Cash::new(currency, price);
+            statement.stock_buys.push(StockBuy::new(
+                "AAA", 1, price, price, Cash::new(currency, dec!(1)), date, date));
+        }
+
+        statement.stock_splits.push(StockSplit {
+            date: date!(10, 1, 2020),
+            symbol: s!("AAA"),
+            from: 2,
+            to: 1,
+            price: Some(Cash::new(currency, dec!(50))),
+            cash_in_lieu: None,
+        });
+
+        statement.open_positions.insert(s!("AAA"), 1);
+        statement.process_trades(TaxLotMethod::Fifo).unwrap();
+
+        let lots: Vec<(Date, u32, Cash, Cash, Cash)> = statement.stock_buys.iter().map(|trade| (
+            trade.conclusion_date, trade.quantity, trade.price, trade.volume, trade.commission,
+        )).collect();
+        assert_eq!(lots, vec![(
+            date!(2, 1, 2020), 1, Cash::new(currency, dec!(60)),
+            Cash::new(currency, dec!(60)), Cash::new(currency, dec!(3)),
+        )]);
+
+        let cash_in_lieu: Vec<(Date, &str, Cash)> = statement.cash_in_lieu.iter().map(|cash_in_lieu| (
+            cash_in_lieu.date, cash_in_lieu.symbol.as_str(), cash_in_lieu.amount,
+        )).collect();
+        assert_eq!(cash_in_lieu, vec![(date!(10, 1, 2020), "AAA", Cash::new(currency, dec!(25)))]);
+    }
+
+    #[test]
+    fn bond_amortization() {
+        let broker = Broker::Tinkoff.get_info(&Config::mock(), None).unwrap();
+
+        let mut partial = PartialBrokerStatement::new();
+        partial.set_period((date!(1, 1, 2020), date!(1, 3, 2020))).unwrap();
+        partial.set_starting_assets(false).unwrap();
+
+        let mut statement = BrokerStatement::new_empty_from(broker, &partial).unwrap();
+        let currency = "RUB";
+        let price = Cash::new(currency, dec!(1000));
+
+        for &(day, quantity) in &[(10, 2), (20, 3)] {
+            let date = date!(day, 1, 2020);
+            statement.stock_buys.push(StockBuy::new(
+                "OFZ", quantity, price, price * quantity, Cash::new(currency, dec!(0)), date, date));
+        }
+
+        statement.bond_amortizations.push(BondAmortization {
+            date: date!(1, 2, 2020),
+            symbol: s!("OFZ"),
+            amount: Cash::new(currency, dec!(1500)),
+        });
+        statement.process_bond_amortizations().unwrap();
+
+        let costs: Vec<(Cash, Cash)> = statement.stock_buys.iter()
+            .map(|trade| (trade.price, trade.volume)).collect();
+        assert_eq!(costs, vec![
+            (Cash::new(currency, dec!(700)), Cash::new(currency, dec!(1400))),
+            (Cash::new(currency, dec!(700)), Cash::new(currency, dec!(2100))),
+        ]);
+    }
+
+    #[test]
+    fn bond_amortization_after_partial_sell() {
+        let broker = Broker::Tinkoff.get_info(&Config::mock(), None).unwrap();
+
+        let mut partial = PartialBrokerStatement::new();
+        partial.set_period((date!(1, 1, 2020), date!(1, 3, 2020))).unwrap();
+        partial.set_starting_assets(false).unwrap();
+
+        let mut statement = BrokerStatement::new_empty_from(broker, &partial).unwrap();
+        let currency = "RUB";
+        let price = Cash::new(currency, dec!(1000));
+        let commission = Cash::new(currency, dec!(0));
+
+        for &(day, quantity) in &[(10, 2), (20, 3)] {
+            let date = date!(day, 1, 2020);
+            statement.stock_buys.push(StockBuy::new(
+                "OFZ", quantity, price, price * quantity, commission, date, date));
+        }
+
+        let date = date!(25, 1, 2020);
+        statement.stock_sells.push(StockSell::new(
+            "OFZ", 3, price, price * 3, commission, date, date, SaleKind::Close));
+
+        statement.bond_amortizations.push(BondAmortization {
+            date: date!(1, 2, 2020),
+            symbol: s!("OFZ"),
+            amount: Cash::new(currency, dec!(600)),
+        });
+        statement.open_positions.insert(s!("OFZ"), 2);
+
+        statement.process_bond_amortizations().unwrap();
+        statement.process_trades(TaxLotMethod::Fifo).unwrap();
+
+        let lots: Vec<(u32, Cash, Cash)> = statement.stock_buys.iter()
+            .map(|trade| (trade.quantity, trade.price, trade.volume)).collect();
+        assert_eq!(lots, vec![
+            (2, price, Cash::new(currency, dec!(2000))),
+            (1, price, Cash::new(currency, dec!(1000))),
+            (2, Cash::new(currency, dec!(700)), Cash::new(currency, dec!(1400))),
+        ]);
+
+        assert!(statement.stock_buys[..2].iter().all(StockBuy::is_sold));
+        assert_eq!(statement.stock_buys[2].get_unsold(), 2);
+    }
+
+    #[test]
+    fn accrued_interest() {
+        let broker = Broker::Tinkoff.get_info(&Config::mock(), None).unwrap();
+
+        let mut partial = PartialBrokerStatement::new();
+        partial.set_period((date!(1, 1, 2020), date!(1, 6, 2020))).unwrap();
+        partial.set_starting_assets(false).unwrap();
+
+        let mut statement = BrokerStatement::new_empty_from(broker, &partial).unwrap();
+        let currency = "RUB";
+        let price = Cash::new(currency, dec!(1000));
+        let commission = Cash::new(currency, dec!(0));
+
+        for &(day, accrued_interest) in &[(10, dec!(30)), (20, dec!(15))] {
+            let date = date!(day, 1, 2020);
+            let mut trade = StockBuy::new("OFZ", 1, price, price, commission, date, date);
+            trade.accrued_interest = Some(Cash::new(currency, accrued_interest));
+            statement.stock_buys.push(trade);
+        }
+
+        let date = date!(1, 5, 2020);
+        let mut trade = StockSell::new("OFZ", 2, price, price * 2, commission, date, date, SaleKind::Close);
+        trade.accrued_interest = Some(Cash::new(currency, dec!(20)));
+        statement.stock_sells.push(trade);
+
+        statement.coupons.push(Coupon::new(date!(1, 3, 2020), "OFZ", Cash::new(currency, dec!(40))));
+        statement.process_accrued_interest().unwrap();
+
+        let coupons: Vec<(Date, Cash)> = statement.coupons.iter()
+            .map(|coupon| (coupon.date, coupon.taxable_amount())).collect();
+        assert_eq!(coupons, vec![
+            (date!(1, 3, 2020), Cash::new(currency, dec!(0))),
+            (date!(1, 5, 2020), Cash::new(currency, dec!(15))),
+        ]);
+
+        let country = localities::russia();
+        let converter = CurrencyConverter::mock(dec!(1), &[]);
+
+        let taxes: Vec<Decimal> = statement.coupons.iter()
+            .map(|coupon| coupon.tax_to_pay(&country, &converter).unwrap()).collect();
+        assert_eq!(taxes, vec![dec!(0), dec!(2)]);
+    }
+
+    #[test]
+    fn open_positions_validation() {
+        let broker = Broker::Bcs.get_info(&Config::mock(), None).unwrap();
+
+        let mut partial = PartialBrokerStatement::new();
+        partial.set_period((date!(1, 1, 2020), date!(1, 2, 2020))).unwrap();
+        partial.set_starting_assets(false).unwrap();
+
+        let mut statement = BrokerStatement::new_empty_from(broker, &partial).unwrap();
+        let date = date!(10, 1, 2020);
+        let price = Cash::new("RUB", dec!(100));
+        let commission = Cash::new("RUB", dec!(0));
+
+        statement.stock_buys.push(StockBuy::new("AAA", 10, price, price * 10, commission, date, date));
+        statement.stock_buys.push(StockBuy::new("BBB", 5, price, price * 5, commission, date, date));
+        statement.open_positions.insert(s!("AAA"), 10);
+
+        assert_eq!(
+            statement.process_trades(TaxLotMethod::Fifo).unwrap_err().to_string(),
+            "The calculated open positions don't match declared ones in the statement: BBB - 0 declared, 5 calculated");
+    }
+
+    #[test]
+    fn overlapping_statements_deduplication() {
+        let broker = Broker::Tinkoff.get_info(&Config::mock(), None).unwrap();
+        let currency = "RUB";
+        let price = Cash::new(currency, dec!(100));
+        let commission = Cash::new(currency, dec!(0));
+
+        let statement = |period: (Date, Date), starting_assets: bool, trade_days: &[u32]| {
+            let mut statement = PartialBrokerStatement::new();
+            statement.set_period(period).unwrap();
+            statement.set_starting_assets(starting_assets).unwrap();
+            statement.cash_assets.deposit(Cash::new(currency, dec!(0)));
+
+            for &day in trade_days {
+                let date = date!(day, 1, 2020);
+                statement.stock_buys.push(StockBuy::new("AAA", 1, price, price, commission, date, date));
+            }
+            statement.open_positions.insert(s!("AAA"), trade_days.len() as u32);
+
+            statement
+        };
+
+        let statement = BrokerStatement::new_from(broker, vec![
+            statement((date!(1, 1, 2020), date!(20, 1, 2020)), false, &[10, 15, 15]),
+            statement((date!(10, 1, 2020), date!(1, 2, 2020)), true, &[10, 15, 15, 25]),
+        ], &hashmap!{}, &hashmap!{}).unwrap();
+
+        assert_eq!(statement.period, (date!(1, 1, 2020), date!(1, 2, 2020)));
+
+        let trades: Vec<Date> = statement.stock_buys.iter().map(|trade| trade.conclusion_date).collect();
+        assert_eq!(trades, vec![date!(10, 1, 2020), date!(15, 1, 2020), date!(15, 1, 2020), date!(25, 1, 2020)]);
+    }
+
+    #[test]
+    fn cash_assets_calculation() {
+        let broker = Broker::InteractiveBrokers.get_info(&Config::mock(), None).unwrap();
+
+        let mut partial = PartialBrokerStatement::new();
+        partial.set_period((date!(1, 1, 2020), date!(1, 2, 2020))).unwrap();
+        partial.set_starting_assets(false).unwrap();
+
+        let mut statement = BrokerStatement::new_empty_from(broker, &partial).unwrap();
+        let date = date!(10, 1, 2020);
+        let (price, commission) = (Cash::new("USD", dec!(100)), Cash::new("USD", dec!(1)));
+
+        statement.cash_flows.push(CashAssets::new(date, "RUB", dec!(100_000)));
+        statement.forex_trades.push(ForexTrade {
+            from: Cash::new("RUB", dec!(75_000)),
+            to: Cash::new("USD", dec!(1000)),
+            commission: Cash::new("USD", dec!(2)),
+            conclusion_date: date,
+        });
+        statement.stock_buys.push(StockBuy::new("AAA", 5, price, price * 5, commission, date, date));
+        statement.stock_sells.push(StockSell::new(
+            "AAA", 2, price, price * 2, commission, date, date, SaleKind::Close));
+        statement.fees.push(Fee {
+            date,
+            amount: Cash::new("USD", dec!(-10)),
+            description: None,
+            tax_deductible: false,
+        });
+        statement.dividends.push(Dividend {
+            date,
+            issuer: s!("AAA"),
+            amount: Cash::new("USD", dec!(10)),
+            paid_tax: Cash::new("USD", dec!(1)),
+        });
+
+        let assets = statement.calculate_cash_assets();
+        assert_eq!(assets.get("RUB"), Some(Cash::new("RUB", dec!(25_000))));
+        assert_eq!(assets.get("USD"), Some(Cash::new("USD", dec!(695))));
+    }
+
+    #[test]
+    fn renamed_symbol_remapping() {
+        let broker = Broker::Firstrade.get_info(&Config::mock(), None).unwrap();
+        let currency = "USD";
+
+        let statement = |period: (Date, Date), symbol: &str, open_positions: u32| {
+            let mut partial = PartialBrokerStatement::new();
+            partial.set_period(period).unwrap();
+            partial.set_starting_assets(false).unwrap();
+            partial.cash_assets.deposit(Cash::new(currency, dec!(0)));
+
+            let date = period.0;
+            let price = Cash::new(currency, dec!(10));
+            partial.stock_buys.push(StockBuy::new(
+                symbol, 10, price, price * 10, Cash::new(currency, dec!(0)), date, date));
+
+            partial.open_positions.insert(symbol.to_owned(), open_positions);
+            partial.instrument_names.insert(symbol.to_owned(), format!("{} Inc.", symbol));
+            partial
+        };
+
+        let statements = vec![
+            statement((date!(1, 1, 2020), date!(1, 2, 2020)), "OLD", 10),
+            statement((date!(1, 2, 2020), date!(1, 3, 2020)), "NEW", 20),
+        ];
+
+        let statement = BrokerStatement::new_from(
+            broker, statements, &hashmap!{s!("OLD") => s!("NEW")}, &hashmap!{}).unwrap();
+
+        let symbols: Vec<&str> = statement.stock_buys.iter().map(|trade| trade.symbol.as_str()).collect();
+        assert_eq!(symbols, vec!["NEW", "NEW"]);
+        assert_eq!(statement.open_positions, hashmap!{s!("NEW") => 20});
+        assert_eq!(statement.get_instrument_name("NEW"), "NEW Inc. (NEW)");
+    }
+
+    #[test]
+    fn performance_merging() {
+        let broker = Broker::Firstrade.get_info(&Config::mock(), None).unwrap();
+
+        let mut partial = PartialBrokerStatement::new();
+        partial.set_period((date!(1, 1, 2020), date!(1, 2, 2020))).unwrap();
+        partial.set_starting_assets(false).unwrap();
+
+        let mut statement = BrokerStatement::new_empty_from(broker, &partial).unwrap();
+        let currency = "USD";
+        let price = Cash::new(currency, dec!(10));
+        let commission = Cash::new(currency, dec!(0));
+
+        for &(symbol, buy_day, sell_day) in &[("FB", 1, 3), ("META", 5, 7)] {
+            let (buy_date, sell_date) = (date!(buy_day, 1, 2020), date!(sell_day, 1, 2020));
+            statement.stock_buys.push(StockBuy::new(
+                symbol, 10, price, price * 10, commission, buy_date, buy_date));
+            statement.stock_sells.push(StockSell::new(
+                symbol, 10, price, price * 10, commission, sell_date, sell_date, SaleKind::Close));
+            statement.instrument_names.insert(symbol.to_owned(), format!("{} Inc.", symbol));
+        }
+
+        statement.dividends.push(Dividend {
+            date: date!(2, 1, 2020),
+            issuer: s!("FB"),
+            amount: Cash::new(currency, dec!(1)),
+            paid_tax: Cash::new(currency, dec!(0)),
+        });
+
+        statement.process_trades(TaxLotMethod::Fifo).unwrap();
+        statement.merge_symbols(&hashmap!{s!("META") => hashset!{s!("FB")}}).unwrap();
+
+        assert!(statement.stock_buys.iter().all(|trade| trade.symbol == "META"));
+        assert!(statement.stock_sells.iter().all(|trade| trade.symbol == "META"));
+        assert_eq!(statement.dividends[0].issuer, "META");
+        assert_eq!(statement.get_instrument_name("FB"), "FB");
+    }
+
+    #[test]
+    fn incompatible_symbol_remapping() {
+        let broker = Broker::Tinkoff.get_info(&Config::mock(), None).unwrap();
+
+        let mut partial = PartialBrokerStatement::new();
+        partial.set_period((date!(1, 1, 2020), date!(1, 2, 2020))).unwrap();
+        partial.set_starting_assets(false).unwrap();
+
+        let mut statement = BrokerStatement::new_empty_from(broker, &partial).unwrap();
+        let date = date!(10, 1, 2020);
+
+        for &(symbol, currency) in &[("AAA", "USD"), ("BBB", "RUB")] {
+            let price = Cash::new(currency, dec!(10));
+            statement.stock_buys.push(StockBuy::new(
+                symbol, 1, price, price, Cash::new(currency, dec!(0)), date, date));
+        }
+
+        let result = statement.remap_symbols(&hashmap!{s!("AAA") => s!("BBB")});
+        assert_eq!(result.unwrap_err().to_string(), concat!(
+            "Invalid symbol remapping configuration: ",
+            "AAA and BBB are traded in different currencies (USD vs RUB)"));
+    }
 }
\ No newline at end of file
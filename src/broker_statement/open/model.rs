@@ -7,7 +7,7 @@ use serde::Deserialize;
 
 use crate::broker_statement::fees::Fee;
 use crate::broker_statement::partial::PartialBrokerStatement;
-use crate::broker_statement::trades::{StockBuy, StockSell};
+use crate::broker_statement::trades::{SaleKind, StockBuy, StockSell};
 use crate::core::{EmptyResult, GenericResult};
 use crate::currency::{Cash, CashAssets};
 use crate::types::{Date, Decimal};
@@ -257,7 +257,7 @@ impl ConcludedTrades {
 
                     statement.stock_sells.push(StockSell::new(
                         symbol, quantity, price, volume, commission,
-                        trade.conclusion_date, execution_date, false));
+                        trade.conclusion_date, execution_date, SaleKind::Close));
                 },
                 _ => return Err!("Got an unexpected trade: Can't match it as buy or sell trade")
             };
@@ -347,6 +347,7 @@ impl CashFlows {
                         date,
                         amount: Cash::new(currency, amount),
                         description: Some(description),
+                        tax_deductible: false,
                     });
                 },
             };
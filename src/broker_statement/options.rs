@@ -0,0 +1,264 @@
+use std::collections::{BTreeMap, HashMap};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::core::GenericResult;
+use crate::currency::Cash;
+use crate::types::{Date, Decimal};
+use crate::util;
+
+use super::trades::{StockBuy, StockSell};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OptionType {
+    Call,
+    Put,
+}
+
+#[derive(Debug)]
+pub struct OptionContract {
+    pub underlying: String,
+    pub expiration: Date,
+    pub strike: Decimal,
+    pub option_type: OptionType,
+}
+
+impl OptionContract {
+    /// Parses option symbol in "AAPL 18DEC20 120.0 C" format. Returns None if the symbol is not an
+    /// option symbol.
+    pub fn parse(symbol: &str) -> Option<OptionContract> {
+        lazy_static! {
+            static ref REGEX: Regex = Regex::new(concat!(
+                r"^(?P<underlying>[A-Z0-9.]+) (?P<expiration>\d{2}[A-Z]{3}\d{2}) ",
+                r"(?P<strike>\d+(?:\.\d+)?) (?P<type>[CP])$",
+            )).unwrap();
+        }
+
+        let captures = REGEX.captures(symbol)?;
+        let expiration = util::parse_date(&captures["expiration"], "%d%b%y").ok()?;
+        let strike = captures["strike"].parse().ok()?;
+        let option_type = match &captures["type"] {
+            "C" => OptionType::Call,
+            "P" => OptionType::Put,
+            _ => unreachable!(),
+        };
+
+        Some(OptionContract {
+            underlying: captures["underlying"].to_owned(),
+            expiration, strike, option_type,
+        })
+    }
+
+    /// Returns option symbol in "AAPL 18DEC20 120.0 C" format
+    pub fn symbol(&self) -> String {
+        let mut strike = self.strike.normalize().to_string();
+        if !strike.contains('.') {
+            strike += ".0";
+        }
+
+        format!("{} {} {} {}",
+                self.underlying, self.expiration.format("%d%b%y").to_string().to_uppercase(), strike,
+                match self.option_type {
+                    OptionType::Call => "C",
+                    OptionType::Put => "P",
+                })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OptionStrategyKind {
+    VerticalSpread,
+    Straddle,
+    Single,
+}
+
+#[derive(Debug)]
+pub struct OptionStrategy {
+    pub kind: OptionStrategyKind,
+    pub underlying: String,
+    pub date: Date,
+    pub legs: Vec<String>,
+
+    pub net_cost: Cash, // Positive for net debit and negative for net credit (including commissions)
+    pub max_risk: Option<Cash>, // None for undefined (unlimited) risk
+}
+
+struct OptionLeg {
+    symbol: String,
+    contract: OptionContract,
+    quantity: i32, // Positive for long and negative for short legs
+    cost: Cash,
+}
+
+/// Groups option trades concluded at the same day on the same underlying into recognized multi-leg
+/// strategies. Legs that can't be matched are reported as standalone ones.
+pub fn group_option_trades(
+    stock_buys: &[StockBuy], stock_sells: &[StockSell], multipliers: &HashMap<String, Decimal>,
+) -> GenericResult<Vec<OptionStrategy>> {
+    let mut groups: BTreeMap<(Date, String), Vec<OptionLeg>> = BTreeMap::new();
+
+    let mut add_leg = |symbol: &str, date, quantity: i32, mut cost: Cash, commission: Cash| -> GenericResult<()> {
+        let contract = match OptionContract::parse(symbol) {
+            Some(contract) => contract,
+            None => return Ok(()),
+        };
+
+        cost.add_assign(commission).map_err(|e| format!(
+            "{} trade and its commission have different currency: {}", symbol, e))?;
+
+        groups.entry((date, contract.underlying.clone())).or_default().push(OptionLeg {
+            symbol: symbol.to_owned(), contract, quantity, cost,
+        });
+
+        Ok(())
+    };
+
+    for trade in stock_buys {
+        add_leg(&trade.symbol, trade.conclusion_date, trade.quantity as i32,
+                trade.volume, trade.commission)?;
+    }
+
+    for trade in stock_sells {
+        add_leg(&trade.symbol, trade.conclusion_date, -(trade.quantity as i32),
+                -trade.volume, trade.commission)?;
+    }
+
+    let mut strategies = Vec::new();
+
+    for ((date, underlying), mut legs) in groups {
+        legs.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+        while !legs.is_empty() {
+            let leg = legs.remove(0);
+            let (kind, other) = match find_pair(&leg, &legs) {
+                Some((kind, index)) => (kind, Some(legs.remove(index))),
+                None => (OptionStrategyKind::Single, None),
+            };
+
+            let mut strategy_legs = vec![leg];
+            strategy_legs.extend(other);
+
+            strategies.push(build_strategy(kind, &underlying, date, strategy_legs, multipliers)?);
+        }
+    }
+
+    Ok(strategies)
+}
+
+fn find_pair(leg: &OptionLeg, legs: &[OptionLeg]) -> Option<(OptionStrategyKind, usize)> {
+    for (index, other) in legs.iter().enumerate() {
+        let (a, b) = (&leg.contract, &other.contract);
+        if a.expiration != b.expiration || leg.quantity.abs() != other.quantity.abs() {
+            continue;
+        }
+
+        if a.option_type == b.option_type && a.strike != b.strike && leg.quantity == -other.quantity {
+            return Some((OptionStrategyKind::VerticalSpread, index));
+        }
+
+        if a.option_type != b.option_type && a.strike == b.strike && leg.quantity == other.quantity {
+            return Some((OptionStrategyKind::Straddle, index));
+        }
+    }
+
+    None
+}
+
+fn build_strategy(
+    kind: OptionStrategyKind, underlying: &str, date: Date, legs: Vec<OptionLeg>,
+    multipliers: &HashMap<String, Decimal>,
+) -> GenericResult<OptionStrategy> {
+    let mut net_cost = legs.first().unwrap().cost;
+    for leg in &legs[1..] {
+        net_cost.add_assign(leg.cost).map_err(|e| format!(
+            "{} option legs have different currency: {}", underlying, e))?;
+    }
+
+    let max_risk = match kind {
+        OptionStrategyKind::VerticalSpread if !net_cost.is_positive() => {
+            let leg = legs.first().unwrap();
+            let multiplier = *multipliers.get(&leg.symbol).ok_or_else(|| format!(
+                "Unable to find multiplier for {}", leg.symbol))?;
+
+            let width = (legs[0].contract.strike - legs[1].contract.strike).abs()
+                * multiplier * Decimal::from(leg.quantity.abs());
+
+            Some(Cash::new(net_cost.currency, width + net_cost.amount))
+        },
+        _ if net_cost.is_positive() && legs.iter().all(|leg| leg.quantity > 0) => Some(net_cost),
+        OptionStrategyKind::VerticalSpread => Some(net_cost),
+        _ => None,
+    };
+
+    Ok(OptionStrategy {
+        kind,
+        underlying: underlying.to_owned(),
+        date,
+        legs: legs.into_iter().map(|leg| leg.symbol).collect(),
+        net_cost,
+        max_risk,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::broker_statement::SaleKind;
+    use super::*;
+
+    #[test]
+    fn option_symbol() {
+        for &symbol in &["AAPL 18DEC20 120.0 C", "AAPL 08JAN21 122.5 P"] {
+            assert_eq!(OptionContract::parse(symbol).unwrap().symbol(), symbol);
+        }
+    }
+
+    #[test]
+    fn vertical_spread() {
+        let date = date!(2, 11, 2020);
+        let currency = "USD";
+
+        let buy = |symbol, quantity, price: Decimal| {
+            let price = Cash::new(currency, price);
+            StockBuy::new(symbol, quantity, price, price * quantity,
+                          Cash::new(currency, dec!(1.05)), date, date)
+        };
+
+        let sell = |symbol, quantity, price: Decimal| {
+            let price = Cash::new(currency, price);
+            StockSell::new(symbol, quantity, price, price * quantity,
+                           Cash::new(currency, dec!(1.05)), date, date, SaleKind::Close)
+        };
+
+        let stock_buys = vec![
+            buy("AAPL 18DEC20 120.0 C", 2, dec!(500)),
+            buy("AAPL 18DEC20 100.0 P", 1, dec!(300)),
+            buy("AAPL", 10, dec!(110)),
+        ];
+        let stock_sells = vec![
+            sell("AAPL 18DEC20 130.0 C", 2, dec!(200)),
+        ];
+        let multipliers = hashmap!{
+            s!("AAPL 18DEC20 120.0 C") => dec!(100),
+            s!("AAPL 18DEC20 130.0 C") => dec!(100),
+            s!("AAPL 18DEC20 100.0 P") => dec!(100),
+        };
+
+        let strategies = group_option_trades(&stock_buys, &stock_sells, &multipliers).unwrap();
+        assert_eq!(strategies.len(), 2);
+
+        let single = &strategies[0];
+        assert_eq!(single.kind, OptionStrategyKind::Single);
+        assert_eq!(single.legs, vec![s!("AAPL 18DEC20 100.0 P")]);
+        assert_eq!(single.net_cost, Cash::new(currency, dec!(301.05)));
+        assert_eq!(single.max_risk, Some(Cash::new(currency, dec!(301.05))));
+
+        let spread = &strategies[1];
+        assert_eq!(spread.kind, OptionStrategyKind::VerticalSpread);
+        assert_eq!(spread.underlying, "AAPL");
+        assert_eq!(spread.date, date);
+        assert_eq!(spread.legs, vec![s!("AAPL 18DEC20 120.0 C"), s!("AAPL 18DEC20 130.0 C")]);
+        assert_eq!(spread.net_cost, Cash::new(currency, dec!(602.1)));
+        assert_eq!(spread.max_risk, Some(Cash::new(currency, dec!(602.1))));
+    }
+}
@@ -3,8 +3,10 @@ use std::collections::HashMap;
 use crate::core::{EmptyResult, GenericResult};
 use crate::currency::{CashAssets, MultiCurrencyCashAccount};
 use crate::formatting;
-use crate::types::Date;
+use crate::types::{Date, Decimal};
 
+use super::bonds::{BondAmortization, Coupon};
+use super::corporate_actions::StockSplit;
 use super::dividends::{Dividend, DividendId, DividendAccruals};
 use super::fees::Fee;
 use super::interest::IdleCashInterest;
@@ -20,17 +22,22 @@ pub struct PartialBrokerStatement {
 
     pub fees: Vec<Fee>,
     pub idle_cash_interest: Vec<IdleCashInterest>,
+    pub coupons: Vec<Coupon>,
+    pub bond_amortizations: Vec<BondAmortization>,
 
     pub forex_trades: Vec<ForexTrade>,
     pub stock_buys: Vec<StockBuy>,
     pub stock_sells: Vec<StockSell>,
     pub dividends: Vec<Dividend>,
+    pub stock_splits: Vec<StockSplit>,
 
     pub dividend_accruals: HashMap<DividendId, DividendAccruals>,
     pub tax_accruals: HashMap<TaxId, TaxAccruals>,
 
     pub open_positions: HashMap<String, u32>,
     pub instrument_names: HashMap<String, String>,
+    pub option_multipliers: HashMap<String, Decimal>,
+    pub unsupported_operations: Vec<UnsupportedOperation>,
 }
 
 impl PartialBrokerStatement {
@@ -44,20 +51,34 @@ impl PartialBrokerStatement {
 
             fees: Vec::new(),
             idle_cash_interest: Vec::new(),
+            coupons: Vec::new(),
+            bond_amortizations: Vec::new(),
 
             forex_trades: Vec::new(),
             stock_buys: Vec::new(),
             stock_sells: Vec::new(),
             dividends: Vec::new(),
+            stock_splits: Vec::new(),
 
             dividend_accruals: HashMap::new(),
             tax_accruals: HashMap::new(),
 
             open_positions: HashMap::new(),
             instrument_names: HashMap::new(),
+            option_multipliers: HashMap::new(),
+            unsupported_operations: Vec::new(),
         }
     }
 
+    /// Registers an operation which is not supported by the parser. The decision whether it's an
+    /// error is made later according to the broker configuration.
+    pub fn add_unsupported_operation(&mut self, kind: &'static str, name: &str) {
+        self.unsupported_operations.push(UnsupportedOperation {
+            kind,
+            name: name.to_owned(),
+        });
+    }
+
     pub fn set_period(&mut self, period: (Date, Date)) -> EmptyResult {
         set_option("statement period", &mut self.period, period)
     }
@@ -103,4 +124,9 @@ fn set_option<T>(name: &str, option: &mut Option<T>, value: T) -> EmptyResult {
     }
     *option = Some(value);
     Ok(())
+}
+
+pub struct UnsupportedOperation {
+    pub kind: &'static str,
+    pub name: String,
 }
\ No newline at end of file
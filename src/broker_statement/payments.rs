@@ -3,6 +3,7 @@ use crate::currency::Cash;
 
 /// Calculates result amount from a series of payments and reversals. Doesn't require payments and
 /// reversals to be in order because Interactive Brokers' statement does't guarantee the order.
+#[derive(Clone)]
 pub struct Payments {
     payments: Vec<Cash>,
     reversals: Vec<Cash>,
@@ -57,4 +58,34 @@ impl Payments {
 
         Ok(Some(result))
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reversal_before_payment() {
+        let mut payments = Payments::new();
+        payments.reverse(Cash::new("USD", dec!(10)));
+        payments.add(Cash::new("USD", dec!(10)));
+        payments.add(Cash::new("USD", dec!(12)));
+        assert_eq!(payments.get_result().unwrap(), Some(Cash::new("USD", dec!(12))));
+    }
+
+    #[test]
+    fn full_reversal() {
+        let mut payments = Payments::new();
+        payments.add(Cash::new("USD", dec!(10)));
+        payments.reverse(Cash::new("USD", dec!(10)));
+        assert_eq!(payments.get_result().unwrap(), None);
+    }
+
+    #[test]
+    fn unexpected_reversal() {
+        let mut payments = Payments::new();
+        payments.add(Cash::new("USD", dec!(10)));
+        payments.reverse(Cash::new("USD", dec!(12)));
+        assert_eq!(payments.get_result().unwrap_err().to_string(), "Unexpected reversal: $12");
+    }
 }
\ No newline at end of file
@@ -0,0 +1,52 @@
+use xls_table_derive::XlsTableRow;
+
+use crate::broker_statement::xls::{XlsStatementParser, SectionParser};
+use crate::core::{EmptyResult, GenericResult};
+use crate::xls::{self, TableReader, Cell, SkipCell};
+
+pub struct AssetsParser {
+}
+
+impl SectionParser for AssetsParser {
+    fn parse(&mut self, parser: &mut XlsStatementParser) -> EmptyResult {
+        for asset in &xls::read_table::<AssetsRow>(&mut parser.sheet)? {
+            let symbol = &asset.symbol;
+
+            if asset.starting != 0 {
+                parser.statement.starting_assets.replace(true);
+            }
+
+            parser.statement.instrument_names.insert(symbol.clone(), asset.name.clone());
+
+            if asset.ending != 0 && parser.statement.open_positions.insert(symbol.clone(), asset.ending).is_some() {
+                return Err!("Got duplicated {} assets", symbol);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(XlsTableRow)]
+struct AssetsRow {
+    #[column(name="Наименование")]
+    name: String,
+    #[column(name="Код")]
+    symbol: String,
+    #[column(name="ISIN ценной бумаги")]
+    _2: SkipCell,
+    #[column(name="Валюта рыночной цены")]
+    _3: SkipCell,
+    #[column(name="Количество на начало периода, шт")]
+    starting: u32,
+    #[column(name="Количество на конец периода, шт")]
+    ending: u32,
+    #[column(name="Рыночная стоимость на конец периода")]
+    _6: SkipCell,
+}
+
+impl TableReader for AssetsRow {
+    fn skip_row(row: &[&Cell]) -> GenericResult<bool> {
+        Ok(xls::get_string_cell(row[0])? == "Итого:")
+    }
+}
\ No newline at end of file
@@ -0,0 +1,50 @@
+use num_traits::Zero;
+
+use xls_table_derive::XlsTableRow;
+
+use crate::broker_statement::xls::{XlsStatementParser, SectionParser};
+use crate::core::{EmptyResult, GenericResult};
+use crate::currency::Cash;
+use crate::types::Decimal;
+use crate::xls::{self, TableReader, Cell, SkipCell};
+
+pub struct CashAssetsParser {
+}
+
+impl SectionParser for CashAssetsParser {
+    fn parse(&mut self, parser: &mut XlsStatementParser) -> EmptyResult {
+        parser.statement.starting_assets.get_or_insert(false);
+
+        for assets in &xls::read_table::<CashAssetsRow>(&mut parser.sheet)? {
+            if !assets.starting.is_zero() {
+                parser.statement.starting_assets.replace(true);
+            }
+
+            parser.statement.cash_assets.deposit(Cash::new(&assets.currency, assets.ending));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(XlsTableRow)]
+struct CashAssetsRow {
+    #[column(name="Торговая площадка")]
+    _0: SkipCell,
+    #[column(name="Описание")]
+    _1: SkipCell,
+    #[column(name="Валюта")]
+    currency: String,
+    #[column(name="Начало периода")]
+    starting: Decimal,
+    #[column(name="Изменение")]
+    _4: SkipCell,
+    #[column(name="Конец периода")]
+    ending: Decimal,
+}
+
+impl TableReader for CashAssetsRow {
+    fn skip_row(row: &[&Cell]) -> GenericResult<bool> {
+        Ok(xls::get_string_cell(row[0])? == "Итого:")
+    }
+}
\ No newline at end of file
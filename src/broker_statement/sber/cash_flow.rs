@@ -0,0 +1,81 @@
+use xls_table_derive::XlsTableRow;
+
+use crate::broker_statement::bcs::common::parse_date;
+use crate::broker_statement::xls::{XlsStatementParser, SectionParser};
+use crate::core::EmptyResult;
+use crate::currency::CashAssets;
+use crate::types::Decimal;
+use crate::util::{self, DecimalRestrictions};
+use crate::xls::{self, TableReader, SkipCell};
+
+pub struct CashFlowParser {
+}
+
+impl SectionParser for CashFlowParser {
+    fn parse(&mut self, parser: &mut XlsStatementParser) -> EmptyResult {
+        for cash_flow in &xls::read_table::<CashFlowRow>(&mut parser.sheet)? {
+            self.process_cash_flow(parser, cash_flow)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CashFlowParser {
+    fn process_cash_flow(&self, parser: &mut XlsStatementParser, cash_flow: &CashFlowRow) -> EmptyResult {
+        let date = parse_date(&cash_flow.date)?;
+        let operation = cash_flow.operation.as_str();
+        let currency = cash_flow.currency.as_str();
+
+        let mut deposit_restrictions = DecimalRestrictions::Zero;
+        let mut withdrawal_restrictions = DecimalRestrictions::Zero;
+
+        match operation {
+            "Ввод ДС" => {
+                deposit_restrictions = DecimalRestrictions::StrictlyPositive;
+                parser.statement.cash_flows.push(CashAssets::new(date, currency, cash_flow.deposit));
+            },
+            "Вывод ДС" => {
+                withdrawal_restrictions = DecimalRestrictions::StrictlyPositive;
+                parser.statement.cash_flows.push(CashAssets::new(date, currency, -cash_flow.withdrawal));
+            },
+            // Trade settlements and commissions are taken from the trades
+            "Расчеты по сделке" | "Списание комиссии" => {
+                deposit_restrictions = DecimalRestrictions::PositiveOrZero;
+                withdrawal_restrictions = DecimalRestrictions::PositiveOrZero;
+            },
+            _ => {
+                parser.statement.add_unsupported_operation("cash flow operation", operation);
+                return Ok(());
+            },
+        };
+
+        for &(name, value, restrictions) in &[
+            ("deposit", cash_flow.deposit, deposit_restrictions),
+            ("withdrawal", cash_flow.withdrawal, withdrawal_restrictions),
+        ] {
+            util::validate_decimal(value, restrictions).map_err(|_| format!(
+                "Unexpected {} amount for {:?} operation: {}", name, operation, value))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(XlsTableRow)]
+struct CashFlowRow {
+    #[column(name="Дата")]
+    date: String,
+    #[column(name="Торговая площадка")]
+    _1: SkipCell,
+    #[column(name="Описание операции")]
+    operation: String,
+    #[column(name="Валюта")]
+    currency: String,
+    #[column(name="Сумма зачисления")]
+    deposit: Decimal,
+    #[column(name="Сумма списания")]
+    withdrawal: Decimal,
+}
+
+impl TableReader for CashFlowRow {}
\ No newline at end of file
@@ -0,0 +1,117 @@
+mod assets;
+mod cash_assets;
+mod cash_flow;
+mod period;
+mod trades;
+
+use crate::core::GenericResult;
+use crate::types::Decimal;
+use crate::xls::SheetParser;
+
+use super::{BrokerStatementReader, PartialBrokerStatement};
+use super::xls::{XlsStatementParser, Section};
+
+use assets::AssetsParser;
+use cash_assets::CashAssetsParser;
+use cash_flow::CashFlowParser;
+use period::PeriodParser;
+use trades::TradesParser;
+
+pub struct StatementReader {
+    trade_volume_tolerance: Decimal,
+}
+
+impl StatementReader {
+    pub fn new(trade_volume_tolerance: Decimal) -> GenericResult<Box<dyn BrokerStatementReader>> {
+        Ok(Box::new(StatementReader {trade_volume_tolerance}))
+    }
+}
+
+impl BrokerStatementReader for StatementReader {
+    fn is_statement(&self, path: &str) -> GenericResult<bool> {
+        Ok(path.ends_with(".xlsx"))
+    }
+
+    fn read(&mut self, path: &str) -> GenericResult<PartialBrokerStatement> {
+        let parser = Box::new(StatementSheetParser{});
+
+        XlsStatementParser::read(path, parser, vec![
+            Section::new(PeriodParser::PERIOD_PREFIX).by_prefix()
+                .parser(Box::new(PeriodParser{})).required(),
+            Section::new("Денежные средства")
+                .parser(Box::new(CashAssetsParser{})).required(),
+            Section::new("Портфель Ценных Бумаг")
+                .parser(Box::new(AssetsParser{})).required(),
+            Section::new("Движение денежных средств за период")
+                .parser(Box::new(CashFlowParser{})),
+            Section::new("Сделки купли/продажи ценных бумаг")
+                .parser(Box::new(TradesParser {
+                    trade_volume_tolerance: self.trade_volume_tolerance,
+                })),
+        ])
+    }
+}
+
+struct StatementSheetParser {
+}
+
+impl SheetParser for StatementSheetParser {
+    fn sheet_name(&self) -> &str {
+        "Отчет брокера"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::currency::Cash;
+    use crate::types::Date;
+
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let path = Path::new(file!()).parent().unwrap().join("testdata/statement.xlsx");
+        let statement = StatementReader::new(dec!(0.01)).unwrap().read(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(statement.get_period().unwrap(), (date!(1, 1, 2020), date!(1, 4, 2020)));
+        assert!(!statement.get_starting_assets().unwrap());
+        assert_eq!(statement.cash_assets.get("RUB"), Some(Cash::new("RUB", dec!(4441.25))));
+
+        let cash_flows: Vec<(Date, Cash)> = statement.cash_flows.iter()
+            .map(|assets| (assets.date, assets.cash)).collect();
+        assert_eq!(cash_flows, vec![
+            (date!(10, 1, 2020), Cash::new("RUB", dec!(10000))),
+            (date!(10, 2, 2020), Cash::new("RUB", dec!(-1000))),
+        ]);
+
+        assert_eq!(statement.stock_buys.len(), 2);
+        assert!(statement.stock_sells.is_empty());
+
+        let trade = &statement.stock_buys[0];
+        assert_eq!(trade.symbol, "AAAA");
+        assert_eq!(trade.quantity, 10);
+        assert_eq!(trade.price, Cash::new("RUB", dec!(250)));
+        assert_eq!(trade.volume, Cash::new("RUB", dec!(2500)));
+        assert_eq!(trade.commission, Cash::new("RUB", dec!(1.75)));
+        assert_eq!(trade.conclusion_date, date!(13, 1, 2020));
+        assert_eq!(trade.execution_date, date!(15, 1, 2020));
+        assert_eq!(trade.accrued_interest, None);
+
+        // Bond price is reported in percents of face value, so it's derived from the volume
+        let trade = &statement.stock_buys[1];
+        assert_eq!(trade.symbol, "SU00000RMFS0");
+        assert_eq!(trade.quantity, 2);
+        assert_eq!(trade.price, Cash::new("RUB", dec!(1020)));
+        assert_eq!(trade.volume, Cash::new("RUB", dec!(2040)));
+        assert_eq!(trade.commission, Cash::new("RUB", dec!(1.5)));
+        assert_eq!(trade.accrued_interest, Some(Cash::new("RUB", dec!(15.5))));
+
+        assert_eq!(statement.open_positions, hashmap!{
+            s!("AAAA") => 10,
+            s!("SU00000RMFS0") => 2,
+        });
+        assert!(statement.unsupported_operations.is_empty());
+    }
+}
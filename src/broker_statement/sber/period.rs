@@ -0,0 +1,25 @@
+use crate::broker_statement::bcs::common::parse_period;
+use crate::broker_statement::xls::{XlsStatementParser, SectionParser};
+use crate::core::EmptyResult;
+use crate::xls;
+
+pub struct PeriodParser {
+}
+
+impl PeriodParser {
+    pub const PERIOD_PREFIX: &'static str = "Отчет брокера за период ";
+}
+
+impl SectionParser for PeriodParser {
+    fn consume_title(&self) -> bool { false }
+
+    fn parse(&mut self, parser: &mut XlsStatementParser) -> EmptyResult {
+        let row = xls::strip_row_expecting_columns(parser.sheet.next_row_checked()?, 1)?;
+        let cell = xls::get_string_cell(row[0])?;
+
+        let period = parse_period(cell[PeriodParser::PERIOD_PREFIX.len()..].trim())?;
+        parser.statement.set_period(period)?;
+
+        Ok(())
+    }
+}
\ No newline at end of file
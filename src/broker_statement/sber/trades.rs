@@ -0,0 +1,137 @@
+use num_traits::Zero;
+
+use xls_table_derive::XlsTableRow;
+
+use crate::broker_statement::bcs::common::parse_date;
+use crate::broker_statement::trades::{SaleKind, StockBuy, StockSell};
+use crate::broker_statement::xls::{XlsStatementParser, SectionParser};
+use crate::core::EmptyResult;
+use crate::currency::Cash;
+use crate::types::Decimal;
+use crate::util::{self, DecimalRestrictions};
+use crate::xls::{self, TableReader, SkipCell};
+
+pub struct TradesParser {
+    pub trade_volume_tolerance: Decimal,
+}
+
+impl SectionParser for TradesParser {
+    fn parse(&mut self, parser: &mut XlsStatementParser) -> EmptyResult {
+        for trade in &xls::read_table::<TradeRow>(&mut parser.sheet)? {
+            self.process_trade(parser, trade)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl TradesParser {
+    fn process_trade(&self, parser: &mut XlsStatementParser, trade: &TradeRow) -> EmptyResult {
+        let conclusion_date = parse_date(&trade.conclusion_date)?;
+        let execution_date = parse_date(&trade.execution_date)?;
+
+        let currency = trade.currency.as_str();
+        let symbol = trade.symbol.as_str();
+        let quantity = trade.quantity;
+
+        if quantity == 0 {
+            return Err!("Invalid {} trade quantity: {}", symbol, quantity);
+        }
+
+        let volume = util::validate_named_decimal(
+            "trade volume", trade.volume, DecimalRestrictions::StrictlyPositive)
+            .map(|volume| Cash::new(currency, volume))?;
+
+        let accrued_interest = util::validate_named_decimal(
+            "accrued interest", trade.accrued_interest, DecimalRestrictions::PositiveOrZero)?;
+
+        let (price, accrued_interest) = if accrued_interest.is_zero() {
+            let price = util::validate_named_decimal(
+                "price", trade.price, DecimalRestrictions::StrictlyPositive)
+                .map(|price| Cash::new(currency, price))?;
+
+            // Broker may round the volume differently, so allow a small difference
+            let expected_volume = (price * quantity).round();
+            if (volume.amount - expected_volume.amount).abs() > self.trade_volume_tolerance {
+                return Err!(
+                    "Got {} trade with unexpected volume: {} ({} * {} = {} is expected)",
+                    symbol, volume, price, quantity, expected_volume);
+            }
+
+            (price, None)
+        } else {
+            // Bond price is specified in percents of its face value
+            (volume / quantity, Some(Cash::new(currency, accrued_interest)))
+        };
+
+        let mut commission = Cash::new(currency, dec!(0));
+        for &(name, amount) in &[
+            ("broker commission", trade.broker_commission),
+            ("exchange commission", trade.exchange_commission),
+        ] {
+            let amount = util::validate_named_decimal(name, amount, DecimalRestrictions::PositiveOrZero)?;
+            commission.add_assign(Cash::new(currency, amount)).unwrap();
+        }
+
+        match trade.operation.as_str() {
+            "Покупка" => {
+                let mut stock_buy = StockBuy::new(
+                    symbol, quantity, price, volume, commission, conclusion_date, execution_date);
+                stock_buy.accrued_interest = accrued_interest;
+                parser.statement.stock_buys.push(stock_buy);
+            },
+            "Продажа" => {
+                let mut stock_sell = StockSell::new(
+                    symbol, quantity, price, volume, commission, conclusion_date, execution_date, SaleKind::Close);
+                stock_sell.accrued_interest = accrued_interest;
+                parser.statement.stock_sells.push(stock_sell);
+            },
+            _ => {
+                parser.statement.add_unsupported_operation("trade operation", &trade.operation);
+                return Ok(());
+            },
+        };
+
+        parser.statement.instrument_names.insert(symbol.to_owned(), trade.name.clone());
+
+        Ok(())
+    }
+}
+
+#[derive(XlsTableRow)]
+struct TradeRow {
+    #[column(name="Дата заключения")]
+    conclusion_date: String,
+    #[column(name="Дата расчетов")]
+    execution_date: String,
+    #[column(name="Время заключения")]
+    _2: SkipCell,
+    #[column(name="Наименование ЦБ")]
+    name: String,
+    #[column(name="Код ЦБ")]
+    symbol: String,
+    #[column(name="Валюта")]
+    currency: String,
+    #[column(name="Вид")]
+    operation: String,
+    #[column(name="Количество, шт.")]
+    quantity: u32,
+    #[column(name="Цена")]
+    price: Decimal,
+    #[column(name="Сумма")]
+    volume: Decimal,
+    #[column(name="НКД")]
+    accrued_interest: Decimal,
+    #[column(name="Комиссия Брокера")]
+    broker_commission: Decimal,
+    #[column(name="Комиссия Биржи")]
+    exchange_commission: Decimal,
+    #[column(name="Номер сделки")]
+    _13: SkipCell,
+    #[column(name="Комментарий")]
+    _14: SkipCell,
+    #[column(name="Статус сделки")]
+    _15: SkipCell,
+}
+
+impl TableReader for TradeRow {}
\ No newline at end of file
@@ -0,0 +1,123 @@
+mod transactions;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+
+use crate::core::GenericResult;
+use crate::currency::Cash;
+
+use super::{BrokerStatementReader, PartialBrokerStatement};
+
+/// Schwab's transaction history export doesn't contain any balances, so cash assets and open
+/// positions are calculated from the transactions. It requires the statements to cover the whole
+/// account history and to be named in chronological order.
+pub struct StatementReader {
+    cash_assets: Cash,
+    open_positions: HashMap<String, u32>,
+}
+
+impl StatementReader {
+    pub fn new() -> GenericResult<Box<dyn BrokerStatementReader>> {
+        Ok(Box::new(StatementReader {
+            cash_assets: Cash::new(transactions::CURRENCY, dec!(0)),
+            open_positions: HashMap::new(),
+        }))
+    }
+
+    fn read_from<R: Read>(&mut self, reader: R) -> GenericResult<PartialBrokerStatement> {
+        let mut statement = PartialBrokerStatement::new();
+        let cash_flow = transactions::parse(&mut statement, reader)?;
+
+        statement.set_starting_assets(!self.cash_assets.is_zero() || !self.open_positions.is_empty())?;
+        self.cash_assets.add_assign(cash_flow)?;
+
+        for trade in &statement.stock_buys {
+            *self.open_positions.entry(trade.symbol.clone()).or_insert(0) += trade.quantity;
+        }
+
+        for trade in &statement.stock_sells {
+            match self.open_positions.get_mut(&trade.symbol) {
+                Some(quantity) if *quantity >= trade.quantity => *quantity -= trade.quantity,
+                _ => return Err!(
+                    "Got {} sell trade without an open position for it. {}",
+                    trade.symbol, "Make sure that the statements cover the whole account history"),
+            };
+        }
+        self.open_positions.retain(|_, quantity| *quantity != 0);
+
+        statement.cash_assets.deposit(self.cash_assets);
+        statement.open_positions = self.open_positions.clone();
+
+        statement.validate()
+    }
+}
+
+impl BrokerStatementReader for StatementReader {
+    fn is_statement(&self, path: &str) -> GenericResult<bool> {
+        Ok(path.ends_with(".csv"))
+    }
+
+    fn read(&mut self, path: &str) -> GenericResult<PartialBrokerStatement> {
+        self.read_from(File::open(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::brokers::Broker;
+    use crate::broker_statement::BrokerStatement;
+    use crate::config::Config;
+
+    use super::*;
+
+    #[test]
+    fn parse_mock() {
+        let statements = [
+            indoc!(r#"
+                "Transactions  for account XXXX-1234 from 01/01/2020 to 01/31/2020 as of 02/01/2020 10:00:00 ET"
+                "Date","Action","Symbol","Description","Quantity","Price","Fees & Comm","Amount",
+                "01/10/2020","Buy","VTI","VANGUARD TOTAL STOCK MARKET ETF","10","$160.50","","-$1,605.00",
+                "01/02/2020","MoneyLink Transfer","","Tfr BANK","","","","$2,000.00",
+                "Transactions Total","","","","","","","$395.00",
+            "#),
+            indoc!(r#"
+                "Transactions  for account XXXX-1234 from 02/01/2020 to 03/31/2020 as of 04/01/2020 10:00:00 ET"
+                "Date","Action","Symbol","Description","Quantity","Price","Fees & Comm","Amount",
+                "03/30/2020","Sell","VTI","VANGUARD TOTAL STOCK MARKET ETF","4","$130.00","$0.05","$519.95",
+                "03/27/2020","NRA Tax Adj","VTI","VANGUARD TOTAL STOCK MARKET ETF","","","","-$0.99",
+                "03/27/2020 as of 03/26/2020","Qualified Dividend","VTI","VANGUARD TOTAL STOCK MARKET ETF","","","","$6.60",
+                "02/28/2020","Credit Interest","","SCHWAB1 INT 01/30-02/27","","","","$0.01",
+                "Transactions Total","","","","","","","$525.57",
+            "#),
+        ];
+
+        let mut reader = StatementReader {
+            cash_assets: Cash::new("USD", dec!(0)),
+            open_positions: HashMap::new(),
+        };
+        let statements = statements.iter()
+            .map(|&statement| reader.read_from(statement.as_bytes()).unwrap())
+            .collect();
+
+        let broker = Broker::Schwab.get_info(&Config::mock(), None).unwrap();
+        let statement = BrokerStatement::new_from(broker, statements, &hashmap!{}, &hashmap!{}).unwrap();
+
+        assert_eq!(statement.period, (date!(1, 1, 2020), date!(1, 4, 2020)));
+        assert_eq!(statement.cash_assets.get("USD"), Some(Cash::new("USD", dec!(920.57))));
+        assert_eq!(statement.open_positions, hashmap!{s!("VTI") => 6});
+
+        assert_eq!(statement.cash_flows.len(), 1);
+        assert_eq!(statement.stock_buys.len(), 1);
+        assert_eq!(statement.stock_sells.len(), 1);
+        assert_eq!(statement.idle_cash_interest.len(), 1);
+
+        assert_eq!(statement.dividends.len(), 1);
+        let dividend = statement.dividends.first().unwrap();
+        assert_eq!(dividend.date, date!(27, 3, 2020));
+        assert_eq!(dividend.amount, Cash::new("USD", dec!(6.60)));
+        assert_eq!(dividend.paid_tax, Cash::new("USD", dec!(0.99)));
+    }
+}
\ No newline at end of file
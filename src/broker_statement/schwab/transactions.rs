@@ -0,0 +1,258 @@
+use std::io::Read;
+
+use csv::StringRecord;
+use lazy_static::lazy_static;
+use num_traits::ToPrimitive;
+use regex::Regex;
+
+use crate::broker_statement::dividends::{DividendId, DividendAccruals};
+use crate::broker_statement::fees::Fee;
+use crate::broker_statement::interest::IdleCashInterest;
+use crate::broker_statement::partial::PartialBrokerStatement;
+use crate::broker_statement::taxes::{TaxId, TaxAccruals};
+use crate::broker_statement::trades::{SaleKind, StockBuy, StockSell};
+use crate::core::{EmptyResult, GenericResult};
+use crate::currency::{Cash, CashAssets};
+use crate::formatting;
+use crate::types::{Date, Decimal};
+use crate::util::{self, DecimalRestrictions};
+
+pub const CURRENCY: &str = "USD";
+
+const COLUMNS: &[&str] = &[
+    "Date", "Action", "Symbol", "Description", "Quantity", "Price", "Fees & Comm", "Amount"];
+
+/// Parses transactions to the statement. Returns total cash flow of the transactions.
+pub fn parse<R: Read>(statement: &mut PartialBrokerStatement, reader: R) -> GenericResult<Cash> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(reader);
+
+    let mut records = reader.records();
+    let mut next_record = || -> GenericResult<StringRecord> {
+        Ok(records.next().ok_or("Got an unexpected end of statement")??)
+    };
+
+    let title = next_record()?;
+    statement.set_period(parse_period(title.get(0).unwrap_or_default())?)?;
+
+    let header = next_record()?;
+    if header.iter().take(COLUMNS.len()).ne(COLUMNS.iter().copied()) ||
+        header.iter().skip(COLUMNS.len()).any(|value| !value.is_empty()) {
+        return Err!("Got an unexpected transactions table header: {:?}", header);
+    }
+
+    let mut cash_flow = Cash::new(CURRENCY, dec!(0));
+
+    loop {
+        let record = next_record()?;
+        if record.get(0) == Some("Transactions Total") {
+            break;
+        }
+
+        let transaction = Transaction::parse(&record)?;
+        transaction.process(statement).map_err(|e| format!(
+            "Failed to process {:?} transaction from {}: {}",
+            transaction.action, formatting::format_date(transaction.date), e))?;
+
+        cash_flow.add_assign(transaction.amount)?;
+    }
+
+    Ok(cash_flow)
+}
+
+struct Transaction {
+    date: Date,
+    action: String,
+    symbol: String,
+    description: String,
+    quantity: Option<u32>,
+    price: Option<Cash>,
+    commission: Cash,
+    amount: Cash,
+}
+
+impl Transaction {
+    fn parse(record: &StringRecord) -> GenericResult<Transaction> {
+        let get = |index: usize| -> GenericResult<&str> {
+            Ok(record.get(index).ok_or_else(|| format!(
+                "Got an invalid transaction record: {:?}", record))?.trim())
+        };
+
+        // Date may be specified as "12/28/2020 as of 12/24/2020"
+        let date = get(0)?.split(" as of ").next().unwrap();
+
+        let quantity = match get(4)? {
+            "" => None,
+            quantity => Some(parse_quantity(quantity)?),
+        };
+
+        let price = match get(5)? {
+            "" => None,
+            price => Some(Cash::new(CURRENCY, parse_decimal(price, DecimalRestrictions::StrictlyPositive)?)),
+        };
+
+        let commission = match get(6)? {
+            "" => dec!(0),
+            commission => parse_decimal(commission, DecimalRestrictions::PositiveOrZero)?,
+        };
+
+        Ok(Transaction {
+            date: parse_date(date)?,
+            action: get(1)?.to_owned(),
+            symbol: get(2)?.to_owned(),
+            description: get(3)?.to_owned(),
+            quantity,
+            price,
+            commission: Cash::new(CURRENCY, commission),
+            amount: Cash::new(CURRENCY, parse_decimal(get(7)?, DecimalRestrictions::No)?),
+        })
+    }
+
+    fn process(&self, statement: &mut PartialBrokerStatement) -> EmptyResult {
+        let date = self.date;
+        let amount = self.amount;
+
+        match self.action.as_str() {
+            "Buy" | "Sell" => self.process_trade(statement)?,
+
+            "MoneyLink Transfer" | "Wire Funds" | "Wire Funds Received" => {
+                statement.cash_flows.push(CashAssets::new_from_cash(date, amount));
+            },
+
+            "Qualified Dividend" | "Non-Qualified Div" | "Cash Dividend" => {
+                let issuer = self.get_symbol()?;
+
+                let accruals = statement.dividend_accruals.entry(DividendId {
+                    date,
+                    issuer: issuer.to_owned(),
+                }).or_insert_with(DividendAccruals::new);
+
+                if amount.is_negative() {
+                    accruals.reverse(-amount);
+                } else {
+                    accruals.add(amount);
+                }
+            },
+
+            "NRA Tax Adj" | "NRA Withholding" => {
+                let issuer = self.get_symbol()?;
+
+                let accruals = statement.tax_accruals.entry(TaxId::new(date, issuer))
+                    .or_insert_with(TaxAccruals::new);
+
+                if amount.is_positive() {
+                    accruals.reverse(amount);
+                } else {
+                    accruals.add(-amount);
+                }
+            },
+
+            "Credit Interest" | "Bank Interest" => {
+                statement.idle_cash_interest.push(IdleCashInterest::new(date, amount));
+            },
+
+            "Service Fee" | "ADR Mgmt Fee" => {
+                statement.fees.push(Fee {
+                    date,
+                    amount,
+                    description: Some(self.description.clone()),
+                    tax_deductible: false,
+                });
+            },
+
+            _ => statement.add_unsupported_operation("transaction", &self.action),
+        };
+
+        Ok(())
+    }
+
+    fn process_trade(&self, statement: &mut PartialBrokerStatement) -> EmptyResult {
+        let symbol = self.get_symbol()?;
+        let (quantity, price) = match (self.quantity, self.price) {
+            (Some(quantity), Some(price)) => (quantity, price),
+            _ => return Err!("Got a trade without quantity or price"),
+        };
+
+        let volume = (price * quantity).round();
+        let commission = self.commission;
+
+        // The statement contains only trade dates, so all calculations are performed in T+0 mode
+        let conclusion_date = self.date;
+        let execution_date = self.date;
+
+        if self.action == "Buy" {
+            if self.amount != -volume.add(commission)? {
+                return Err!("Got an unexpected trade amount: {}", self.amount);
+            }
+
+            statement.stock_buys.push(StockBuy::new(
+                symbol, quantity, price, volume, commission, conclusion_date, execution_date));
+        } else {
+            if self.amount != volume.sub(commission)? {
+                return Err!("Got an unexpected trade amount: {}", self.amount);
+            }
+
+            statement.stock_sells.push(StockSell::new(
+                symbol, quantity, price, volume, commission, conclusion_date, execution_date, SaleKind::Close));
+        }
+
+        statement.instrument_names.insert(symbol.to_owned(), self.description.clone());
+
+        Ok(())
+    }
+
+    fn get_symbol(&self) -> GenericResult<&str> {
+        if self.symbol.is_empty() {
+            return Err!("Got {:?} transaction without symbol", self.action);
+        }
+        Ok(&self.symbol)
+    }
+}
+
+fn parse_period(title: &str) -> GenericResult<(Date, Date)> {
+    lazy_static! {
+        static ref TITLE_REGEX: Regex = Regex::new(concat!(
+            r"^Transactions +for account .+ ",
+            r"from (?P<start>\d{2}/\d{2}/\d{4}) to (?P<end>\d{2}/\d{2}/\d{4}) as of ",
+        )).unwrap();
+    }
+
+    let captures = TITLE_REGEX.captures(title).ok_or_else(|| format!(
+        "Unable to find statement period in {:?}", title))?;
+
+    util::parse_period(
+        parse_date(captures.name("start").unwrap().as_str())?,
+        parse_date(captures.name("end").unwrap().as_str())?,
+    )
+}
+
+fn parse_date(date: &str) -> GenericResult<Date> {
+    util::parse_date(date, "%m/%d/%Y")
+}
+
+fn parse_decimal(value: &str, restrictions: DecimalRestrictions) -> GenericResult<Decimal> {
+    // Amounts are specified as "$1,234.56" or "-$1,234.56"
+    let value = value.replace(&['$', ','][..], "");
+    util::parse_decimal(&value, restrictions)
+}
+
+fn parse_quantity(value: &str) -> GenericResult<u32> {
+    let quantity = parse_decimal(value, DecimalRestrictions::StrictlyPositive)?;
+    if quantity.trunc() != quantity {
+        return Err!("Fractional shares are not supported yet: {}", value);
+    }
+    Ok(quantity.to_u32().ok_or_else(|| format!("Invalid quantity: {}", value))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amount_parsing() {
+        assert_eq!(parse_decimal("$1,234.56", DecimalRestrictions::StrictlyPositive).unwrap(), dec!(1234.56));
+        assert_eq!(parse_decimal("-$0.99", DecimalRestrictions::StrictlyNegative).unwrap(), dec!(-0.99));
+    }
+}
\ No newline at end of file
@@ -4,11 +4,13 @@ use num_traits::Zero;
 
 use xls_table_derive::XlsTableRow;
 
+use crate::broker_statement::bonds::{BondAmortization, Coupon};
 use crate::broker_statement::fees::Fee;
 use crate::broker_statement::partial::PartialBrokerStatement;
 use crate::broker_statement::xls::{XlsStatementParser, SectionParser};
 use crate::core::{EmptyResult, GenericResult};
 use crate::currency::{Cash, CashAssets};
+use crate::formatting;
 use crate::types::{Date, Time};
 use crate::util::DecimalRestrictions;
 use crate::xls::{self, SheetReader, Cell, SkipCell, TableReader};
@@ -92,7 +94,7 @@ struct CashFlowRow {
     #[column(name="Сумма списания")]
     withdrawal: String,
     #[column(name="Примечание")]
-    _6: SkipCell,
+    note: Option<String>,
 }
 
 impl TableReader for CashFlowRow {
@@ -183,14 +185,39 @@ fn parse_cash_flow(
             date,
             amount: -check_amount(withdrawal)?,
             description: Some(operation.clone()),
+            tax_deductible: false,
         }),
-        "Покупка/продажа" | "Комиссия за сделки" => {},
-        _ => {
-            if cfg!(debug_assertions) {
-                return Err!("Unsupported cash flow operation: {:?}", operation)
-            }
+        "Выплата купонов" => {
+            let amount = check_amount(deposit)?;
+            statement.coupons.push(Coupon::new(date, &get_bond_symbol(cash_flow)?, amount));
+        },
+        "Налог (купонный доход)" => {
+            let tax = check_amount(withdrawal)?;
+            let symbol = get_bond_symbol(cash_flow)?;
+
+            let coupon = statement.coupons.iter_mut()
+                .find(|coupon| coupon.date == date && coupon.symbol == symbol)
+                .ok_or_else(|| format!(
+                    "Unable to find origin coupon for {} tax from {}",
+                    symbol, formatting::format_date(date)))?;
+
+            coupon.paid_tax.add_assign(tax)?;
         },
+        "Частичное погашение облигации" => statement.bond_amortizations.push(BondAmortization {
+            date,
+            symbol: get_bond_symbol(cash_flow)?,
+            amount: check_amount(deposit)?,
+        }),
+        "Покупка/продажа" | "Комиссия за сделки" => {},
+        _ => statement.add_unsupported_operation("cash flow operation", operation),
     };
 
     Ok(())
+}
+
+fn get_bond_symbol(cash_flow: &CashFlowRow) -> GenericResult<String> {
+    match cash_flow.note.as_ref().map(|note| note.trim()) {
+        Some(symbol) if !symbol.is_empty() => Ok(symbol.to_owned()),
+        _ => Err!("Unable to determine the bond for {:?} operation", cash_flow.operation),
+    }
 }
\ No newline at end of file
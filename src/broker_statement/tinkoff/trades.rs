@@ -1,8 +1,8 @@
-use num_traits::{FromPrimitive, Zero};
+use num_traits::FromPrimitive;
 
 use xls_table_derive::XlsTableRow;
 
-use crate::broker_statement::trades::{ForexTrade, StockBuy, StockSell};
+use crate::broker_statement::trades::{ForexTrade, SaleKind, StockBuy, StockSell};
 use crate::broker_statement::xls::{XlsStatementParser, SectionParser};
 use crate::core::EmptyResult;
 use crate::currency::Cash;
@@ -10,7 +10,7 @@ use crate::types::{Date, Time, Decimal};
 use crate::util::DecimalRestrictions;
 use crate::xls::{self, SheetReader, Cell, SkipCell, TableReader};
 
-use super::common::{read_next_table_row, parse_date, parse_time, parse_decimal, parse_cash};
+use super::common::{read_next_table_row, parse_date, parse_time, parse_cash};
 
 pub struct TradesParser {
 }
@@ -35,13 +35,6 @@ impl SectionParser for TradesParser {
         trades.sort_by_key(|trade| (trade.date, trade.time));
 
         for Trade {date: conclusion_date, info: trade, ..} in trades {
-            let accumulated_coupon_income = parse_decimal(
-                &trade.accumulated_coupon_income, DecimalRestrictions::No)?;
-
-            if !accumulated_coupon_income.is_zero() {
-                return Err!("Bonds aren't supported yet");
-            }
-
             if trade.leverage_rate.is_some() {
                 return Err!("Leverage is not supported yet");
             }
@@ -53,12 +46,27 @@ impl SectionParser for TradesParser {
                 _ => return Err!("Invalid {} trade quantity: {:?}", trade.symbol, trade.quantity),
             };
 
-            let price = parse_cash(
-                &trade.price_currency, &trade.price, DecimalRestrictions::StrictlyPositive)?;
+            let accrued_interest = parse_cash(
+                &trade.settlement_currency, &trade.accrued_interest, DecimalRestrictions::PositiveOrZero)?;
+
+            let (price, volume, accrued_interest) = if accrued_interest.is_zero() {
+                let price = parse_cash(
+                    &trade.price_currency, &trade.price, DecimalRestrictions::StrictlyPositive)?;
 
-            let volume = parse_cash(
-                &trade.settlement_currency, &trade.volume, DecimalRestrictions::StrictlyPositive)?;
-            debug_assert_eq!(volume, (price * quantity).round());
+                let volume = parse_cash(
+                    &trade.settlement_currency, &trade.volume, DecimalRestrictions::StrictlyPositive)?;
+                debug_assert_eq!(volume, (price * quantity).round());
+
+                (price, volume, None)
+            } else {
+                // Bond price is specified in percents of its face value, so calculate the price
+                // from the trade volume which doesn't include accrued interest.
+                let volume = parse_cash(
+                    &trade.settlement_currency, &trade.volume_without_accrued_interest,
+                    DecimalRestrictions::StrictlyPositive)?;
+
+                (volume / quantity, volume, Some(accrued_interest))
+            };
 
             let commission = parse_cash(
                 &trade.commission_currency, &trade.commission, DecimalRestrictions::PositiveOrZero)?;
@@ -79,9 +87,11 @@ impl SectionParser for TradesParser {
                             conclusion_date
                         })
                     } else {
-                        parser.statement.stock_buys.push(StockBuy::new(
+                        let mut stock_buy = StockBuy::new(
                             &trade.symbol, quantity, price, volume, commission,
-                            conclusion_date, execution_date));
+                            conclusion_date, execution_date);
+                        stock_buy.accrued_interest = accrued_interest;
+                        parser.statement.stock_buys.push(stock_buy);
                     }
                 },
                 "Продажа" => {
@@ -93,12 +103,14 @@ impl SectionParser for TradesParser {
                             conclusion_date
                         })
                     } else {
-                        parser.statement.stock_sells.push(StockSell::new(
+                        let mut stock_sell = StockSell::new(
                             &trade.symbol, quantity, price, volume, commission,
-                            conclusion_date, execution_date, false));
+                            conclusion_date, execution_date, SaleKind::Close);
+                        stock_sell.accrued_interest = accrued_interest;
+                        parser.statement.stock_sells.push(stock_sell);
                     }
                 },
-                _ => return Err!("Unsupported trade operation: {:?}", trade.operation),
+                _ => parser.statement.add_unsupported_operation("trade operation", &trade.operation),
             }
         }
 
@@ -133,9 +145,9 @@ struct TradeRow {
     #[column(name="Количество")]
     quantity: String,
     #[column(name="Сумма (без НКД)")]
-    _12: SkipCell,
+    volume_without_accrued_interest: String,
     #[column(name="НКД")]
-    accumulated_coupon_income: String,
+    accrued_interest: String,
     #[column(name="Сумма сделки")]
     volume: String,
     #[column(name="Валюта расчетов")]
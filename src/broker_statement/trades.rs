@@ -1,4 +1,4 @@
-use crate::core::GenericResult;
+use crate::core::{EmptyResult, GenericResult};
 use crate::currency::Cash;
 use crate::currency::converter::CurrencyConverter;
 use crate::formatting;
@@ -13,8 +13,35 @@ pub struct ForexTrade {
     pub conclusion_date: Date,
 }
 
+impl ForexTrade {
+    /// Compares the effective rate of the conversion (including commission) with the official
+    /// currency rate. The official rate for non-trading days is resolved by the converter in the
+    /// same way as for any other conversion (the last known rate).
+    pub fn conversion_cost(&self, currency: &str, converter: &CurrencyConverter) -> GenericResult<ForexConversionCost> {
+        let date = self.conclusion_date;
+
+        let commission = converter.convert_to(date, self.commission, self.to.currency)?;
+        let effective_rate = (self.to.amount - commission) / self.from.amount;
+        let mid_rate = converter.precise_currency_rate(date, self.from.currency, self.to.currency)?;
+
+        let cost =
+            converter.convert_to(date, self.from, currency)?
+            - converter.convert_to(date, self.to, currency)?
+            + converter.convert_to(date, self.commission, currency)?;
+
+        Ok(ForexConversionCost {effective_rate, mid_rate, cost})
+    }
+}
+
+pub struct ForexConversionCost {
+    pub effective_rate: Decimal, // Units of the target currency received per unit of the source one
+    pub mid_rate: Decimal,
+    pub cost: Decimal, // Conversion cost in the specified currency
+}
+
 #[derive(Debug)]
 pub struct StockBuy {
+    pub id: Option<String>, // Broker's transaction ID if it's provided
     pub symbol: String,
     pub quantity: u32,
     pub price: Cash,
@@ -24,6 +51,10 @@ pub struct StockBuy {
     pub conclusion_date: Date,
     pub execution_date: Date,
 
+    // Accrued coupon interest (НКД) paid on bond purchase. It's not a part of the bond's cost basis
+    // and reduces taxable income of the next coupons.
+    pub accrued_interest: Option<Cash>,
+
     sold: u32,
 }
 
@@ -33,8 +64,8 @@ impl StockBuy {
         conclusion_date: Date, execution_date: Date,
     ) -> StockBuy {
         StockBuy {
-            symbol: symbol.to_owned(), quantity, price, volume, commission,
-            conclusion_date, execution_date, sold: 0,
+            id: None, symbol: symbol.to_owned(), quantity, price, volume, commission,
+            conclusion_date, execution_date, accrued_interest: None, sold: 0,
         }
     }
 
@@ -46,14 +77,92 @@ impl StockBuy {
         self.quantity - self.sold
     }
 
+    /// Applies stock split to the unsold part of the lot. If the lot is partially sold, the unsold
+    /// part is detached into a new lot which is returned.
+    pub fn split(&mut self, quantity: u32) -> Option<StockBuy> {
+        assert_ne!(quantity, 0);
+
+        if self.sold == 0 {
+            self.quantity = quantity;
+            self.price = self.volume / quantity;
+            return None;
+        }
+
+        let (volume, commission) = self.detach_unsold();
+
+        Some(StockBuy::new(
+            &self.symbol, quantity, volume / quantity, volume, commission,
+            self.conclusion_date, self.execution_date))
+    }
+
+    /// Leaves only the sold part in the lot and returns volume and commission of the unsold one.
+    pub fn detach_unsold(&mut self) -> (Cash, Cash) {
+        // Take the whole cost of fully unsold lots to not lose anything on division
+        let (volume, commission) = if self.sold == 0 {
+            (self.volume, self.commission)
+        } else {
+            let unsold = self.get_unsold();
+            (self.volume / self.quantity * unsold, self.commission / self.quantity * unsold)
+        };
+
+        self.quantity = self.sold;
+        self.volume.amount -= volume.amount;
+        self.commission.amount -= commission.amount;
+
+        (volume, commission)
+    }
+
+    /// Detaches the specified quantity from the end of an unsold lot into a new lot.
+    pub fn detach(&mut self, quantity: u32) -> StockBuy {
+        assert_eq!(self.sold, 0);
+        assert!(quantity > 0 && quantity < self.quantity);
+
+        let volume = self.volume / self.quantity * quantity;
+        let commission = self.commission / self.quantity * quantity;
+
+        self.quantity -= quantity;
+        self.volume.amount -= volume.amount;
+        self.commission.amount -= commission.amount;
+
+        let mut lot = StockBuy::new(
+            &self.symbol, quantity, self.price, volume, commission,
+            self.conclusion_date, self.execution_date);
+        lot.id = self.id.clone();
+        lot
+    }
+
+    /// Adds cost basis of another lot which has been merged into this one.
+    pub fn add_cost(&mut self, volume: Cash, commission: Cash) -> EmptyResult {
+        self.volume.add_assign(volume)?;
+        self.commission.add_assign(commission)?;
+        self.price = self.volume / self.quantity;
+        Ok(())
+    }
+
     pub fn sell(&mut self, quantity: u32) {
         assert!(self.get_unsold() >= quantity);
         self.sold += quantity;
     }
 }
 
+/// Order in which stock buys are consumed by stock sells. Russian tax law requires FIFO, so LIFO
+/// is intended only for what-if analysis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TaxLotMethod {
+    Fifo,
+    Lifo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SaleKind {
+    Close,     // Sale of held shares
+    Short,     // Short sale which is covered by the following purchases
+    Emulation, // Emulated sale of held shares (see `BrokerStatement::emulate_sell()`)
+}
+
 #[derive(Clone, Debug)]
 pub struct StockSell {
+    pub id: Option<String>, // Broker's transaction ID if it's provided
     pub symbol: String,
     pub quantity: u32,
     pub price: Cash,
@@ -63,18 +172,21 @@ pub struct StockSell {
     pub conclusion_date: Date,
     pub execution_date: Date,
 
-    pub emulation: bool,
+    // Accrued coupon interest (НКД) received on bond sale. It's taxed as coupon income.
+    pub accrued_interest: Option<Cash>,
+
+    pub kind: SaleKind,
     sources: Vec<StockSellSource>,
 }
 
 impl StockSell {
     pub fn new(
         symbol: &str, quantity: u32, price: Cash, volume: Cash, commission: Cash,
-        conclusion_date: Date, execution_date: Date, emulation: bool,
+        conclusion_date: Date, execution_date: Date, kind: SaleKind,
     ) -> StockSell {
         StockSell {
-            symbol: symbol.to_owned(), quantity, price, volume, commission,
-            conclusion_date, execution_date, emulation, sources: Vec::new(),
+            id: None, symbol: symbol.to_owned(), quantity, price, volume, commission,
+            conclusion_date, execution_date, accrued_interest: None, kind, sources: Vec::new(),
         }
     }
 
@@ -103,7 +215,9 @@ impl StockSell {
         let local_commission = converter.convert_to_cash_rounding(
             self.conclusion_date, commission, country.currency)?;
 
-        let mut total_cost = commission;
+        // Commission may be charged in a currency other than the trade's one
+        let mut total_cost = converter.convert_to_cash_rounding(
+            self.conclusion_date, commission, revenue.currency)?;
         let mut total_local_cost = local_commission;
 
         let mut purchase_cost = Cash::new(total_cost.currency, dec!(0));
@@ -191,8 +305,8 @@ impl StockSellSource {
         let mut total_cost = cost;
         let mut total_local_cost = local_cost;
 
-        total_cost.add_assign(self.commission.round()).map_err(|e| format!(
-            "Trade and commission have different currency: {}", e))?;
+        total_cost.add_assign(converter.convert_to_cash_rounding(
+            self.conclusion_date, commission, cost.currency)?).unwrap();
         total_local_cost += local_commission;
 
         Ok(FifoDetails {
@@ -251,4 +365,94 @@ pub struct FifoDetails {
 
     pub total_cost: Cash,
     pub total_local_cost: Cash,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::db;
+    use crate::localities;
+
+    use super::*;
+
+    #[test]
+    fn local_currency_profit() {
+        let converter = CurrencyConverter::mock(dec!(60), &[(date!(1, 1, 2020), dec!(70))]);
+        let country = localities::russia();
+
+        let buy_date = date!(1, 6, 2019);
+        let sell_date = date!(1, 6, 2020);
+        let commission = Cash::new("USD", dec!(0));
+
+        let price = Cash::new("USD", dec!(95));
+        let mut trade = StockSell::new(
+            "AAA", 10, price, price * 10, commission, sell_date, sell_date, SaleKind::Close);
+
+        trade.process(vec![StockSellSource {
+            quantity: 10,
+            price: Cash::new("USD", dec!(100)),
+            commission,
+
+            conclusion_date: buy_date,
+            execution_date: buy_date,
+        }]);
+
+        // Each leg is converted at its own date rate, so the loss in USD is a taxable gain in RUB
+        let details = trade.calculate(&country, &converter).unwrap();
+        assert_eq!(details.profit, Cash::new("USD", dec!(-50)));
+        assert_eq!(details.purchase_local_cost, Cash::new("RUB", dec!(60000)));
+        assert_eq!(details.local_revenue, Cash::new("RUB", dec!(66500)));
+        assert_eq!(details.local_profit, Cash::new("RUB", dec!(6500)));
+        assert_eq!(details.tax_to_pay, Cash::new("RUB", dec!(845)));
+    }
+
+    #[test]
+    fn foreign_currency_commission() {
+        let converter = CurrencyConverter::mock(dec!(60), &[(date!(1, 1, 2020), dec!(70))]);
+        let country = localities::russia();
+
+        let buy_date = date!(1, 6, 2019);
+        let sell_date = date!(1, 6, 2020);
+
+        let price = Cash::new("USD", dec!(100));
+        let mut trade = StockSell::new(
+            "AAA", 10, price, price * 10, Cash::new("RUB", dec!(70)), sell_date, sell_date, SaleKind::Close);
+
+        trade.process(vec![StockSellSource {
+            quantity: 10,
+            price: Cash::new("USD", dec!(90)),
+            commission: Cash::new("RUB", dec!(60)),
+
+            conclusion_date: buy_date,
+            execution_date: buy_date,
+        }]);
+
+        // Commissions are converted to trade currency at their own date rate
+        let details = trade.calculate(&country, &converter).unwrap();
+        assert_eq!(details.purchase_cost, Cash::new("USD", dec!(901)));
+        assert_eq!(details.total_cost, Cash::new("USD", dec!(902)));
+        assert_eq!(details.profit, Cash::new("USD", dec!(98)));
+        assert_eq!(details.local_commission, Cash::new("RUB", dec!(70)));
+        assert_eq!(details.total_local_cost, Cash::new("RUB", dec!(54130)));
+        assert_eq!(details.local_profit, Cash::new("RUB", dec!(15870)));
+    }
+
+    #[test]
+    fn forex_conversion_cost() {
+        let (_database, connection) = db::new_temporary();
+        let converter = CurrencyConverter::new(&Config::mock(), connection, None, false);
+
+        // Sunday: the rate from Saturday (68.0447) must be used
+        let trade = ForexTrade {
+            from: Cash::new("USD", dec!(1000)),
+            to: Cash::new("RUB", dec!(67500)),
+            commission: Cash::new("RUB", dec!(40)),
+            conclusion_date: date!(2, 9, 2018),
+        };
+
+        let cost = trade.conversion_cost("RUB", &converter).unwrap();
+        assert_eq!(cost.effective_rate, dec!(67.46));
+        assert_eq!(cost.mid_rate, dec!(68.0447));
+        assert_eq!(cost.cost, dec!(584.7));
+    }
+}
@@ -2,15 +2,18 @@ mod plans;
 
 use std::collections::BTreeMap;
 
+use log::warn;
 use matches::matches;
+use num_traits::Zero;
 use serde::Deserialize;
 use serde::de::{Deserializer, Error as _};
 
-use crate::broker_statement::StatementsMergingStrategy;
+use crate::broker_statement::{Fee, StatementsMergingStrategy, UnsupportedOperation};
 use crate::commissions::CommissionSpec;
-use crate::config::{Config, BrokersConfig, BrokerConfig};
-use crate::core::GenericResult;
-use crate::currency::CashAssets;
+use crate::config::{
+    Config, BrokersConfig, BrokerConfig, TransactionCommissionSpec, UnsupportedOperationPolicy};
+use crate::core::{EmptyResult, GenericResult};
+use crate::currency::{Cash, CashAssets};
 use crate::types::Decimal;
 
 #[derive(Debug, Clone, Copy)]
@@ -19,6 +22,8 @@ pub enum Broker {
     Firstrade,
     InteractiveBrokers,
     Open,
+    Sber,
+    Schwab,
     Tinkoff,
 }
 
@@ -36,13 +41,20 @@ impl Broker {
             _ => StatementsMergingStrategy::ContinuousOnly,
         };
 
+        let mut commission_spec = self.get_commission_spec(plan)?;
+        if let Some(ref tiers) = config.monthly_commission_tiers {
+            commission_spec.set_monthly_tiers(tiers.clone()).map_err(|e| format!(
+                "Invalid monthly commission tiers for {}: {}", self.get_name(), e))?;
+        }
+
         Ok(BrokerInfo {
             type_: self,
             name: self.get_name(),
             config: config,
-            commission_spec: self.get_commission_spec(plan)?,
+            commission_spec: commission_spec,
             allow_future_fees: matches!(self, Broker::Tinkoff),
             statements_merging_strategy: statements_merging_strategy,
+            sweep_fund: None,
         })
     }
 
@@ -52,6 +64,8 @@ impl Broker {
             Broker::Firstrade => "Firstrade Securities Inc.",
             Broker::InteractiveBrokers => "Interactive Brokers LLC",
             Broker::Open => "АО «Открытие Брокер»",
+            Broker::Sber => "ПАО «Сбербанк»",
+            Broker::Schwab => "Charles Schwab & Co., Inc.",
             Broker::Tinkoff => "АО «Тинькофф Банк»",
         }
     }
@@ -62,6 +76,8 @@ impl Broker {
             Broker::Firstrade => &config.firstrade,
             Broker::InteractiveBrokers => &config.interactive_brokers,
             Broker::Open => &config.open_broker,
+            Broker::Sber => &config.sber,
+            Broker::Schwab => &config.schwab,
             Broker::Tinkoff => &config.tinkoff,
         }.as_ref()
     }
@@ -80,6 +96,10 @@ impl Broker {
             Broker::Open => (plans::open::iia, btreemap!{
                 "Самостоятельное управление (ИИС)" => plans::open::iia as PlanFn,
             }),
+            Broker::Sber => (plans::sber::manual, btreemap!{
+                "Самостоятельный" => plans::sber::manual as PlanFn,
+            }),
+            Broker::Schwab => (plans::schwab::free, btreemap!{}),
             Broker::Tinkoff => (plans::tinkoff::trader, btreemap!{
                 "Трейдер" => plans::tinkoff::trader as PlanFn,
             }),
@@ -108,10 +128,13 @@ impl<'de> Deserialize<'de> for Broker {
             "firstrade" => Broker::Firstrade,
             "interactive-brokers" => Broker::InteractiveBrokers,
             "open-broker" => Broker::Open,
+            "sber" => Broker::Sber,
+            "schwab" => Broker::Schwab,
             "tinkoff" => Broker::Tinkoff,
 
             _ => return Err(D::Error::unknown_variant(&value, &[
-                "bcs", "firstrade", "interactive-brokers", "open-broker", "tinkoff",
+                "bcs", "firstrade", "interactive-brokers", "open-broker", "sber", "schwab",
+                "tinkoff",
             ])),
         })
     }
@@ -126,19 +149,209 @@ pub struct BrokerInfo {
     pub commission_spec: CommissionSpec,
     pub allow_future_fees: bool,
     pub statements_merging_strategy: StatementsMergingStrategy,
+    pub sweep_fund: Option<String>,
 }
 
 impl BrokerInfo {
-    pub fn get_deposit_commission(&self, assets: CashAssets) -> GenericResult<Decimal> {
-        let currency = assets.cash.currency;
-
-        let commission_spec = match self.config.deposit_commissions.get(currency) {
-            Some(commission_spec) => commission_spec,
-            None => return Err!(concat!(
-                "Unable to calculate commission for {} deposit to {}: there is no commission ",
-                "specification in the configuration file"), currency, self.name),
+    pub fn with_sweep_fund(mut self, sweep_fund: Option<&String>) -> BrokerInfo {
+        self.sweep_fund = sweep_fund.cloned();
+        self
+    }
+
+    /// Maps deposits and withdrawals to the commissions that are specified for their currency
+    pub fn get_deposit_commissions(&self, cash_flows: &[CashAssets]) -> GenericResult<Vec<Fee>> {
+        let mut commissions = Vec::new();
+
+        for assets in cash_flows {
+            let currency = assets.cash.currency;
+
+            let commission = match self.config.deposit_commissions.get(currency) {
+                Some(commission_spec) => commission_spec.calculate(assets.cash.amount),
+                None if self.config.require_deposit_commissions => {
+                    self.get_deposit_commission_spec(currency)?;
+                    continue;
+                },
+                None => continue,
+            };
+
+            if commission.is_zero() {
+                continue;
+            }
+
+            commissions.push(Fee {
+                date: assets.date,
+                amount: Cash::new(currency, -commission),
+                description: Some(if assets.cash.is_positive() {
+                    "Комиссия за ввод денежных средств"
+                } else {
+                    "Комиссия за вывод денежных средств"
+                }.to_owned()),
+                tax_deductible: false,
+            });
+        }
+
+        Ok(commissions)
+    }
+
+    fn get_deposit_commission_spec(&self, currency: &str) -> GenericResult<&TransactionCommissionSpec> {
+        Ok(self.config.deposit_commissions.get(currency).ok_or_else(|| format!(concat!(
+            "Unable to calculate commission for {} deposit to {}: there is no commission ",
+            "specification in the configuration file"), currency, self.name))?)
+    }
+
+    /// Maximum allowed difference between trade volume declared in broker statement and the one
+    /// calculated from its price and quantity
+    pub fn trade_volume_tolerance(&self) -> Decimal {
+        self.config.trade_volume_tolerance
+    }
+
+    /// Applies the configured policy to the operations which aren't supported by statement parser
+    pub fn check_unsupported_operations(&self, operations: &[UnsupportedOperation]) -> EmptyResult {
+        let config = &self.config.unsupported_operations;
+
+        for operation in operations {
+            if config.ignore.contains(&operation.name) {
+                continue;
+            }
+
+            match config.policy {
+                UnsupportedOperationPolicy::Error => return Err!(
+                    "Unsupported {}: {:?}", operation.kind, operation.name),
+                UnsupportedOperationPolicy::Warn => warn!(
+                    "Skipping unsupported {}: {:?}.", operation.kind, operation.name),
+                UnsupportedOperationPolicy::Skip => {},
+            };
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::commissions::CommissionCalc;
+    use crate::types::TradeType;
+    use super::*;
+
+    #[test]
+    fn deposit_commissions() {
+        let mut config = Config::mock();
+        config.brokers.as_mut().unwrap().firstrade.as_mut().unwrap().deposit_commissions.insert(
+            s!("USD"), TransactionCommissionSpec::new_fixed(dec!(15)));
+
+        let cash_flows = [
+            CashAssets::new_from_cash(date!(1, 2, 2020), Cash::new("USD", dec!(1000))),
+            CashAssets::new_from_cash(date!(2, 2, 2020), Cash::new("RUB", dec!(1000))),
+        ];
+
+        let broker = Broker::Firstrade.get_info(&config, None).unwrap();
+        let commissions = broker.get_deposit_commissions(&cash_flows).unwrap();
+
+        assert_eq!(commissions.len(), 1);
+
+        let commission = commissions.first().unwrap();
+        assert_eq!(commission.date, date!(1, 2, 2020));
+        assert_eq!(commission.amount, Cash::new("USD", dec!(-15)));
+
+        config.brokers.as_mut().unwrap().firstrade.as_mut().unwrap().require_deposit_commissions = true;
+        let broker = Broker::Firstrade.get_info(&config, None).unwrap();
+        assert_eq!(broker.get_deposit_commissions(&cash_flows).unwrap_err().to_string(), concat!(
+            "Unable to calculate commission for RUB deposit to Firstrade Securities Inc.: ",
+            "there is no commission specification in the configuration file",
+        ));
+    }
+
+    #[test]
+    fn percent_deposit_commission() {
+        let mut config = Config::mock();
+        config.brokers.as_mut().unwrap().tinkoff.as_mut().unwrap().deposit_commissions.insert(
+            s!("RUB"), TransactionCommissionSpec {
+                percent: Some(dec!(0.5)),
+                minimum: Some(dec!(100)),
+                maximum: Some(dec!(1000)),
+                ..Default::default()
+            });
+
+        let broker = Broker::Tinkoff.get_info(&config, None).unwrap();
+        let cash_flows: Vec<CashAssets> = [dec!(10_000), dec!(-50_002), dec!(1_000_000)].iter()
+            .map(|&amount| CashAssets::new(date!(1, 2, 2020), "RUB", amount))
+            .collect();
+
+        let commissions: Vec<Cash> = broker.get_deposit_commissions(&cash_flows).unwrap().iter()
+            .map(|commission| commission.amount)
+            .collect();
+
+        assert_eq!(commissions, vec![
+            Cash::new("RUB", dec!(-100)),
+            Cash::new("RUB", dec!(-250.01)),
+            Cash::new("RUB", dec!(-1000)),
+        ]);
+    }
+
+    #[test]
+    fn monthly_commission_tiers() {
+        let mut config = Config::mock();
+        let tiers = &mut config.brokers.as_mut().unwrap().sber.as_mut().unwrap().monthly_commission_tiers;
+
+        tiers.replace(btreemap!{dec!(1_000_000) => dec!(0.05)});
+        assert_eq!(Broker::Sber.get_info(&config, None).unwrap_err().to_string(), concat!(
+            "Invalid monthly commission tiers for ПАО «Сбербанк»: ",
+            "Invalid tiered commission specification: There is no tier with zero starting volume",
+        ));
+
+        let tiers = &mut config.brokers.as_mut().unwrap().sber.as_mut().unwrap().monthly_commission_tiers;
+        tiers.replace(btreemap!{
+            dec!(        0) => dec!(0.1),
+            dec!(1_000_000) => dec!(0.05),
+        });
+
+        let broker = Broker::Sber.get_info(&config, None).unwrap();
+        let mut calc = CommissionCalc::new(broker.commission_spec);
+        let currency = "RUB";
+
+        // Trade commissions of the plan are preserved
+        assert_eq!(
+            calc.add_trade(date!(1, 12, 2020), TradeType::Buy, 10, Cash::new(currency, dec!(100_000))).unwrap(),
+            Cash::new(currency, dec!(600)),
+        );
+        assert_eq!(
+            calc.add_trade(date!(2, 12, 2020), TradeType::Sell, 1, Cash::new(currency, dec!(100_000))).unwrap(),
+            Cash::new(currency, dec!(60)),
+        );
+
+        assert_eq!(calc.calculate(), hashmap!{
+            date!(1, 12, 2020) => Cash::new(currency, dec!(1000)),
+            date!(2, 12, 2020) => Cash::new(currency, dec!(50)),
+        });
+    }
+
+    #[test]
+    fn unsupported_operations() {
+        let operations = vec![UnsupportedOperation {
+            kind: "cash flow operation",
+            name: s!("Unknown operation"),
+        }];
+
+        let check = |policy, ignore: &[&str]| {
+            let mut config = Config::mock();
+            {
+                let config = &mut config.brokers.as_mut().unwrap().tinkoff.as_mut().unwrap()
+                    .unsupported_operations;
+                config.policy = policy;
+                config.ignore = ignore.iter().map(|&name| name.to_owned()).collect();
+            }
+
+            let broker = Broker::Tinkoff.get_info(&config, None).unwrap();
+            broker.check_unsupported_operations(&operations).map_err(|e| e.to_string())
         };
 
-        Ok(commission_spec.fixed_amount)
+        // Unsupported operations are skipped with a warning unless strict mode is enabled
+        assert_eq!(check(UnsupportedOperationPolicy::default(), &[]), Ok(()));
+
+        assert_eq!(check(UnsupportedOperationPolicy::Error, &[]),
+                   Err(s!("Unsupported cash flow operation: \"Unknown operation\"")));
+        assert_eq!(check(UnsupportedOperationPolicy::Error, &["Unknown operation"]), Ok(()));
+        assert_eq!(check(UnsupportedOperationPolicy::Warn, &[]), Ok(()));
+        assert_eq!(check(UnsupportedOperationPolicy::Skip, &[]), Ok(()));
     }
-}
\ No newline at end of file
+}
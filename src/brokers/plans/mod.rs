@@ -2,4 +2,6 @@ pub mod bcs;
 pub mod firstrade;
 pub mod ib;
 pub mod open;
+pub mod sber;
+pub mod schwab;
 pub mod tinkoff;
\ No newline at end of file
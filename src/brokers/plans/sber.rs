@@ -0,0 +1,37 @@
+#[cfg(test)] use crate::commissions::CommissionCalc;
+use crate::commissions::{
+    CommissionSpec, CommissionSpecBuilder, TradeCommissionSpecBuilder,
+    TransactionCommissionSpecBuilder};
+#[cfg(test)] use crate::currency::Cash;
+#[cfg(test)] use crate::types::TradeType;
+
+pub fn manual() -> CommissionSpec {
+    CommissionSpecBuilder::new("RUB")
+        .trade(TradeCommissionSpecBuilder::new()
+            .commission(TransactionCommissionSpecBuilder::new()
+                .percent(dec!(0.06))
+                .build().unwrap())
+            .build())
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+
+    #[rstest(trade_type => [TradeType::Buy, TradeType::Sell])]
+    fn manual(trade_type: TradeType) {
+        let mut calc = CommissionCalc::new(super::manual());
+
+        let currency = "RUB";
+        let date = date!(10, 9, 2020);
+
+        assert_eq!(
+            calc.add_trade(date, trade_type, 10, Cash::new(currency, dec!(2251.5))).unwrap(),
+            Cash::new(currency, dec!(13.51)),
+        );
+
+        assert_eq!(calc.calculate(), hashmap!{});
+    }
+}
\ No newline at end of file
@@ -1,5 +1,6 @@
 use crate::broker_statement::{
-    BrokerStatement, ForexTrade, StockBuy, StockSell, Dividend, Fee, IdleCashInterest};
+    BrokerStatement, ForexTrade, StockBuy, StockSell, Dividend, Fee, IdleCashInterest, Coupon,
+    BondAmortization};
 use crate::currency::{Cash, CashAssets};
 use crate::types::Date;
 
@@ -34,6 +35,14 @@ impl CashFlowMapper {
             self.dividend(&statement.get_instrument_name(&dividend.issuer), dividend);
         }
 
+        for coupon in &statement.coupons {
+            self.coupon(&statement.get_instrument_name(&coupon.symbol), coupon);
+        }
+
+        for amortization in &statement.bond_amortizations {
+            self.bond_amortization(&statement.get_instrument_name(&amortization.symbol), amortization);
+        }
+
         for trade in &statement.forex_trades {
             self.forex_trade(trade);
         }
@@ -124,6 +133,21 @@ impl CashFlowMapper {
         };
     }
 
+    fn coupon(&mut self, name: &str, coupon: &Coupon) {
+        let description = format!("Купон по облигации {}", name);
+        self.add(coupon.date, coupon.amount, description);
+
+        if !coupon.paid_tax.is_zero() {
+            let description = format!("Налог, удержанный с купона по облигации {}", name);
+            self.add(coupon.date, -coupon.paid_tax, description);
+        };
+    }
+
+    fn bond_amortization(&mut self, name: &str, amortization: &BondAmortization) {
+        let description = format!("Частичное погашение облигации {}", name);
+        self.add(amortization.date, amortization.amount, description);
+    }
+
     fn add_static(&mut self, date: Date, amount: Cash, description: &str) -> &mut CashFlow {
         self.add(date, amount, description.to_owned())
     }
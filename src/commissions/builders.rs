@@ -1,6 +1,6 @@
 use std::collections::BTreeMap;
 
-use crate::core::GenericResult;
+use crate::core::{EmptyResult, GenericResult};
 use crate::types::{Decimal, TradeType};
 use crate::util::RoundingMethod;
 
@@ -111,17 +111,27 @@ impl CumulativeCommissionSpecBuilder {
     }
 
     pub fn tiers(mut self, tiers: BTreeMap<Decimal, Decimal>) -> GenericResult<CumulativeCommissionSpecBuilder> {
-        if tiers.is_empty() || tiers.get(&dec!(0)).is_none() {
-            return Err!(concat!(
-                "Invalid tiered commission specification: ",
-                "There is no tier with zero starting volume",
-            ));
+        validate_tiers(&tiers)?;
+        if self.0.monthly_tiers.is_some() {
+            return Err!("Daily and monthly commission tiers are mutually exclusive");
         }
 
         self.0.tiers.replace(tiers);
         Ok(self)
     }
 
+    /// Specifies commission percent which depends on volume traded since the beginning of the
+    /// month. See `CommissionCalc::calculate()` for details.
+    pub fn monthly_tiers(mut self, tiers: BTreeMap<Decimal, Decimal>) -> GenericResult<CumulativeCommissionSpecBuilder> {
+        validate_tiers(&tiers)?;
+        if self.0.tiers.is_some() {
+            return Err!("Daily and monthly commission tiers are mutually exclusive");
+        }
+
+        self.0.monthly_tiers.replace(tiers);
+        Ok(self)
+    }
+
     pub fn minimum_daily(mut self, minimum: Decimal) -> CumulativeCommissionSpecBuilder {
         self.0.minimum_daily.replace(minimum);
         self
@@ -147,4 +157,14 @@ impl CumulativeCommissionSpecBuilder {
     pub fn build(self) -> CumulativeCommissionSpec {
         self.0
     }
-}
\ No newline at end of file
+}
+
+pub(super) fn validate_tiers(tiers: &BTreeMap<Decimal, Decimal>) -> EmptyResult {
+    if tiers.is_empty() || tiers.get(&dec!(0)).is_none() {
+        return Err!(concat!(
+            "Invalid tiered commission specification: ",
+            "There is no tier with zero starting volume",
+        ));
+    }
+    Ok(())
+}
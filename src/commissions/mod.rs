@@ -6,7 +6,7 @@ use std::ops::Bound;
 use chrono::Datelike;
 use num_traits::Zero;
 
-use crate::core::GenericResult;
+use crate::core::{EmptyResult, GenericResult};
 use crate::currency::Cash;
 use crate::types::{Date, Decimal, TradeType};
 use crate::util::{self, RoundingMethod};
@@ -22,6 +22,17 @@ pub struct CommissionSpec {
     cumulative: CumulativeCommissionSpec,
 }
 
+impl CommissionSpec {
+    /// Replaces plan's commission tiers with the ones which depend on volume traded since the
+    /// beginning of the month (for plans which tiers are specified in the configuration file)
+    pub fn set_monthly_tiers(&mut self, tiers: BTreeMap<Decimal, Decimal>) -> EmptyResult {
+        builders::validate_tiers(&tiers)?;
+        self.cumulative.tiers = None;
+        self.cumulative.monthly_tiers.replace(tiers);
+        Ok(())
+    }
+}
+
 #[derive(Default, Clone, Debug)]
 pub struct TradeCommissionSpec {
     commission: TransactionCommissionSpec,
@@ -70,6 +81,7 @@ impl TransactionCommissionSpec {
 pub struct CumulativeCommissionSpec {
     // Broker commissions
     tiers: Option<BTreeMap<Decimal, Decimal>>,
+    monthly_tiers: Option<BTreeMap<Decimal, Decimal>>,
     minimum_daily: Option<Decimal>,
     minimum_monthly: Option<Decimal>,
 
@@ -123,12 +135,23 @@ impl CommissionCalc {
         Ok(Cash::new(self.spec.currency, commission))
     }
 
+    /// Calculates cumulative commissions. Months are determined by the trade dates passed to
+    /// `add_trade()` (conclusion dates). Monthly tiers are applied to daily volume by volume traded
+    /// in the month before the day, so a lower tier takes effect starting from the next day after
+    /// the threshold is crossed.
     pub fn calculate(self) -> HashMap<Date, Cash> {
         let mut total_by_date = HashMap::new();
         let mut monthly = HashMap::new();
+        let mut monthly_volume: HashMap<(i32, u32), Decimal> = HashMap::new();
+
+        let mut daily_volume: Vec<(Date, Decimal)> = self.volume.iter()
+            .map(|(&date, &volume)| (date, volume)).collect();
+        daily_volume.sort_by_key(|&(date, _volume)| date);
 
-        for (&date, &volume) in &self.volume {
-            let (commission, fee) = self.calculate_daily(volume);
+        for (date, volume) in daily_volume {
+            let month_volume = monthly_volume.entry((date.year(), date.month())).or_default();
+            let (commission, fee) = self.calculate_daily(volume, *month_volume);
+            *month_volume += volume;
 
             let total = commission + fee;
             if !total.is_zero() {
@@ -164,14 +187,20 @@ impl CommissionCalc {
         }).collect()
     }
 
-    fn calculate_daily(&self, volume: Decimal) -> (Decimal, Decimal) {
-        let mut commission = if let Some(ref tiers) = self.spec.cumulative.tiers {
-            let percent = *tiers.range((Bound::Unbounded, Bound::Included(volume)))
-                .last().unwrap().1;
+    fn calculate_daily(&self, volume: Decimal, month_volume: Decimal) -> (Decimal, Decimal) {
+        let cumulative = &self.spec.cumulative;
 
-            util::round_with(volume * percent / dec!(100), 2, self.spec.rounding_method)
+        let tier_percent = if let Some(ref tiers) = cumulative.tiers {
+            Some(get_tier_percent(tiers, volume))
+        } else if let Some(ref tiers) = cumulative.monthly_tiers {
+            Some(get_tier_percent(tiers, month_volume))
         } else {
-            dec!(0)
+            None
+        };
+
+        let mut commission = match tier_percent {
+            Some(percent) => util::round_with(volume * percent / dec!(100), 2, self.spec.rounding_method),
+            None => dec!(0),
         };
 
         if let Some(minimum) = self.spec.cumulative.minimum_daily {
@@ -189,6 +218,10 @@ impl CommissionCalc {
     }
 }
 
+fn get_tier_percent(tiers: &BTreeMap<Decimal, Decimal>, volume: Decimal) -> Decimal {
+    *tiers.range((Bound::Unbounded, Bound::Included(volume))).last().unwrap().1
+}
+
 fn get_trade_volume(commission_currency: &str, volume: Cash) -> GenericResult<Decimal> {
     if volume.currency != commission_currency {
         return Err!(concat!(
@@ -207,4 +240,44 @@ fn get_monthly_commission_date(year: i32, month: u32) -> Date {
     } else {
         Date::from_ymd(year, month + 1, 1)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monthly_tiers() {
+        let spec = CommissionSpecBuilder::new("RUB")
+            .cumulative(CumulativeCommissionSpecBuilder::new()
+                .monthly_tiers(btreemap!{
+                    dec!(        0) => dec!(0.1),
+                    dec!(1_000_000) => dec!(0.05),
+                }).unwrap()
+                .build())
+            .build();
+
+        let mut calc = CommissionCalc::new(spec);
+        let currency = "RUB";
+
+        for &(date, shares, price) in &[
+            (date!(30, 11, 2020), 10, dec!(100_000)),
+            (date!( 1, 12, 2020),  6, dec!(100_000)),
+            (date!( 2, 12, 2020),  5, dec!(100_000)),
+            (date!( 3, 12, 2020),  1, dec!(100_000)),
+        ] {
+            calc.add_trade(date, TradeType::Buy, shares, Cash::new(currency, price)).unwrap();
+        }
+
+        assert_eq!(calc.calculate(), hashmap!{
+            // November volume doesn't affect December tiers
+            date!(30, 11, 2020) => Cash::new(currency, dec!(1000)),
+            date!( 1, 12, 2020) => Cash::new(currency, dec!(600)),
+
+            // The threshold is crossed during the day, so the lower tier is applied starting from
+            // the next day
+            date!( 2, 12, 2020) => Cash::new(currency, dec!(500)),
+            date!( 3, 12, 2020) => Cash::new(currency, dec!(50)),
+        });
+    }
+}
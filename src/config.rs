@@ -1,18 +1,18 @@
-use std::collections::{HashSet, HashMap};
+use std::collections::{BTreeMap, HashSet, HashMap};
 use std::fs::File;
 use std::io::Read;
 
 use chrono::{Duration, Datelike};
-use num_traits::FromPrimitive;
 use regex::Regex;
 use serde::Deserialize;
 use serde::de::{Deserializer, Error};
 
-use crate::brokers::Broker;
-use crate::core::GenericResult;
+use crate::brokers::{Broker, BrokerInfo};
+use crate::core::{EmptyResult, GenericResult};
+use crate::currency;
 use crate::formatting;
 use crate::localities::{self, Country};
-use crate::taxes::{TaxPaymentDay, TaxRemapping};
+use crate::taxes::{self, IiaType, TaxPaymentDay, TaxRemapping};
 use crate::types::{Date, Decimal};
 use crate::util::{self, DecimalRestrictions};
 
@@ -21,6 +21,8 @@ use crate::util::{self, DecimalRestrictions};
 pub struct Config {
     #[serde(skip)]
     pub db_path: String,
+    // Applies only to real-time quotes (including Forex pairs). Historical currency rates never
+    // change, so they are cached permanently and don't expire.
     #[serde(skip, default = "default_expire_time")]
     pub cache_expire_time: Duration,
 
@@ -32,6 +34,15 @@ pub struct Config {
     pub portfolios: Vec<PortfolioConfig>,
     pub brokers: Option<BrokersConfig>,
 
+    #[serde(default = "default_currency_rate_providers")]
+    pub currency_rate_providers: Vec<CurrencyRateProviderType>,
+    #[serde(default = "default_currency_rates_timeout", deserialize_with = "deserialize_timeout")]
+    pub currency_rates_timeout: Duration,
+
+    // In offline mode only cached currency rates and quotes are used
+    #[serde(default)]
+    pub offline: bool,
+
     pub alphavantage: Option<AlphaVantageConfig>,
     pub finnhub: Option<FinnhubConfig>,
     pub twelvedata: Option<TwelveDataConfig>,
@@ -50,6 +61,10 @@ impl Config {
             portfolios: Vec::new(),
             brokers: Some(BrokersConfig::mock()),
 
+            currency_rate_providers: default_currency_rate_providers(),
+            currency_rates_timeout: default_currency_rates_timeout(),
+            offline: false,
+
             alphavantage: None,
             finnhub: None,
             twelvedata: None,
@@ -67,6 +82,24 @@ impl Config {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CurrencyRateProviderType {
+    Cbr,
+    Ecb,
+}
+
+impl<'de> Deserialize<'de> for CurrencyRateProviderType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        let value = String::deserialize(deserializer)?;
+
+        Ok(match value.as_str() {
+            "cbr" => CurrencyRateProviderType::Cbr,
+            "ecb" => CurrencyRateProviderType::Ecb,
+            _ => return Err(D::Error::unknown_variant(&value, &["cbr", "ecb"])),
+        })
+    }
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct DepositConfig {
@@ -93,9 +126,12 @@ pub struct PortfolioConfig {
     pub name: String,
     pub broker: Broker,
     pub plan: Option<String>,
+    pub sweep_fund: Option<String>,
 
     pub statements: String,
     #[serde(default)]
+    pub accounts: Vec<BrokerAccountConfig>,
+    #[serde(default)]
     pub symbol_remapping: HashMap<String, String>,
     #[serde(default)]
     pub instrument_names: HashMap<String, String>,
@@ -105,8 +141,17 @@ pub struct PortfolioConfig {
     pub currency: Option<String>,
     pub min_trade_volume: Option<Decimal>,
     pub min_cash_assets: Option<Decimal>,
+    #[serde(default)]
+    pub allow_margin_debt: bool,
+    #[serde(default)]
+    pub avoid_selling: bool,
     pub restrict_buying: Option<bool>,
     pub restrict_selling: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_rebalance_interval")]
+    pub rebalance_interval: Option<Duration>,
+    #[serde(default, deserialize_with = "deserialize_rebalance_threshold")]
+    pub rebalance_threshold: Option<Decimal>,
+    pub valuation_frequency: Option<ValuationFrequency>,
 
     #[serde(default)]
     pub merge_performance: HashMap<String, HashSet<String>>,
@@ -117,8 +162,31 @@ pub struct PortfolioConfig {
     #[serde(default, deserialize_with = "deserialize_tax_payment_day")]
     pub tax_payment_day: TaxPaymentDay,
 
+    #[serde(default, deserialize_with = "deserialize_iia_type")]
+    pub iia_type: Option<IiaType>,
     #[serde(default, deserialize_with = "deserialize_cash_flows")]
     pub tax_deductions: Vec<(Date, Decimal)>,
+
+    // Portfolio value snapshots in portfolio currency which are used to calculate time-weighted
+    // return
+    #[serde(default, deserialize_with = "deserialize_cash_flows")]
+    pub valuations: Vec<(Date, Decimal)>,
+}
+
+// Additional broker account which positions are merged into the portfolio
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BrokerAccountConfig {
+    pub broker: Broker,
+    pub plan: Option<String>,
+    pub sweep_fund: Option<String>,
+    pub statements: String,
+}
+
+impl BrokerAccountConfig {
+    pub fn get_broker_info(&self, config: &Config) -> GenericResult<BrokerInfo> {
+        Ok(self.broker.get_info(config, self.plan.as_ref())?.with_sweep_fund(self.sweep_fund.as_ref()))
+    }
 }
 
 impl PortfolioConfig {
@@ -132,6 +200,10 @@ impl PortfolioConfig {
         symbols
     }
 
+    pub fn get_broker_info(&self, config: &Config) -> GenericResult<BrokerInfo> {
+        Ok(self.broker.get_info(config, self.plan.as_ref())?.with_sweep_fund(self.sweep_fund.as_ref()))
+    }
+
     pub fn get_tax_country(&self) -> Country {
         localities::russia()
     }
@@ -164,6 +236,8 @@ pub struct AssetAllocationConfig {
 
     #[serde(deserialize_with = "deserialize_weight")]
     pub weight: Decimal,
+    #[serde(default, deserialize_with = "deserialize_min_trade_volume")]
+    pub min_trade_volume: Option<Decimal>,
     pub restrict_buying: Option<bool>,
     pub restrict_selling: Option<bool>,
 
@@ -191,6 +265,8 @@ pub struct BrokersConfig {
     pub firstrade: Option<BrokerConfig>,
     pub interactive_brokers: Option<BrokerConfig>,
     pub open_broker: Option<BrokerConfig>,
+    pub sber: Option<BrokerConfig>,
+    pub schwab: Option<BrokerConfig>,
     pub tinkoff: Option<BrokerConfig>,
 }
 
@@ -202,6 +278,8 @@ impl BrokersConfig {
             firstrade: Some(BrokerConfig::mock()),
             interactive_brokers: Some(BrokerConfig::mock()),
             open_broker: Some(BrokerConfig::mock()),
+            sber: Some(BrokerConfig::mock()),
+            schwab: Some(BrokerConfig::mock()),
             tinkoff: Some(BrokerConfig::mock()),
         }
     }
@@ -211,6 +289,13 @@ impl BrokersConfig {
 #[serde(deny_unknown_fields)]
 pub struct BrokerConfig {
     pub deposit_commissions: HashMap<String, TransactionCommissionSpec>,
+    #[serde(default)]
+    pub require_deposit_commissions: bool,
+    #[serde(default)]
+    pub unsupported_operations: UnsupportedOperationsConfig,
+    #[serde(default = "default_trade_volume_tolerance")]
+    pub trade_volume_tolerance: Decimal,
+    pub monthly_commission_tiers: Option<BTreeMap<Decimal, Decimal>>,
 }
 
 impl BrokerConfig {
@@ -218,14 +303,136 @@ impl BrokerConfig {
     pub fn mock() -> BrokerConfig {
         BrokerConfig {
             deposit_commissions: HashMap::new(),
+            require_deposit_commissions: false,
+            unsupported_operations: UnsupportedOperationsConfig::default(),
+            trade_volume_tolerance: default_trade_volume_tolerance(),
+            monthly_commission_tiers: None,
         }
     }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValuationFrequency {
+    Daily,
+    Weekly,
+}
+
+impl<'de> Deserialize<'de> for ValuationFrequency {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        let value = String::deserialize(deserializer)?;
+
+        Ok(match value.as_str() {
+            "daily" => ValuationFrequency::Daily,
+            "weekly" => ValuationFrequency::Weekly,
+            _ => return Err(D::Error::unknown_variant(&value, &["daily", "weekly"])),
+        })
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct UnsupportedOperationsConfig {
+    #[serde(default)]
+    pub policy: UnsupportedOperationPolicy,
+    #[serde(default)]
+    pub ignore: HashSet<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnsupportedOperationPolicy {
+    Error,
+    Warn,
+    Skip,
+}
+
+impl Default for UnsupportedOperationPolicy {
+    fn default() -> UnsupportedOperationPolicy {
+        UnsupportedOperationPolicy::Warn
+    }
+}
+
+impl<'de> Deserialize<'de> for UnsupportedOperationPolicy {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        let value = String::deserialize(deserializer)?;
+
+        Ok(match value.as_str() {
+            "error" => UnsupportedOperationPolicy::Error,
+            "warn" => UnsupportedOperationPolicy::Warn,
+            "skip" => UnsupportedOperationPolicy::Skip,
+            _ => return Err(D::Error::unknown_variant(&value, &["error", "warn", "skip"])),
+        })
+    }
+}
+
+/// Either a fixed commission or a percent of transaction volume limited by optional minimum and
+/// maximum values
+#[derive(Deserialize, Debug, Clone, Default)]
 #[serde(deny_unknown_fields)]
 pub struct TransactionCommissionSpec {
-    pub fixed_amount: Decimal,
+    pub fixed_amount: Option<Decimal>,
+    pub percent: Option<Decimal>,
+    pub minimum: Option<Decimal>,
+    pub maximum: Option<Decimal>,
+}
+
+impl TransactionCommissionSpec {
+    pub fn new_fixed(amount: Decimal) -> TransactionCommissionSpec {
+        TransactionCommissionSpec {
+            fixed_amount: Some(amount),
+            ..Default::default()
+        }
+    }
+
+    fn validate(&self) -> EmptyResult {
+        for &(name, value) in &[
+            ("fixed amount", self.fixed_amount),
+            ("percent", self.percent),
+            ("minimum", self.minimum),
+            ("maximum", self.maximum),
+        ] {
+            if let Some(value) = value {
+                util::validate_named_decimal(name, value, DecimalRestrictions::PositiveOrZero)?;
+            }
+        }
+
+        match (self.fixed_amount, self.percent) {
+            (Some(_), None) => {
+                if self.minimum.is_some() || self.maximum.is_some() {
+                    return Err!("Minimum and maximum can't be specified for a fixed commission");
+                }
+            },
+            (None, Some(_)) => {
+                if let (Some(minimum), Some(maximum)) = (self.minimum, self.maximum) {
+                    if minimum > maximum {
+                        return Err!("Minimum commission is greater than maximum: {} > {}", minimum, maximum);
+                    }
+                }
+            },
+            (Some(_), Some(_)) => return Err!("Fixed amount and percent are mutually exclusive"),
+            (None, None) => return Err!("Either fixed amount or percent must be specified"),
+        }
+
+        Ok(())
+    }
+
+    /// Calculates commission for a transaction of the specified volume (sign is ignored)
+    pub fn calculate(&self, volume: Decimal) -> Decimal {
+        if let Some(fixed_amount) = self.fixed_amount {
+            return fixed_amount;
+        }
+
+        let mut commission = currency::round(volume.abs() * self.percent.unwrap_or_default() / dec!(100));
+
+        if let Some(minimum) = self.minimum {
+            commission = std::cmp::max(commission, minimum);
+        }
+
+        if let Some(maximum) = self.maximum {
+            commission = std::cmp::min(commission, maximum);
+        }
+
+        commission
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -269,6 +476,28 @@ pub fn load_config(path: &str) -> GenericResult<Config> {
         }
     }
 
+    if let Some(ref brokers) = config.brokers {
+        for (name, broker) in &[
+            ("bcs", &brokers.bcs),
+            ("firstrade", &brokers.firstrade),
+            ("interactive_brokers", &brokers.interactive_brokers),
+            ("open_broker", &brokers.open_broker),
+            ("sber", &brokers.sber),
+            ("schwab", &brokers.schwab),
+            ("tinkoff", &brokers.tinkoff),
+        ] {
+            let broker = match broker {
+                Some(broker) => broker,
+                None => continue,
+            };
+
+            for (currency, spec) in &broker.deposit_commissions {
+                spec.validate().map_err(|e| format!(
+                    "Invalid {} deposit commission configuration for {}: {}", currency, name, e))?;
+            }
+        }
+    }
+
     {
         let mut portfolio_names = HashSet::new();
 
@@ -278,10 +507,15 @@ pub fn load_config(path: &str) -> GenericResult<Config> {
             }
 
             if let Some(ref currency) = portfolio.currency {
-                match currency.as_str() {
-                    "RUB" | "USD" => (),
-                    _ => return Err!("Unsupported portfolio currency: {}", currency),
-                };
+                if !currency::is_supported(currency) {
+                    return Err!("Unsupported portfolio currency: {}", currency);
+                }
+            }
+
+            if !portfolio.valuations.is_empty() && portfolio.currency.is_none() {
+                return Err!(
+                    "Invalid {:?} portfolio configuration: Valuations require the portfolio currency to be specified",
+                    portfolio.name);
             }
 
             for (symbol, mapping) in &portfolio.symbol_remapping {
@@ -292,6 +526,32 @@ pub fn load_config(path: &str) -> GenericResult<Config> {
                 }
             }
 
+            if !portfolio.assets.is_empty() {
+                check_weights(&portfolio.name, &portfolio.assets)?;
+                check_symbols(&portfolio.assets, None, &mut HashMap::new())?;
+            }
+
+            match portfolio.iia_type {
+                Some(IiaType::A) => {
+                    let country = portfolio.get_tax_country();
+
+                    for &(date, amount) in &portfolio.tax_deductions {
+                        let max_deduction = taxes::get_iia_max_tax_deduction(&country, date.year());
+                        if amount > max_deduction {
+                            return Err!(
+                                "Invalid {:?} portfolio tax deduction from {}: {} exceeds the annual limit of {}",
+                                portfolio.name, formatting::format_date(date), amount, max_deduction);
+                        }
+                    }
+                },
+                Some(IiaType::B) => if !portfolio.tax_deductions.is_empty() {
+                    return Err!(
+                        "Invalid {:?} portfolio configuration: Type B IIA has no tax deductions",
+                        portfolio.name);
+                },
+                None => {},
+            }
+
             let mut symbols_to_merge: HashSet<&String> = HashSet::new();
             for (master_symbol, slave_symbols) in &portfolio.merge_performance {
                 if !symbols_to_merge.insert(master_symbol) {
@@ -312,16 +572,86 @@ pub fn load_config(path: &str) -> GenericResult<Config> {
     }
 
     for portfolio in &mut config.portfolios {
-        portfolio.statements = shellexpand::tilde(&portfolio.statements).to_string();
+        portfolio.statements = expand_path(&portfolio.statements)?;
+
+        for account in &mut portfolio.accounts {
+            account.statements = expand_path(&account.statements)?;
+        }
     }
 
     Ok(config)
 }
 
+// Expands `~` and environment variables
+fn expand_path(path: &str) -> GenericResult<String> {
+    Ok(shellexpand::full(path).map_err(|e| format!(
+        "Unable to expand {:?} path: {}", path, e))?.to_string())
+}
+
+// Weights are exact percentages, so no tolerance is allowed
+fn check_weights(name: &str, assets: &[AssetAllocationConfig]) -> EmptyResult {
+    let mut weight = dec!(0);
+
+    for asset in assets {
+        weight += asset.weight;
+
+        if let Some(ref assets) = asset.assets {
+            check_weights(&asset.name, assets)?;
+        }
+    }
+
+    if weight != dec!(1) {
+        return Err!("Invalid asset allocation configuration: {:?} assets have unbalanced weights: {}% total",
+                    name, (weight * dec!(100)).normalize());
+    }
+
+    Ok(())
+}
+
+// The same stock in several places of asset allocation tree makes its weight ambiguous
+fn check_symbols<'a>(
+    assets: &'a [AssetAllocationConfig], parent_path: Option<&str>,
+    symbols: &mut HashMap<&'a str, String>,
+) -> EmptyResult {
+    for asset in assets {
+        let path = match parent_path {
+            Some(parent_path) => format!("{} » {}", parent_path, asset.name),
+            None => asset.name.clone(),
+        };
+
+        if let Some(ref symbol) = asset.symbol {
+            if let Some(other_path) = symbols.get(symbol.as_str()) {
+                return Err!(
+                    "Invalid asset allocation configuration: {} symbol is specified in both {:?} and {:?}",
+                    symbol, other_path, path);
+            }
+            symbols.insert(symbol, path.clone());
+        }
+
+        if let Some(ref assets) = asset.assets {
+            check_symbols(assets, Some(&path), symbols)?;
+        }
+    }
+
+    Ok(())
+}
+
 fn default_expire_time() -> Duration {
     Duration::minutes(1)
 }
 
+fn default_currency_rate_providers() -> Vec<CurrencyRateProviderType> {
+    vec![CurrencyRateProviderType::Cbr]
+}
+
+fn default_currency_rates_timeout() -> Duration {
+    Duration::seconds(10)
+}
+
+fn default_trade_volume_tolerance() -> Decimal {
+    dec!(0.01)
+}
+
 fn deserialize_tax_payment_day<'de, D>(deserializer: D) -> Result<TaxPaymentDay, D::Error>
     where D: Deserializer<'de>
 {
@@ -338,7 +668,8 @@ fn deserialize_tax_payment_day<'de, D>(deserializer: D) -> Result<TaxPaymentDay,
             _ => return None,
         };
 
-        if Date::from_ymd_opt(util::today().year(), month, day).is_none() || (day, month) == (29, 2) {
+        // Check against a leap year to allow Feb 29
+        if Date::from_ymd_opt(2020, month, day).is_none() {
             return None;
         }
 
@@ -346,6 +677,37 @@ fn deserialize_tax_payment_day<'de, D>(deserializer: D) -> Result<TaxPaymentDay,
     }).ok_or_else(|| D::Error::custom(format!("Invalid tax payment day: {:?}", tax_payment_day)))?)
 }
 
+fn deserialize_iia_type<'de, D>(deserializer: D) -> Result<Option<IiaType>, D::Error>
+    where D: Deserializer<'de>
+{
+    let iia_type: String = Deserialize::deserialize(deserializer)?;
+    Ok(Some(match iia_type.as_str() {
+        "A" => IiaType::A,
+        "B" => IiaType::B,
+        _ => return Err(D::Error::unknown_variant(&iia_type, &["A", "B"])),
+    }))
+}
+
+fn deserialize_rebalance_interval<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+    where D: Deserializer<'de>
+{
+    let interval: String = Deserialize::deserialize(deserializer)?;
+    let interval = util::parse_duration(&interval).map_err(D::Error::custom)?;
+
+    if interval < Duration::days(1) {
+        return Err(D::Error::custom("Rebalance interval must be at least one day"));
+    }
+
+    Ok(Some(interval))
+}
+
+fn deserialize_timeout<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+    where D: Deserializer<'de>
+{
+    let timeout: String = Deserialize::deserialize(deserializer)?;
+    util::parse_duration(&timeout).map_err(D::Error::custom)
+}
+
 fn deserialize_cash_flows<'de, D>(deserializer: D) -> Result<Vec<(Date, Decimal)>, D::Error>
     where D: Deserializer<'de>
 {
@@ -372,6 +734,20 @@ fn deserialize_date<'de, D>(deserializer: D) -> Result<Date, D::Error>
     Ok(util::parse_date(&date, "%d.%m.%Y").map_err(D::Error::custom)?)
 }
 
+fn deserialize_min_trade_volume<'de, D>(deserializer: D) -> Result<Option<Decimal>, D::Error>
+    where D: Deserializer<'de>
+{
+    let volume: Decimal = Deserialize::deserialize(deserializer)?;
+    util::validate_named_decimal("minimum trade volume", volume, DecimalRestrictions::StrictlyPositive)
+        .map(Some).map_err(D::Error::custom)
+}
+
+fn deserialize_rebalance_threshold<'de, D>(deserializer: D) -> Result<Option<Decimal>, D::Error>
+    where D: Deserializer<'de>
+{
+    Ok(Some(deserialize_weight(deserializer)?))
+}
+
 fn deserialize_weight<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
     where D: Deserializer<'de>
 {
@@ -380,10 +756,67 @@ fn deserialize_weight<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
         return Err(D::Error::custom(format!("Invalid weight: {}", weight)));
     }
 
-    let weight = match weight[..weight.len() - 1].parse::<u8>().ok() {
-        Some(weight) if weight <= 100 => weight,
+    let weight = match util::parse_decimal(&weight[..weight.len() - 1], DecimalRestrictions::PositiveOrZero).ok() {
+        Some(weight) if weight <= dec!(100) => weight,
         _ => return Err(D::Error::custom(format!("Invalid weight: {}", weight))),
     };
 
-    Ok(Decimal::from_u8(weight).unwrap() / dec!(100))
-}
\ No newline at end of file
+    Ok(weight / dec!(100))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_assets(config: &str) -> Vec<AssetAllocationConfig> {
+        serde_yaml::from_str(config).unwrap()
+    }
+
+    #[test]
+    fn unbalanced_weights() {
+        let assets = parse_assets(r#"
+- name: Stocks
+  weight: 60%
+  assets:
+    - name: Russian stocks
+      symbol: FXRL
+      weight: 50%
+    - name: US stocks
+      symbol: FXUS
+      weight: 40%
+- name: Bonds
+  symbol: FXRB
+  weight: 40%
+"#);
+
+        assert_eq!(
+            check_weights("Portfolio", &assets).unwrap_err().to_string(),
+            "Invalid asset allocation configuration: \"Stocks\" assets have unbalanced weights: 90% total");
+    }
+
+    #[test]
+    fn duplicated_symbols() {
+        let assets = parse_assets(r#"
+- name: Stocks
+  weight: 60%
+  assets:
+    - name: Russian stocks
+      symbol: FXRL
+      weight: 50%
+    - name: US stocks
+      symbol: FXUS
+      weight: 50%
+- name: Other
+  weight: 40%
+  assets:
+    - name: Russian stocks
+      symbol: FXRL
+      weight: 100%
+"#);
+
+        assert_eq!(
+            check_symbols(&assets, None, &mut HashMap::new()).unwrap_err().to_string(),
+            "Invalid asset allocation configuration: FXRL symbol is specified in both \
+             \"Stocks » Russian stocks\" and \"Other » Russian stocks\"");
+    }
+}
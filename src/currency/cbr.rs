@@ -1,4 +1,5 @@
 use std::str::FromStr;
+use std::time::Duration;
 
 #[cfg(test)] use indoc::indoc;
 use log::debug;
@@ -13,11 +14,35 @@ use crate::formatting;
 use crate::types::{Date, Decimal};
 use crate::util;
 
-pub fn get_rates(currency: &str, start_date: Date, end_date: Date) -> GenericResult<Vec<CurrencyRate>> {
-    let currency_code = match currency {
-        "USD" => "R01235",
-        _ => return Err!("{} currency is not supported yet.", currency),
-    };
+use super::rates::{self, RateProvider, CurrencyRates};
+
+pub struct Cbr {
+    client: Client,
+}
+
+impl Cbr {
+    pub fn new(timeout: Duration) -> Cbr {
+        Cbr {
+            client: rates::new_client(timeout),
+        }
+    }
+}
+
+impl RateProvider for Cbr {
+    fn name(&self) -> &'static str {
+        "the Central Bank of the Russian Federation"
+    }
+
+    fn get_rates(&self, currency: &str, start_date: Date, end_date: Date) -> GenericResult<CurrencyRates> {
+        get_rates(&self.client, currency, start_date, end_date)
+    }
+}
+
+/// Returns currency rates and the period which they actually cover: CBR may clamp the requested
+/// period (to data availability for example).
+fn get_rates(client: &Client, currency: &str, start_date: Date, end_date: Date) -> GenericResult<CurrencyRates> {
+    let currency_code = get_currency_code(currency).ok_or_else(|| format!(
+        "{} currency is not supported yet.", currency))?;
 
     let date_format = "%d/%m/%Y";
     let start_date_string = start_date.format(date_format).to_string();
@@ -35,16 +60,12 @@ pub fn get_rates(currency: &str, start_date: Date, end_date: Date) -> GenericRes
         ("VAL_NM_RQ", currency_code),
     ])?;
 
-    let get = |url| -> GenericResult<Vec<CurrencyRate>> {
+    let get = |url| -> GenericResult<CurrencyRates> {
         debug!("Getting {} currency rates for {} - {}...", currency,
                formatting::format_date(start_date), formatting::format_date(end_date));
 
-        let response = Client::new().get(url).send()?;
-        if !response.status().is_success() {
-            return Err!("The server returned an error: {}", response.status());
-        }
-
-        Ok(parse_rates(start_date, end_date, &response.text()?).map_err(|e| format!(
+        let data = rates::send_request(client, url)?;
+        Ok(parse_rates(currency_code, start_date, end_date, &data).map_err(|e| format!(
             "Rates info parsing error: {}", e))?)
     };
 
@@ -52,9 +73,24 @@ pub fn get_rates(currency: &str, start_date: Date, end_date: Date) -> GenericRes
         "Failed to get currency rates from {}: {}", url, e))?)
 }
 
-fn parse_rates(start_date: Date, end_date: Date, data: &str) -> GenericResult<Vec<CurrencyRate>> {
+// Maps ISO currency codes to CBR internal currency codes (VAL_NM_RQ)
+pub fn get_currency_code(currency: &str) -> Option<&'static str> {
+    Some(match currency {
+        "USD" => "R01235",
+        "EUR" => "R01239",
+        "GBP" => "R01035",
+        "CHF" => "R01775",
+        "JPY" => "R01820",
+        _ => return None,
+    })
+}
+
+fn parse_rates(currency_code: &str, start_date: Date, end_date: Date, data: &str) -> GenericResult<CurrencyRates> {
     #[derive(Deserialize)]
     struct Rate {
+        #[serde(rename = "Id")]
+        id: String,
+
         #[serde(rename = "Date")]
         date: String,
 
@@ -80,14 +116,24 @@ fn parse_rates(start_date: Date, end_date: Date, data: &str) -> GenericResult<Ve
     let date_format = "%d.%m.%Y";
     let result: Rates = serde_xml_rs::from_str(data).map_err(|e| e.to_string())?;
 
-    if util::parse_date(&result.start_date, date_format)? != start_date ||
-        util::parse_date(&result.end_date, date_format)? != end_date {
-        return Err!("The server returned currency rates info for an invalid period");
+    // The server may return a narrower period than requested, but it must be within the requested
+    // one
+    let period = (
+        util::parse_date(&result.start_date, date_format)?,
+        util::parse_date(&result.end_date, date_format)?,
+    );
+    if period.0 < start_date || period.1 > end_date || period.0 > period.1 {
+        return Err!("The server returned currency rates info for an invalid period: {}",
+                    formatting::format_period((period.0, period.1.succ())));
     }
 
     let mut rates = Vec::with_capacity(result.rates.len());
 
     for rate in result.rates {
+        if rate.id != currency_code {
+            return Err!("Got a currency rate for an unexpected currency: {:?}", rate.id);
+        }
+
         let lot = rate.lot;
         if lot <= 0 {
             return Err!("Invalid lot: {}", lot);
@@ -97,13 +143,27 @@ fn parse_rates(start_date: Date, end_date: Date, data: &str) -> GenericResult<Ve
         let price = Decimal::from_str(&price).map_err(|_| format!(
             "Invalid price: {:?}", rate.price))?;
 
+        let date = util::parse_date(&rate.date, date_format)?;
+        if date < period.0 || date > period.1 {
+            return Err!("Got a currency rate outside of the returned period: {}",
+                        formatting::format_date(date));
+        }
+
         rates.push(CurrencyRate {
-            date: util::parse_date(&rate.date, date_format)?,
+            date: date,
             price: price / Decimal::from(lot),
         })
     }
 
-    Ok(rates)
+    // Don't rely on the order of the records
+    rates.sort_by_key(|rate| rate.date);
+    for pair in rates.windows(2) {
+        if pair[0].date == pair[1].date {
+            return Err!("Got a duplicated currency rate for {}", formatting::format_date(pair[0].date));
+        }
+    }
+
+    Ok((rates, period))
 }
 
 #[cfg(test)]
@@ -121,7 +181,8 @@ mod tests {
             "#)
         );
 
-        assert_eq!(get_rates("USD", date!(2, 9, 2018), date!(3, 9, 2018)).unwrap(), vec![]);
+        assert_eq!(get_rates(&Client::new(), "USD", date!(2, 9, 2018), date!(3, 9, 2018)).unwrap(),
+                   (vec![], (date!(2, 9, 2018), date!(3, 9, 2018))));
     }
 
     #[test]
@@ -144,15 +205,135 @@ mod tests {
         );
 
         assert_eq!(
-            get_rates("USD", date!(1, 9, 2018), date!(4, 9, 2018)).unwrap(),
-            vec![CurrencyRate {
+            get_rates(&Client::new(), "USD", date!(1, 9, 2018), date!(4, 9, 2018)).unwrap(),
+            (vec![CurrencyRate {
                 date: date!(1, 9, 2018),
                 price: dec!(68.0447),
             }, CurrencyRate {
                 date: date!(4, 9, 2018),
                 price: dec!(67.7443),
-            }],
+            }], (date!(1, 9, 2018), date!(4, 9, 2018))),
+        );
+    }
+
+    #[test]
+    fn lot_rates() {
+        let _mock = mock_cbr_response(
+            "/scripts/XML_dynamic.asp?date_req1=01%2F09%2F2018&date_req2=04%2F09%2F2018&VAL_NM_RQ=R01820",
+            indoc!(r#"
+                <?xml version="1.0" encoding="windows-1251"?>
+                <ValCurs ID="R01820" DateRange1="01.09.2018" DateRange2="04.09.2018" name="Foreign Currency Market Dynamic">
+                    <Record Date="01.09.2018" Id="R01820">
+                        <Nominal>100</Nominal>
+                        <Value>61,2838</Value>
+                    </Record>
+                </ValCurs>
+            "#)
+        );
+
+        assert_eq!(
+            get_rates(&Client::new(), "JPY", date!(1, 9, 2018), date!(4, 9, 2018)).unwrap(),
+            (vec![CurrencyRate {
+                date: date!(1, 9, 2018),
+                price: dec!(0.612838),
+            }], (date!(1, 9, 2018), date!(4, 9, 2018))),
+        );
+    }
+
+    #[test]
+    fn unsupported_currency() {
+        assert_eq!(
+            get_rates(&Client::new(), "XXX", date!(1, 9, 2018), date!(4, 9, 2018)).unwrap_err().to_string(),
+            "XXX currency is not supported yet.");
+    }
+
+    #[test]
+    fn clamped_period() {
+        let _mock = mock_cbr_response(
+            "/scripts/XML_dynamic.asp?date_req1=01%2F09%2F2018&date_req2=10%2F09%2F2018&VAL_NM_RQ=R01235",
+            indoc!(r#"
+                <?xml version="1.0" encoding="windows-1251"?>
+                <ValCurs ID="R01235" DateRange1="01.09.2018" DateRange2="04.09.2018" name="Foreign Currency Market Dynamic">
+                    <Record Date="04.09.2018" Id="R01235">
+                        <Nominal>1</Nominal>
+                        <Value>67,7443</Value>
+                    </Record>
+                </ValCurs>
+            "#)
+        );
+
+        assert_eq!(
+            get_rates(&Client::new(), "USD", date!(1, 9, 2018), date!(10, 9, 2018)).unwrap(),
+            (vec![CurrencyRate {
+                date: date!(4, 9, 2018),
+                price: dec!(67.7443),
+            }], (date!(1, 9, 2018), date!(4, 9, 2018))),
+        );
+    }
+
+    #[test]
+    fn unordered_rates() {
+        let _mock = mock_cbr_response(
+            "/scripts/XML_dynamic.asp?date_req1=05%2F09%2F2018&date_req2=07%2F09%2F2018&VAL_NM_RQ=R01235",
+            indoc!(r#"
+                <?xml version="1.0" encoding="windows-1251"?>
+                <ValCurs ID="R01235" DateRange1="05.09.2018" DateRange2="07.09.2018" name="Foreign Currency Market Dynamic">
+                    <Record Date="07.09.2018" Id="R01235">
+                        <Nominal>1</Nominal>
+                        <Value>68,0552</Value>
+                    </Record>
+                    <Record Date="05.09.2018" Id="R01235">
+                        <Nominal>1</Nominal>
+                        <Value>68,0473</Value>
+                    </Record>
+                </ValCurs>
+            "#)
+        );
+
+        assert_eq!(
+            get_rates(&Client::new(), "USD", date!(5, 9, 2018), date!(7, 9, 2018)).unwrap(),
+            (vec![CurrencyRate {
+                date: date!(5, 9, 2018),
+                price: dec!(68.0473),
+            }, CurrencyRate {
+                date: date!(7, 9, 2018),
+                price: dec!(68.0552),
+            }], (date!(5, 9, 2018), date!(7, 9, 2018))),
+        );
+    }
+
+    #[test]
+    fn unexpected_currency() {
+        let _mock = mock_cbr_response(
+            "/scripts/XML_dynamic.asp?date_req1=11%2F09%2F2018&date_req2=12%2F09%2F2018&VAL_NM_RQ=R01235",
+            indoc!(r#"
+                <?xml version="1.0" encoding="windows-1251"?>
+                <ValCurs ID="R01235" DateRange1="11.09.2018" DateRange2="12.09.2018" name="Foreign Currency Market Dynamic">
+                    <Record Date="11.09.2018" Id="R01239">
+                        <Nominal>1</Nominal>
+                        <Value>79,6370</Value>
+                    </Record>
+                </ValCurs>
+            "#)
         );
+
+        let error = get_rates(&Client::new(), "USD", date!(11, 9, 2018), date!(12, 9, 2018)).unwrap_err();
+        assert!(error.to_string().ends_with(
+            r#"Rates info parsing error: Got a currency rate for an unexpected currency: "R01239""#));
+    }
+
+    #[test]
+    fn invalid_period() {
+        let _mock = mock_cbr_response(
+            "/scripts/XML_dynamic.asp?date_req1=02%2F09%2F2018&date_req2=04%2F09%2F2018&VAL_NM_RQ=R01235",
+            indoc!(r#"
+                <?xml version="1.0" encoding="windows-1251"?>
+                <ValCurs ID="R01235" DateRange1="01.09.2018" DateRange2="04.09.2018" name="Foreign Currency Market Dynamic">
+                </ValCurs>
+            "#)
+        );
+
+        assert!(get_rates(&Client::new(), "USD", date!(2, 9, 2018), date!(4, 9, 2018)).is_err());
     }
 
     fn mock_cbr_response(path: &str, data: &str) -> Mock {
@@ -1,11 +1,17 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
 use std::rc::Rc;
 
-use chrono::Duration;
+use chrono::{Datelike, Duration};
 #[cfg(test)] use matches::assert_matches;
+use rayon::prelude::*;
 
-use crate::core::GenericResult;
-use crate::currency::{self, Cash, CurrencyRate};
+use crate::config::Config;
+use crate::core::{GenericResult, EmptyResult};
+use crate::currency::{self, Cash};
+#[cfg(test)] use crate::currency::CurrencyRate;
 use crate::currency::rate_cache::{CurrencyRateCache, CurrencyRateCacheResult};
+use crate::currency::rates::{self, CurrencyRates, RateProvider};
 use crate::db;
 use crate::formatting;
 use crate::localities;
@@ -35,9 +41,21 @@ pub struct CurrencyConverter {
 }
 
 impl CurrencyConverter {
-    pub fn new(database: db::Connection, quotes: Option<Rc<Quotes>>, strict_mode: bool) -> CurrencyConverter {
+    pub fn new(
+        config: &Config, database: db::Connection, quotes: Option<Rc<Quotes>>, strict_mode: bool,
+    ) -> CurrencyConverter {
         let rate_cache = CurrencyRateCache::new(database);
-        let backend = CurrencyRateCacheBackend::new(rate_cache, quotes, strict_mode);
+        let providers = rates::get_providers(config);
+        let backend = CurrencyRateCacheBackend::new(rate_cache, providers, quotes, strict_mode);
+        CurrencyConverter::new_with_backend(backend)
+    }
+
+    /// Creates a converter which doesn't fetch missing currency rates but collects them to a fetch
+    /// plan instead. Conversion results of such converter are meaningless: it uses placeholder
+    /// values for all missing rates and real time rates.
+    pub fn new_dry_run(database: db::Connection, strict_mode: bool) -> CurrencyConverter {
+        let rate_cache = CurrencyRateCache::new(database);
+        let backend = CurrencyRateCacheBackend::new_dry_run(rate_cache, strict_mode);
         CurrencyConverter::new_with_backend(backend)
     }
 
@@ -45,6 +63,15 @@ impl CurrencyConverter {
         CurrencyConverter { backend: source }
     }
 
+    /// Creates a converter with fixed USD/RUB rate which changes to new values on the specified
+    /// dates.
+    #[cfg(test)]
+    pub fn mock(rate: Decimal, rate_changes: &[(Date, Decimal)]) -> CurrencyConverter {
+        CurrencyConverter::new_with_backend(Box::new(MockCurrencyConverterBackend {
+            rate, rate_changes: rate_changes.to_vec(),
+        }))
+    }
+
     pub fn currency_rate(&self, date: Date, from: &str, to: &str) -> GenericResult<Decimal> {
         self.convert(from, to, date, dec!(1))
     }
@@ -81,6 +108,29 @@ impl CurrencyConverter {
         self.backend.convert(from, to, date, amount)
     }
 
+    /// Fetches all missing currency rates for the specified year with a single request
+    pub fn prefetch_year(&self, currency: &str, year: i32) -> EmptyResult {
+        self.backend.prefetch_year(currency, year)
+    }
+
+    /// Fetches all missing currency rates of the specified currencies for the specified year
+    /// concurrently
+    pub fn prefetch(&self, currencies: &[&str], year: i32) -> EmptyResult {
+        self.backend.prefetch(currencies, year)
+    }
+
+    /// Refetches currency rates for the specified period replacing the cached ones. Intended for
+    /// handling of corrections of already published rates.
+    pub fn refresh(&self, currency: &str, start_date: Date, end_date: Date) -> EmptyResult {
+        self.backend.refresh(currency, start_date, end_date)
+    }
+
+    /// Returns currency rates which are missing in the cache and have been requested so far.
+    /// Available only in dry run mode.
+    pub fn get_fetch_plan(&self) -> Option<RateFetchPlan> {
+        self.backend.get_fetch_plan()
+    }
+
     fn real_time_date(&self) -> Date {
         util::today_trade_execution_date()
     }
@@ -88,24 +138,122 @@ impl CurrencyConverter {
 
 pub trait CurrencyConverterBackend {
     fn convert(&self, from: &str, to: &str, date: Date, amount: Decimal) -> GenericResult<Decimal>;
+
+    fn prefetch_year(&self, _currency: &str, _year: i32) -> EmptyResult {
+        Ok(())
+    }
+
+    fn prefetch(&self, currencies: &[&str], year: i32) -> EmptyResult {
+        for currency in currencies {
+            self.prefetch_year(currency, year)?;
+        }
+        Ok(())
+    }
+
+    fn refresh(&self, _currency: &str, _start_date: Date, _end_date: Date) -> EmptyResult {
+        Err!("The currency converter doesn't support currency rates refreshing")
+    }
+
+    fn get_fetch_plan(&self) -> Option<RateFetchPlan> {
+        None
+    }
+}
+
+/// Currency rate periods which are missing in the cache
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RateFetchPlan {
+    periods: BTreeMap<String, Vec<(Date, Date)>>,
+}
+
+impl RateFetchPlan {
+    pub fn new() -> RateFetchPlan {
+        RateFetchPlan::default()
+    }
+
+    /// Adds the period merging it with overlapping and adjacent periods of the currency
+    pub fn add(&mut self, currency: &str, start_date: Date, end_date: Date) {
+        let periods = self.periods.entry(currency.to_owned()).or_default();
+        periods.push((start_date, end_date));
+        periods.sort();
+
+        let mut merged: Vec<(Date, Date)> = Vec::with_capacity(periods.len());
+
+        for &(start_date, end_date) in periods.iter() {
+            if let Some(last) = merged.last_mut() {
+                if start_date <= last.1 + Duration::days(1) {
+                    last.1 = std::cmp::max(last.1, end_date);
+                    continue;
+                }
+            }
+            merged.push((start_date, end_date));
+        }
+
+        *periods = merged;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.periods.is_empty()
+    }
+
+    /// Returns missing periods per currency sorted by date
+    pub fn periods(&self) -> &BTreeMap<String, Vec<(Date, Date)>> {
+        &self.periods
+    }
 }
 
 struct CurrencyRateCacheBackend {
     quotes: Option<Rc<Quotes>>,
     rate_cache: CurrencyRateCache,
+    providers: Vec<Box<dyn RateProvider>>,
     strict_mode: bool,
+    fetch_plan: Option<RefCell<RateFetchPlan>>,
+
+    // Conversions are usually performed for many dates of the same years, so cached rates are
+    // loaded to memory by years to avoid a database query per date.
+    loaded_rates: RefCell<HashMap<(String, i32), HashMap<Date, Option<Decimal>>>>,
+
+    // Unofficial rates from fallback providers are never saved to the currency rate cache, so they
+    // can't be mistaken for the official ones later
+    unofficial_rates: RefCell<HashMap<String, HashMap<Date, Option<Decimal>>>>,
 }
 
 impl CurrencyRateCacheBackend {
-    pub fn new(rate_cache: CurrencyRateCache, quotes: Option<Rc<Quotes>>, strict_mode: bool) -> Box<dyn CurrencyConverterBackend> {
+    pub fn new(
+        rate_cache: CurrencyRateCache, providers: Vec<Box<dyn RateProvider>>,
+        quotes: Option<Rc<Quotes>>, strict_mode: bool,
+    ) -> Box<dyn CurrencyConverterBackend> {
         Box::new(CurrencyRateCacheBackend {
             quotes,
             rate_cache,
+            providers,
             strict_mode,
+            fetch_plan: None,
+            loaded_rates: RefCell::new(HashMap::new()),
+            unofficial_rates: RefCell::new(HashMap::new()),
+        })
+    }
+
+    pub fn new_dry_run(rate_cache: CurrencyRateCache, strict_mode: bool) -> Box<dyn CurrencyConverterBackend> {
+        Box::new(CurrencyRateCacheBackend {
+            quotes: None,
+            rate_cache,
+            providers: Vec::new(),
+            strict_mode,
+            fetch_plan: Some(RefCell::new(RateFetchPlan::new())),
+            loaded_rates: RefCell::new(HashMap::new()),
+            unofficial_rates: RefCell::new(HashMap::new()),
         })
     }
 
     fn get_price(&self, currency: &str, date: Date, from_cache_only: bool) -> GenericResult<Option<Decimal>> {
+        if let Some(price) = self.get_loaded_price(currency, date)? {
+            return Ok(price);
+        }
+
+        if let Some(&price) = self.unofficial_rates.borrow().get(currency).and_then(|rates| rates.get(&date)) {
+            return Ok(price);
+        }
+
         let cache_result = self.rate_cache.get(currency, date).map_err(|e| format!(
             "Failed to get currency rate from the currency rate cache: {}", e))?;
 
@@ -119,16 +267,156 @@ impl CurrencyRateCacheBackend {
                         currency, formatting::format_date(date));
                 }
 
-                let currency_rates = get_currency_rates(currency, start_date, end_date)?;
-                self.rate_cache.save(currency, start_date, end_date, currency_rates)?;
+                if let Some(ref fetch_plan) = self.fetch_plan {
+                    fetch_plan.borrow_mut().add(currency, start_date, end_date);
+                    return Ok(Some(dec!(1)));
+                }
+
+                let rates = get_currency_rates(
+                    &self.providers, currency, start_date, end_date, self.strict_mode)?;
+                self.save_rates(currency, start_date, end_date, rates)?;
 
                 self.get_price(currency, date, true)?
             },
         })
     }
+
+    fn get_loaded_price(&self, currency: &str, date: Date) -> GenericResult<Option<Option<Decimal>>> {
+        let mut loaded_rates = self.loaded_rates.borrow_mut();
+
+        let key = (currency.to_owned(), date.year());
+        if !loaded_rates.contains_key(&key) {
+            let rates = self.rate_cache.load_year(currency, date.year()).map_err(|e| format!(
+                "Failed to load currency rates from the currency rate cache: {}", e))?;
+            loaded_rates.insert(key.clone(), rates);
+        }
+
+        Ok(loaded_rates[&key].get(&date).cloned())
+    }
+
+    /// Saves rates fetched for the specified period. The server may return rates for a narrower
+    /// period than requested, so the uncovered part of the period is saved as known-missing to not
+    /// request it again on each access.
+    fn save_rates(&self, currency: &str, start_date: Date, end_date: Date, rates: (CurrencyRates, bool)) -> EmptyResult {
+        let ((currency_rates, (rates_start_date, rates_end_date)), official) = rates;
+
+        if official {
+            self.rate_cache.save(currency, rates_start_date, rates_end_date, currency_rates)?;
+
+            if start_date < rates_start_date {
+                self.rate_cache.save_missing(currency, start_date, rates_start_date - Duration::days(1));
+            }
+
+            if end_date > rates_end_date {
+                self.rate_cache.save_missing(currency, rates_end_date + Duration::days(1), end_date);
+            }
+
+            self.unload_rates(currency);
+        } else {
+            let mut unofficial_rates = self.unofficial_rates.borrow_mut();
+            let rates = unofficial_rates.entry(currency.to_owned()).or_insert_with(HashMap::new);
+
+            let mut date = start_date;
+            while date <= end_date {
+                rates.insert(date, None);
+                date = date.succ();
+            }
+
+            for rate in currency_rates {
+                rates.insert(rate.date, Some(rate.price));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn unload_rates(&self, currency: &str) {
+        self.loaded_rates.borrow_mut().retain(|(loaded_currency, _), _| loaded_currency != currency);
+    }
+
+    /// Returns RUB prices of both currencies for the specified date, so cross rate for currencies
+    /// which are not quoted against each other directly can be calculated by chaining them. Returns
+    /// None if any of them is missing.
+    fn get_cross_prices(&self, from: &str, to: &str, date: Date) -> GenericResult<Option<(Decimal, Decimal)>> {
+        let mut prices = [dec!(1), dec!(1)];
+
+        for (currency, price) in [from, to].iter().zip(prices.iter_mut()) {
+            if *currency == "RUB" {
+                continue;
+            }
+
+            *price = match self.get_price(currency, date, false)? {
+                Some(value) => value,
+                None => return Ok(None),
+            };
+        }
+
+        Ok(Some((prices[0], prices[1])))
+    }
 }
 
 impl CurrencyConverterBackend for CurrencyRateCacheBackend {
+    fn prefetch_year(&self, currency: &str, year: i32) -> EmptyResult {
+        self.prefetch(&[currency], year)
+    }
+
+    fn prefetch(&self, currencies: &[&str], year: i32) -> EmptyResult {
+        let mut missing_periods = Vec::new();
+
+        for &currency in currencies {
+            if currency == "RUB" {
+                continue;
+            }
+
+            let (start_date, end_date) = match self.rate_cache.get_year_missing_period(currency, year)? {
+                Some(period) => period,
+                None => continue,
+            };
+
+            if let Some(ref fetch_plan) = self.fetch_plan {
+                fetch_plan.borrow_mut().add(currency, start_date, end_date);
+                continue;
+            }
+
+            missing_periods.push((currency, start_date, end_date));
+        }
+
+        // The rates are fetched concurrently, but saved sequentially to not compete for the
+        // database lock
+        let (providers, strict_mode) = (&self.providers, self.strict_mode);
+        let results: Vec<GenericResult<(CurrencyRates, bool)>> = missing_periods.par_iter().map(
+            |&(currency, start_date, end_date)| get_currency_rates(
+                providers, currency, start_date, end_date, strict_mode)
+        ).collect();
+
+        for (&(currency, start_date, end_date), result) in missing_periods.iter().zip(results) {
+            self.save_rates(currency, start_date, end_date, result?)?;
+        }
+
+        Ok(())
+    }
+
+    fn refresh(&self, currency: &str, start_date: Date, end_date: Date) -> EmptyResult {
+        if self.fetch_plan.is_some() {
+            return Err!("Currency rates can't be refreshed in dry run mode");
+        }
+
+        let end_date = std::cmp::min(end_date, self.rate_cache.today());
+        // The cache is refreshed only with official rates
+        let ((currency_rates, (start_date, end_date)), _) = get_currency_rates(
+            &self.providers, currency, start_date, end_date, true)?;
+
+        self.rate_cache.invalidate(currency, start_date, end_date)?;
+        self.rate_cache.save(currency, start_date, end_date, currency_rates)?;
+        self.unload_rates(currency);
+
+        Ok(())
+    }
+
+    fn get_fetch_plan(&self) -> Option<RateFetchPlan> {
+        self.fetch_plan.as_ref().map(|fetch_plan| fetch_plan.borrow().clone())
+    }
+
     fn convert(&self, from: &str, to: &str, date: Date, amount: Decimal) -> GenericResult<Decimal> {
         if from == to {
             return Ok(amount);
@@ -149,6 +437,11 @@ impl CurrencyConverterBackend for CurrencyRateCacheBackend {
         }
 
         if !self.strict_mode && date > today {
+            // Real time rates are taken from quotes, so there is nothing to plan for them
+            if self.fetch_plan.is_some() {
+                return Ok(amount);
+            }
+
             if let Some(ref quotes) = self.quotes {
                 let price = quotes.get(&get_currency_pair(from, to))?;
                 assert_eq!(price.currency, to);
@@ -156,53 +449,98 @@ impl CurrencyConverterBackend for CurrencyRateCacheBackend {
             }
         }
 
-        let (currency, inverse) = match (from, to) {
-            ("USD", "RUB") => ("USD", false),
-            ("RUB", "USD") => ("USD", true),
-            _ => return Err!("Unsupported currency conversion: {} -> {}", from, to),
-        };
-
         let mut cur_date = date;
         let min_date = localities::get_russian_stock_exchange_min_last_working_day(cur_date);
 
         while cur_date >= min_date {
-            if let Some(price) = self.get_price(currency, cur_date, false)? {
-                return Ok(if inverse {
-                    amount / price
-                } else {
-                    price * amount
-                });
+            if let Some((from_price, to_price)) = self.get_cross_prices(from, to, cur_date)? {
+                return Ok(amount * from_price / to_price);
             }
 
             cur_date -= Duration::days(1);
         }
 
+        let currency = match (from, to) {
+            (currency, "RUB") | ("RUB", currency) => currency.to_owned(),
+            _ => format!("{}/{}", from, to),
+        };
+
         Err!("Unable to find {} currency rate for {} with {} days precision",
              currency, formatting::format_date(date), (date - min_date).num_days())
     }
 }
 
+#[cfg(test)]
+struct MockCurrencyConverterBackend {
+    rate: Decimal,
+    rate_changes: Vec<(Date, Decimal)>,
+}
+
+#[cfg(test)]
+impl CurrencyConverterBackend for MockCurrencyConverterBackend {
+    fn convert(&self, from: &str, to: &str, date: Date, amount: Decimal) -> GenericResult<Decimal> {
+        let rate = self.rate_changes.iter().rev()
+            .find(|&&(change_date, _)| change_date <= date)
+            .map_or(self.rate, |&(_, rate)| rate);
+
+        Ok(match (from, to) {
+            _ if from == to => amount,
+            ("USD", "RUB") => amount * rate,
+            ("RUB", "USD") => amount / rate,
+            _ => unreachable!(),
+        })
+    }
+}
 
 #[cfg(not(test))]
-fn get_currency_rates(currency: &str, start_date: Date, end_date: Date) -> GenericResult<Vec<CurrencyRate>> {
-    Ok(crate::currency::cbr::get_rates(currency, start_date, end_date).map_err(|e| format!(
-        "Failed to get currency rates from the Central Bank of the Russian Federation: {}", e))?)
+fn get_currency_rates(
+    providers: &[Box<dyn RateProvider>], currency: &str, start_date: Date, end_date: Date,
+    official_only: bool,
+) -> GenericResult<(CurrencyRates, bool)> {
+    rates::get_rates(providers, currency, start_date, end_date, official_only)
 }
 
 #[cfg(test)]
-fn get_currency_rates(currency: &str, _start_date: Date, _end_date: Date) -> GenericResult<Vec<CurrencyRate>> {
-    assert_eq!(currency, "USD");
-
-    Ok(vec![
-        CurrencyRate {
-            date: date!(1, 9, 2018),
-            price: dec!(68.0447),
-        },
-        CurrencyRate {
-            date: date!(4, 9, 2018),
-            price: dec!(67.7443),
+fn get_currency_rates(
+    _providers: &[Box<dyn RateProvider>], currency: &str, start_date: Date, end_date: Date,
+    _official_only: bool,
+) -> GenericResult<(CurrencyRates, bool)> {
+    let rates = match currency {
+        "USD" => vec![
+            CurrencyRate {
+                date: date!(1, 9, 2018),
+                price: dec!(68.0447),
+            },
+            CurrencyRate {
+                date: date!(4, 9, 2018),
+                price: dec!(67.7443),
+            },
+        ],
+        "EUR" => vec![
+            CurrencyRate {
+                date: date!(1, 9, 2018),
+                price: dec!(79.4545),
+            },
+        ],
+        // Emulates a server which returns rates only for a part of the requested period
+        "GBP" => {
+            let (start_date, end_date) = (date!(1, 9, 2018), date!(4, 9, 2018));
+            let rates = vec![
+                CurrencyRate {
+                    date: start_date,
+                    price: dec!(88.4567),
+                },
+                CurrencyRate {
+                    date: end_date,
+                    price: dec!(87.6543),
+                },
+            ];
+            return Ok(((rates, (start_date, end_date)), true));
         },
-    ])
+        _ => unreachable!(),
+    };
+
+    Ok(((rates, (start_date, end_date)), true))
 }
 
 #[cfg(test)]
@@ -216,7 +554,7 @@ mod tests {
         let amount = dec!(3);
         let today = cache.today();
         let converter = CurrencyConverter::new_with_backend(
-            CurrencyRateCacheBackend::new(cache, None, true));
+            CurrencyRateCacheBackend::new(cache, Vec::new(), None, true));
 
         for currency in ["RUB", "USD"].iter() {
             assert_eq!(converter.convert(currency, currency, today, amount).unwrap(), amount);
@@ -256,4 +594,135 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn cross_rate_conversion() {
+        let (_database, cache) = CurrencyRateCache::new_temporary();
+        let converter = CurrencyConverter::new_with_backend(
+            CurrencyRateCacheBackend::new(cache, Vec::new(), None, true));
+
+        let amount = dec!(3);
+
+        assert_matches!(
+            converter.convert("EUR", "USD", date!(31, 8, 2018), amount),
+            Err(ref e) if e.to_string().starts_with("Unable to find EUR/USD currency rate")
+        );
+
+        for day in 1..5 {
+            assert_eq!(
+                converter.convert("EUR", "USD", date!(day, 9, 2018), amount).unwrap(),
+                amount * dec!(79.4545) / dec!(68.0447),
+            );
+        }
+    }
+
+    #[test]
+    fn prefetch_year() {
+        let (_database, cache) = CurrencyRateCache::new_temporary();
+        let backend = CurrencyRateCacheBackend {
+            quotes: None,
+            rate_cache: cache,
+            providers: Vec::new(),
+            strict_mode: true,
+            fetch_plan: None,
+            loaded_rates: RefCell::new(HashMap::new()),
+            unofficial_rates: RefCell::new(HashMap::new()),
+        };
+
+        backend.prefetch_year("RUB", 2018).unwrap();
+        backend.prefetch_year("USD", 2018).unwrap();
+        assert_eq!(backend.rate_cache.get_year_missing_period("USD", 2018).unwrap(), None);
+
+        assert_matches!(
+            backend.rate_cache.get("USD", date!(4, 9, 2018)).unwrap(),
+            CurrencyRateCacheResult::Exists(Some(price)) if price == dec!(67.7443)
+        );
+        assert_matches!(
+            backend.rate_cache.get("USD", date!(5, 9, 2018)).unwrap(),
+            CurrencyRateCacheResult::Exists(None)
+        );
+    }
+
+    #[test]
+    fn narrowed_period() {
+        let (_database, cache) = CurrencyRateCache::new_temporary();
+        let backend = CurrencyRateCacheBackend {
+            quotes: None,
+            rate_cache: cache,
+            providers: Vec::new(),
+            strict_mode: true,
+            fetch_plan: None,
+            loaded_rates: RefCell::new(HashMap::new()),
+            unofficial_rates: RefCell::new(HashMap::new()),
+        };
+
+        assert_matches!(
+            backend.convert("GBP", "RUB", date!(31, 8, 2018), dec!(1)),
+            Err(ref e) if e.to_string().starts_with("Unable to find GBP currency rate")
+        );
+        assert_eq!(backend.convert("GBP", "RUB", date!(4, 9, 2018), dec!(1)).unwrap(), dec!(87.6543));
+        assert_eq!(backend.convert("GBP", "RUB", date!(5, 9, 2018), dec!(1)).unwrap(), dec!(87.6543));
+
+        // The part of the requested period which isn't covered by the server is known to be missing
+        for &date in &[date!(31, 8, 2018), date!(5, 9, 2018)] {
+            assert_matches!(
+                backend.rate_cache.get("GBP", date).unwrap(),
+                CurrencyRateCacheResult::Exists(None)
+            );
+        }
+    }
+
+    #[test]
+    fn concurrent_prefetch() {
+        let (_database, cache) = CurrencyRateCache::new_temporary();
+        let converter = CurrencyConverter::new_with_backend(
+            CurrencyRateCacheBackend::new(cache, Vec::new(), None, true));
+
+        converter.prefetch(&["RUB", "USD", "EUR"], 2018).unwrap();
+
+        for &(currency, price) in &[("USD", dec!(68.0447)), ("EUR", dec!(79.4545))] {
+            assert_eq!(converter.convert(currency, "RUB", date!(1, 9, 2018), dec!(1)).unwrap(), price);
+        }
+    }
+
+    #[test]
+    fn dry_run() {
+        let (_database, cache) = CurrencyRateCache::new_temporary();
+        let today = cache.today();
+        let converter = CurrencyConverter::new_with_backend(
+            CurrencyRateCacheBackend::new_dry_run(cache, true));
+
+        converter.convert("USD", "RUB", date!(1, 9, 2018), dec!(1)).unwrap();
+        converter.convert("EUR", "USD", date!(1, 9, 2018), dec!(1)).unwrap();
+        converter.prefetch_year("EUR", 2018).unwrap();
+
+        let fetch_plan = converter.get_fetch_plan().unwrap();
+        assert_eq!(fetch_plan.periods().keys().collect::<Vec<_>>(), vec!["EUR", "USD"]);
+        assert_eq!(fetch_plan.periods()["USD"], vec![
+            (date!(1, 9, 2018) - Duration::days(365), today + Duration::days(1)),
+        ]);
+        assert_eq!(fetch_plan.periods()["EUR"], vec![
+            (date!(1, 9, 2018) - Duration::days(365), today + Duration::days(1)),
+        ]);
+    }
+
+    #[test]
+    fn fetch_plan_merging() {
+        let mut fetch_plan = RateFetchPlan::new();
+        assert!(fetch_plan.is_empty());
+
+        fetch_plan.add("USD", date!(10, 1, 2020), date!(15, 1, 2020));
+        fetch_plan.add("USD", date!(1, 1, 2020), date!(5, 1, 2020));
+        fetch_plan.add("USD", date!(16, 1, 2020), date!(20, 1, 2020));
+        fetch_plan.add("USD", date!(18, 1, 2020), date!(19, 1, 2020));
+        fetch_plan.add("EUR", date!(6, 1, 2020), date!(9, 1, 2020));
+
+        assert_eq!(fetch_plan.periods()["USD"], vec![
+            (date!(1, 1, 2020), date!(5, 1, 2020)),
+            (date!(10, 1, 2020), date!(20, 1, 2020)),
+        ]);
+        assert_eq!(fetch_plan.periods()["EUR"], vec![
+            (date!(6, 1, 2020), date!(9, 1, 2020)),
+        ]);
+    }
 }
\ No newline at end of file
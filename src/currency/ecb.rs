@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[cfg(test)] use indoc::indoc;
+use log::debug;
+#[cfg(test)] use mockito::{self, Mock, mock};
+use reqwest::blocking::Client;
+use serde::Deserialize;
+
+use crate::core::GenericResult;
+use crate::currency::CurrencyRate;
+use crate::formatting;
+use crate::types::{Date, Decimal};
+use crate::util;
+
+use super::rates::{self, RateProvider, CurrencyRates};
+
+/// European Central Bank provides EUR-based reference rates, so RUB rates are derived from them as
+/// cross rates.
+pub struct Ecb {
+    client: Client,
+}
+
+impl Ecb {
+    pub fn new(timeout: Duration) -> Ecb {
+        Ecb {
+            client: rates::new_client(timeout),
+        }
+    }
+}
+
+impl RateProvider for Ecb {
+    fn name(&self) -> &'static str {
+        "the European Central Bank"
+    }
+
+    fn is_official(&self) -> bool {
+        false
+    }
+
+    fn get_rates(&self, currency: &str, start_date: Date, end_date: Date) -> GenericResult<CurrencyRates> {
+        get_rates(&self.client, currency, start_date, end_date)
+    }
+}
+
+fn get_rates(client: &Client, currency: &str, start_date: Date, end_date: Date) -> GenericResult<CurrencyRates> {
+    #[cfg(not(test))]
+    let base_url = "https://www.ecb.europa.eu";
+
+    #[cfg(test)]
+    let base_url = mockito::server_url();
+
+    let url = format!("{}/stats/eurofxref/eurofxref-hist.xml", base_url);
+
+    let get = || -> GenericResult<CurrencyRates> {
+        debug!("Getting {} currency rates for {} - {}...", currency,
+               formatting::format_date(start_date), formatting::format_date(end_date));
+
+        let data = rates::send_request(client, &url)?;
+        Ok(parse_rates(currency, start_date, end_date, &data).map_err(|e| format!(
+            "Rates info parsing error: {}", e))?)
+    };
+
+    Ok(get().map_err(|e| format!("Failed to get currency rates from {}: {}", url, e))?)
+}
+
+fn parse_rates(currency: &str, start_date: Date, end_date: Date, data: &str) -> GenericResult<CurrencyRates> {
+    #[derive(Deserialize)]
+    struct Envelope {
+        #[serde(rename = "Cube")]
+        cube: Days,
+    }
+
+    #[derive(Deserialize)]
+    struct Days {
+        #[serde(rename = "Cube", default)]
+        days: Vec<Day>,
+    }
+
+    #[derive(Deserialize)]
+    struct Day {
+        time: String,
+
+        #[serde(rename = "Cube", default)]
+        rates: Vec<Rate>,
+    }
+
+    #[derive(Deserialize)]
+    struct Rate {
+        currency: String,
+        rate: String,
+    }
+
+    let result: Envelope = serde_xml_rs::from_str(data).map_err(|e| e.to_string())?;
+
+    let mut last_date = None;
+    let mut rates = Vec::new();
+
+    for day in result.cube.days {
+        let date = util::parse_date(&day.time, "%Y-%m-%d")?;
+        let mut day_rates = HashMap::new();
+
+        for rate in day.rates {
+            let price = Decimal::from_str(&rate.rate).ok()
+                .filter(|&price| price > dec!(0))
+                .ok_or_else(|| format!("Invalid {} rate: {:?}", rate.currency, rate.rate))?;
+            day_rates.insert(rate.currency, price);
+        }
+        day_rates.insert("EUR".to_owned(), dec!(1));
+
+        // The rates are specified as an amount of currency per 1 EUR
+        let (rub, price) = match (day_rates.get("RUB"), day_rates.get(currency)) {
+            (Some(rub), Some(price)) => (rub, price),
+            _ => continue,
+        };
+
+        // ECB doesn't publish RUB rates since March 2022, so the period is considered as covered
+        // only up to the last date which has both rates
+        if last_date.map_or(true, |last_date| date > last_date) {
+            last_date.replace(date);
+        }
+
+        if date >= start_date && date <= end_date {
+            rates.push(CurrencyRate {
+                date: date,
+                price: rub / price,
+            });
+        }
+    }
+
+    let end_date = match last_date {
+        Some(last_date) if last_date >= start_date => std::cmp::min(end_date, last_date),
+        _ => return Err!("The server returned no currency rates for {}",
+                         formatting::format_period((start_date, end_date.succ()))),
+    };
+
+    rates.sort_by_key(|rate| rate.date);
+
+    Ok((rates, (start_date, end_date)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates() {
+        let _mock = mock_ecb_response(indoc!(r#"
+            <?xml version="1.0" encoding="UTF-8"?>
+            <gesmes:Envelope xmlns:gesmes="http://www.gesmes.org/xml/2002-08-01" xmlns="http://www.ecb.int/vocabulary/2002-08-01/eurofxref">
+                <gesmes:subject>Reference rates</gesmes:subject>
+                <gesmes:Sender>
+                    <gesmes:name>European Central Bank</gesmes:name>
+                </gesmes:Sender>
+                <Cube>
+                    <Cube time="2018-09-06">
+                        <Cube currency="USD" rate="1.1623"/>
+                    </Cube>
+                    <Cube time="2018-09-05">
+                        <Cube currency="USD" rate="1.1602"/>
+                        <Cube currency="RUB" rate="79.6543"/>
+                    </Cube>
+                    <Cube time="2018-09-04">
+                        <Cube currency="USD" rate="1.1588"/>
+                        <Cube currency="RUB" rate="78.9896"/>
+                    </Cube>
+                    <Cube time="2018-09-03">
+                        <Cube currency="USD" rate="1.1604"/>
+                    </Cube>
+                    <Cube time="2018-08-31">
+                        <Cube currency="USD" rate="1.1651"/>
+                        <Cube currency="RUB" rate="79.5693"/>
+                    </Cube>
+                </Cube>
+            </gesmes:Envelope>
+        "#));
+
+        assert_eq!(get_rates(&Client::new(), "USD", date!(1, 9, 2018), date!(4, 9, 2018)).unwrap(), (vec![
+            CurrencyRate {
+                date: date!(4, 9, 2018),
+                price: dec!(78.9896) / dec!(1.1588),
+            },
+        ], (date!(1, 9, 2018), date!(4, 9, 2018))));
+
+        assert_eq!(get_rates(&Client::new(), "EUR", date!(4, 9, 2018), date!(10, 9, 2018)).unwrap(), (vec![
+            CurrencyRate {
+                date: date!(4, 9, 2018),
+                price: dec!(78.9896),
+            },
+            CurrencyRate {
+                date: date!(5, 9, 2018),
+                price: dec!(79.6543),
+            },
+        ], (date!(4, 9, 2018), date!(5, 9, 2018))));
+
+        assert!(get_rates(&Client::new(), "USD", date!(6, 9, 2018), date!(10, 9, 2018)).is_err());
+    }
+
+    fn mock_ecb_response(data: &str) -> Mock {
+        mock("GET", "/stats/eurofxref/eurofxref-hist.xml")
+            .with_status(200)
+            .with_header("Content-Type", "text/xml")
+            .with_body(data)
+            .create()
+    }
+}
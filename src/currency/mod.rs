@@ -15,10 +15,12 @@ use crate::util;
 use self::converter::CurrencyConverter;
 
 mod cbr;
+mod ecb;
 mod name_cache;
 mod rate_cache;
 
 pub mod converter;
+pub mod rates;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Cash {
@@ -221,6 +223,11 @@ impl MultiCurrencyCashAccount {
     }
 }
 
+/// Checks whether the currency is convertible by currency rate providers
+pub fn is_supported(currency: &str) -> bool {
+    currency == "RUB" || cbr::get_currency_code(currency).is_some()
+}
+
 pub fn round(amount: Decimal) -> Decimal {
     util::round(amount, 2)
 }
@@ -246,6 +253,7 @@ fn format_currency(currency: &str, mut amount: &str) -> String {
     match currency {
         "USD" => (),
         "RUB" => write!(&mut buffer, "₽").unwrap(),
+        "EUR" => write!(&mut buffer, "€").unwrap(),
         _ => write!(&mut buffer, " {}", currency).unwrap(),
     };
 
@@ -291,6 +299,9 @@ mod tests {
         assert_eq!(Cash::new("RUB", dec!(12.345)).to_string(), "12.345₽");
         assert_eq!(Cash::new("RUB", dec!(-12.345)).to_string(), "-12.345₽");
 
+        assert_eq!(Cash::new("EUR", dec!(12.345)).to_string(), "12.345€");
+        assert_eq!(Cash::new("EUR", dec!(-12.345)).to_string(), "-12.345€");
+
         assert_eq!(Cash::new("UNKNOWN", dec!(12.345)).to_string(), "12.345 UNKNOWN");
         assert_eq!(Cash::new("UNKNOWN", dec!(-12.345)).to_string(), "-12.345 UNKNOWN");
     }
@@ -1,7 +1,8 @@
 use std::collections::{HashMap, HashSet};
 use std::sync::Mutex;
 
-use chrono::Duration;
+use chrono::{Datelike, Duration};
+use chrono_tz::Tz;
 use diesel::{self, prelude::*};
 #[cfg(test)] use matches::assert_matches;
 #[cfg(test)] use tempfile::NamedTempFile;
@@ -26,6 +27,13 @@ use crate::util::{self, DecimalRestrictions};
 // no data from sunday for monday, but will be data from monday for tuesday), but don't save
 // tomorrow's currency rates - just in case: we don't actually need them, but by not saving them we
 // can handle a possible corrections, for example.
+//
+// Rates are stored with a fixed precision: CBR publishes rates with 4 decimal places, but rates
+// for currencies quoted per lot (JPY, for example) are calculated by division and may have an
+// arbitrary long fractional part. 10 decimal places are enough for any practical usage and make
+// the stored values predictable.
+const PRICE_PRECISION: u32 = 10;
+
 pub struct CurrencyRateCache {
     today: Date,
     tomorrow: Date,
@@ -36,7 +44,13 @@ pub struct CurrencyRateCache {
 
 impl CurrencyRateCache {
     pub fn new(connection: db::Connection) -> CurrencyRateCache {
-        let today = util::today();
+        CurrencyRateCache::new_with_timezone(connection, chrono_tz::Europe::Moscow)
+    }
+
+    /// CBR publishes currency rates in Moscow time, so the cache uses Moscow date as today by
+    /// default instead of local one which may differ for users in other time zones.
+    pub fn new_with_timezone(connection: db::Connection, timezone: Tz) -> CurrencyRateCache {
+        let today = util::today_in(timezone);
         CurrencyRateCache {
             today: today,
             tomorrow: today + Duration::days(1),
@@ -119,6 +133,170 @@ impl CurrencyRateCache {
         })
     }
 
+    /// Returns the rate for the specified date or the closest published one in the specified
+    /// direction (CBR doesn't publish rates on weekends and holidays) along with its actual date.
+    /// Doesn't look beyond the date's year and today.
+    pub fn get_closest(
+        &self, currency: &str, date: Date, direction: RateSearchDirection,
+    ) -> GenericResult<(Date, Decimal)> {
+        match self.get(currency, date)? {
+            CurrencyRateCacheResult::Exists(Some(price)) => return Ok((date, price)),
+            CurrencyRateCacheResult::Exists(None) => {},
+            CurrencyRateCacheResult::Missing(_, _) => return Err!(
+                "{} currency rate for {} is missing in the cache",
+                currency, formatting::format_date(date)),
+        };
+
+        let previous = match direction {
+            RateSearchDirection::Backward | RateSearchDirection::Nearest => {
+                self.find_closest(currency, date, false)?
+            },
+            RateSearchDirection::Forward => None,
+        };
+
+        let next = match direction {
+            RateSearchDirection::Forward | RateSearchDirection::Nearest => {
+                self.find_closest(currency, date, true)?
+            },
+            RateSearchDirection::Backward => None,
+        };
+
+        // The previous rate wins on a tie since it's the one which is in effect on the date
+        Ok(match (previous, next) {
+            (Some(previous), Some(next)) => if next.0 - date < date - previous.0 {
+                next
+            } else {
+                previous
+            },
+            (Some(rate), None) | (None, Some(rate)) => rate,
+            (None, None) => return Err!(
+                "Unable to find {} currency rate for {}: there are no rates for the {} dates of the year",
+                currency, formatting::format_date(date), match direction {
+                    RateSearchDirection::Backward => "previous",
+                    RateSearchDirection::Forward => "next",
+                    RateSearchDirection::Nearest => "other",
+                }),
+        })
+    }
+
+    fn find_closest(&self, currency: &str, date: Date, forward: bool) -> GenericResult<Option<(Date, Decimal)>> {
+        let query = currency_rates::table
+            .select((currency_rates::date, currency_rates::price))
+            .filter(currency_rates::currency.eq(currency))
+            .filter(currency_rates::price.is_not_null())
+            .limit(1);
+
+        let result = if forward {
+            let end_date = std::cmp::min(date!(31, 12, date.year()), self.today);
+            query
+                .filter(currency_rates::date.gt(date))
+                .filter(currency_rates::date.le(end_date))
+                .order(currency_rates::date.asc())
+                .get_result::<(Date, Option<String>)>(&*self.db).optional()?
+        } else {
+            query
+                .filter(currency_rates::date.lt(date))
+                .filter(currency_rates::date.ge(date!(1, 1, date.year())))
+                .order(currency_rates::date.desc())
+                .get_result::<(Date, Option<String>)>(&*self.db).optional()?
+        };
+
+        Ok(match result {
+            Some((closest_date, Some(price))) => Some((closest_date, util::parse_decimal(
+                &price, DecimalRestrictions::StrictlyPositive
+            ).map_err(|_| format!("Got an invalid price from the database: {:?}", price))?)),
+            _ => None,
+        })
+    }
+
+    /// Returns the minimal period which covers all dates of the specified year that are missing in
+    /// the cache or None if the year is fully cached. As with `get`, the period ends with the next
+    /// known rate or tomorrow.
+    pub fn get_year_missing_period(&self, currency: &str, year: i32) -> GenericResult<Option<(Date, Date)>> {
+        let start_date = date!(1, 1, year);
+        if start_date > self.today {
+            return Err!("An attempt to get currency rates for the future");
+        }
+
+        let end_date = std::cmp::min(date!(31, 12, year), self.today);
+        let in_memory_missing = self.in_memory_missing.lock().unwrap().get(currency).cloned()
+            .unwrap_or_default();
+
+        let cached_dates: HashSet<Date> = currency_rates::table
+            .select(currency_rates::date)
+            .filter(currency_rates::currency.eq(currency))
+            .filter(currency_rates::date.ge(start_date))
+            .filter(currency_rates::date.le(end_date))
+            .load::<Date>(&*self.db)?
+            .into_iter().chain(in_memory_missing.into_iter())
+            .collect();
+
+        let mut missing_period: Option<(Date, Date)> = None;
+        let mut date = start_date;
+
+        while date <= end_date {
+            if !cached_dates.contains(&date) {
+                missing_period = Some(match missing_period {
+                    Some((first_date, _)) => (first_date, date),
+                    None => (date, date),
+                });
+            }
+            date += Duration::days(1);
+        }
+
+        let (first_date, last_date) = match missing_period {
+            Some(period) => period,
+            None => return Ok(None),
+        };
+
+        // Extend the period in the same way as `get` does to make it end with a known rate
+        let end_date = currency_rates::table
+            .select(currency_rates::date)
+            .filter(currency_rates::currency.eq(currency))
+            .filter(currency_rates::date.gt(last_date))
+            .filter(currency_rates::price.is_not_null())
+            .order(currency_rates::date.asc())
+            .limit(1)
+            .get_result::<Date>(&*self.db).optional()?
+            .unwrap_or(self.tomorrow);
+
+        Ok(Some((first_date, end_date)))
+    }
+
+    /// Loads all cached rates of the specified year with a single query. Dates which are known to
+    /// have no rate are returned as None, dates which are missing in the cache aren't returned at
+    /// all.
+    pub fn load_year(&self, currency: &str, year: i32) -> GenericResult<HashMap<Date, Option<Decimal>>> {
+        let start_date = date!(1, 1, year);
+        let end_date = date!(31, 12, year);
+
+        let mut rates: HashMap<Date, Option<Decimal>> = currency_rates::table
+            .select((currency_rates::date, currency_rates::price))
+            .filter(currency_rates::currency.eq(currency))
+            .filter(currency_rates::date.ge(start_date))
+            .filter(currency_rates::date.le(end_date))
+            .load::<(Date, Option<String>)>(&*self.db)?
+            .into_iter().map(|(date, price)| -> GenericResult<(Date, Option<Decimal>)> {
+                Ok((date, match price {
+                    Some(price) => Some(
+                        util::parse_decimal(&price, DecimalRestrictions::StrictlyPositive).map_err(|_| format!(
+                            "Got an invalid price from the database: {:?}", price))?),
+                    None => None,
+                }))
+            })
+            .collect::<GenericResult<_>>()?;
+
+        if let Some(missing) = self.in_memory_missing.lock().unwrap().get(currency) {
+            for &date in missing {
+                if date >= start_date && date <= end_date {
+                    rates.insert(date, None);
+                }
+            }
+        }
+
+        Ok(rates)
+    }
+
     pub fn save(&self, currency: &str, start_date: Date, end_date: Date, mut rates: Vec<CurrencyRate>) -> EmptyResult {
         if start_date > end_date {
             return Err!("Invalid date range: {} - {}",
@@ -163,7 +341,7 @@ impl CurrencyRateCache {
             rows.push(models::NewCurrencyRate {
                 currency: currency,
                 date: rate.date,
-                price: Some(rate.price.to_string()),
+                price: Some(util::round(rate.price, PRICE_PRECISION).to_string()),
             });
         }
 
@@ -173,24 +351,64 @@ impl CurrencyRateCache {
                 None => start_date,
             };
             debug_assert!(date > end_date || end_date == self.tomorrow);
+            self.save_missing(currency, date, end_date);
+        }
 
-            while date <= std::cmp::min(end_date, self.today) {
-                self.in_memory_missing.lock().unwrap()
-                    .entry(currency.to_owned())
-                    .or_insert_with(HashSet::new)
-                    .insert(date);
-                date += Duration::days(1);
-            }
+        db::retry_on_lock(|| self.db.transaction(|| {
+            diesel::replace_into(currency_rates::table)
+                .values(&rows)
+                .execute(&*self.db)
+        }))?;
+
+        Ok(())
+    }
+
+    /// Marks the specified period as having no rates until the end of the process. Unlike `save`,
+    /// the dates aren't persisted, because absence of the rates may be temporary.
+    pub fn save_missing(&self, currency: &str, start_date: Date, end_date: Date) {
+        let mut in_memory_missing = self.in_memory_missing.lock().unwrap();
+        let missing = in_memory_missing.entry(currency.to_owned()).or_insert_with(HashSet::new);
+
+        let mut date = start_date;
+        while date <= std::cmp::min(end_date, self.today) {
+            missing.insert(date);
+            date += Duration::days(1);
+        }
+    }
+
+    /// Drops all cached rates (including the dates which are known to have no rate) for the
+    /// specified period, so they will be fetched again on next access. Intended for handling of
+    /// corrections of already published rates.
+    pub fn invalidate(&self, currency: &str, start_date: Date, end_date: Date) -> EmptyResult {
+        if start_date > end_date {
+            return Err!("Invalid date range: {} - {}",
+                formatting::format_date(start_date), formatting::format_date(end_date));
+        }
+
+        if let Some(missing) = self.in_memory_missing.lock().unwrap().get_mut(currency) {
+            missing.retain(|&date| date < start_date || date > end_date);
         }
 
-        diesel::replace_into(currency_rates::table)
-            .values(rows)
-            .execute(&*self.db)?;
+        db::retry_on_lock(|| self.db.transaction(|| {
+            diesel::delete(
+                currency_rates::table
+                    .filter(currency_rates::currency.eq(currency))
+                    .filter(currency_rates::date.ge(start_date))
+                    .filter(currency_rates::date.le(end_date))
+            ).execute(&*self.db)
+        }))?;
 
         Ok(())
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+pub enum RateSearchDirection {
+    Backward,
+    Forward,
+    Nearest,
+}
+
 #[derive(Debug)]
 pub enum CurrencyRateCacheResult {
     Exists(Option<Decimal>),
@@ -257,7 +475,8 @@ mod tests {
                     if date == currency_rate.date {
                         assert_matches!(
                             cache.get(currency, date).unwrap(),
-                            CurrencyRateCacheResult::Exists(Some(ref price)) if *price == currency_rate.price
+                            CurrencyRateCacheResult::Exists(Some(ref price))
+                                if *price == util::round(currency_rate.price, PRICE_PRECISION)
                         );
                         continue 'date_loop;
                     }
@@ -288,4 +507,180 @@ mod tests {
                 if from == last_date + Duration::days(1) && to == cache.tomorrow
         );
     }
+
+    #[test]
+    fn closest_rate() {
+        let currency = "USD";
+        let (_database, mut cache) = CurrencyRateCache::new_temporary();
+
+        let today = date!(8, 2, 2018);
+        cache.today = today;
+        cache.tomorrow = today + Duration::days(1);
+
+        cache.save(currency, date!(1, 1, 2018), date!(10, 1, 2018), vec![CurrencyRate {
+            date: date!(10, 1, 2018),
+            price: dec!(56.8859),
+        }]).unwrap();
+        cache.save(currency, date!(11, 1, 2018), cache.tomorrow, vec![CurrencyRate {
+            date: date!(12, 1, 2018),
+            price: dec!(56.7275),
+        }]).unwrap();
+
+        let backward = RateSearchDirection::Backward;
+        let forward = RateSearchDirection::Forward;
+        let nearest = RateSearchDirection::Nearest;
+
+        assert_eq!(cache.get_closest(currency, date!(12, 1, 2018), backward).unwrap(),
+                   (date!(12, 1, 2018), dec!(56.7275)));
+        assert_eq!(cache.get_closest(currency, date!(11, 1, 2018), backward).unwrap(),
+                   (date!(10, 1, 2018), dec!(56.8859)));
+        assert_eq!(cache.get_closest(currency, today, backward).unwrap(),
+                   (date!(12, 1, 2018), dec!(56.7275)));
+
+        assert!(cache.get_closest(currency, date!(9, 1, 2018), backward).is_err());
+        assert!(cache.get_closest(currency, date!(31, 12, 2017), backward).is_err());
+
+        assert_eq!(cache.get_closest(currency, date!(9, 1, 2018), forward).unwrap(),
+                   (date!(10, 1, 2018), dec!(56.8859)));
+        assert_eq!(cache.get_closest(currency, date!(11, 1, 2018), forward).unwrap(),
+                   (date!(12, 1, 2018), dec!(56.7275)));
+        assert!(cache.get_closest(currency, today, forward).is_err());
+
+        assert_eq!(cache.get_closest(currency, date!(1, 1, 2018), nearest).unwrap(),
+                   (date!(10, 1, 2018), dec!(56.8859)));
+        assert_eq!(cache.get_closest(currency, date!(11, 1, 2018), nearest).unwrap(),
+                   (date!(10, 1, 2018), dec!(56.8859)));
+        assert_eq!(cache.get_closest(currency, today, nearest).unwrap(),
+                   (date!(12, 1, 2018), dec!(56.7275)));
+    }
+
+    #[test]
+    fn invalidation() {
+        let currency = "USD";
+        let (_database, mut cache) = CurrencyRateCache::new_temporary();
+
+        let today = date!(8, 2, 2018);
+        cache.today = today;
+        cache.tomorrow = today + Duration::days(1);
+
+        cache.save(currency, date!(1, 1, 2018), cache.tomorrow, vec![CurrencyRate {
+            date: date!(10, 1, 2018),
+            price: dec!(56.8859),
+        }]).unwrap();
+
+        for &date in &[date!(9, 1, 2018), date!(1, 2, 2018)] {
+            assert_matches!(cache.get(currency, date).unwrap(), CurrencyRateCacheResult::Exists(None));
+        }
+
+        cache.invalidate(currency, date!(9, 1, 2018), today).unwrap();
+
+        assert_matches!(
+            cache.get(currency, date!(8, 1, 2018)).unwrap(),
+            CurrencyRateCacheResult::Exists(None)
+        );
+        assert_matches!(
+            cache.get(currency, date!(9, 1, 2018)).unwrap(),
+            CurrencyRateCacheResult::Missing(from, to) if from == date!(9, 1, 2018) && to == cache.tomorrow
+        );
+        assert_matches!(
+            cache.get(currency, date!(1, 2, 2018)).unwrap(),
+            CurrencyRateCacheResult::Missing(from, to) if from == date!(9, 1, 2018) && to == cache.tomorrow
+        );
+    }
+
+    #[test]
+    fn today_timezone() {
+        let (_database, connection) = db::new_temporary();
+
+        for timezone in [chrono_tz::Europe::Moscow, chrono_tz::Asia::Vladivostok].iter() {
+            let cache = CurrencyRateCache::new_with_timezone(connection.clone(), *timezone);
+            assert_eq!(cache.today(), util::today_in(*timezone));
+            assert_eq!(cache.tomorrow, cache.today() + Duration::days(1));
+        }
+    }
+
+    #[test]
+    fn load_year() {
+        let currency = "USD";
+        let (_database, mut cache) = CurrencyRateCache::new_temporary();
+
+        let today = date!(8, 2, 2018);
+        cache.today = today;
+        cache.tomorrow = today + Duration::days(1);
+
+        cache.save(currency, date!(30, 12, 2017), date!(11, 1, 2018), vec![CurrencyRate {
+            date: date!(10, 1, 2018),
+            price: dec!(56.8859),
+        }]).unwrap();
+
+        let rates = cache.load_year(currency, 2018).unwrap();
+        assert_eq!(rates.len(), 11);
+        assert_eq!(rates[&date!(1, 1, 2018)], None);
+        assert_eq!(rates[&date!(10, 1, 2018)], Some(dec!(56.8859)));
+        assert_eq!(rates[&date!(11, 1, 2018)], None);
+        assert!(rates.get(&date!(12, 1, 2018)).is_none());
+
+        assert_eq!(cache.load_year(currency, 2017).unwrap().len(), 2);
+        assert!(cache.load_year("EUR", 2018).unwrap().is_empty());
+    }
+
+    #[test]
+    fn year_missing_period() {
+        let currency = "USD";
+        let (_database, mut cache) = CurrencyRateCache::new_temporary();
+
+        let today = date!(8, 2, 2018);
+        cache.today = today;
+        cache.tomorrow = today + Duration::days(1);
+
+        assert_matches!(
+            cache.get_year_missing_period(currency, 2019),
+            Err(ref e) if e.to_string() == "An attempt to get currency rates for the future"
+        );
+
+        assert_eq!(cache.get_year_missing_period(currency, 2018).unwrap(),
+                   Some((date!(1, 1, 2018), cache.tomorrow)));
+
+        cache.save(currency, date!(10, 1, 2018), cache.tomorrow, vec![CurrencyRate {
+            date: date!(10, 1, 2018),
+            price: dec!(57.0),
+        }]).unwrap();
+        assert_eq!(cache.get_year_missing_period(currency, 2018).unwrap(),
+                   Some((date!(1, 1, 2018), date!(10, 1, 2018))));
+        assert_eq!(cache.get_year_missing_period(currency, 2017).unwrap(),
+                   Some((date!(1, 1, 2017), date!(10, 1, 2018))));
+
+        cache.save(currency, date!(1, 1, 2018), date!(10, 1, 2018), vec![CurrencyRate {
+            date: date!(10, 1, 2018),
+            price: dec!(57.0),
+        }]).unwrap();
+        assert_eq!(cache.get_year_missing_period(currency, 2018).unwrap(), None);
+    }
+
+    #[test]
+    fn rate_precision() {
+        let currency = "JPY";
+        let (_database, mut cache) = CurrencyRateCache::new_temporary();
+
+        let date = date!(8, 2, 2018);
+        cache.today = date;
+        cache.tomorrow = date + Duration::days(1);
+
+        cache.save(currency, date, date, vec![CurrencyRate {
+            date: date,
+            price: dec!(1) / dec!(3),
+        }]).unwrap();
+
+        let stored_price = currency_rates::table
+            .select(currency_rates::price)
+            .filter(currency_rates::currency.eq(currency))
+            .filter(currency_rates::date.eq(date))
+            .get_result::<Option<String>>(&*cache.db).unwrap();
+        assert_eq!(stored_price, Some(s!("0.3333333333")));
+
+        assert_matches!(
+            cache.get(currency, date).unwrap(),
+            CurrencyRateCacheResult::Exists(Some(price)) if price == dec!(0.3333333333)
+        );
+    }
 }
\ No newline at end of file
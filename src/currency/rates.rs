@@ -0,0 +1,217 @@
+use std::thread;
+use std::time::Duration;
+
+use log::{debug, warn};
+use reqwest::blocking::Client;
+
+use crate::config::{Config, CurrencyRateProviderType};
+use crate::core::GenericResult;
+use crate::currency::CurrencyRate;
+use crate::formatting;
+use crate::types::Date;
+
+use super::cbr::Cbr;
+use super::ecb::Ecb;
+
+/// Currency rates relative to RUB and the period which they actually cover
+pub type CurrencyRates = (Vec<CurrencyRate>, (Date, Date));
+
+// Providers are required to be thread safe to fetch rates of different currencies concurrently
+pub trait RateProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Only official rates may be used for tax calculations and saved to the currency rate cache
+    fn is_official(&self) -> bool {
+        true
+    }
+
+    fn get_rates(&self, currency: &str, start_date: Date, end_date: Date) -> GenericResult<CurrencyRates>;
+}
+
+pub fn get_providers(config: &Config) -> Vec<Box<dyn RateProvider>> {
+    if config.offline {
+        return vec![Box::new(OfflineProvider {})];
+    }
+
+    let timeout = config.currency_rates_timeout.to_std().unwrap();
+
+    config.currency_rate_providers.iter().map(|provider| -> Box<dyn RateProvider> {
+        match provider {
+            CurrencyRateProviderType::Cbr => Box::new(Cbr::new(timeout)),
+            CurrencyRateProviderType::Ecb => Box::new(Ecb::new(timeout)),
+        }
+    }).collect()
+}
+
+/// Used in offline mode instead of the configured providers to never hit the network
+struct OfflineProvider {
+}
+
+impl RateProvider for OfflineProvider {
+    fn name(&self) -> &'static str {
+        "offline mode"
+    }
+
+    fn get_rates(&self, currency: &str, start_date: Date, end_date: Date) -> GenericResult<CurrencyRates> {
+        Err!(concat!(
+            "{} currency rates for {} - {} are missing in the cache. ",
+            "Run the command without offline mode to fetch them into the cache"
+        ), currency, formatting::format_date(start_date), formatting::format_date(end_date))
+    }
+}
+
+pub fn new_client(timeout: Duration) -> Client {
+    Client::builder()
+        .connect_timeout(timeout)
+        .timeout(timeout)
+        .build()
+        .expect("Failed to create HTTP client")
+}
+
+const MAX_ATTEMPTS: u32 = 3;
+
+#[cfg(not(test))]
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+#[cfg(test)]
+const RETRY_DELAY: Duration = Duration::from_millis(1);
+
+/// Sends GET request retrying it with exponential backoff on transient failures (timeouts,
+/// connection errors and 5xx responses)
+pub fn send_request(client: &Client, url: &str) -> GenericResult<String> {
+    let mut delay = RETRY_DELAY;
+    let mut attempt = 1;
+
+    loop {
+        let error = match client.get(url).send() {
+            Ok(response) if response.status().is_success() => return Ok(response.text()?),
+            Ok(response) if response.status().is_server_error() => {
+                format!("The server returned an error: {}", response.status())
+            },
+            Ok(response) => return Err!("The server returned an error: {}", response.status()),
+            Err(e) if e.is_timeout() || e.is_request() => e.to_string(),
+            Err(e) => return Err(e.into()),
+        };
+
+        if attempt >= MAX_ATTEMPTS {
+            return Err!("{}. Giving up after {} attempts", error, attempt);
+        }
+
+        debug!("Request to {} has failed: {}. Retrying in {:?}...", url, error, delay);
+        thread::sleep(delay);
+
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+/// Tries the providers in the specified order and returns the first successful result along with
+/// a flag whether the rates are official
+pub fn get_rates(
+    providers: &[Box<dyn RateProvider>], currency: &str, start_date: Date, end_date: Date,
+    official_only: bool,
+) -> GenericResult<(CurrencyRates, bool)> {
+    let mut errors = Vec::new();
+
+    for provider in providers {
+        if official_only && !provider.is_official() {
+            continue;
+        }
+
+        match provider.get_rates(currency, start_date, end_date) {
+            Ok(rates) => return Ok((rates, provider.is_official())),
+            Err(e) => {
+                let error = format!("Failed to get currency rates from {}: {}", provider.name(), e);
+                warn!("{}.", error);
+                errors.push(error);
+            },
+        }
+    }
+
+    if errors.is_empty() {
+        return Err!("No {}currency rate providers are configured",
+                    if official_only { "official " } else { "" });
+    }
+
+    Err!("Unable to get {} currency rates from any provider:\n{}", currency, errors.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::Decimal;
+    use super::*;
+
+    struct MockProvider {
+        name: &'static str,
+        price: Option<Decimal>,
+        official: bool,
+    }
+
+    impl RateProvider for MockProvider {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn is_official(&self) -> bool {
+            self.official
+        }
+
+        fn get_rates(&self, _currency: &str, start_date: Date, end_date: Date) -> GenericResult<CurrencyRates> {
+            let price = self.price.ok_or("The server returned an error: 503 Service Unavailable")?;
+            Ok((vec![CurrencyRate {date: start_date, price}], (start_date, end_date)))
+        }
+    }
+
+    #[test]
+    fn offline() {
+        let mut config = Config::mock();
+        config.offline = true;
+
+        let providers = get_providers(&config);
+        let error = get_rates(&providers, "USD", date!(1, 2, 2020), date!(3, 2, 2020), false).unwrap_err();
+        assert_eq!(error.to_string(), concat!(
+            "Unable to get USD currency rates from any provider:\n",
+            "Failed to get currency rates from offline mode: USD currency rates for 01.02.2020 - 03.02.2020 ",
+            "are missing in the cache. Run the command without offline mode to fetch them into the cache",
+        ));
+    }
+
+    #[test]
+    fn retry() {
+        let path = "/retry";
+        let _mock = mockito::mock("GET", path)
+            .with_status(503)
+            .expect(MAX_ATTEMPTS as usize)
+            .create();
+
+        let url = format!("{}{}", mockito::server_url(), path);
+        let error = send_request(&new_client(Duration::from_secs(1)), &url).unwrap_err();
+
+        assert_eq!(error.to_string(), format!(
+            "The server returned an error: 503 Service Unavailable. Giving up after {} attempts",
+            MAX_ATTEMPTS));
+        _mock.assert();
+    }
+
+    #[test]
+    fn fallback() {
+        let (start_date, end_date) = (date!(1, 9, 2018), date!(4, 9, 2018));
+
+        let providers: Vec<Box<dyn RateProvider>> = vec![
+            Box::new(MockProvider {name: "first", price: None, official: true}),
+            Box::new(MockProvider {name: "second", price: Some(dec!(68.0447)), official: false}),
+        ];
+        assert_eq!(get_rates(&providers, "USD", start_date, end_date, false).unwrap(), ((vec![CurrencyRate {
+            date: start_date,
+            price: dec!(68.0447),
+        }], (start_date, end_date)), false));
+
+        // Unofficial rates must not be used for tax calculations
+        assert!(get_rates(&providers, "USD", start_date, end_date, true).is_err());
+
+        let providers: Vec<Box<dyn RateProvider>> = vec![
+            Box::new(MockProvider {name: "first", price: None, official: true}),
+        ];
+        assert!(get_rates(&providers, "USD", start_date, end_date, false).is_err());
+    }
+}
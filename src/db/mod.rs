@@ -1,6 +1,10 @@
 use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
 
 use diesel::{Connection as ConnectionTrait, SqliteConnection};
+use diesel::connection::SimpleConnection;
+use diesel::result::{Error as DieselError, QueryResult};
 #[cfg(test)] use tempfile::NamedTempFile;
 
 use crate::core::GenericResult;
@@ -12,19 +16,91 @@ pub type Connection = Rc<SqliteConnection>;
 
 embed_migrations!();
 
+// Several program instances may be run concurrently, so wait for the lock instead of failing
+// immediately. WAL mode allows readers to not block writers and vice versa.
+const BUSY_TIMEOUT_MS: u32 = 10_000;
+
+const MAX_LOCK_RETRIES: u32 = 5;
+const LOCK_RETRY_DELAY: Duration = Duration::from_millis(100);
+
 pub fn connect(url: &str) -> GenericResult<Connection> {
     let connection = SqliteConnection::establish(url).map_err(|e| format!(
         "Unable to open {:?} database: {}", url, e))?;
 
+    connection.batch_execute(&format!(
+        "PRAGMA busy_timeout = {}; PRAGMA journal_mode = WAL;", BUSY_TIMEOUT_MS
+    )).map_err(|e| format!("Unable to configure {:?} database: {}", url, e))?;
+
     embedded_migrations::run(&connection).map_err(|e| format!(
         "Failed to prepare the database: {}", e))?;
 
     Ok(Rc::new(connection))
 }
 
+/// Retries the operation on transient "database is locked" errors which may be returned despite
+/// the busy timeout (for example, when a read transaction is upgraded to a write one)
+pub fn retry_on_lock<T, F>(mut operation: F) -> QueryResult<T>
+    where F: FnMut() -> QueryResult<T>
+{
+    let mut attempt = 1;
+
+    loop {
+        match operation() {
+            Err(DieselError::DatabaseError(_, ref info))
+                if info.message().contains("database is locked") && attempt < MAX_LOCK_RETRIES => {},
+            result => return result,
+        }
+
+        thread::sleep(LOCK_RETRY_DELAY * attempt);
+        attempt += 1;
+    }
+}
+
 #[cfg(test)]
 pub fn new_temporary() -> (NamedTempFile, Connection) {
     let database = NamedTempFile::new().unwrap();
     let connection = connect(database.path().to_str().unwrap()).unwrap();
     (database, connection)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::{self, prelude::*};
+
+    use super::*;
+    use super::schema::currency_rates;
+
+    #[test]
+    fn concurrent_writers() {
+        let (database, _connection) = new_temporary();
+        let path = database.path().to_str().unwrap().to_owned();
+
+        let writers: Vec<_> = ["USD", "EUR"].iter().map(|&currency| {
+            let path = path.clone();
+
+            thread::spawn(move || {
+                let connection = connect(&path).unwrap();
+
+                for day in 1..=28 {
+                    retry_on_lock(|| connection.transaction::<_, DieselError, _>(|| {
+                        diesel::replace_into(currency_rates::table)
+                            .values(models::NewCurrencyRate {
+                                currency,
+                                date: date!(day, 2, 2020),
+                                price: Some(day.to_string()),
+                            })
+                            .execute(&*connection)
+                    })).unwrap();
+                }
+            })
+        }).collect();
+
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let connection = connect(&path).unwrap();
+        let count: i64 = currency_rates::table.count().get_result(&*connection).unwrap();
+        assert_eq!(count, 56);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::db::schema::{AssetType, assets, currency_rates, quotes};
+use crate::db::schema::{AssetType, assets, currency_rates, quotes, rebalancing, valuations};
 use crate::types::{Date, DateTime};
 
 #[derive(Insertable, Queryable)]
@@ -25,4 +25,20 @@ pub struct NewQuote<'a> {
     pub time: DateTime,
     pub currency: &'a str,
     pub price: String,
+}
+
+#[derive(Insertable)]
+#[table_name="rebalancing"]
+pub struct NewRebalancing<'a> {
+    pub portfolio: &'a str,
+    pub date: Date,
+}
+
+#[derive(Insertable)]
+#[table_name="valuations"]
+pub struct NewValuation<'a> {
+    pub portfolio: &'a str,
+    pub date: Date,
+    pub currency: &'a str,
+    pub value: String,
 }
\ No newline at end of file
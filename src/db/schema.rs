@@ -26,6 +26,13 @@ table! {
     }
 }
 
+table! {
+    rebalancing (portfolio) {
+        portfolio -> Text,
+        date -> Date,
+    }
+}
+
 table! {
     quotes (symbol) {
         symbol -> Text,
@@ -33,4 +40,13 @@ table! {
         currency -> Text,
         price -> Text,
     }
+}
+
+table! {
+    valuations (portfolio, date) {
+        portfolio -> Text,
+        date -> Date,
+        currency -> Text,
+        value -> Text,
+    }
 }
\ No newline at end of file
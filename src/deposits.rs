@@ -3,11 +3,15 @@ use chrono::Duration;
 use static_table_derive::StaticTable;
 
 use crate::analyse::deposit_emulator::{DepositEmulator, Transaction};
+use crate::analyse::xirr;
 use crate::config::DepositConfig;
+use crate::core::GenericResult;
 use crate::currency::{Cash, MultiCurrencyCashAccount};
+use crate::currency::converter::CurrencyConverter;
 use crate::formatting::{self, table::Style};
 use crate::localities;
 use crate::types::{Date, Decimal};
+use crate::util;
 
 pub fn list(mut deposits: Vec<DepositConfig>, today: Date, cron_mode: bool, notify_days: Option<u32>) {
     let mut deposits: Vec<DepositConfig> = deposits.drain(..).filter(|deposit| {
@@ -76,19 +80,28 @@ fn print(deposits: Vec<DepositConfig>, today: Date) {
     table.print("Open deposits");
 }
 
+/// Returns deposits that are going to be closed within the specified number of days sorted by
+/// close date
+pub fn get_closing_deposits(deposits: &[DepositConfig], today: Date, notify_days: u32) -> Vec<&DepositConfig> {
+    let notify_date = today + Duration::days(i64::from(notify_days));
+
+    let mut closing_deposits: Vec<&DepositConfig> = deposits.iter().filter(|deposit| {
+        deposit.open_date <= today && today < deposit.close_date && deposit.close_date <= notify_date
+    }).collect();
+    closing_deposits.sort_by_key(|deposit| deposit.close_date);
+
+    closing_deposits
+}
+
 fn print_cron_mode(deposits: Vec<DepositConfig>, today: Date, notify_days: Option<u32>) {
-    let mut expiring_deposits = Vec::new();
-    let mut closed_deposits = Vec::new();
+    let expiring_deposits = match notify_days {
+        Some(notify_days) => get_closing_deposits(&deposits, today, notify_days),
+        None => Vec::new(),
+    };
 
-    for deposit in deposits {
-        if deposit.close_date <= today {
-            closed_deposits.push(deposit);
-        } else if let Some(notify_days) = notify_days {
-            if today + Duration::days(i64::from(notify_days)) == deposit.close_date {
-                expiring_deposits.push(deposit);
-            }
-        }
-    }
+    let closed_deposits: Vec<&DepositConfig> = deposits.iter().filter(|deposit| {
+        deposit.close_date <= today
+    }).collect();
 
     if !expiring_deposits.is_empty() {
         println!("The following deposits are about to close:");
@@ -118,19 +131,87 @@ fn print_closed_deposit(deposit: &DepositConfig) {
 }
 
 fn calculate_amounts(deposit: &DepositConfig, today: Date) -> (Cash, Cash) {
-    let currency = deposit.currency.as_ref().map_or_else(
-        || localities::russia().currency, String::as_str);
+    let projection = calculate(deposit, today).unwrap();
+    (projection.contributions, projection.balance)
+}
 
-    let mut contributions = vec![(deposit.open_date, deposit.amount)];
-    contributions.extend(&deposit.contributions);
+pub struct DepositProjection {
+    pub contributions: Cash,
+    pub interest: Cash,
+    pub balance: Cash,
+}
 
-    let transactions: Vec<_> = contributions.iter().filter_map(|&(date, amount)| {
-        if date <= today {
-            Some(Transaction::new(date, amount))
-        } else {
-            None
+/// Projects the deposit balance at its close date
+pub fn project(deposit: &DepositConfig) -> GenericResult<DepositProjection> {
+    calculate(deposit, deposit.close_date)
+}
+
+pub struct DepositComparison {
+    pub name: String,
+    pub balance: Cash,
+    pub converted_balance: Cash,
+
+    // Both values are in percents and are calculated in the comparison currency. Effective yield is
+    // an annual rate of return which accounts for capitalization and contributions timing.
+    pub total_return: Decimal,
+    pub effective_yield: Option<Decimal>,
+}
+
+/// Compares deposits by their effective annual yield over the whole term. All deposit cash flows
+/// are converted to the specified currency at their dates, so currency rate changes are taken into
+/// account. The result is sorted from the best deposit to the worst one.
+pub fn compare(
+    deposits: &[DepositConfig], currency: &str, converter: &CurrencyConverter,
+) -> GenericResult<Vec<DepositComparison>> {
+    let mut comparisons = Vec::with_capacity(deposits.len());
+
+    for deposit in deposits {
+        let projection = project(deposit)?;
+
+        let mut cash_flows = Vec::new();
+        let mut converted_contributions = dec!(0);
+
+        for (date, amount) in get_contributions(deposit)? {
+            let amount = converter.convert_to_rounding(
+                date, Cash::new(projection.balance.currency, amount), currency)?;
+
+            cash_flows.push((date, -amount));
+            converted_contributions += amount;
         }
-    }).collect();
+
+        let converted_balance = Cash::new(currency, converter.convert_to_rounding(
+            deposit.close_date, projection.balance, currency)?);
+        cash_flows.push((deposit.close_date, converted_balance.amount));
+
+        let total_return = if converted_contributions.is_zero() {
+            dec!(0)
+        } else {
+            util::round(
+                (converted_balance.amount - converted_contributions) / converted_contributions * dec!(100), 2)
+        };
+
+        comparisons.push(DepositComparison {
+            name: deposit.name.clone(),
+            balance: projection.balance,
+            converted_balance,
+            total_return,
+            effective_yield: xirr::calculate(&cash_flows),
+        });
+    }
+
+    comparisons.sort_by(|a, b| b.effective_yield.cmp(&a.effective_yield));
+
+    Ok(comparisons)
+}
+
+fn calculate(deposit: &DepositConfig, today: Date) -> GenericResult<DepositProjection> {
+    let currency = deposit.currency.as_ref().map_or_else(
+        || localities::russia().currency, String::as_str);
+
+    let transactions: Vec<Transaction> = get_contributions(deposit)?.into_iter()
+        .filter(|&(date, _amount)| date <= today)
+        .map(|(date, amount)| Transaction::new(date, amount))
+        .collect();
 
     let amount = transactions.iter().map(|transaction| transaction.amount).sum();
     let amount = Cash::new(currency, amount);
@@ -141,10 +222,140 @@ fn calculate_amounts(deposit: &DepositConfig, today: Date) -> (Cash, Cash) {
         deposit.close_date
     };
 
-    let current_amount = DepositEmulator::new(deposit.open_date, end_date, deposit.interest)
+    let balance = DepositEmulator::new(deposit.open_date, end_date, deposit.interest)
         .with_monthly_capitalization(deposit.capitalization)
         .emulate(&transactions);
-    let current_amount = Cash::new(currency, current_amount).round();
+    let balance = Cash::new(currency, balance).round();
+
+    Ok(DepositProjection {
+        contributions: amount,
+        interest: Cash::new(currency, balance.amount - amount.amount),
+        balance,
+    })
+}
+
+fn get_contributions(deposit: &DepositConfig) -> GenericResult<Vec<(Date, Decimal)>> {
+    let mut contributions = vec![(deposit.open_date, deposit.amount)];
+    contributions.extend(&deposit.contributions);
+    contributions.sort_by_key(|&(date, _amount)| date);
+
+    for &(date, _amount) in &contributions {
+        if date < deposit.open_date || date > deposit.close_date {
+            return Err!(
+                "Invalid {:?} deposit contribution date: {}",
+                deposit.name, formatting::format_date(date));
+        }
+    }
+
+    Ok(contributions)
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use crate::currency::converter::CurrencyConverterBackend;
+    use super::*;
+
+    #[test]
+    fn closing_deposits() {
+        let deposit = |name: &str, open_date, close_date| DepositConfig {
+            name: name.to_owned(),
+            open_date, close_date,
+            currency: None,
+            amount: dec!(100_000),
+            interest: dec!(5),
+            capitalization: false,
+            contributions: Vec::new(),
+        };
 
-    (amount, current_amount)
-}
\ No newline at end of file
+        let deposits = vec![
+            deposit("Closed", date!(1, 1, 2021), date!(1, 3, 2021)),
+            deposit("Later", date!(1, 1, 2021), date!(15, 3, 2021)),
+            deposit("Not open", date!(5, 3, 2021), date!(10, 3, 2021)),
+            deposit("Sooner", date!(1, 1, 2021), date!(5, 3, 2021)),
+            deposit("Far", date!(1, 1, 2021), date!(16, 3, 2021)),
+        ];
+
+        let closing_deposits: Vec<&str> = get_closing_deposits(&deposits, date!(1, 3, 2021), 14)
+            .iter().map(|deposit| deposit.name.as_str()).collect();
+        assert_eq!(closing_deposits, vec!["Sooner", "Later"]);
+    }
+
+    #[rstest(capitalization, interest,
+        case(false, dec!(1380)),
+        case(true, dec!(1382.79)),
+    )]
+    fn projection(capitalization: bool, interest: Decimal) {
+        let deposit = DepositConfig {
+            name: s!("Deposit"),
+            open_date: date!(1, 1, 2021),
+            close_date: date!(16, 2, 2021),
+            currency: None,
+            amount: dec!(100_000),
+            interest: dec!(7.3),
+            capitalization,
+            contributions: vec![(date!(1, 1, 2021), dec!(50_000))],
+        };
+
+        let projection = project(&deposit).unwrap();
+        assert_eq!(projection.contributions, Cash::new("RUB", dec!(150_000)));
+        assert_eq!(projection.interest, Cash::new("RUB", interest));
+        assert_eq!(projection.balance, Cash::new("RUB", dec!(150_000) + interest));
+    }
+
+    #[test]
+    fn comparison() {
+        struct MockConverterBackend {}
+
+        impl CurrencyConverterBackend for MockConverterBackend {
+            fn convert(&self, from: &str, to: &str, date: Date, amount: Decimal) -> GenericResult<Decimal> {
+                // Deposits are opened and closed with different currency rates
+                let rate = match date {
+                    _ if date == date!(1, 1, 2019) => dec!(60),
+                    _ if date == date!(1, 1, 2020) => dec!(70),
+                    _ => unreachable!(),
+                };
+
+                Ok(match (from, to) {
+                    _ if from == to => amount,
+                    ("USD", "RUB") => amount * rate,
+                    _ => unreachable!(),
+                })
+            }
+        }
+
+        let converter = CurrencyConverter::new_with_backend(Box::new(MockConverterBackend {}));
+
+        let deposit = |name: &str, currency: &str, amount, interest| DepositConfig {
+            name: name.to_owned(),
+            open_date: date!(1, 1, 2019),
+            close_date: date!(1, 1, 2020),
+            currency: Some(currency.to_owned()),
+            amount,
+            interest,
+            capitalization: false,
+            contributions: Vec::new(),
+        };
+
+        let deposits = vec![
+            deposit("Dollar", "USD", dec!(1000), dec!(3)),
+            deposit("Ruble", "RUB", dec!(100_000), dec!(10)),
+        ];
+
+        let comparisons = compare(&deposits, "RUB", &converter).unwrap();
+        let names: Vec<&str> = comparisons.iter().map(|comparison| comparison.name.as_str()).collect();
+        assert_eq!(names, vec!["Dollar", "Ruble"]);
+
+        let comparison = &comparisons[0];
+        assert_eq!(comparison.balance, Cash::new("USD", dec!(1030)));
+        assert_eq!(comparison.converted_balance, Cash::new("RUB", dec!(72100)));
+        assert_eq!(comparison.total_return, dec!(20.17));
+        assert_eq!(comparison.effective_yield, Some(dec!(20.17)));
+
+        let comparison = &comparisons[1];
+        assert_eq!(comparison.balance, Cash::new("RUB", dec!(110_000)));
+        assert_eq!(comparison.converted_balance, Cash::new("RUB", dec!(110_000)));
+        assert_eq!(comparison.total_return, dec!(10));
+        assert_eq!(comparison.effective_yield, Some(dec!(10)));
+    }
+}
@@ -9,9 +9,18 @@ use crate::types::{Date, Decimal};
 pub struct Country {
     pub currency: &'static str,
     tax_rate: Decimal,
+    progressive_tax: Option<ProgressiveTax>,
     tax_precision: u32,
 }
 
+// Annual income which exceeds the threshold is taxed by the increased rate
+#[derive(Clone, Copy)]
+struct ProgressiveTax {
+    since_year: i32,
+    threshold: Decimal,
+    tax_rate: Decimal,
+}
+
 impl Country {
     // When we work with taxes in Russia, the following rounding rules are applied:
     // 1. Result of all calculations must be with kopecks precision
@@ -31,6 +40,35 @@ impl Country {
         currency::round_to(currency::round(tax), self.tax_precision)
     }
 
+    /// Returns tax rate for income which is received when the specified annual income has been
+    /// already reached
+    pub fn tax_rate(&self, year: i32, annual_income: Decimal) -> Decimal {
+        match self.progressive_tax {
+            Some(tax) if year >= tax.since_year && annual_income >= tax.threshold => tax.tax_rate,
+            _ => self.tax_rate,
+        }
+    }
+
+    /// Calculates tax for the total taxable income received during the year
+    pub fn annual_tax_to_pay(&self, year: i32, income: Decimal) -> Decimal {
+        let income = currency::round(income);
+
+        if income.is_sign_negative() || income.is_zero() {
+            return dec!(0);
+        }
+
+        let (base_income, increased_income) = match self.progressive_tax {
+            Some(tax) if year >= tax.since_year && income > tax.threshold => {
+                (tax.threshold, income - tax.threshold)
+            },
+            _ => (income, dec!(0)),
+        };
+
+        self.round_tax(
+            base_income * self.tax_rate(year, dec!(0)) +
+            increased_income * self.tax_rate(year, income))
+    }
+
     pub fn tax_to_pay(&self, income: Decimal, paid_tax: Option<Decimal>) -> Decimal {
         let income = currency::round(income);
 
@@ -59,10 +97,25 @@ pub fn russia() -> Country {
     Country {
         currency: "RUB",
         tax_rate: Decimal::new(13, 2),
+        progressive_tax: Some(ProgressiveTax {
+            since_year: 2021,
+            threshold: dec!(5_000_000),
+            tax_rate: Decimal::new(15, 2),
+        }),
         tax_precision: 0,
     }
 }
 
+/// Returns dividend tax rate according to the double taxation treaty with the country which
+/// securities are traded in the specified currency
+pub fn get_dividend_treaty_tax_rate(currency: &str) -> Option<Decimal> {
+    match currency {
+        // W-8BEN form must be filled to get the treaty rate
+        "USD" => Some(Decimal::new(10, 2)),
+        _ => None,
+    }
+}
+
 pub fn is_valid_execution_date(conclusion: Date, execution: Date) -> bool {
     let expected_execution = conclusion + Duration::days(2);
     conclusion <= execution && get_russian_stock_exchange_min_last_working_day(execution) <= expected_execution
@@ -84,4 +137,30 @@ pub fn get_russian_stock_exchange_min_last_working_day(today: Date) -> Date {
     } else {
         today - Duration::days(3)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use super::*;
+
+    #[rstest(year, income, expected_tax,
+        case(2020, dec!(6_000_000), dec!(780_000)),
+        case(2021, dec!(4_999_999), dec!(650_000)),
+        case(2021, dec!(5_000_000), dec!(650_000)),
+        case(2021, dec!(5_000_001), dec!(650_000)),
+        case(2021, dec!(6_000_000), dec!(800_000)),
+    )]
+    fn progressive_tax(year: i32, income: Decimal, expected_tax: Decimal) {
+        let country = russia();
+        assert_eq!(country.annual_tax_to_pay(year, income), expected_tax);
+    }
+
+    #[test]
+    fn progressive_tax_rate() {
+        let country = russia();
+        assert_eq!(country.tax_rate(2020, dec!(5_000_000)), dec!(0.13));
+        assert_eq!(country.tax_rate(2021, dec!(4_999_999)), dec!(0.13));
+        assert_eq!(country.tax_rate(2021, dec!(5_000_000)), dec!(0.15));
+    }
 }
\ No newline at end of file
@@ -1,8 +1,8 @@
-use std::collections::{HashSet, HashMap};
+use std::collections::HashMap;
 
 use crate::brokers::BrokerInfo;
 use crate::config::{Config, PortfolioConfig, AssetAllocationConfig};
-use crate::core::{EmptyResult, GenericResult};
+use crate::core::GenericResult;
 use crate::currency::Cash;
 use crate::currency::converter::CurrencyConverter;
 use crate::quotes::Quotes;
@@ -17,18 +17,31 @@ pub struct Portfolio {
 
     pub min_trade_volume: Decimal,
     pub min_cash_assets: Decimal,
+    pub allow_margin_debt: bool,
+    pub avoid_selling: bool,
 
     pub assets: Vec<AssetAllocation>,
     pub current_cash_assets: Decimal,
     pub target_cash_assets: Decimal,
     pub commissions: Decimal,
     pub total_value: Decimal,
+
+    pub unavailable_prices: Vec<UnavailablePrice>,
+}
+
+/// A stock which price couldn't be obtained. Such stocks are valued as zero.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnavailablePrice {
+    pub symbol: String,
+    pub error: String,
 }
 
 impl Portfolio {
+    // If `allow_unavailable_prices` is set, quote errors don't abort the loading, but are collected
+    // into `unavailable_prices` instead.
     pub fn load(
         config: &Config, portfolio_config: &PortfolioConfig, assets: Assets,
-        converter: &CurrencyConverter, quotes: &Quotes
+        converter: &CurrencyConverter, quotes: &Quotes, allow_unavailable_prices: bool,
     ) -> GenericResult<Portfolio> {
         let currency = match portfolio_config.currency.as_ref() {
             Some(currency) => currency,
@@ -57,25 +70,34 @@ impl Portfolio {
 
         let mut portfolio = Portfolio {
             name: portfolio_config.name.clone(),
-            broker: portfolio_config.broker.get_info(config, portfolio_config.plan.as_ref())?,
+            broker: portfolio_config.get_broker_info(config)?,
             currency: currency.clone(),
 
             min_trade_volume: min_trade_volume,
             min_cash_assets: min_cash_assets,
+            allow_margin_debt: portfolio_config.allow_margin_debt,
+            avoid_selling: portfolio_config.avoid_selling,
 
             assets: Vec::new(),
             current_cash_assets: cash_assets,
             target_cash_assets: cash_assets,
             commissions: dec!(0),
             total_value: cash_assets,
+
+            unavailable_prices: Vec::new(),
         };
 
         let mut stocks = assets.stocks;
-        let mut symbols = HashSet::new();
+        let mut unavailable_prices = Vec::new();
 
         for assets_config in &portfolio_config.assets {
             let mut asset_allocation = AssetAllocation::load(
-                assets_config, &currency, &mut symbols, &mut stocks, converter, quotes)?;
+                assets_config, &currency, min_trade_volume, &mut stocks,
+                converter, quotes, if allow_unavailable_prices {
+                    Some(&mut unavailable_prices)
+                } else {
+                    None
+                })?;
 
             asset_allocation.apply_restrictions(
                 portfolio_config.restrict_buying, portfolio_config.restrict_selling);
@@ -83,7 +105,6 @@ impl Portfolio {
             portfolio.total_value += asset_allocation.current_value;
             portfolio.assets.push(asset_allocation);
         }
-        check_weights(&portfolio.name, &portfolio.assets)?;
 
         if !stocks.is_empty() {
             let mut missing_symbols: Vec<String> = stocks.keys().cloned().collect();
@@ -94,9 +115,22 @@ impl Portfolio {
                     missing_symbols.join(", "));
         }
 
+        portfolio.unavailable_prices = unavailable_prices;
+
         Ok(portfolio)
     }
 
+    // Negative cash assets (margin debt) are treated as negative investable cash: by default
+    // rebalancing sells the assets to cover the debt, but if the debt is allowed it's kept as is and
+    // only buying on margin is prohibited.
+    pub fn get_min_cash_assets(&self) -> Decimal {
+        if self.allow_margin_debt && self.current_cash_assets.is_sign_negative() {
+            self.current_cash_assets
+        } else {
+            self.min_cash_assets
+        }
+    }
+
     pub fn change_commission(&mut self, commission: Decimal) {
         // The commission may be positive in case of withdrawal or negative in case of reverting of
         // previously withdrawn commission.
@@ -124,6 +158,7 @@ pub struct AssetAllocation {
     pub name: String,
 
     pub expected_weight: Decimal,
+    pub min_trade_volume: Decimal,
     pub restrict_buying: Option<bool>,
     pub restrict_selling: Option<bool>,
 
@@ -146,21 +181,39 @@ impl AssetAllocation {
         }
     }
 
+    /// Full name prefixed with names of all parent groups to locate the asset in nested asset
+    /// allocation tree
+    pub fn full_path(&self, parent_path: Option<&str>) -> String {
+        match parent_path {
+            Some(parent_path) => format!("{} » {}", parent_path, self.full_name()),
+            None => self.full_name(),
+        }
+    }
+
     fn load(
-        config: &AssetAllocationConfig, currency: &str,
-        symbols: &mut HashSet<String>, stocks: &mut HashMap<String, u32>,
-        converter: &CurrencyConverter, quotes: &Quotes,
+        config: &AssetAllocationConfig, currency: &str, min_trade_volume: Decimal,
+        stocks: &mut HashMap<String, u32>, converter: &CurrencyConverter, quotes: &Quotes,
+        mut unavailable_prices: Option<&mut Vec<UnavailablePrice>>,
     ) -> GenericResult<AssetAllocation> {
+        let min_trade_volume = config.min_trade_volume.unwrap_or(min_trade_volume);
+
         let (holding, current_value) = match (&config.symbol, &config.assets) {
             (Some(symbol), None) => {
-                if !symbols.insert(symbol.clone()) {
-                    return Err!("Invalid asset allocation configuration: Duplicated symbol: {}",
-                        symbol);
-                }
-
-                let currency_price = quotes.get(symbol)?;
-                let price = converter.real_time_convert_to(currency_price, currency)?;
+                let (currency_price, price) = match (quotes.get(symbol), unavailable_prices) {
+                    (Ok(currency_price), _) => {
+                        (currency_price, converter.real_time_convert_to(currency_price, currency)?)
+                    },
+                    (Err(e), Some(unavailable_prices)) => {
+                        unavailable_prices.push(UnavailablePrice {
+                            symbol: symbol.clone(),
+                            error: e.to_string(),
+                        });
+                        (Cash::new(currency, dec!(0)), dec!(0))
+                    },
+                    (Err(e), None) => return Err(e),
+                };
 
+                // Symbols are unique across the tree: it's checked on config loading
                 let shares = stocks.remove(symbol).unwrap_or(0);
                 let current_value = Decimal::from(shares) * price;
 
@@ -180,14 +233,13 @@ impl AssetAllocation {
 
                 for asset in assets {
                     let holding = AssetAllocation::load(
-                        asset, currency, symbols, stocks, converter, quotes)?;
+                        asset, currency, min_trade_volume, stocks, converter, quotes,
+                        unavailable_prices.as_deref_mut())?;
 
                     current_value += holding.current_value;
                     holdings.push(holding);
                 }
 
-                check_weights(&config.name, &holdings)?;
-
                 (Holding::Group(holdings), current_value)
             },
             _ => return Err!(
@@ -199,6 +251,7 @@ impl AssetAllocation {
             name: config.name.clone(),
 
             expected_weight: config.weight,
+            min_trade_volume: min_trade_volume,
             restrict_buying: None,
             restrict_selling: None,
 
@@ -218,6 +271,8 @@ impl AssetAllocation {
         Ok(asset_allocation)
     }
 
+    // Restrictions are applied bottom-up (asset's own restrictions first, then its parent groups'
+    // ones), so explicitly specified restrictions are never overridden by the inherited ones
     fn apply_restrictions(&mut self, restrict_buying: Option<bool>, restrict_selling: Option<bool>) {
         if let Some(restrict) = restrict_buying {
             self.apply_buying_restriction(restrict);
@@ -256,18 +311,3 @@ impl AssetAllocation {
         }
     }
 }
-
-fn check_weights(name: &str, assets: &[AssetAllocation]) -> EmptyResult {
-    let mut weight = dec!(0);
-
-    for asset in assets {
-        weight += asset.expected_weight;
-    }
-
-    if weight != dec!(1) {
-        return Err!("{:?} assets have unbalanced weights: {}% total",
-            name, (weight * dec!(100)).normalize());
-    }
-
-    Ok(())
-}
\ No newline at end of file
@@ -23,6 +23,16 @@ impl Assets {
         }
     }
 
+    pub fn merge(&mut self, other: Assets) {
+        for cash in other.cash.iter() {
+            self.cash.deposit(cash);
+        }
+
+        for (symbol, quantity) in other.stocks {
+            *self.stocks.entry(symbol).or_default() += quantity;
+        }
+    }
+
     pub fn load(database: db::Connection, portfolio: &str) -> GenericResult<Assets> {
         let assets = assets::table.filter(assets::portfolio.eq(portfolio))
             .load::<models::Asset>(&*database)?;
@@ -159,4 +169,38 @@ mod tests {
         assert_eq!(Assets::load(connection.clone(), "second").unwrap(), third_assets);
         assert_eq!(Assets::load(connection.clone(), "third").unwrap(), second_assets);
     }
+
+    #[test]
+    fn merge() {
+        let mut assets = {
+            let mut cash = MultiCurrencyCashAccount::new();
+            cash.deposit(Cash::new("USD", dec!(100)));
+
+            Assets::new(cash, hashmap!{
+                s!("AAA") => 10,
+                s!("BBB") => 20,
+            })
+        };
+
+        assets.merge({
+            let mut cash = MultiCurrencyCashAccount::new();
+            cash.deposit(Cash::new("USD", dec!(20)));
+            cash.deposit(Cash::new("RUB", dec!(1000)));
+
+            Assets::new(cash, hashmap!{
+                s!("BBB") => 5,
+                s!("CCC") => 1,
+            })
+        });
+
+        let mut cash = MultiCurrencyCashAccount::new();
+        cash.deposit(Cash::new("USD", dec!(120)));
+        cash.deposit(Cash::new("RUB", dec!(1000)));
+
+        assert_eq!(assets, Assets::new(cash, hashmap!{
+            s!("AAA") => 10,
+            s!("BBB") => 25,
+            s!("CCC") => 1,
+        }));
+    }
 }
\ No newline at end of file
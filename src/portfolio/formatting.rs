@@ -4,18 +4,21 @@ use ansi_term::{Style, Color, ANSIString};
 use num_traits::{ToPrimitive, Zero};
 
 use crate::currency::Cash;
-use crate::types::Decimal;
+use crate::types::{Decimal, TradeType};
 use crate::util;
 
 use super::asset_allocation::{Portfolio, AssetAllocation, Holding};
+use super::rebalancing::RebalancingPlan;
+
+pub fn print_portfolio(portfolio: Portfolio, plan: Option<RebalancingPlan>, flat: bool) {
+    let min_cash_assets = portfolio.get_min_cash_assets();
 
-pub fn print_portfolio(portfolio: Portfolio, flat: bool) {
     let mut assets = portfolio.assets;
     if flat {
         assets = flatify(assets, dec!(1));
     }
 
-    print_assets(assets, portfolio.total_value - portfolio.min_cash_assets, &portfolio.currency, 0);
+    print_assets(assets, portfolio.total_value - min_cash_assets, &portfolio.currency, 0);
 
     println!("\n{} {}", colorify_title("Total value:"),
              format_cash(&portfolio.currency, portfolio.total_value));
@@ -31,6 +34,32 @@ pub fn print_portfolio(portfolio: Portfolio, flat: bool) {
         println!("{} {}", colorify_title("Commissions:"),
                  colorify_commission(&format_cash(&portfolio.currency, portfolio.commissions)));
     }
+
+    let plan = match plan {
+        Some(plan) => plan,
+        None => return,
+    };
+
+    if !plan.uninvested_cash.is_zero() {
+        print!("{} {}", colorify_title("Uninvested cash:"),
+               format_cash(&portfolio.currency, plan.uninvested_cash));
+        if let Some(reason) = plan.uninvested_cash_reason {
+            print!(" ({})", reason.description());
+        }
+        println!();
+    }
+
+    if !plan.trades.is_empty() {
+        println!("\n{}", colorify_title("Orders:"));
+
+        for trade in plan.trades {
+            let colorify_func = match trade.trade_type() {
+                TradeType::Buy => colorify_buy,
+                TradeType::Sell => colorify_sell,
+            };
+            println!("{:>2} {}", '•', colorify_func(&trade.to_string()));
+        }
+    }
 }
 
 fn flatify(assets: Vec<AssetAllocation>, expected_weight: Decimal) -> Vec<AssetAllocation> {
@@ -1,40 +1,71 @@
 use std::rc::Rc;
 
+use log::warn;
+
 use crate::broker_statement::BrokerStatement;
+use crate::brokers::BrokerInfo;
 use crate::config::{Config, PortfolioConfig};
-use crate::core::EmptyResult;
+use crate::core::{EmptyResult, GenericResult};
 use crate::currency::Cash;
 use crate::currency::converter::CurrencyConverter;
 use crate::db;
 use crate::quotes::Quotes;
+use crate::taxes::TaxRemapping;
 use crate::types::Decimal;
+use crate::util;
 
 use self::asset_allocation::Portfolio;
 use self::assets::Assets;
 use self::formatting::print_portfolio;
+use self::report::get_valuation_report;
+use self::schedule::RebalanceSchedule;
+use self::valuations::Valuations;
 
 mod asset_allocation;
 mod assets;
 mod formatting;
+mod orders;
 mod rebalancing;
+mod report;
+mod schedule;
+mod valuations;
+
+pub use self::orders::{PendingOrder, TradeAction};
+pub use self::rebalancing::{RebalancingPlan, UninvestedCashReason, write_plan};
+pub use self::asset_allocation::UnavailablePrice;
+pub use self::report::{ValuationReport, AssetValuation, Valuation};
 
 pub fn sync(config: &Config, portfolio_name: &str) -> EmptyResult {
     let portfolio = config.get_portfolio(portfolio_name)?;
-    let broker = portfolio.broker.get_info(config, portfolio.plan.as_ref())?;
     let database = db::connect(&config.db_path)?;
 
-    let statement = BrokerStatement::read(
-        broker, &portfolio.statements, &portfolio.symbol_remapping, &portfolio.instrument_names,
-        portfolio.get_tax_remapping()?, false)?;
-    statement.check_date();
+    let mut assets = read_assets(
+        portfolio, portfolio.get_broker_info(config)?, &portfolio.statements,
+        portfolio.get_tax_remapping()?)?;
+
+    for account in &portfolio.accounts {
+        assets.merge(read_assets(
+            portfolio, account.get_broker_info(config)?, &account.statements, TaxRemapping::new())?);
+    }
 
-    let assets = Assets::new(statement.cash_assets, statement.open_positions);
     assets.validate(&portfolio)?;
     assets.save(database, &portfolio.name)?;
 
     Ok(())
 }
 
+fn read_assets(
+    portfolio: &PortfolioConfig, broker: BrokerInfo, statements_path: &str,
+    tax_remapping: TaxRemapping,
+) -> GenericResult<Assets> {
+    let statement = BrokerStatement::read(
+        broker, statements_path, &portfolio.symbol_remapping, &portfolio.instrument_names,
+        tax_remapping, false)?;
+    statement.check_date();
+
+    Ok(Assets::new(statement.cash_assets, statement.open_positions))
+}
+
 pub fn buy(config: &Config, portfolio_name: &str, shares: u32, symbol: &str, cash_assets: Decimal) -> EmptyResult {
     modify_assets(config, portfolio_name, |portfolio, assets| {
         if portfolio.get_stock_symbols().get(symbol).is_none() {
@@ -97,29 +128,154 @@ fn set_cash_assets_impl(portfolio: &PortfolioConfig, assets: &mut Assets, cash_a
 }
 
 pub fn show(config: &Config, portfolio_name: &str, flat: bool) -> EmptyResult {
-    process(config, portfolio_name, false, flat)
+    process(config, portfolio_name, Action::Show, flat)
 }
 
-pub fn rebalance(config: &Config, portfolio_name: &str, flat: bool) -> EmptyResult {
-    process(config, portfolio_name, true, flat)
+pub fn rebalance(
+    config: &Config, portfolio_name: &str, pending_orders: &[PendingOrder], flat: bool, force: bool,
+) -> EmptyResult {
+    let portfolio_config = config.get_portfolio(portfolio_name)?;
+
+    let interval = match portfolio_config.rebalance_interval {
+        Some(interval) => interval,
+        None => return process(config, portfolio_name, Action::Rebalance {pending_orders}, flat),
+    };
+
+    let database = db::connect(&config.db_path)?;
+    let schedule = RebalanceSchedule::new(database, &portfolio_config.name, interval);
+    let today = util::today();
+
+    if !force && !schedule.is_due(today)? {
+        warn!("{} portfolio rebalancing is not due until {} (use --force to rebalance it anyway).",
+              portfolio_config.name, schedule.get_next_date()?.unwrap().format("%d.%m.%Y"));
+        return process(config, portfolio_name, Action::Show, flat);
+    }
+
+    process(config, portfolio_name, Action::Rebalance {pending_orders}, flat)?;
+    schedule.save(today)
 }
 
-fn process(config: &Config, portfolio_name: &str, rebalance: bool, flat: bool) -> EmptyResult {
+/// Plans investment of the specified cash into the portfolio without selling any assets
+pub fn invest(config: &Config, portfolio_name: &str, cash: Decimal, flat: bool) -> EmptyResult {
+    process(config, portfolio_name, Action::Invest {cash}, flat)
+}
+
+/// Values the portfolio. Positions which prices are unavailable (for example, delisted stocks) don't
+/// abort the valuation: they are valued as zero and reported in `ValuationReport::unavailable_prices`.
+pub fn valuation(config: &Config, portfolio_name: &str) -> GenericResult<ValuationReport> {
     let portfolio_config = config.get_portfolio(portfolio_name)?;
     let database = db::connect(&config.db_path)?;
+    let (portfolio, converter) = load(config, portfolio_config, database, true)?;
+    get_valuation_report(&portfolio, &converter)
+}
 
-    let quotes = Rc::new(Quotes::new(&config, database.clone())?);
-    let converter = CurrencyConverter::new(database.clone(), Some(quotes.clone()), false);
+/// Returns the portfolio assets which weight has drifted from the target one by more than the
+/// configured rebalance threshold
+pub fn drifted_assets(config: &Config, portfolio_name: &str) -> GenericResult<Vec<AssetValuation>> {
+    let threshold = get_rebalance_threshold(config, portfolio_name)?;
+    let report = valuation(config, portfolio_name)?;
+    Ok(report.get_drifted_assets(threshold).into_iter().cloned().collect())
+}
 
-    let assets = Assets::load(database, &portfolio_config.name)?;
-    assets.validate(&portfolio_config)?;
+/// Prints the drifted assets. Fails if some positions couldn't be valued, because their drift can't
+/// be checked.
+pub fn check_drift(config: &Config, portfolio_name: &str) -> EmptyResult {
+    let threshold = get_rebalance_threshold(config, portfolio_name)?;
+    let report = valuation(config, portfolio_name)?;
 
-    let mut portfolio = Portfolio::load(config, portfolio_config, assets, &converter, &quotes)?;
-    if rebalance {
-        rebalancing::rebalance_portfolio(&mut portfolio, &converter)?;
+    let drifted_assets = report.get_drifted_assets(threshold);
+    if !drifted_assets.is_empty() {
+        println!("The following assets have drifted beyond the rebalance threshold:");
+        for asset in drifted_assets {
+            println!(
+                "• {name}: {weight}% (target {target_weight}%)",
+                name=asset.name, weight=util::round(asset.weight * dec!(100), 1),
+                target_weight=util::round(asset.target_weight * dec!(100), 1));
+        }
     }
 
-    print_portfolio(portfolio, flat);
+    if !report.is_complete() {
+        let symbols: Vec<&str> = report.unavailable_prices.iter()
+            .map(|price| price.symbol.as_str()).collect();
+        return Err!(
+            "Unable to check drift of assets with unavailable prices: {}",
+            symbols.join(", "));
+    }
 
     Ok(())
+}
+
+fn get_rebalance_threshold(config: &Config, portfolio_name: &str) -> GenericResult<Decimal> {
+    let portfolio_config = config.get_portfolio(portfolio_name)?;
+    Ok(portfolio_config.rebalance_threshold.ok_or_else(|| format!(
+        "Rebalance threshold is not specified for {:?} portfolio", portfolio_config.name))?)
+}
+
+/// Calculates rebalancing plan for the portfolio without printing it. If cash to invest is
+/// specified, plans its investment instead of full rebalancing (see `invest`).
+pub fn rebalancing_plan(
+    config: &Config, portfolio_name: &str, pending_orders: &[PendingOrder],
+    cash_to_invest: Option<Decimal>,
+) -> GenericResult<RebalancingPlan> {
+    let portfolio_config = config.get_portfolio(portfolio_name)?;
+    let database = db::connect(&config.db_path)?;
+    let (mut portfolio, converter) = load(config, portfolio_config, database, false)?;
+
+    rebalancing::apply_pending_orders(&mut portfolio, pending_orders)?;
+    plan(&mut portfolio, &converter, cash_to_invest)
+}
+
+fn plan(
+    portfolio: &mut Portfolio, converter: &CurrencyConverter, cash_to_invest: Option<Decimal>,
+) -> GenericResult<RebalancingPlan> {
+    match cash_to_invest {
+        Some(cash) => rebalancing::invest_cash(portfolio, converter, cash),
+        None => rebalancing::rebalance_portfolio(portfolio, converter),
+    }
+}
+
+enum Action<'a> {
+    Show,
+    Rebalance {pending_orders: &'a [PendingOrder]},
+    Invest {cash: Decimal},
+}
+
+fn process(config: &Config, portfolio_name: &str, action: Action, flat: bool) -> EmptyResult {
+    let portfolio_config = config.get_portfolio(portfolio_name)?;
+    let database = db::connect(&config.db_path)?;
+    let (mut portfolio, converter) = load(config, portfolio_config, database.clone(), false)?;
+
+    if let Some(frequency) = portfolio_config.valuation_frequency {
+        Valuations::new(database, &portfolio.name).save_if_missing(util::today(), frequency, || {
+            Ok(Cash::new(&portfolio.currency, portfolio.total_value))
+        })?;
+    }
+
+    let plan = match action {
+        Action::Show => None,
+        Action::Rebalance {pending_orders} => {
+            rebalancing::apply_pending_orders(&mut portfolio, pending_orders)?;
+            Some(plan(&mut portfolio, &converter, None)?)
+        },
+        Action::Invest {cash} => Some(plan(&mut portfolio, &converter, Some(cash))?),
+    };
+
+    print_portfolio(portfolio, plan, flat);
+
+    Ok(())
+}
+
+fn load(
+    config: &Config, portfolio_config: &PortfolioConfig, database: db::Connection,
+    allow_unavailable_prices: bool,
+) -> GenericResult<(Portfolio, CurrencyConverter)> {
+    let quotes = Rc::new(Quotes::new(&config, database.clone())?);
+    let converter = CurrencyConverter::new(config, database.clone(), Some(quotes.clone()), false);
+
+    let assets = Assets::load(database, &portfolio_config.name)?;
+    assets.validate(&portfolio_config)?;
+
+    let portfolio = Portfolio::load(
+        config, portfolio_config, assets, &converter, &quotes, allow_unavailable_prices)?;
+    Ok((portfolio, converter))
 }
\ No newline at end of file
@@ -0,0 +1,83 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::GenericError;
+use crate::types::{Decimal, TradeType};
+
+/// An order that has been placed but isn't executed yet
+pub struct PendingOrder {
+    pub symbol: String,
+    pub action: TradeType,
+    pub quantity: u32,
+}
+
+impl FromStr for PendingOrder {
+    type Err = GenericError;
+
+    // Parses orders in $action:$quantity:$symbol format
+    fn from_str(order: &str) -> Result<PendingOrder, GenericError> {
+        let parts: Vec<&str> = order.split(':').collect();
+        if parts.len() != 3 {
+            return Err!("Invalid pending order: {:?}", order);
+        }
+
+        let action = match parts[0] {
+            "buy" => TradeType::Buy,
+            "sell" => TradeType::Sell,
+            _ => return Err!("Invalid pending order action: {:?}", parts[0]),
+        };
+
+        let quantity = match parts[1].parse::<u32>() {
+            Ok(quantity) if quantity > 0 => quantity,
+            _ => return Err!("Invalid pending order quantity: {:?}", parts[1]),
+        };
+
+        let symbol = parts[2];
+        if symbol.is_empty() {
+            return Err!("Invalid pending order: {:?}", order);
+        }
+
+        Ok(PendingOrder {
+            symbol: symbol.to_owned(),
+            action, quantity,
+        })
+    }
+}
+
+/// A trade which has to be executed to bring the portfolio to its target state
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum TradeAction {
+    Buy {symbol: String, shares: u32, volume: Decimal},
+    Sell {symbol: String, shares: u32, volume: Decimal},
+}
+
+impl TradeAction {
+    pub fn trade_type(&self) -> TradeType {
+        match self {
+            TradeAction::Buy {..} => TradeType::Buy,
+            TradeAction::Sell {..} => TradeType::Sell,
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        match self {
+            TradeAction::Buy {symbol, ..} | TradeAction::Sell {symbol, ..} => symbol,
+        }
+    }
+}
+
+// Formats the trade in pending order format, so it can be passed back to rebalancing after placing
+// the order
+impl fmt::Display for TradeAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (action, symbol, shares) = match self {
+            TradeAction::Buy {symbol, shares, ..} => ("buy", symbol, shares),
+            TradeAction::Sell {symbol, shares, ..} => ("sell", symbol, shares),
+        };
+
+        write!(f, "{}:{}:{}", action, shares, symbol)
+    }
+}
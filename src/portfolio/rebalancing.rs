@@ -1,32 +1,173 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
 
 use log::{self, log_enabled, debug};
 use num_traits::{FromPrimitive, ToPrimitive, Zero};
+use serde::{Deserialize, Serialize};
 
 use crate::brokers::BrokerInfo;
 use crate::commissions::CommissionCalc;
 use crate::core::{GenericResult, EmptyResult};
+use crate::currency::Cash;
 use crate::currency::converter::CurrencyConverter;
 use crate::types::{Decimal, TradeType};
 use crate::util;
 
 use super::asset_allocation::{Portfolio, AssetAllocation, Holding, StockHolding};
+use super::orders::{PendingOrder, TradeAction};
+
+/// Pending orders are treated as already executed, so rebalancing calculates only the remaining
+/// trades
+pub fn apply_pending_orders(portfolio: &mut Portfolio, orders: &[PendingOrder]) -> EmptyResult {
+    for order in orders {
+        let volume = apply_pending_order(&mut portfolio.assets, order)?.ok_or_else(|| format!(
+            "Got a pending order for {} which is missing in asset allocation configuration",
+            order.symbol))?;
+
+        portfolio.current_cash_assets -= volume;
+        portfolio.target_cash_assets -= volume;
+    }
+
+    Ok(())
+}
+
+// Returns the order volume (negative for sell orders) if the order's stock has been found
+fn apply_pending_order(assets: &mut Vec<AssetAllocation>, order: &PendingOrder) -> GenericResult<Option<Decimal>> {
+    for asset in assets {
+        let volume = match asset.holding {
+            Holding::Stock(ref mut holding) => {
+                if holding.symbol != order.symbol {
+                    continue;
+                }
+
+                let shares = match order.action {
+                    TradeType::Buy => holding.current_shares + order.quantity,
+                    TradeType::Sell => holding.current_shares.checked_sub(order.quantity).ok_or_else(|| format!(
+                        "Unable to apply pending sell order for {} shares of {}: the portfolio contains only {} shares",
+                        order.quantity, order.symbol, holding.current_shares))?,
+                };
+
+                let volume = holding.price * (Decimal::from(shares) - Decimal::from(holding.current_shares));
+                holding.current_shares = shares;
+                holding.target_shares = shares;
+
+                volume
+            },
+            Holding::Group(ref mut holdings) => match apply_pending_order(holdings, order)? {
+                Some(volume) => volume,
+                None => continue,
+            },
+        };
+
+        asset.current_value += volume;
+        asset.target_value += volume;
+
+        return Ok(Some(volume));
+    }
+
+    Ok(None)
+}
+
+// Decimals are serialized as strings to preserve their precision
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct RebalancingPlan {
+    // Sell trades go first to free up cash for the buy trades
+    pub trades: Vec<TradeAction>,
+    // Estimated commissions of all plan's trades
+    pub commissions: Decimal,
+    // Free cash which isn't deployed by the plan
+    pub uninvested_cash: Decimal,
+    pub uninvested_cash_reason: Option<UninvestedCashReason>,
+    // Full names of the traded assets (including their parent groups) by symbol
+    pub asset_names: BTreeMap<String, String>,
+}
+
+/// Explains why free cash is left uninvested
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UninvestedCashReason {
+    // All assets are blocked for buying by restrictions or zero weight
+    AllBuysBlocked,
+    // The cash isn't enough for the cheapest possible trade (a single share or minimum trade volume)
+    BelowMinTrade,
+}
+
+impl UninvestedCashReason {
+    pub fn description(self) -> &'static str {
+        match self {
+            UninvestedCashReason::AllBuysBlocked => "all assets are blocked for buying",
+            UninvestedCashReason::BelowMinTrade => "the cash isn't enough for any trade",
+        }
+    }
+}
+
+pub fn write_plan<W: Write>(plan: &RebalancingPlan, writer: W) -> EmptyResult {
+    serde_json::to_writer_pretty(writer, plan).map_err(|e| format!(
+        "Failed to serialize the rebalancing plan: {}", e))?;
+    Ok(())
+}
+
+pub fn rebalance_portfolio(portfolio: &mut Portfolio, converter: &CurrencyConverter) -> GenericResult<RebalancingPlan> {
+    rebalance_portfolio_impl(portfolio, converter, false)
+}
+
+/// Plans investment of the specified cash (a new deposit, for example) into the portfolio. Unlike
+/// full rebalancing, the cash is allocated only toward underweight assets and nothing is sold. The
+/// cash left after rounding to whole shares is reported as uninvested cash of the plan.
+pub fn invest_cash(
+    portfolio: &mut Portfolio, converter: &CurrencyConverter, cash_to_invest: Decimal,
+) -> GenericResult<RebalancingPlan> {
+    if cash_to_invest <= dec!(0) {
+        return Err!("Invalid cash to invest: {}", cash_to_invest);
+    }
+
+    portfolio.current_cash_assets += cash_to_invest;
+    portfolio.target_cash_assets += cash_to_invest;
+    portfolio.total_value += cash_to_invest;
+
+    rebalance_portfolio_impl(portfolio, converter, true)
+}
+
+fn rebalance_portfolio_impl(
+    portfolio: &mut Portfolio, converter: &CurrencyConverter, forbid_selling: bool,
+) -> GenericResult<RebalancingPlan> {
+    let min_cash_assets = portfolio.get_min_cash_assets();
+    if portfolio.total_value < min_cash_assets {
+        return Err!("Unable to rebalance the portfolio: its debt exceeds the value of its assets");
+    }
 
-pub fn rebalance_portfolio(portfolio: &mut Portfolio, converter: &CurrencyConverter) -> EmptyResult {
     // The first step is bottom-up and calculates strict limits on asset min/max value
-    calculate_restrictions(&mut portfolio.assets);
+    let avoid_selling = forbid_selling || portfolio.avoid_selling;
+    let (mut min_value, _) = calculate_restrictions(&mut portfolio.assets, avoid_selling);
+
+    // Selling avoidance is a soft restriction: if the free cash isn't enough to keep the minimum
+    // cash assets, it's relaxed only by the shortfall
+    let available_value = portfolio.total_value - min_cash_assets;
+    if !forbid_selling && avoid_selling && min_value > available_value {
+        debug!("There is not enough free cash to avoid selling.");
+
+        if relax_selling_restrictions(&mut portfolio.assets, available_value, min_value - available_value) {
+            min_value = available_value;
+        } else {
+            min_value = calculate_restrictions(&mut portfolio.assets, false).0;
+        }
+    }
+
+    check_restrictions(portfolio, min_value)?;
+
+    calculate_commission_restrictions(
+        &mut portfolio.assets, None, &portfolio.broker, &portfolio.currency, converter)?;
 
     // The second step is top-down and tries to apply the specified weights and limits calculated in
     // the first step to the current assets
     debug!("");
     debug!("Calculating assets target value...");
     AssetGroupRebalancer::rebalance(
-        &portfolio.name, &mut portfolio.assets, portfolio.total_value - portfolio.min_cash_assets,
-        portfolio.min_trade_volume);
+        &portfolio.name, None, &mut portfolio.assets, portfolio.total_value - min_cash_assets);
 
     // The next step is bottom-up and calculates the result of the previous step
     let target_value = calculate_result_value(
-        &mut portfolio.assets, &portfolio.broker, &portfolio.currency, converter)?;
+        &mut portfolio.assets, None, &portfolio.broker, &portfolio.currency, converter)?;
     portfolio.target_cash_assets = portfolio.total_value - target_value;
 
     let (interim_trade_commissions, interim_additional_commissions) =
@@ -50,19 +191,80 @@ pub fn rebalance_portfolio(portfolio: &mut Portfolio, converter: &CurrencyConver
     );
     portfolio.change_commission(additional_commissions - interim_additional_commissions);
 
-    Ok(())
+    let mut trades = Vec::new();
+    let mut asset_names = BTreeMap::new();
+    get_trades(&portfolio.assets, None, &mut trades, &mut asset_names);
+
+    trades.sort_by(|a, b| {
+        let sell_first = |trade: &TradeAction| trade.trade_type() != TradeType::Sell;
+        sell_first(a).cmp(&sell_first(b)).then_with(|| a.symbol().cmp(b.symbol()))
+    });
+
+    let uninvested_cash = portfolio.target_cash_assets - portfolio.get_min_cash_assets();
+    let uninvested_cash_reason = if uninvested_cash > dec!(0) {
+        // Cash distribution stops when there is no trade which fits the free cash, so the reason is
+        // determined by checking whether there is any possible trade at all
+        let possible_trade = find_assets_for_cash_distribution(
+            TradeType::Buy, &portfolio.assets, portfolio.total_value - portfolio.get_min_cash_assets(),
+            Decimal::max_value());
+
+        Some(match possible_trade {
+            Some(_) => UninvestedCashReason::BelowMinTrade,
+            None => UninvestedCashReason::AllBuysBlocked,
+        })
+    } else {
+        None
+    };
+
+    Ok(RebalancingPlan {
+        trades,
+        commissions: portfolio.commissions,
+        uninvested_cash,
+        uninvested_cash_reason,
+        asset_names,
+    })
 }
 
-fn calculate_restrictions(assets: &mut Vec<AssetAllocation>) -> (Decimal, Option<Decimal>) {
+fn get_trades(
+    assets: &[AssetAllocation], parent_path: Option<&str>, trades: &mut Vec<TradeAction>,
+    asset_names: &mut BTreeMap<String, String>,
+) {
+    for asset in assets {
+        let path = asset.full_path(parent_path);
+
+        let holding = match asset.holding {
+            Holding::Stock(ref holding) => holding,
+            Holding::Group(ref holdings) => {
+                get_trades(holdings, Some(&path), trades, asset_names);
+                continue;
+            },
+        };
+
+        let symbol = holding.symbol.clone();
+        let volume = (asset.target_value - asset.current_value).abs();
+
+        trades.push(if holding.target_shares > holding.current_shares {
+            TradeAction::Buy {symbol, shares: holding.target_shares - holding.current_shares, volume}
+        } else if holding.target_shares < holding.current_shares {
+            TradeAction::Sell {symbol, shares: holding.current_shares - holding.target_shares, volume}
+        } else {
+            continue;
+        });
+
+        asset_names.insert(holding.symbol.clone(), path);
+    }
+}
+
+fn calculate_restrictions(assets: &mut Vec<AssetAllocation>, avoid_selling: bool) -> (Decimal, Option<Decimal>) {
     let mut total_min_value = dec!(0);
     let mut total_max_value = dec!(0);
     let mut all_with_max_value = true;
 
     for asset in assets {
         let (min_value, max_value) = match &mut asset.holding {
-            Holding::Group(holdings) => calculate_restrictions(holdings),
+            Holding::Group(holdings) => calculate_restrictions(holdings, avoid_selling),
             Holding::Stock(_) => {
-                let min_value = if asset.restrict_selling.unwrap_or(false) {
+                let min_value = if avoid_selling || asset.restrict_selling.unwrap_or(false) {
                     asset.current_value
                 } else {
                     dec!(0)
@@ -105,6 +307,122 @@ fn calculate_restrictions(assets: &mut Vec<AssetAllocation>) -> (Decimal, Option
     (total_min_value, total_max_value)
 }
 
+// Distributes the shortfall between the assets proportionally to the value they exceed their target
+// value by, so only the overweight assets are sold and only as much as needed. Returns false if the
+// shortfall can't be covered without violating the hard restrictions.
+fn relax_selling_restrictions(assets: &mut [AssetAllocation], value: Decimal, shortfall: Decimal) -> bool {
+    let excesses: Vec<Decimal> = assets.iter().map(|asset| {
+        if asset.restrict_selling.unwrap_or(false) {
+            dec!(0)
+        } else {
+            std::cmp::max(dec!(0), asset.min_value - value * asset.expected_weight)
+        }
+    }).collect();
+
+    let total_excess: Decimal = excesses.iter().copied().sum();
+    if total_excess < shortfall {
+        return false;
+    }
+
+    let last_index = excesses.iter().rposition(|excess| !excess.is_zero());
+    let mut remaining_shortfall = shortfall;
+
+    for (index, (asset, excess)) in assets.iter_mut().zip(excesses).enumerate() {
+        if excess.is_zero() {
+            continue;
+        }
+
+        // Give the remainder to the last asset to not get any rounding errors
+        let reduction = if Some(index) == last_index {
+            remaining_shortfall
+        } else {
+            shortfall * excess / total_excess
+        };
+
+        remaining_shortfall -= reduction;
+        asset.min_value -= reduction;
+
+        if let Holding::Group(ref mut holdings) = asset.holding {
+            if !relax_selling_restrictions(holdings, asset.min_value, reduction) {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+// Hard restrictions can't be satisfied if the assets which can't be sold are worth more than the
+// portfolio value available for investment
+fn check_restrictions(portfolio: &Portfolio, min_value: Decimal) -> EmptyResult {
+    let available_value = portfolio.total_value - portfolio.get_min_cash_assets();
+    if min_value <= available_value {
+        return Ok(());
+    }
+
+    let mut restricted_assets = Vec::new();
+    get_sell_restricted_assets(&portfolio.assets, None, &mut restricted_assets);
+
+    let restricted_assets: Vec<String> = restricted_assets.into_iter().map(|(name, value)| {
+        format!("{}: {}", name, Cash::new(&portfolio.currency, value))
+    }).collect();
+
+    Err!(concat!(
+        "Unable to rebalance the portfolio: the assets which can't be sold are worth {} which ",
+        "exceeds the portfolio value available for investment ({}). Sell restricted assets: {}"),
+        Cash::new(&portfolio.currency, min_value), Cash::new(&portfolio.currency, available_value),
+        restricted_assets.join(", "))
+}
+
+fn get_sell_restricted_assets(
+    assets: &[AssetAllocation], parent_path: Option<&str>, restricted_assets: &mut Vec<(String, Decimal)>,
+) {
+    for asset in assets {
+        let path = asset.full_path(parent_path);
+
+        match asset.holding {
+            Holding::Stock(_) => if !asset.min_value.is_zero() {
+                restricted_assets.push((path, asset.min_value));
+            },
+            Holding::Group(ref holdings) => {
+                get_sell_restricted_assets(holdings, Some(&path), restricted_assets);
+            },
+        }
+    }
+}
+
+// A trade which volume is less than its commission costs more than the drift it fixes, so the
+// commission of a single share trade is treated as a lower bound of minimum trade volume
+fn calculate_commission_restrictions(
+    assets: &mut Vec<AssetAllocation>, parent_path: Option<&str>, broker: &BrokerInfo,
+    currency: &str, converter: &CurrencyConverter,
+) -> EmptyResult {
+    for asset in assets {
+        let name = asset.full_path(parent_path);
+
+        match asset.holding {
+            Holding::Stock(ref holding) => {
+                let mut commission_calc = CommissionCalc::new(broker.commission_spec.clone());
+                let commission = calculate_target_commission(
+                    &name, holding, holding.current_shares + 1, &mut commission_calc,
+                    currency, converter)?;
+
+                if commission > asset.min_trade_volume {
+                    debug!("{name}: minimum trade volume is limited by commission: {commission}",
+                           name=name, commission=commission.normalize());
+                    asset.min_trade_volume = commission;
+                }
+            },
+            Holding::Group(ref mut holdings) => {
+                calculate_commission_restrictions(
+                    holdings, Some(&name), broker, currency, converter)?;
+            },
+        }
+    }
+
+    Ok(())
+}
+
 fn propagate_zero_weight(asset: &mut AssetAllocation) {
     if asset.min_value.is_zero() {
         if let Holding::Group(ref mut holdings) = asset.holding {
@@ -122,19 +440,18 @@ fn propagate_zero_weight(asset: &mut AssetAllocation) {
 
 struct AssetGroupRebalancer<'a> {
     name: &'a str,
+    path: Option<&'a str>,
     assets: &'a mut Vec<AssetAllocation>,
     target_total_value: Decimal,
-    min_trade_volume: Decimal,
     balance: Decimal,
 }
 
 impl<'a> AssetGroupRebalancer<'a> {
     fn rebalance(
-        name: &str, assets: &mut Vec<AssetAllocation>, target_total_value: Decimal,
-        min_trade_volume: Decimal
+        name: &str, path: Option<&str>, assets: &mut Vec<AssetAllocation>, target_total_value: Decimal,
     ) -> Decimal {
         let mut rebalancer = AssetGroupRebalancer {
-            name, assets, target_total_value, min_trade_volume,
+            name, path, assets, target_total_value,
             balance: dec!(0),
         };
 
@@ -161,10 +478,19 @@ impl<'a> AssetGroupRebalancer<'a> {
             let mut difference = asset.target_value - asset.current_value;
 
             if let Holding::Stock(ref holding) = asset.holding {
-                difference = util::round(difference / holding.price, 0) * holding.price;
+                let shares = difference / holding.price;
+
+                // Don't buy a share which is more expensive than the whole asset's allotment
+                let shares = if shares.is_sign_positive() && asset.target_value < holding.price {
+                    shares.floor()
+                } else {
+                    util::round(shares, 0)
+                };
+
+                difference = shares * holding.price;
             }
 
-            if difference.abs() < self.min_trade_volume {
+            if difference.abs() < asset.min_trade_volume {
                 difference = dec!(0);
             }
 
@@ -190,11 +516,13 @@ impl<'a> AssetGroupRebalancer<'a> {
                    name=name, action=action, value=value.normalize());
         };
 
+        let path = self.path;
+
         for asset in self.assets.iter_mut() {
             if let Some(max_value) = asset.max_value {
                 if asset.target_value > max_value {
                     if asset.restrict_buying.unwrap_or(false) && asset.target_value > asset.current_value {
-                        log_restriction_applying(&asset.full_name(), "buying", max_value);
+                        log_restriction_applying(&asset.full_path(path), "buying", max_value);
                         asset.buy_blocked = true;
                     }
 
@@ -206,7 +534,7 @@ impl<'a> AssetGroupRebalancer<'a> {
             let min_value = asset.min_value;
 
             if asset.target_value < min_value {
-                log_restriction_applying(&asset.full_name(), "selling", min_value);
+                log_restriction_applying(&asset.full_path(path), "selling", min_value);
                 asset.sell_blocked = true;
 
                 self.balance += asset.target_value - min_value;
@@ -221,12 +549,14 @@ impl<'a> AssetGroupRebalancer<'a> {
         let state = self.get_current_state();
         let mut propagated = false;
 
+        let path = self.path;
+
         for asset in self.assets.iter_mut() {
-            let asset_name = asset.full_name();
+            let asset_path = asset.full_path(path);
 
             if let Holding::Group(ref mut holdings) = asset.holding {
                 let balance = AssetGroupRebalancer::rebalance(
-                    &asset_name, holdings, asset.target_value, self.min_trade_volume);
+                    &asset_path, Some(&asset_path), holdings, asset.target_value);
 
                 asset.target_value -= balance;
                 self.balance += balance;
@@ -244,7 +574,9 @@ impl<'a> AssetGroupRebalancer<'a> {
         let state = self.get_current_state();
 
         for trade_type in [TradeType::Sell, TradeType::Buy].iter().cloned() {
-            let mut correctable_assets: HashSet<usize> = (0..self.assets.len()).collect();
+            // Assets are iterated in a stable order to make the result reproducible: when there are
+            // several equally good trades, the first one wins
+            let mut correctable_assets: BTreeSet<usize> = (0..self.assets.len()).collect();
 
             while match trade_type {
                 TradeType::Sell => self.balance.is_sign_negative(),
@@ -256,7 +588,7 @@ impl<'a> AssetGroupRebalancer<'a> {
                     let asset = &mut self.assets[index];
                     let expected_value = self.target_total_value * asset.expected_weight;
                     let possible_trade = calculate_min_trade_volume(
-                        trade_type, asset, expected_value, self.balance, self.min_trade_volume);
+                        trade_type, asset, expected_value, self.balance);
 
                     match possible_trade {
                         Some(mut trade) => {
@@ -339,13 +671,13 @@ struct AssetGroupRebalancingState {
 }
 
 fn calculate_result_value(
-    assets: &mut Vec<AssetAllocation>, broker: &BrokerInfo,
+    assets: &mut Vec<AssetAllocation>, parent_path: Option<&str>, broker: &BrokerInfo,
     currency: &str, converter: &CurrencyConverter
 ) -> GenericResult<Decimal> {
     let mut total_value = dec!(0);
 
     for asset in assets.iter_mut() {
-        let name = asset.full_name();
+        let name = asset.full_path(parent_path);
 
         total_value += match asset.holding {
             Holding::Stock(ref mut holding) => {
@@ -354,7 +686,7 @@ fn calculate_result_value(
                 asset.target_value
             },
             Holding::Group(ref mut holdings) => {
-                calculate_result_value(holdings, broker, currency, converter)?
+                calculate_result_value(holdings, Some(&name), broker, currency, converter)?
             },
         };
     }
@@ -366,9 +698,11 @@ fn distribute_cash_assets(portfolio: &mut Portfolio, converter: &CurrencyConvert
     debug!("");
     debug!("Cash assets distribution:");
 
+    let min_cash_assets = portfolio.get_min_cash_assets();
+
     for trade_type in [TradeType::Sell, TradeType::Buy].iter().cloned() {
         loop {
-            let free_cash_assets = portfolio.target_cash_assets - portfolio.min_cash_assets;
+            let free_cash_assets = portfolio.target_cash_assets - min_cash_assets;
 
             if !match trade_type {
                 TradeType::Sell => free_cash_assets.is_sign_negative(),
@@ -377,11 +711,10 @@ fn distribute_cash_assets(portfolio: &mut Portfolio, converter: &CurrencyConvert
                 break;
             }
 
-            let expected_total_value = portfolio.total_value - portfolio.min_cash_assets;
+            let expected_total_value = portfolio.total_value - min_cash_assets;
 
             let trade = find_assets_for_cash_distribution(
-                trade_type, &portfolio.assets, expected_total_value, free_cash_assets,
-                portfolio.min_trade_volume);
+                trade_type, &portfolio.assets, expected_total_value, free_cash_assets);
 
             let trade = match trade {
                 Some(trade) => trade,
@@ -391,7 +724,7 @@ fn distribute_cash_assets(portfolio: &mut Portfolio, converter: &CurrencyConvert
             portfolio.target_cash_assets -= trade.volume;
 
             let commission = process_trade(
-                &mut portfolio.assets, trade, &portfolio.broker, &portfolio.currency, converter)?;
+                &mut portfolio.assets, None, trade, &portfolio.broker, &portfolio.currency, converter)?;
 
             portfolio.change_commission(commission);
         }
@@ -408,7 +741,7 @@ struct PossibleTrade {
 
 fn find_assets_for_cash_distribution(
     trade_type: TradeType, assets: &[AssetAllocation], expected_total_value: Decimal,
-    cash_assets: Decimal, min_trade_volume: Decimal
+    cash_assets: Decimal,
 ) -> Option<PossibleTrade> {
     let mut best_trade: Option<PossibleTrade> = None;
 
@@ -417,12 +750,11 @@ fn find_assets_for_cash_distribution(
 
         let trade = match asset.holding {
             Holding::Stock(_) => {
-                calculate_min_trade_volume(
-                    trade_type, asset, expected_value, cash_assets, min_trade_volume)
+                calculate_min_trade_volume(trade_type, asset, expected_value, cash_assets)
             },
             Holding::Group(ref holdings) => {
                 let mut trade = find_assets_for_cash_distribution(
-                    trade_type, holdings, expected_value, cash_assets, min_trade_volume);
+                    trade_type, holdings, expected_value, cash_assets);
 
                 if let Some(ref mut trade) = trade {
                     trade.result = calculate_trade_result(
@@ -448,13 +780,13 @@ fn find_assets_for_cash_distribution(
 }
 
 fn process_trade(
-    assets: &mut Vec<AssetAllocation>, mut trade: PossibleTrade,
+    assets: &mut Vec<AssetAllocation>, parent_path: Option<&str>, mut trade: PossibleTrade,
     broker: &BrokerInfo, currency: &str, converter: &CurrencyConverter
 ) -> GenericResult<Decimal> {
     let index = trade.path.pop().unwrap();
     let asset = &mut assets[index];
 
-    let name = asset.full_name();
+    let name = asset.full_path(parent_path);
     let target_value = asset.target_value + trade.volume;
 
     let commission = match asset.holding {
@@ -468,7 +800,7 @@ fn process_trade(
             change_to(&name, holding, target_value, broker, currency, converter)?
         },
         Holding::Group(ref mut holdings) => {
-            process_trade(holdings, trade, broker, currency, converter)?
+            process_trade(holdings, Some(&name), trade, broker, currency, converter)?
         },
     };
 
@@ -478,13 +810,12 @@ fn process_trade(
 }
 
 fn calculate_min_trade_volume(
-    trade_type: TradeType, asset: &AssetAllocation, expected_value: Decimal,
-    cash_assets: Decimal, min_trade_volume: Decimal
+    trade_type: TradeType, asset: &AssetAllocation, expected_value: Decimal, cash_assets: Decimal,
 ) -> Option<PossibleTrade> {
     let trade_volume = match trade_type {
-        TradeType::Sell => calculate_min_sell_volume(asset, min_trade_volume),
+        TradeType::Sell => calculate_min_sell_volume(asset),
         TradeType::Buy => {
-            match calculate_min_buy_volume(asset, min_trade_volume) {
+            match calculate_min_buy_volume(asset) {
                 Some(trade_volume) if trade_volume <= cash_assets => Some(trade_volume),
                 _ => None,
             }
@@ -594,7 +925,7 @@ fn calculate_total_commissions(portfolio: &Portfolio, converter: &CurrencyConver
     let mut commission_calc = CommissionCalc::new(portfolio.broker.commission_spec.clone());
 
     let trade_commissions = calculate_trade_commissions(
-        &portfolio.assets, &mut commission_calc, &portfolio.currency, converter)?;
+        &portfolio.assets, None, &mut commission_calc, &portfolio.currency, converter)?;
 
     let date = util::today_trade_conclusion_date();
     let mut additional_commissions = dec!(0);
@@ -607,22 +938,24 @@ fn calculate_total_commissions(portfolio: &Portfolio, converter: &CurrencyConver
 }
 
 fn calculate_trade_commissions(
-    assets: &[AssetAllocation], commission_calc: &mut CommissionCalc,
+    assets: &[AssetAllocation], parent_path: Option<&str>, commission_calc: &mut CommissionCalc,
     currency: &str, converter: &CurrencyConverter,
 ) -> GenericResult<Decimal> {
     let mut trade_commissions = dec!(0);
 
     for asset in assets {
+        let name = asset.full_path(parent_path);
+
         match &asset.holding {
             Holding::Stock(holding) => {
                 trade_commissions += calculate_target_commission(
-                    &asset.full_name(), holding, holding.target_shares, commission_calc,
+                    &name, holding, holding.target_shares, commission_calc,
                     currency, converter,
                 )?;
             },
             Holding::Group(assets) => {
                 trade_commissions += calculate_trade_commissions(
-                    assets, commission_calc, currency, converter)?;
+                    assets, Some(&name), commission_calc, currency, converter)?;
             },
         }
     }
@@ -630,7 +963,8 @@ fn calculate_trade_commissions(
     Ok(trade_commissions)
 }
 
-fn calculate_min_sell_volume(asset: &AssetAllocation, min_trade_volume: Decimal) -> Option<Decimal> {
+fn calculate_min_sell_volume(asset: &AssetAllocation) -> Option<Decimal> {
+    let min_trade_volume = asset.min_trade_volume;
     let trade_granularity = get_trade_granularity(asset);
 
     let trade_volume = if asset.target_value <= asset.current_value {
@@ -665,7 +999,8 @@ fn calculate_min_sell_volume(asset: &AssetAllocation, min_trade_volume: Decimal)
     Some(trade_volume)
 }
 
-fn calculate_min_buy_volume(asset: &AssetAllocation, min_trade_volume: Decimal) -> Option<Decimal> {
+fn calculate_min_buy_volume(asset: &AssetAllocation) -> Option<Decimal> {
+    let min_trade_volume = asset.min_trade_volume;
     let trade_granularity = get_trade_granularity(asset);
 
     let trade_volume = if asset.target_value >= asset.current_value {
@@ -724,4 +1059,414 @@ fn get_trade_granularity(asset: &AssetAllocation) -> Decimal {
 
 fn round_min_trade_volume(volume: Decimal, granularity: Decimal) -> Decimal {
     (volume / granularity).ceil() * granularity
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use rstest::rstest;
+
+    use crate::brokers::Broker;
+    use crate::commissions::{
+        CommissionSpecBuilder, TradeCommissionSpecBuilder, TransactionCommissionSpecBuilder};
+    use crate::config::Config;
+    use crate::currency::Cash;
+    use crate::db;
+
+    use super::*;
+
+    #[rstest(allow_margin_debt, expected_shares, expected_cash_assets,
+        case(false, (8, 8), dec!(0)),
+        case(true, (10, 10), dec!(-400)),
+    )]
+    fn margin_debt(allow_margin_debt: bool, expected_shares: (u32, u32), expected_cash_assets: Decimal) {
+        let (_database, connection) = db::new_temporary();
+        let converter = CurrencyConverter::new(&Config::mock(), connection, None, false);
+
+        let mut portfolio = mock_portfolio(vec![
+            mock_stock("AAA", dec!(0.5), dec!(100), 12),
+            mock_stock("BBB", dec!(0.5), dec!(100), 8),
+        ], dec!(-400), allow_margin_debt);
+        assert_eq!(portfolio.total_value, dec!(1600));
+
+        rebalance_portfolio(&mut portfolio, &converter).unwrap();
+        assert_eq!(get_target_shares(&portfolio), expected_shares);
+        assert_eq!(portfolio.target_cash_assets, expected_cash_assets);
+        assert_eq!(portfolio.total_value, dec!(1600));
+    }
+
+    #[test]
+    fn plan_serialization() {
+        let plan = RebalancingPlan {
+            trades: vec![
+                TradeAction::Sell {symbol: s!("AAA"), shares: 3, volume: dec!(300.10)},
+                TradeAction::Buy {symbol: s!("BBB"), shares: 7, volume: dec!(0.123456789012345678901)},
+            ],
+            commissions: dec!(1.00),
+            uninvested_cash: dec!(12.34),
+            uninvested_cash_reason: Some(UninvestedCashReason::BelowMinTrade),
+            asset_names: btreemap!{
+                s!("AAA") => s!("Stocks » AAA (AAA)"),
+                s!("BBB") => s!("Stocks » BBB (BBB)"),
+            },
+        };
+
+        let mut buffer = Vec::new();
+        write_plan(&plan, &mut buffer).unwrap();
+
+        let json = String::from_utf8(buffer).unwrap();
+        assert!(json.contains(r#""action": "buy""#));
+        assert!(json.contains(r#""volume": "0.123456789012345678901""#));
+        assert!(json.contains(r#""commissions": "1.00""#));
+        assert!(json.contains(r#""AAA": "Stocks » AAA (AAA)""#));
+        assert!(json.contains(r#""uninvested_cash_reason": "below_min_trade""#));
+
+        let deserialized: RebalancingPlan = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, plan);
+        assert_eq!(deserialized.commissions.to_string(), "1.00");
+    }
+
+    #[test]
+    fn pending_orders() {
+        let (_database, connection) = db::new_temporary();
+        let converter = CurrencyConverter::new(&Config::mock(), connection, None, false);
+
+        let mut portfolio = mock_portfolio(vec![
+            mock_stock("AAA", dec!(0.5), dec!(100), 5),
+            mock_stock("BBB", dec!(0.5), dec!(100), 5),
+        ], dec!(1000), false);
+
+        apply_pending_orders(&mut portfolio, &[PendingOrder {
+            symbol: s!("AAA"),
+            action: TradeType::Buy,
+            quantity: 3,
+        }]).unwrap();
+
+        assert_eq!(portfolio.current_cash_assets, dec!(700));
+        assert_eq!(portfolio.total_value, dec!(2000));
+
+        rebalance_portfolio(&mut portfolio, &converter).unwrap();
+        assert_eq!(get_current_shares(&portfolio), (8, 5));
+        assert_eq!(get_target_shares(&portfolio), (10, 10));
+        assert_eq!(portfolio.target_cash_assets, dec!(0));
+    }
+
+    #[rstest(min_trade_volume, expected_shares,
+        case(dec!(0), (10, 10)),
+        case(dec!(300), (9, 11)),
+    )]
+    fn asset_min_trade_volume(min_trade_volume: Decimal, expected_shares: (u32, u32)) {
+        let (_database, connection) = db::new_temporary();
+        let converter = CurrencyConverter::new(&Config::mock(), connection, None, false);
+
+        let mut stock = mock_stock("AAA", dec!(0.5), dec!(100), 9);
+        stock.min_trade_volume = min_trade_volume;
+
+        let mut portfolio = mock_portfolio(vec![
+            stock, mock_stock("BBB", dec!(0.5), dec!(100), 11),
+        ], dec!(0), false);
+
+        rebalance_portfolio(&mut portfolio, &converter).unwrap();
+        assert_eq!(get_target_shares(&portfolio), expected_shares);
+        assert_eq!(portfolio.target_cash_assets, dec!(0));
+    }
+
+    #[rstest(fixed_commission, expected_shares, expected_trades,
+        case(None, (100, 100), vec![
+            TradeAction::Sell {symbol: s!("BBB"), shares: 1, volume: dec!(10)},
+            TradeAction::Buy {symbol: s!("AAA"), shares: 1, volume: dec!(10)},
+        ]),
+        // The trades cost more than the drift they fix, so they are skipped
+        case(Some(dec!(15)), (99, 101), vec![]),
+    )]
+    fn commission_restrictions(
+        fixed_commission: Option<Decimal>, expected_shares: (u32, u32), expected_trades: Vec<TradeAction>,
+    ) {
+        let (_database, connection) = db::new_temporary();
+        let converter = CurrencyConverter::new(&Config::mock(), connection, None, false);
+
+        let mut portfolio = mock_portfolio(vec![
+            mock_stock("AAA", dec!(0.5), dec!(10), 99),
+            mock_stock("BBB", dec!(0.5), dec!(10), 101),
+        ], dec!(0), false);
+
+        if let Some(commission) = fixed_commission {
+            portfolio.broker.commission_spec = CommissionSpecBuilder::new("USD")
+                .trade(TradeCommissionSpecBuilder::new()
+                    .commission(TransactionCommissionSpecBuilder::new()
+                        .percent(dec!(0))
+                        .minimum(commission)
+                        .build().unwrap())
+                    .build())
+                .build();
+        }
+
+        let plan = rebalance_portfolio(&mut portfolio, &converter).unwrap();
+        assert_eq!(get_target_shares(&portfolio), expected_shares);
+        assert_eq!(plan.trades, expected_trades);
+        assert_eq!(plan.commissions, dec!(0));
+        assert_eq!(plan.uninvested_cash, dec!(0));
+        assert_eq!(portfolio.target_cash_assets, dec!(0));
+    }
+
+    #[test]
+    fn zero_weight() {
+        let (_database, connection) = db::new_temporary();
+        let converter = CurrencyConverter::new(&Config::mock(), connection, None, false);
+
+        let mut portfolio = mock_portfolio(vec![
+            mock_stock("AAA", dec!(0), dec!(100), 5),
+            mock_stock("BBB", dec!(0.5), dec!(100), 5),
+            mock_stock("CCC", dec!(0.5), dec!(100), 5),
+        ], dec!(100), false);
+
+        let plan = rebalance_portfolio(&mut portfolio, &converter).unwrap();
+        assert_eq!(plan.trades, vec![
+            TradeAction::Sell {symbol: s!("AAA"), shares: 5, volume: dec!(500)},
+            TradeAction::Buy {symbol: s!("BBB"), shares: 3, volume: dec!(300)},
+            TradeAction::Buy {symbol: s!("CCC"), shares: 3, volume: dec!(300)},
+        ]);
+        assert_eq!(plan.uninvested_cash, dec!(0));
+    }
+
+    #[test]
+    fn negative_balance_correction() {
+        let (_database, connection) = db::new_temporary();
+        let converter = CurrencyConverter::new(&Config::mock(), connection, None, false);
+
+        let mut portfolio = mock_portfolio(vec![
+            mock_stock("AAA", dec!(0.2), dec!(100), 10),
+            mock_stock("BBB", dec!(0.8), dec!(100), 8),
+        ], dec!(200), false);
+        portfolio.assets[0].restrict_selling = Some(true);
+
+        // The minimum value of AAA exceeds its target value, so the excess has to be covered by
+        // selling BBB in the balance correction step
+        let plan = rebalance_portfolio(&mut portfolio, &converter).unwrap();
+        assert_eq!(get_target_shares(&portfolio), (10, 10));
+        assert!(portfolio.assets[0].sell_blocked);
+        assert_eq!(plan.trades, vec![
+            TradeAction::Buy {symbol: s!("BBB"), shares: 2, volume: dec!(200)},
+        ]);
+        assert_eq!(plan.uninvested_cash, dec!(0));
+    }
+
+    #[test]
+    fn infeasible_restrictions() {
+        let (_database, connection) = db::new_temporary();
+        let converter = CurrencyConverter::new(&Config::mock(), connection, None, false);
+
+        let mut portfolio = mock_portfolio(vec![
+            mock_stock("AAA", dec!(0.5), dec!(100), 10),
+            mock_stock("BBB", dec!(0.5), dec!(100), 5),
+        ], dec!(-600), false);
+        portfolio.assets[0].restrict_selling = Some(true);
+
+        let error = rebalance_portfolio(&mut portfolio, &converter).unwrap_err().to_string();
+        assert!(error.contains("Sell restricted assets: AAA (AAA)"), "{}", error);
+    }
+
+    #[test]
+    fn deterministic() {
+        let (_database, connection) = db::new_temporary();
+        let converter = CurrencyConverter::new(&Config::mock(), connection, None, false);
+
+        let plans: Vec<RebalancingPlan> = (0..10).map(|_| {
+            let mut portfolio = mock_portfolio(vec![
+                mock_stock("AAA", dec!(0.25), dec!(100), 1),
+                mock_stock("BBB", dec!(0.25), dec!(100), 1),
+                mock_stock("CCC", dec!(0.25), dec!(100), 1),
+                mock_stock("DDD", dec!(0.25), dec!(100), 1),
+            ], dec!(250), false);
+
+            rebalance_portfolio(&mut portfolio, &converter).unwrap()
+        }).collect();
+
+        for plan in &plans[1..] {
+            assert_eq!(*plan, plans[0]);
+        }
+    }
+
+    #[test]
+    fn expensive_share() {
+        let (_database, connection) = db::new_temporary();
+        let converter = CurrencyConverter::new(&Config::mock(), connection, None, false);
+
+        let mut portfolio = mock_portfolio(vec![
+            mock_stock("AAA", dec!(0.5), dec!(200), 0),
+            mock_stock("BBB", dec!(0.5), dec!(40), 0),
+        ], dec!(240), false);
+
+        rebalance_portfolio(&mut portfolio, &converter).unwrap();
+        assert_eq!(get_target_shares(&portfolio), (0, 6));
+        assert_eq!(portfolio.target_cash_assets, dec!(0));
+    }
+
+    #[test]
+    fn trades() {
+        let (_database, connection) = db::new_temporary();
+        let converter = CurrencyConverter::new(&Config::mock(), connection, None, false);
+
+        let mut portfolio = mock_portfolio(vec![
+            mock_stock("AAA", dec!(0.5), dec!(100), 5),
+            mock_stock("BBB", dec!(0.5), dec!(100), 13),
+        ], dec!(200), false);
+
+        let plan = rebalance_portfolio(&mut portfolio, &converter).unwrap();
+        assert_eq!(get_target_shares(&portfolio), (10, 10));
+
+        assert_eq!(plan.trades, vec![
+            TradeAction::Sell {symbol: s!("BBB"), shares: 3, volume: dec!(300)},
+            TradeAction::Buy {symbol: s!("AAA"), shares: 5, volume: dec!(500)},
+        ]);
+        assert_eq!(plan.uninvested_cash, dec!(0));
+    }
+
+    #[test]
+    fn uninvested_cash() {
+        let (_database, connection) = db::new_temporary();
+        let converter = CurrencyConverter::new(&Config::mock(), connection, None, false);
+
+        let mut portfolio = mock_portfolio(vec![
+            mock_stock("AAA", dec!(1), dec!(100), 0),
+        ], dec!(250), false);
+        portfolio.min_cash_assets = dec!(20);
+
+        let plan = rebalance_portfolio(&mut portfolio, &converter).unwrap();
+        assert_eq!(plan.trades, vec![
+            TradeAction::Buy {symbol: s!("AAA"), shares: 2, volume: dec!(200)},
+        ]);
+        assert_eq!(portfolio.target_cash_assets, dec!(50));
+        assert_eq!(plan.uninvested_cash, dec!(30));
+        assert_eq!(plan.uninvested_cash_reason, Some(UninvestedCashReason::BelowMinTrade));
+
+        let mut portfolio = mock_portfolio(vec![
+            mock_stock("AAA", dec!(1), dec!(100), 0),
+        ], dec!(250), false);
+        portfolio.min_cash_assets = dec!(20);
+        portfolio.assets[0].restrict_buying = Some(true);
+
+        let plan = rebalance_portfolio(&mut portfolio, &converter).unwrap();
+        assert!(plan.trades.is_empty());
+        assert_eq!(plan.uninvested_cash, dec!(230));
+        assert_eq!(plan.uninvested_cash_reason, Some(UninvestedCashReason::AllBuysBlocked));
+    }
+
+    #[rstest(cash_assets, expected_shares,
+        case(dec!(200), (7, 13)),
+        case(dec!(-200), (5, 11)),
+    )]
+    fn avoid_selling(cash_assets: Decimal, expected_shares: (u32, u32)) {
+        let (_database, connection) = db::new_temporary();
+        let converter = CurrencyConverter::new(&Config::mock(), connection, None, false);
+
+        let mut portfolio = mock_portfolio(vec![
+            mock_stock("AAA", dec!(0.5), dec!(100), 5),
+            mock_stock("BBB", dec!(0.5), dec!(100), 13),
+        ], cash_assets, false);
+        portfolio.avoid_selling = true;
+
+        rebalance_portfolio(&mut portfolio, &converter).unwrap();
+        assert_eq!(get_target_shares(&portfolio), expected_shares);
+    }
+
+    #[test]
+    fn cash_investment() {
+        let (_database, connection) = db::new_temporary();
+        let converter = CurrencyConverter::new(&Config::mock(), connection, None, false);
+
+        let mut portfolio = mock_portfolio(vec![
+            mock_stock("AAA", dec!(0.5), dec!(100), 5),
+            mock_stock("BBB", dec!(0.5), dec!(100), 13),
+        ], dec!(0), false);
+
+        let plan = invest_cash(&mut portfolio, &converter, dec!(500)).unwrap();
+        assert_eq!(get_target_shares(&portfolio), (10, 13));
+        assert_eq!(plan.trades, vec![
+            TradeAction::Buy {symbol: s!("AAA"), shares: 5, volume: dec!(500)},
+        ]);
+        assert_eq!(plan.uninvested_cash, dec!(0));
+    }
+
+    #[test]
+    fn margin_debt_exceeding_assets() {
+        let (_database, connection) = db::new_temporary();
+        let converter = CurrencyConverter::new(&Config::mock(), connection, None, false);
+
+        let mut portfolio = mock_portfolio(vec![
+            mock_stock("AAA", dec!(1), dec!(100), 1),
+        ], dec!(-200), false);
+
+        assert!(rebalance_portfolio(&mut portfolio, &converter).is_err());
+    }
+
+    fn mock_portfolio(assets: Vec<AssetAllocation>, cash_assets: Decimal, allow_margin_debt: bool) -> Portfolio {
+        let currency = "USD";
+        let total_value = assets.iter().fold(cash_assets, |total, asset| total + asset.current_value);
+
+        Portfolio {
+            name: s!("Portfolio"),
+            broker: Broker::Firstrade.get_info(&Config::mock(), None).unwrap(),
+            currency: currency.to_owned(),
+
+            min_trade_volume: dec!(0),
+            min_cash_assets: dec!(0),
+            allow_margin_debt,
+            avoid_selling: false,
+
+            assets,
+            current_cash_assets: cash_assets,
+            target_cash_assets: cash_assets,
+            commissions: dec!(0),
+            total_value,
+
+            unavailable_prices: Vec::new(),
+        }
+    }
+
+    pub(crate) fn mock_stock(symbol: &str, weight: Decimal, price: Decimal, shares: u32) -> AssetAllocation {
+        let current_value = price * Decimal::from(shares);
+
+        AssetAllocation {
+            name: symbol.to_owned(),
+
+            expected_weight: weight,
+            min_trade_volume: dec!(0),
+            restrict_buying: None,
+            restrict_selling: None,
+
+            holding: Holding::Stock(StockHolding {
+                symbol: symbol.to_owned(),
+                price,
+                currency_price: Cash::new("USD", price),
+                current_shares: shares,
+                target_shares: shares,
+            }),
+            current_value,
+            target_value: current_value,
+
+            min_value: dec!(0),
+            max_value: None,
+
+            buy_blocked: false,
+            sell_blocked: false,
+        }
+    }
+
+    fn get_current_shares(portfolio: &Portfolio) -> (u32, u32) {
+        let shares = portfolio.assets.iter().map(|asset| match asset.holding {
+            Holding::Stock(ref holding) => holding.current_shares,
+            Holding::Group(_) => unreachable!(),
+        }).collect::<Vec<_>>();
+
+        (shares[0], shares[1])
+    }
+
+    fn get_target_shares(portfolio: &Portfolio) -> (u32, u32) {
+        let shares = portfolio.assets.iter().map(|asset| match asset.holding {
+            Holding::Stock(ref holding) => holding.target_shares,
+            Holding::Group(_) => unreachable!(),
+        }).collect::<Vec<_>>();
+
+        (shares[0], shares[1])
+    }
+}
@@ -0,0 +1,224 @@
+use num_traits::Zero;
+
+use crate::core::GenericResult;
+use crate::currency::Cash;
+use crate::currency::converter::CurrencyConverter;
+use crate::types::Decimal;
+
+use super::asset_allocation::{Portfolio, AssetAllocation, Holding, UnavailablePrice};
+
+const REPORT_CURRENCY: &str = "RUB";
+
+/// Current portfolio valuation in a machine-readable form
+#[derive(Debug)]
+pub struct ValuationReport {
+    pub name: String,
+    pub currency: String,
+    pub assets: Vec<AssetValuation>,
+    pub cash: Valuation,
+    pub total: Valuation,
+
+    // Positions with unavailable prices are excluded from the total value
+    pub unavailable_prices: Vec<UnavailablePrice>,
+}
+
+impl ValuationReport {
+    /// Returns assets (including nested ones) which absolute weight drift exceeds the threshold
+    pub fn get_drifted_assets(&self, threshold: Decimal) -> Vec<&AssetValuation> {
+        let mut drifted_assets = Vec::new();
+        collect_drifted_assets(&self.assets, threshold, &mut drifted_assets);
+        drifted_assets
+    }
+
+    /// Returns false if some positions couldn't be valued. Callers are expected to treat it as a
+    /// failure (exit with a non-zero code, for example) after reporting the valuation.
+    pub fn is_complete(&self) -> bool {
+        self.unavailable_prices.is_empty()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AssetValuation {
+    pub name: String,
+    pub symbol: Option<String>,
+    pub shares: Option<u32>,
+    pub value: Valuation,
+    pub price_unavailable: bool,
+
+    // Weights are relative to the expected value of the parent group (or of the whole portfolio
+    // minus the minimum cash assets for top-level assets) just like in portfolio output.
+    pub weight: Decimal,
+    pub target_weight: Decimal,
+    pub drift: Decimal,
+
+    pub assets: Vec<AssetValuation>,
+}
+
+impl AssetValuation {
+    fn has_unavailable_prices(&self) -> bool {
+        self.price_unavailable || self.assets.iter().any(AssetValuation::has_unavailable_prices)
+    }
+}
+
+/// Value in portfolio currency and in RUB
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Valuation {
+    pub value: Cash,
+    pub value_rub: Cash,
+}
+
+impl Valuation {
+    fn new(converter: &CurrencyConverter, value: Cash) -> GenericResult<Valuation> {
+        let value_rub = converter.real_time_convert_to(value, REPORT_CURRENCY)?;
+        Ok(Valuation {
+            value,
+            value_rub: Cash::new(REPORT_CURRENCY, value_rub),
+        })
+    }
+}
+
+pub fn get_valuation_report(portfolio: &Portfolio, converter: &CurrencyConverter) -> GenericResult<ValuationReport> {
+    let currency = &portfolio.currency;
+    let expected_total_value = portfolio.total_value - portfolio.get_min_cash_assets();
+
+    Ok(ValuationReport {
+        name: portfolio.name.clone(),
+        currency: currency.clone(),
+        assets: get_assets_valuation(portfolio, &portfolio.assets, expected_total_value, converter)?,
+        cash: Valuation::new(converter, Cash::new(currency, portfolio.current_cash_assets))?,
+        total: Valuation::new(converter, Cash::new(currency, portfolio.total_value))?,
+        unavailable_prices: portfolio.unavailable_prices.clone(),
+    })
+}
+
+fn collect_drifted_assets<'a>(
+    assets: &'a [AssetValuation], threshold: Decimal, drifted_assets: &mut Vec<&'a AssetValuation>,
+) {
+    for asset in assets {
+        // Assets with unavailable prices are valued as zero, so their drift is meaningless
+        if asset.drift.abs() > threshold && !asset.has_unavailable_prices() {
+            drifted_assets.push(asset);
+        }
+        collect_drifted_assets(&asset.assets, threshold, drifted_assets);
+    }
+}
+
+fn get_assets_valuation(
+    portfolio: &Portfolio, assets: &[AssetAllocation], expected_total_value: Decimal,
+    converter: &CurrencyConverter,
+) -> GenericResult<Vec<AssetValuation>> {
+    let currency = &portfolio.currency;
+    let mut valuations = Vec::with_capacity(assets.len());
+
+    for asset in assets {
+        let weight = if expected_total_value.is_zero() {
+            dec!(0)
+        } else {
+            asset.current_value / expected_total_value
+        };
+
+        let (symbol, shares, price_unavailable, assets) = match asset.holding {
+            Holding::Stock(ref holding) => {
+                let price_unavailable = portfolio.unavailable_prices.iter()
+                    .any(|unavailable| unavailable.symbol == holding.symbol);
+                (Some(holding.symbol.clone()), Some(holding.current_shares), price_unavailable, Vec::new())
+            },
+            Holding::Group(ref holdings) => {
+                let expected_value = expected_total_value * asset.expected_weight;
+                (None, None, false, get_assets_valuation(portfolio, holdings, expected_value, converter)?)
+            },
+        };
+
+        valuations.push(AssetValuation {
+            name: asset.name.clone(),
+            symbol,
+            shares,
+            value: Valuation::new(converter, Cash::new(currency, asset.current_value))?,
+            price_unavailable,
+
+            weight,
+            target_weight: asset.expected_weight,
+            drift: weight - asset.expected_weight,
+
+            assets,
+        });
+    }
+
+    Ok(valuations)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::brokers::Broker;
+    use crate::config::Config;
+
+    use super::super::rebalancing::tests::mock_stock;
+    use super::*;
+
+    #[test]
+    fn valuation_report() {
+        let converter = CurrencyConverter::mock(dec!(70), &[]);
+
+        let mut portfolio = Portfolio {
+            name: s!("Portfolio"),
+            broker: Broker::Firstrade.get_info(&Config::mock(), None).unwrap(),
+            currency: s!("USD"),
+
+            min_trade_volume: dec!(0),
+            min_cash_assets: dec!(100),
+            allow_margin_debt: false,
+            avoid_selling: false,
+
+            assets: vec![
+                mock_stock("AAA", dec!(0.6), dec!(100), 7),
+                mock_stock("BBB", dec!(0.4), dec!(50), 6),
+            ],
+            current_cash_assets: dec!(100),
+            target_cash_assets: dec!(100),
+            commissions: dec!(0),
+            total_value: dec!(1100),
+
+            unavailable_prices: Vec::new(),
+        };
+
+        let report = get_valuation_report(&portfolio, &converter).unwrap();
+        assert!(report.is_complete());
+
+        assert_eq!(report.total, Valuation {
+            value: Cash::new("USD", dec!(1100)),
+            value_rub: Cash::new("RUB", dec!(77000)),
+        });
+        assert_eq!(report.cash.value_rub, Cash::new("RUB", dec!(7000)));
+
+        let asset = &report.assets[0];
+        assert_eq!(asset.symbol, Some(s!("AAA")));
+        assert_eq!(asset.value.value, Cash::new("USD", dec!(700)));
+        assert_eq!(asset.weight, dec!(0.7));
+        assert_eq!(asset.target_weight, dec!(0.6));
+        assert_eq!(asset.drift, dec!(0.1));
+
+        let asset = &report.assets[1];
+        assert_eq!(asset.weight, dec!(0.3));
+        assert_eq!(asset.drift, dec!(-0.1));
+        assert!(!asset.price_unavailable);
+
+        let drifted_assets: Vec<&str> = report.get_drifted_assets(dec!(0.05)).iter()
+            .map(|asset| asset.name.as_str()).collect();
+        assert_eq!(drifted_assets, vec!["AAA", "BBB"]);
+        assert!(report.get_drifted_assets(dec!(0.1)).is_empty());
+
+        portfolio.unavailable_prices.push(UnavailablePrice {
+            symbol: s!("BBB"),
+            error: s!("Unable to find quotes for following symbols: BBB"),
+        });
+
+        let report = get_valuation_report(&portfolio, &converter).unwrap();
+        assert!(!report.is_complete());
+        assert!(!report.assets[0].price_unavailable);
+        assert!(report.assets[1].price_unavailable);
+
+        let drifted_assets: Vec<&str> = report.get_drifted_assets(dec!(0.05)).iter()
+            .map(|asset| asset.name.as_str()).collect();
+        assert_eq!(drifted_assets, vec!["AAA"]);
+    }
+}
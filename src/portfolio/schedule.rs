@@ -0,0 +1,77 @@
+use chrono::Duration;
+use diesel::{self, prelude::*};
+
+use crate::core::{EmptyResult, GenericResult};
+use crate::db::{self, schema::rebalancing, models};
+use crate::types::Date;
+
+pub struct RebalanceSchedule {
+    database: db::Connection,
+    portfolio: String,
+    interval: Duration,
+}
+
+impl RebalanceSchedule {
+    pub fn new(database: db::Connection, portfolio: &str, interval: Duration) -> RebalanceSchedule {
+        RebalanceSchedule {
+            database,
+            portfolio: portfolio.to_owned(),
+            interval,
+        }
+    }
+
+    pub fn get_next_date(&self) -> GenericResult<Option<Date>> {
+        Ok(self.get_last_date()?.map(|date| date + self.interval))
+    }
+
+    pub fn is_due(&self, today: Date) -> GenericResult<bool> {
+        Ok(match self.get_next_date()? {
+            Some(next_date) => today >= next_date,
+            None => true,
+        })
+    }
+
+    pub fn get_last_date(&self) -> GenericResult<Option<Date>> {
+        Ok(rebalancing::table
+            .select(rebalancing::date)
+            .filter(rebalancing::portfolio.eq(&self.portfolio))
+            .get_result::<Date>(&*self.database).optional()?)
+    }
+
+    pub fn save(&self, date: Date) -> EmptyResult {
+        diesel::replace_into(rebalancing::table)
+            .values(models::NewRebalancing {
+                portfolio: &self.portfolio,
+                date: date,
+            })
+            .execute(&*self.database)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedule() {
+        let (_database, connection) = db::new_temporary();
+        let schedule = RebalanceSchedule::new(connection.clone(), "test", Duration::days(90));
+        let other_schedule = RebalanceSchedule::new(connection, "other", Duration::days(90));
+
+        assert_eq!(schedule.get_next_date().unwrap(), None);
+        assert!(schedule.is_due(date!(1, 1, 2020)).unwrap());
+
+        schedule.save(date!(1, 1, 2020)).unwrap();
+        assert_eq!(schedule.get_last_date().unwrap(), Some(date!(1, 1, 2020)));
+        assert_eq!(schedule.get_next_date().unwrap(), Some(date!(31, 3, 2020)));
+        assert_eq!(other_schedule.get_last_date().unwrap(), None);
+
+        assert!(!schedule.is_due(date!(1, 1, 2020)).unwrap());
+        assert!(!schedule.is_due(date!(30, 3, 2020)).unwrap());
+        assert!(schedule.is_due(date!(31, 3, 2020)).unwrap());
+
+        schedule.save(date!(1, 4, 2020)).unwrap();
+        assert!(!schedule.is_due(date!(31, 3, 2020)).unwrap());
+    }
+}
@@ -0,0 +1,111 @@
+use chrono::Duration;
+use diesel::{self, prelude::*};
+
+use crate::config::ValuationFrequency;
+use crate::core::{EmptyResult, GenericResult};
+use crate::currency::Cash;
+use crate::db::{self, schema::valuations, models};
+use crate::types::Date;
+use crate::util::{self, DecimalRestrictions};
+
+pub struct Valuations {
+    database: db::Connection,
+    portfolio: String,
+}
+
+impl Valuations {
+    pub fn new(database: db::Connection, portfolio: &str) -> Valuations {
+        Valuations {
+            database,
+            portfolio: portfolio.to_owned(),
+        }
+    }
+
+    /// Saves the valuation calculated by the specified function if there is no valuation for the
+    /// date's period. Returns true if the valuation has been saved.
+    pub fn save_if_missing<F>(&self, date: Date, frequency: ValuationFrequency, calculate: F) -> GenericResult<bool>
+        where F: FnOnce() -> GenericResult<Cash>
+    {
+        let period = match frequency {
+            ValuationFrequency::Daily => Duration::days(1),
+            ValuationFrequency::Weekly => Duration::weeks(1),
+        };
+
+        let last_date = valuations::table
+            .select(valuations::date)
+            .filter(valuations::portfolio.eq(&self.portfolio))
+            .filter(valuations::date.le(date))
+            .order(valuations::date.desc())
+            .limit(1)
+            .get_result::<Date>(&*self.database).optional()?;
+
+        if let Some(last_date) = last_date {
+            if date - last_date < period {
+                return Ok(false);
+            }
+        }
+
+        self.save(date, calculate()?)?;
+        Ok(true)
+    }
+
+    pub fn save(&self, date: Date, value: Cash) -> EmptyResult {
+        diesel::replace_into(valuations::table)
+            .values(models::NewValuation {
+                portfolio: &self.portfolio,
+                date: date,
+                currency: value.currency,
+                value: value.amount.to_string(),
+            })
+            .execute(&*self.database)?;
+        Ok(())
+    }
+
+    pub fn get_all(&self) -> GenericResult<Vec<(Date, Cash)>> {
+        let rows = valuations::table
+            .select((valuations::date, valuations::currency, valuations::value))
+            .filter(valuations::portfolio.eq(&self.portfolio))
+            .order(valuations::date.asc())
+            .load::<(Date, String, String)>(&*self.database)?;
+
+        let mut valuations = Vec::new();
+
+        for (date, currency, value) in rows {
+            let value = util::parse_decimal(&value, DecimalRestrictions::No).map_err(|_| format!(
+                "Got an invalid valuation from the database: {:?}", value))?;
+            valuations.push((date, Cash::new(&currency, value)));
+        }
+
+        Ok(valuations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valuations() {
+        let (_database, connection) = db::new_temporary();
+        let valuations = Valuations::new(connection.clone(), "test");
+        let other_valuations = Valuations::new(connection, "other");
+
+        let save = |date, frequency, value| {
+            valuations.save_if_missing(date, frequency, || Ok(Cash::new("RUB", value))).unwrap()
+        };
+
+        assert!(save(date!(1, 2, 2020), ValuationFrequency::Weekly, dec!(100)));
+        assert!(!save(date!(1, 2, 2020), ValuationFrequency::Weekly, dec!(101)));
+        assert!(!save(date!(7, 2, 2020), ValuationFrequency::Weekly, dec!(102)));
+        assert!(save(date!(8, 2, 2020), ValuationFrequency::Weekly, dec!(103)));
+        assert!(save(date!(9, 2, 2020), ValuationFrequency::Daily, dec!(104)));
+        assert!(!save(date!(9, 2, 2020), ValuationFrequency::Daily, dec!(105)));
+
+        assert_eq!(valuations.get_all().unwrap(), vec![
+            (date!(1, 2, 2020), Cash::new("RUB", dec!(100))),
+            (date!(8, 2, 2020), Cash::new("RUB", dec!(103))),
+            (date!(9, 2, 2020), Cash::new("RUB", dec!(104))),
+        ]);
+        assert!(other_valuations.get_all().unwrap().is_empty());
+    }
+}
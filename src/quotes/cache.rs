@@ -9,11 +9,11 @@ use crate::util::{self, DecimalRestrictions};
 
 pub struct Cache {
     db: db::Connection,
-    expire_time: Duration,
+    expire_time: Option<Duration>, // Cached quotes never expire if it's not set
 }
 
 impl Cache {
-    pub fn new(connection: db::Connection, expire_time: Duration) -> Cache {
+    pub fn new(connection: db::Connection, expire_time: Option<Duration>) -> Cache {
         Cache {
             db: connection,
             expire_time: expire_time,
@@ -23,17 +23,20 @@ impl Cache {
     #[cfg(test)]
     pub fn new_temporary() -> (NamedTempFile, Cache) {
         let (database, connection) = db::new_temporary();
-        (database, Cache::new(connection, Duration::minutes(1)))
+        (database, Cache::new(connection, Some(Duration::minutes(1))))
     }
 
     pub fn get(&self, symbol: &str) -> GenericResult<Option<Cash>> {
-        let expire_time = util::now() - self.expire_time;
-
-        let result = quotes::table
+        let mut query = quotes::table
             .select((quotes::currency, quotes::price))
             .filter(quotes::symbol.eq(symbol))
-            .filter(quotes::time.gt(&expire_time))
-            .get_result::<(String, String)>(&*self.db).optional()?;
+            .into_boxed();
+
+        if let Some(expire_time) = self.expire_time {
+            query = query.filter(quotes::time.gt(util::now() - expire_time));
+        }
+
+        let result = query.get_result::<(String, String)>(&*self.db).optional()?;
 
         let (currency, price) = match result {
             Some(result) => result,
@@ -47,14 +50,16 @@ impl Cache {
     }
 
     pub fn save(&self, symbol: &str, price: Cash) -> EmptyResult {
-        diesel::replace_into(quotes::table)
-            .values(models::NewQuote {
-                symbol: symbol,
-                time: util::now(),
-                currency: price.currency,
-                price: price.amount.to_string(),
-            })
-            .execute(&*self.db)?;
+        db::retry_on_lock(|| {
+            diesel::replace_into(quotes::table)
+                .values(models::NewQuote {
+                    symbol: symbol,
+                    time: util::now(),
+                    currency: price.currency,
+                    price: price.amount.to_string(),
+                })
+                .execute(&*self.db)
+        })?;
         Ok(())
     }
 }
@@ -76,7 +81,7 @@ mod tests {
         diesel::replace_into(quotes::table)
             .values(models::NewQuote {
                 symbol: symbol,
-                time: util::now() - cache.expire_time,
+                time: util::now() - cache.expire_time.unwrap(),
                 currency: "EUR",
                 price: s!("12.34"),
             })
@@ -93,8 +98,12 @@ mod tests {
         assert_eq!(cache.get(symbol).unwrap(), Some(price));
         assert_eq!(cache.get(other_symbol).unwrap(), Some(other_price));
 
-        cache.expire_time = Duration::seconds(0);
+        cache.expire_time = Some(Duration::seconds(0));
         assert_eq!(cache.get(symbol).unwrap(), None);
         assert_eq!(cache.get(other_symbol).unwrap(), None);
+
+        cache.expire_time = None;
+        assert_eq!(cache.get(symbol).unwrap(), Some(price));
+        assert_eq!(cache.get(other_symbol).unwrap(), Some(other_price));
     }
 }
\ No newline at end of file
@@ -3,7 +3,7 @@ use std::collections::{HashMap, HashSet};
 
 #[cfg(not(test))] use chrono::{DateTime, TimeZone};
 use lazy_static::lazy_static;
-use log::debug;
+use log::{debug, warn};
 use regex::Regex;
 
 use crate::config::Config;
@@ -31,13 +31,21 @@ pub struct Quotes {
 
 impl Quotes {
     pub fn new(config: &Config, database: db::Connection) -> GenericResult<Quotes> {
+        if config.offline {
+            // There is no way to update the quotes in offline mode, so cached ones never expire
+            let cache = Cache::new(database, None);
+            return Ok(Quotes::new_with(cache, vec![Box::new(OfflineProvider {})]));
+        }
+
+        let cache = Cache::new(database, Some(config.cache_expire_time));
+
         let finnhub = config.finnhub.as_ref().ok_or(
             "Finnhub configuration is not set in the configuration file")?;
 
         let twelvedata = config.twelvedata.as_ref().ok_or(
             "Twelve Data configuration is not set in the configuration file")?;
 
-        Ok(Quotes::new_with(Cache::new(database, config.cache_expire_time), vec![
+        Ok(Quotes::new_with(cache, vec![
             Box::new(Finnhub::new(&finnhub.token)),
             Box::new(TwelveData::new(&twelvedata.token)),
             Box::new(Moex::new()),
@@ -65,7 +73,10 @@ impl Quotes {
         let mut batched_symbols = self.batched_symbols.borrow_mut();
 
         let mut price = None;
+        let mut errors = Vec::new();
 
+        // Providers are tried in order: if one of them fails (for example, because of rate limiting),
+        // the remaining symbols are requested from the next one.
         for provider in &self.providers {
             let quotes = {
                 let symbols: Vec<&str> = batched_symbols.iter().filter_map(|symbol| {
@@ -88,8 +99,15 @@ impl Quotes {
                 debug!("Getting quotes from {} for the following symbols: {}...",
                        provider.name(), symbols.join(", "));
 
-                provider.get_quotes(&symbols).map_err(|e| format!(
-                    "Failed to get quotes from {}: {}", provider.name(), e))?
+                match provider.get_quotes(&symbols) {
+                    Ok(quotes) => quotes,
+                    Err(e) => {
+                        let error = format!("Failed to get quotes from {}: {}", provider.name(), e);
+                        warn!("{}.", error);
+                        errors.push(error);
+                        continue;
+                    },
+                }
             };
 
             for (other_symbol, other_price) in quotes.iter() {
@@ -108,6 +126,8 @@ impl Quotes {
                     }
                 };
 
+                debug!("Got {} quote from {}: {}.", other_symbol, provider.name(), other_price);
+
                 if *other_symbol == symbol {
                     price.replace(other_price);
                 }
@@ -121,9 +141,21 @@ impl Quotes {
             }
         }
 
-        if !batched_symbols.is_empty() {
-            let symbols = batched_symbols.iter().cloned().collect::<Vec<String>>();
-            return Err!("Unable to find quotes for following symbols: {}", symbols.join(", "));
+        // Unresolved symbols are dropped from the batch, so a single unavailable (for example,
+        // delisted) symbol doesn't fail the following requests of other symbols.
+        let unresolved_symbols = std::mem::replace(&mut *batched_symbols, HashSet::new());
+
+        if price.is_none() {
+            let mut symbols = unresolved_symbols.into_iter().collect::<Vec<String>>();
+            symbols.sort();
+
+            let mut message = format!("Unable to find quotes for following symbols: {}", symbols.join(", "));
+
+            if !errors.is_empty() {
+                message = format!("{}. {}", message, errors.join(". "));
+            }
+
+            return Err(message.into());
         }
 
         Ok(price.unwrap())
@@ -140,6 +172,24 @@ trait QuotesProvider {
     fn get_quotes(&self, symbols: &[&str]) -> GenericResult<QuotesMap>;
 }
 
+/// Used in offline mode instead of the real providers to never hit the network
+struct OfflineProvider {
+}
+
+impl QuotesProvider for OfflineProvider {
+    fn name(&self) -> &'static str {
+        "offline mode"
+    }
+
+    fn get_quotes(&self, symbols: &[&str]) -> GenericResult<QuotesMap> {
+        let mut symbols = symbols.to_vec();
+        symbols.sort();
+
+        Err!("Quotes for {} are missing in the cache. Run the command without offline mode to fetch them into the cache",
+             symbols.join(", "))
+    }
+}
+
 pub fn get_currency_pair(base: &str, quote: &str) -> String {
     format!("{}/{}", base, quote)
 }
@@ -232,4 +282,66 @@ mod tests {
         assert_eq!(quotes.get("VTI").unwrap(), Cash::new("USD", dec!(56.78)));
         assert_eq!(quotes.get("BNDX").unwrap(), Cash::new("USD", dec!(90.12)));
     }
+
+    #[test]
+    fn fallback() {
+        struct FailingProvider {}
+
+        impl QuotesProvider for FailingProvider {
+            fn name(&self) -> &'static str {
+                "failing-provider"
+            }
+
+            fn get_quotes(&self, _symbols: &[&str]) -> GenericResult<QuotesMap> {
+                Err!("Rate limit exceeded")
+            }
+        }
+
+        struct PartialProvider {}
+
+        impl QuotesProvider for PartialProvider {
+            fn name(&self) -> &'static str {
+                "partial-provider"
+            }
+
+            fn get_quotes(&self, symbols: &[&str]) -> GenericResult<QuotesMap> {
+                let mut quotes = HashMap::new();
+                if symbols.contains(&"BND") {
+                    quotes.insert(s!("BND"), Cash::new("USD", dec!(12.34)));
+                }
+                Ok(quotes)
+            }
+        }
+
+        let (_database, cache) = Cache::new_temporary();
+        let quotes = Quotes::new_with(cache, vec![
+            Box::new(FailingProvider {}),
+            Box::new(PartialProvider {}),
+        ]);
+
+        assert_eq!(quotes.get("BND").unwrap(), Cash::new("USD", dec!(12.34)));
+        assert_eq!(quotes.get("VTI").unwrap_err().to_string(), concat!(
+            "Unable to find quotes for following symbols: VTI. ",
+            "Failed to get quotes from failing-provider: Rate limit exceeded",
+        ));
+    }
+
+    #[test]
+    fn offline() {
+        let mut config = Config::mock();
+        config.offline = true;
+
+        let (_database, connection) = db::new_temporary();
+        let quotes = Quotes::new(&config, connection).unwrap();
+
+        let price = Cash::new("USD", dec!(12.34));
+        quotes.cache.save("BND", price).unwrap();
+        assert_eq!(quotes.get("BND").unwrap(), price);
+
+        assert_eq!(quotes.get("VTI").unwrap_err().to_string(), concat!(
+            "Unable to find quotes for following symbols: VTI. ",
+            "Failed to get quotes from offline mode: Quotes for VTI are missing in the cache. ",
+            "Run the command without offline mode to fetch them into the cache",
+        ));
+    }
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::sync::Mutex;
+use std::time::Duration;
 
 use chrono::{DateTime, TimeZone, Utc};
 #[cfg(test)] use chrono::NaiveDate;
@@ -13,6 +14,7 @@ use serde::{Deserialize, de::DeserializeOwned};
 
 use crate::core::{GenericResult, EmptyResult};
 use crate::currency::Cash;
+use crate::rate_limiter::RateLimiter;
 use crate::util::{self, DecimalRestrictions};
 use crate::types::Decimal;
 
@@ -21,6 +23,7 @@ use super::{QuotesMap, QuotesProvider, parse_currency_pair};
 pub struct TwelveData {
     token: String,
     client: Client,
+    rate_limiter: RateLimiter,
 }
 
 impl TwelveData {
@@ -28,6 +31,8 @@ impl TwelveData {
         TwelveData {
             token: token.to_owned(),
             client: Client::new(),
+            // Basic plan limit
+            rate_limiter: RateLimiter::new().with_limit(8, Duration::from_secs(60)),
         }
     }
 
@@ -44,6 +49,8 @@ impl TwelveData {
         ])?;
 
         let get = |url| {
+            self.rate_limiter.wait(&format!("request to {}", url));
+
             trace!("Sending request to {}...", url);
             let response = self.client.get(url).send()?;
             trace!("Got response from {}.", url);
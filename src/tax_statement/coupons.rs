@@ -0,0 +1,151 @@
+use chrono::Datelike;
+use num_traits::Zero;
+use static_table_derive::StaticTable;
+
+use crate::broker_statement::BrokerStatement;
+use crate::config::PortfolioConfig;
+use crate::core::EmptyResult;
+use crate::currency::{Cash, MultiCurrencyCashAccount};
+use crate::currency::converter::CurrencyConverter;
+use crate::formatting;
+use crate::types::{Date, Decimal};
+
+use super::statement::TaxStatement;
+
+#[derive(StaticTable)]
+struct Row {
+    #[column(name="Дата")]
+    date: Date,
+    #[column(name="Облигация")]
+    bond: String,
+    #[column(name="Валюта", align="center")]
+    currency: String,
+
+    #[column(name="Сумма")]
+    foreign_amount: Cash,
+    #[column(name="Уплаченный НКД")]
+    foreign_accrued_interest: Cash,
+    #[column(name="Облагаемая сумма")]
+    foreign_taxable_amount: Cash,
+    #[column(name="Курс руб.")]
+    currency_rate: Decimal,
+    #[column(name="Сумма (руб)")]
+    amount: Cash,
+
+    #[column(name="Удержано (руб)")]
+    paid_tax: Cash,
+    #[column(name="К доплате")]
+    tax_to_pay: Cash,
+    #[column(name="Реальный доход")]
+    income: Cash,
+}
+
+pub fn process_income(
+    portfolio: &PortfolioConfig, broker_statement: &BrokerStatement, year: Option<i32>,
+    mut tax_statement: Option<&mut TaxStatement>, converter: &CurrencyConverter,
+) -> EmptyResult {
+    let mut table = Table::new();
+    let country = portfolio.get_tax_country();
+
+    let mut total_foreign_amount = MultiCurrencyCashAccount::new();
+    let mut total_foreign_accrued_interest = MultiCurrencyCashAccount::new();
+    let mut total_foreign_taxable_amount = MultiCurrencyCashAccount::new();
+    let mut total_amount = dec!(0);
+    let mut total_paid_tax = dec!(0);
+    let mut total_tax_to_pay = dec!(0);
+    let mut total_income = dec!(0);
+
+    for coupon in &broker_statement.coupons {
+        if let Some(year) = year {
+            if coupon.date.year() != year {
+                continue;
+            }
+        }
+
+        let bond = broker_statement.get_instrument_name(&coupon.symbol);
+
+        let foreign_amount = coupon.amount.round();
+        total_foreign_amount.deposit(foreign_amount);
+
+        // Accrued interest paid on bond purchase is deducted from the coupon's taxable income
+        let foreign_accrued_interest = coupon.paid_accrued_interest.round();
+        total_foreign_accrued_interest.deposit(foreign_accrued_interest);
+
+        let foreign_taxable_amount = coupon.taxable_amount().round();
+        total_foreign_taxable_amount.deposit(foreign_taxable_amount);
+
+        let precise_currency_rate = converter.precise_currency_rate(
+            coupon.date, foreign_amount.currency, country.currency)?;
+
+        let amount = converter.convert_to_rounding(
+            coupon.date, foreign_taxable_amount, country.currency)?;
+        total_amount += amount;
+
+        let paid_tax = converter.convert_to_rounding(coupon.date, coupon.paid_tax, country.currency)?;
+        total_paid_tax += paid_tax;
+
+        let tax_to_pay = coupon.tax_to_pay(&country, converter)?;
+        total_tax_to_pay += tax_to_pay;
+
+        let income = amount - paid_tax - tax_to_pay;
+        total_income += income;
+
+        table.add_row(Row {
+            date: coupon.date,
+            bond: bond.to_owned(),
+            currency: foreign_amount.currency.to_owned(),
+
+            foreign_amount: foreign_amount,
+            foreign_accrued_interest: foreign_accrued_interest,
+            foreign_taxable_amount: foreign_taxable_amount,
+            currency_rate: precise_currency_rate,
+            amount: Cash::new(country.currency, amount),
+
+            paid_tax: Cash::new(country.currency, paid_tax),
+            tax_to_pay: Cash::new(country.currency, tax_to_pay),
+            income: Cash::new(country.currency, income),
+        });
+
+        // Coupons which have been taxed by the broker as a tax agent don't have to be declared
+        if tax_to_pay.is_zero() {
+            continue;
+        }
+
+        if let Some(ref mut tax_statement) = tax_statement {
+            let description = format!("{}: Купон по облигации {}", broker_statement.broker.name, bond);
+
+            let foreign_paid_tax = coupon.paid_tax.round();
+            if foreign_paid_tax.currency != foreign_taxable_amount.currency {
+                return Err!(
+                    "{} coupon from {}: Tax currency is different from coupon currency: {} vs {}",
+                    coupon.symbol, formatting::format_date(coupon.date),
+                    foreign_paid_tax.currency, foreign_taxable_amount.currency);
+            }
+
+            tax_statement.add_coupon_income(
+                &description, coupon.date, foreign_taxable_amount.currency, precise_currency_rate,
+                foreign_taxable_amount.amount, foreign_paid_tax.amount, amount, paid_tax
+            ).map_err(|e| format!(
+                "Unable to add {} coupon from {} to the tax statement: {}",
+                coupon.symbol, formatting::format_date(coupon.date), e
+            ))?;
+        }
+    }
+
+    if !table.is_empty() {
+        let mut totals = table.add_empty_row();
+        totals.set_foreign_amount(total_foreign_amount);
+        totals.set_foreign_accrued_interest(total_foreign_accrued_interest);
+        totals.set_foreign_taxable_amount(total_foreign_taxable_amount);
+        totals.set_amount(Cash::new(country.currency, total_amount));
+        totals.set_paid_tax(Cash::new(country.currency, total_paid_tax));
+        totals.set_tax_to_pay(Cash::new(country.currency, total_tax_to_pay));
+        totals.set_income(Cash::new(country.currency, total_income));
+
+        table.print(&format!(
+            "Расчет дохода от купонов по облигациям, полученных через {}",
+            broker_statement.broker.name));
+    }
+
+    Ok(())
+}
\ No newline at end of file
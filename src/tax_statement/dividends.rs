@@ -1,4 +1,5 @@
 use chrono::Datelike;
+use log::warn;
 use num_traits::Zero;
 use static_table_derive::StaticTable;
 
@@ -82,6 +83,11 @@ pub fn process_income(
         let foreign_paid_tax = dividend.paid_tax.round();
         total_foreign_paid_tax.deposit(foreign_paid_tax);
 
+        if dividend.exceeds_treaty_tax_rate() {
+            warn!("{}: The withheld tax ({}) exceeds the tax treaty rate.",
+                  dividend.description(), foreign_paid_tax);
+        }
+
         let paid_tax = converter.convert_to_rounding(
             dividend.date, foreign_paid_tax, country.currency)?;
         total_paid_tax += paid_tax;
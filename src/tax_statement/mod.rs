@@ -6,6 +6,7 @@ use crate::db;
 
 pub use self::statement::TaxStatement;
 
+mod coupons;
 mod dividends;
 mod interest;
 mod statement;
@@ -15,7 +16,7 @@ pub fn generate_tax_statement(
     config: &Config, portfolio_name: &str, year: Option<i32>, tax_statement_path: Option<&str>
 ) -> EmptyResult {
     let portfolio = config.get_portfolio(portfolio_name)?;
-    let broker = portfolio.broker.get_info(config, portfolio.plan.as_ref())?;
+    let broker = portfolio.get_broker_info(config)?;
 
     let broker_statement = BrokerStatement::read(
         broker, &portfolio.statements, &portfolio.symbol_remapping, &portfolio.instrument_names,
@@ -42,7 +43,13 @@ pub fn generate_tax_statement(
     };
 
     let database = db::connect(&config.db_path)?;
-    let converter = CurrencyConverter::new(database, None, true);
+    let converter = CurrencyConverter::new(config, database, None, true);
+
+    if let Some(year) = year {
+        let currencies: Vec<&str> = broker_statement.cash_assets.iter()
+            .map(|assets| assets.currency).collect();
+        converter.prefetch(&currencies, year)?;
+    }
 
     trades::process_income(&portfolio, &broker_statement, year, tax_statement.as_mut(), &converter)
         .map_err(|e| format!("Failed to process income from stock trading: {}", e))?;
@@ -53,6 +60,9 @@ pub fn generate_tax_statement(
     interest::process_income(&portfolio, &broker_statement, year, tax_statement.as_mut(), &converter)
         .map_err(|e| format!("Failed to process income from idle cash interest: {}", e))?;
 
+    coupons::process_income(&portfolio, &broker_statement, year, tax_statement.as_mut(), &converter)
+        .map_err(|e| format!("Failed to process coupon income: {}", e))?;
+
     if let Some(ref tax_statement) = tax_statement {
         tax_statement.save()?;
     }
@@ -95,6 +95,32 @@ impl TaxStatement {
         Ok(())
     }
 
+    pub fn add_coupon_income(
+        &mut self, description: &str, date: Date, currency: &str, currency_rate: Decimal,
+        amount: Decimal, paid_tax: Decimal, local_amount: Decimal, local_paid_tax: Decimal,
+    ) -> EmptyResult {
+        self.get_foreign_incomes()?.push(CurrencyIncome {
+            type_: IncomeType::Interest,
+            description: description.to_owned(),
+            county_code: CountryCode::Usa,
+
+            date: date,
+            tax_payment_date: date,
+            currency: CurrencyInfo::new(currency, currency_rate)?,
+
+            amount: amount,
+            local_amount: local_amount,
+
+            paid_tax: paid_tax,
+            local_paid_tax: local_paid_tax,
+            deduction: DeductionInfo::new_none(),
+
+            controlled_foreign_company: ControlledForeignCompanyInfo::new_none(),
+        });
+
+        Ok(())
+    }
+
     pub fn add_stock_income(
         &mut self, description: &str, date: Date, currency: &str, currency_rate: Decimal,
         amount: Decimal, local_amount: Decimal, purchase_local_cost: Decimal,
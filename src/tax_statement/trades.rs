@@ -1,14 +1,14 @@
 use chrono::Datelike;
 use static_table_derive::StaticTable;
 
-use crate::broker_statement::{BrokerStatement, StockSell, SellDetails, FifoDetails};
+use crate::broker_statement::{BrokerStatement, Fee, StockSell, SellDetails, FifoDetails};
 use crate::config::PortfolioConfig;
 use crate::core::EmptyResult;
 use crate::currency::Cash;
 use crate::currency::converter::CurrencyConverter;
 use crate::formatting::{self, table::Cell};
 use crate::localities::Country;
-use crate::taxes::TaxPaymentDay;
+use crate::taxes::{self, TaxPaymentDay};
 use crate::types::{Date, Decimal};
 
 use super::statement::TaxStatement;
@@ -28,10 +28,13 @@ pub fn process_income(
 
         trades_table: TradesTable::new(),
         fifo_table: FifoTable::new(),
+        expenses_table: ExpensesTable::new(),
 
+        tax_exempt: taxes::is_iia_income_tax_exempt(portfolio.iia_type, broker_statement.period),
         same_dates: true,
         same_currency: true,
         total_local_profit: Cash::new(country.currency, dec!(0)),
+        total_local_expenses: Cash::new(country.currency, dec!(0)),
     };
 
     let mut trade_id = 0;
@@ -47,13 +50,29 @@ pub fn process_income(
         processor.process_trade(trade_id, trade, &details)?;
 
         if let Some(ref mut tax_statement) = tax_statement {
-            processor.add_income(tax_statement, trade, &details)?;
+            if !processor.tax_exempt {
+                processor.add_income(tax_statement, trade, &details)?;
+            }
         }
 
         trade_id += 1;
     }
 
     if trade_id != 0 {
+        for fee in &broker_statement.fees {
+            if !fee.tax_deductible {
+                continue;
+            }
+
+            if let Some(year) = year {
+                if fee.date.year() != year {
+                    continue;
+                }
+            }
+
+            processor.process_expense(fee)?;
+        }
+
         processor.print();
     }
 
@@ -70,10 +89,13 @@ struct TradesProcessor<'a> {
 
     trades_table: TradesTable,
     fifo_table: FifoTable,
+    expenses_table: ExpensesTable,
 
+    tax_exempt: bool,
     same_dates: bool,
     same_currency: bool,
     total_local_profit: Cash,
+    total_local_expenses: Cash,
 }
 
 #[derive(StaticTable)]
@@ -148,6 +170,21 @@ struct FifoRow {
     total_local_cost: Cash,
 }
 
+#[derive(StaticTable)]
+#[table(name="ExpensesTable")]
+struct ExpenseRow {
+    #[column(name="Дата")]
+    date: Date,
+    #[column(name="Описание")]
+    description: String,
+    #[column(name="Сумма")]
+    amount: Cash,
+    #[column(name="Курс руб.")]
+    currency_rate: Decimal,
+    #[column(name="Сумма (руб)")]
+    local_amount: Cash,
+}
+
 impl<'a> TradesProcessor<'a> {
     fn add_income(&self, tax_statement: &mut TaxStatement, trade: &StockSell, details: &SellDetails) -> EmptyResult {
         let name = self.broker_statement.get_instrument_name(&trade.symbol);
@@ -199,7 +236,11 @@ impl<'a> TradesProcessor<'a> {
             purchase_local_cost: details.purchase_local_cost,
             total_local_cost: details.total_local_cost,
             local_profit: details.local_profit,
-            tax_to_pay: details.tax_to_pay,
+            tax_to_pay: if self.tax_exempt {
+                Cash::new(self.country.currency, dec!(0))
+            } else {
+                details.tax_to_pay
+            },
             real_profit_ratio: Cell::new_ratio(details.real_profit_ratio),
             real_local_profit_ratio: Cell::new_ratio(details.real_local_profit_ratio),
         });
@@ -246,6 +287,29 @@ impl<'a> TradesProcessor<'a> {
         Ok(())
     }
 
+    fn process_expense(&mut self, fee: &Fee) -> EmptyResult {
+        // Fee amount is negative for commission and positive for refund
+        let amount = -fee.amount.round();
+        self.same_currency &= amount.currency == self.country.currency;
+
+        let currency_rate = self.converter.precise_currency_rate(
+            fee.date, amount.currency, self.country.currency)?;
+
+        let local_amount = self.converter.convert_to_cash_rounding(
+            fee.date, amount, self.country.currency)?;
+        self.total_local_expenses.add_assign(local_amount).unwrap();
+
+        self.expenses_table.add_row(ExpenseRow {
+            date: fee.date,
+            description: fee.description.clone().unwrap_or_else(|| s!("Комиссия брокера")),
+            amount: amount,
+            currency_rate: currency_rate,
+            local_amount: local_amount,
+        });
+
+        Ok(())
+    }
+
     fn print(mut self) {
         if self.same_dates {
             self.trades_table.hide_execution_date();
@@ -279,7 +343,14 @@ impl<'a> TradesProcessor<'a> {
         };
 
         if show_net_tax {
-            let tax_to_pay = self.country.tax_to_pay(self.total_local_profit.amount, None);
+            // Deductible investment expenses reduce the taxable profit
+            let taxable_profit = self.total_local_profit.amount - self.total_local_expenses.amount;
+
+            let tax_to_pay = match self.year {
+                _ if self.tax_exempt => dec!(0),
+                Some(year) => self.country.annual_tax_to_pay(year, taxable_profit),
+                None => self.country.tax_to_pay(taxable_profit, None),
+            };
             totals.set_tax_to_pay(Cash::new(self.country.currency, tax_to_pay));
         }
 
@@ -288,5 +359,17 @@ impl<'a> TradesProcessor<'a> {
             self.broker_statement.broker.name));
 
         self.fifo_table.print("Детализация расчета сделок по ФИФО");
+
+        if !self.expenses_table.is_empty() {
+            if self.same_currency {
+                self.expenses_table.hide_currency_rate();
+                self.expenses_table.hide_local_amount();
+            }
+
+            let mut totals = self.expenses_table.add_empty_row();
+            totals.set_local_amount(self.total_local_expenses);
+
+            self.expenses_table.print("Расходы, уменьшающие налоговую базу");
+        }
     }
 }
\ No newline at end of file
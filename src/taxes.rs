@@ -1,8 +1,9 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::default::Default;
 
 use chrono::Datelike;
 use lazy_static::lazy_static;
+use num_traits::Zero;
 
 use crate::core::EmptyResult;
 use crate::currency;
@@ -34,12 +35,72 @@ impl TaxPaymentDay {
         }
 
         match *self {
-            TaxPaymentDay::Day {month, day} => Date::from_ymd(income_date.year() + 1, month, day),
+            TaxPaymentDay::Day {month, day} => {
+                let year = income_date.year() + 1;
+
+                // Feb 29 falls back to Feb 28 in non-leap years
+                Date::from_ymd_opt(year, month, day).unwrap_or_else(|| Date::from_ymd(year, month, day - 1))
+            },
             TaxPaymentDay::OnClose => *ACCOUNT_CLOSE_DATE,
         }
     }
 }
 
+/// Individual investment account (ИИС) type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IiaType {
+    A, // Tax deduction for contributions
+    B, // Tax exemption for income
+}
+
+/// Checks whether income of the account with the specified period is exempt from taxes: type B IIA
+/// income is exempt if the account is held for at least 3 years.
+pub fn is_iia_income_tax_exempt(iia_type: Option<IiaType>, period: (Date, Date)) -> bool {
+    if iia_type != Some(IiaType::B) {
+        return false;
+    }
+
+    let (open_date, close_date) = period;
+    let year = open_date.year() + 3;
+
+    // Feb 29 falls back to Mar 1 in non-leap years
+    let min_close_date = Date::from_ymd_opt(year, open_date.month(), open_date.day())
+        .unwrap_or_else(|| Date::from_ymd(year, 3, 1));
+
+    close_date >= min_close_date
+}
+
+/// Returns the maximum annual type A IIA tax deduction
+pub fn get_iia_max_tax_deduction(country: &Country, year: i32) -> Decimal {
+    country.round_tax(get_iia_max_annual_contributions() * country.tax_rate(year, dec!(0)))
+}
+
+/// Calculates type A IIA tax deductions for the specified contributions. Returns approximate dates
+/// when the deductions are received with the deduction amounts.
+pub fn calculate_iia_tax_deductions(country: &Country, contributions: &[(Date, Decimal)]) -> Vec<(Date, Decimal)> {
+    let mut annual_contributions: BTreeMap<i32, Decimal> = BTreeMap::new();
+
+    for &(date, amount) in contributions {
+        *annual_contributions.entry(date.year()).or_default() += amount;
+    }
+
+    annual_contributions.into_iter().filter_map(|(year, contributions)| {
+        let contributions = std::cmp::min(contributions, get_iia_max_annual_contributions());
+        let deduction = country.round_tax(contributions * country.tax_rate(year, dec!(0)));
+
+        if deduction.is_sign_positive() && !deduction.is_zero() {
+            let date = TaxPaymentDay::default().get(Date::from_ymd(year, 12, 31));
+            Some((date, deduction))
+        } else {
+            None
+        }
+    }).collect()
+}
+
+fn get_iia_max_annual_contributions() -> Decimal {
+    dec!(400_000)
+}
+
 pub struct TaxRemapping {
     remapping: HashMap<(Date, String), (Date, bool)>
 }
@@ -118,4 +179,50 @@ impl NetTaxCalculator {
 
         taxes
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::localities;
+    use super::*;
+
+    #[test]
+    fn tax_payment_day() {
+        let tax_payment_day = TaxPaymentDay::Day {month: 2, day: 29};
+        assert_eq!(tax_payment_day.get(date!(1, 6, 2022)), date!(28, 2, 2023));
+        assert_eq!(tax_payment_day.get(date!(1, 6, 2023)), date!(29, 2, 2024));
+
+        let tax_payment_day = TaxPaymentDay::default();
+        assert_eq!(tax_payment_day.get(date!(1, 6, 2023)), date!(15, 3, 2024));
+    }
+
+    #[test]
+    fn iia_tax_deductions() {
+        let country = localities::russia();
+        assert_eq!(get_iia_max_tax_deduction(&country, 2020), dec!(52000));
+
+        assert_eq!(calculate_iia_tax_deductions(&country, &[
+            (date!(1, 2, 2019), dec!(100_000)),
+            (date!(1, 3, 2019), dec!(50_000)),
+            (date!(1, 2, 2020), dec!(300_000)),
+            (date!(1, 3, 2020), dec!(200_000)),
+        ]), vec![
+            (date!(15, 3, 2020), dec!(19500)),
+            (date!(15, 3, 2021), dec!(52000)),
+        ]);
+    }
+
+    #[test]
+    fn iia_tax_exemption() {
+        let open_date = date!(29, 2, 2020);
+
+        for &(iia_type, close_date, exempt) in &[
+            (None, date!(1, 3, 2023), false),
+            (Some(IiaType::A), date!(1, 3, 2023), false),
+            (Some(IiaType::B), date!(28, 2, 2023), false),
+            (Some(IiaType::B), date!(1, 3, 2023), true),
+        ] {
+            assert_eq!(is_iia_income_tax_exempt(iia_type, (open_date, close_date)), exempt);
+        }
+    }
 }
\ No newline at end of file
@@ -125,7 +125,7 @@ pub fn parse_timezone(timezone: &str) -> GenericResult<Tz> {
 }
 
 pub fn parse_duration(string: &str) -> GenericResult<Duration> {
-    let re = Regex::new(r"^(?P<number>[1-9]\d*)(?P<unit>[mhd])$").unwrap();
+    let re = Regex::new(r"^(?P<number>[1-9]\d*)(?P<unit>[smhd])$").unwrap();
 
     let seconds = re.captures(string).and_then(|captures| {
         let mut duration = match captures.name("number").unwrap().as_str().parse::<i64>().ok() {
@@ -134,6 +134,7 @@ pub fn parse_duration(string: &str) -> GenericResult<Duration> {
         };
 
         duration *= match captures.name("unit").unwrap().as_str() {
+            "s" => 1,
             "m" => 60,
             "h" => 60 * 60,
             "d" => 60 * 60 * 24,
@@ -150,6 +151,15 @@ pub fn today() -> Date {
     tz_now().date().naive_local()
 }
 
+/// Returns today's date in the specified time zone
+pub fn today_in(timezone: Tz) -> Date {
+    local_date(utc_now(), timezone)
+}
+
+fn local_date(utc_time: DateTime, timezone: Tz) -> Date {
+    timezone.from_utc_datetime(&utc_time).naive_local().date()
+}
+
 pub fn today_trade_conclusion_date() -> Date {
     today()
 }
@@ -249,4 +259,14 @@ mod tests {
     fn truncate_rounding(value: Decimal, expected: Decimal) {
         assert_eq!(round_with(value, 0, RoundingMethod::Truncate), expected);
     }
+
+    #[rstest(time, timezone, expected,
+        case(date!(1, 1, 2020).and_hms(20, 59, 59), "Europe/Moscow", date!(1, 1, 2020)),
+        case(date!(1, 1, 2020).and_hms(21, 0, 0), "Europe/Moscow", date!(2, 1, 2020)),
+        case(date!(1, 1, 2020).and_hms(21, 0, 0), "America/New_York", date!(1, 1, 2020)),
+        case(date!(1, 1, 2020).and_hms(14, 0, 0), "Asia/Vladivostok", date!(2, 1, 2020)),
+    )]
+    fn timezone_local_date(time: DateTime, timezone: &str, expected: Date) {
+        assert_eq!(local_date(time, parse_timezone(timezone).unwrap()), expected);
+    }
 }
\ No newline at end of file